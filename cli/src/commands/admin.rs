@@ -1,9 +1,16 @@
 use anyhow::Result;
 use clap::{Args, Subcommand};
-use ent_proto::ent::{schema_service_client::SchemaServiceClient, CreateSchemaRequest};
+use ent_proto::ent::{
+    import_schemas_request::Mode, schema_service_client::SchemaServiceClient, CreateSchemaRequest,
+    DeleteSchemaRequest, ExportSchemasRequest, GetSchemaRequest, ImportSchemasRequest,
+    ListSchemasRequest,
+};
 use std::path::PathBuf;
 use tonic::transport::Channel;
 
+use super::authorized;
+use super::output::{self, OutputFormat};
+
 #[derive(Args)]
 pub struct AdminCommands {
     #[command(subcommand)]
@@ -14,6 +21,16 @@ pub struct AdminCommands {
 pub enum AdminSubcommands {
     /// Create a new schema
     CreateSchema(CreateSchemaCommand),
+    /// Export every type's latest schema as a JSON bundle
+    ExportSchemas(ExportSchemasCommand),
+    /// Register the schemas of an exported bundle
+    ImportSchemas(ImportSchemasCommand),
+    /// List the latest schema of every registered type
+    ListSchemas,
+    /// Print the latest schema registered for a type
+    GetSchema(GetSchemaCommand),
+    /// Remove a type's schema, or deprecate it if objects of the type remain
+    DeleteSchema(DeleteSchemaCommand),
 }
 
 #[derive(Args)]
@@ -29,28 +46,228 @@ pub struct CreateSchemaCommand {
     /// Optional description of the schema
     #[arg(long, short)]
     pub description: Option<String>,
+
+    /// JSON Schema draft to validate under, e.g. 2020-12 or draft-07.
+    /// Detected from the schema's `$schema` when omitted
+    #[arg(long)]
+    pub draft: Option<String>,
+}
+
+#[derive(Args)]
+pub struct ExportSchemasCommand {
+    /// Write the bundle to this file instead of stdout
+    #[arg(long, short)]
+    pub output: Option<PathBuf>,
+}
+
+#[derive(Args)]
+pub struct ImportSchemasCommand {
+    /// Path to a bundle produced by export-schemas
+    #[arg(long, short)]
+    pub file: PathBuf,
+
+    /// Register bundled schemas for types that already exist instead of skipping them
+    #[arg(long)]
+    pub update_existing: bool,
+}
+
+#[derive(Args)]
+pub struct GetSchemaCommand {
+    /// Type name whose schema to print
+    #[arg(long)]
+    pub type_name: String,
 }
 
-pub async fn execute(cmd: AdminCommands, client: &mut SchemaServiceClient<Channel>) -> Result<()> {
+#[derive(Args)]
+pub struct DeleteSchemaCommand {
+    /// Type name whose schema to remove
+    #[arg(long)]
+    pub type_name: String,
+
+    /// Keep the schema and existing objects, but reject new objects of the type
+    #[arg(long)]
+    pub deprecate: bool,
+}
+
+pub async fn execute(
+    cmd: AdminCommands,
+    client: &mut SchemaServiceClient<Channel>,
+    auth: Option<String>,
+    format: OutputFormat,
+) -> Result<()> {
+    let auth = &auth;
     match cmd.command {
-        AdminSubcommands::CreateSchema(cmd) => create_schema(cmd, client).await,
+        AdminSubcommands::CreateSchema(cmd) => create_schema(cmd, client, auth).await,
+        AdminSubcommands::ExportSchemas(cmd) => export_schemas(cmd, client, auth).await,
+        AdminSubcommands::ImportSchemas(cmd) => import_schemas(cmd, client, auth).await,
+        AdminSubcommands::ListSchemas => list_schemas(client, auth, format).await,
+        AdminSubcommands::GetSchema(cmd) => get_schema(cmd, client, auth, format).await,
+        AdminSubcommands::DeleteSchema(cmd) => delete_schema(cmd, client, auth).await,
     }
 }
 
 async fn create_schema(
     cmd: CreateSchemaCommand,
     client: &mut SchemaServiceClient<Channel>,
+    auth: &Option<String>,
 ) -> Result<()> {
     let schema = std::fs::read_to_string(cmd.file)?;
 
-    let request = tonic::Request::new(CreateSchemaRequest {
-        schema: schema,
-        description: cmd.description.unwrap_or_default(),
-        type_name: cmd.type_name,
-    });
+    let request = authorized(
+        CreateSchemaRequest {
+            schema,
+            description: cmd.description.unwrap_or_default(),
+            type_name: cmd.type_name,
+            draft: cmd.draft.unwrap_or_default(),
+        },
+        auth,
+    )?;
+
+    let response = client.create_schema(request).await?.into_inner();
+    println!("Created schema with ID: {}", response.schema_id);
+
+    // Show the schema the way the server interpreted and stored it
+    let schema: serde_json::Value = serde_json::from_str(&response.schema)?;
+    println!("{}", serde_json::to_string_pretty(&schema)?);
+
+    Ok(())
+}
+
+async fn export_schemas(
+    cmd: ExportSchemasCommand,
+    client: &mut SchemaServiceClient<Channel>,
+    auth: &Option<String>,
+) -> Result<()> {
+    let request = authorized(ExportSchemasRequest {}, auth)?;
+
+    let response = client.export_schemas(request).await?;
+    let bundle = &response.get_ref().bundle;
+    match cmd.output {
+        Some(path) => std::fs::write(path, bundle)?,
+        None => println!("{}", bundle),
+    }
+
+    Ok(())
+}
+
+async fn import_schemas(
+    cmd: ImportSchemasCommand,
+    client: &mut SchemaServiceClient<Channel>,
+    auth: &Option<String>,
+) -> Result<()> {
+    let bundle = std::fs::read_to_string(cmd.file)?;
+    let mode = if cmd.update_existing {
+        Mode::UpdateExisting
+    } else {
+        Mode::SkipExisting
+    };
+
+    let request = authorized(
+        ImportSchemasRequest {
+            bundle,
+            mode: mode as i32,
+        },
+        auth,
+    )?;
 
-    let response = client.create_schema(request).await?;
-    println!("Created schema with ID: {}", response.get_ref().schema_id);
+    let response = client.import_schemas(request).await?;
+    let response = response.get_ref();
+    println!(
+        "Created: {}, updated: {}, skipped: {}",
+        response.created.len(),
+        response.updated.len(),
+        response.skipped.len()
+    );
+
+    Ok(())
+}
+
+async fn list_schemas(
+    client: &mut SchemaServiceClient<Channel>,
+    auth: &Option<String>,
+    format: OutputFormat,
+) -> Result<()> {
+    let request = authorized(ListSchemasRequest {}, auth)?;
+
+    let response = client.list_schemas(request).await?.into_inner();
+    if format == OutputFormat::Json {
+        return output::print(format, &response);
+    }
+
+    let id_width = response
+        .schemas
+        .iter()
+        .map(|schema| schema.id.to_string().len())
+        .chain(["ID".len()])
+        .max()
+        .unwrap_or_default();
+    let type_width = response
+        .schemas
+        .iter()
+        .map(|schema| schema.type_name.len())
+        .chain(["TYPE NAME".len()])
+        .max()
+        .unwrap_or_default();
+
+    println!(
+        "{:<id_width$}  {:<type_width$}  DESCRIPTION",
+        "ID", "TYPE NAME"
+    );
+    for schema in &response.schemas {
+        println!(
+            "{:<id_width$}  {:<type_width$}  {}",
+            schema.id, schema.type_name, schema.description
+        );
+    }
+
+    Ok(())
+}
+
+async fn get_schema(
+    cmd: GetSchemaCommand,
+    client: &mut SchemaServiceClient<Channel>,
+    auth: &Option<String>,
+    format: OutputFormat,
+) -> Result<()> {
+    let request = authorized(
+        GetSchemaRequest {
+            type_name: cmd.type_name,
+        },
+        auth,
+    )?;
+
+    let response = client.get_schema(request).await?.into_inner();
+    if format == OutputFormat::Json {
+        return output::print(format, &response);
+    }
+
+    if let Some(schema) = response.schema {
+        let schema: serde_json::Value = serde_json::from_str(&schema.schema)?;
+        println!("{}", serde_json::to_string_pretty(&schema)?);
+    }
+
+    Ok(())
+}
+
+async fn delete_schema(
+    cmd: DeleteSchemaCommand,
+    client: &mut SchemaServiceClient<Channel>,
+    auth: &Option<String>,
+) -> Result<()> {
+    let request = authorized(
+        DeleteSchemaRequest {
+            type_name: cmd.type_name.clone(),
+            deprecate: cmd.deprecate,
+        },
+        auth,
+    )?;
+
+    let response = client.delete_schema(request).await?.into_inner();
+    if response.deprecated {
+        println!("Deprecated schema for type: {}", cmd.type_name);
+    } else {
+        println!("Deleted schema for type: {}", cmd.type_name);
+    }
 
     Ok(())
 }
@@ -1,7 +1,9 @@
 use anyhow::Result;
-use clap::Args;
+use clap::{Args, ValueEnum};
 use ent_proto::ent::{
-    graph_service_client::GraphServiceClient, CreateEdgeRequest, GetEdgeRequest, GetEdgesRequest,
+    graph_service_client::GraphServiceClient, CreateEdgeRequest, DeleteEdgeRequest,
+    EdgeMetadataFilter, EdgeOrder, GetEdgeByIdRequest, GetEdgeRequest, GetEdgesRequest,
+    GetRelatedObjectsRequest, ListRelationsRequest,
 };
 use ent_server::auth::RequestExt;
 use prost_types::Struct;
@@ -10,7 +12,8 @@ use std::fs;
 use std::path::PathBuf;
 use tonic::transport::Channel;
 
-use super::object::{json_value_to_prost_value, parse_consistency};
+use super::object::{confirm, json_value_to_prost_value, parse_consistency};
+use super::output::{self, OutputFormat};
 
 #[derive(Args)]
 pub struct GetEdgeCommand {
@@ -22,9 +25,55 @@ pub struct GetEdgeCommand {
     #[arg(long, short)]
     pub edge_type: String,
 
-    /// Optional consistency requirement
+    /// Optional consistency requirement (`full` or `minimum`)
     #[arg(long)]
     pub consistency: Option<String>,
+
+    /// Read at least as fresh as this zookie, e.g. one returned by a write
+    #[arg(long, conflicts_with = "consistency")]
+    pub zookie: Option<String>,
+
+    /// Read the graph as it was at this RFC 3339 time, e.g. 2024-05-07T00:00:00Z
+    #[arg(long, conflicts_with_all = ["consistency", "zookie"])]
+    pub at: Option<String>,
+}
+
+#[derive(Args)]
+pub struct GetEdgeByIdCommand {
+    /// Edge ID to retrieve
+    #[arg(long)]
+    pub edge_id: i64,
+
+    /// Optional consistency requirement (`full` or `minimum`)
+    #[arg(long)]
+    pub consistency: Option<String>,
+
+    /// Read at least as fresh as this zookie, e.g. one returned by a write
+    #[arg(long, conflicts_with = "consistency")]
+    pub zookie: Option<String>,
+
+    /// Read the graph as it was at this RFC 3339 time, e.g. 2024-05-07T00:00:00Z
+    #[arg(long, conflicts_with_all = ["consistency", "zookie"])]
+    pub at: Option<String>,
+}
+
+#[derive(Args)]
+pub struct ListRelationsCommand {
+    /// Source object ID
+    #[arg(long)]
+    pub object_id: i64,
+
+    /// Optional consistency requirement (`full` or `minimum`)
+    #[arg(long)]
+    pub consistency: Option<String>,
+
+    /// Read at least as fresh as this zookie, e.g. one returned by a write
+    #[arg(long, conflicts_with = "consistency")]
+    pub zookie: Option<String>,
+
+    /// Read the graph as it was at this RFC 3339 time, e.g. 2024-05-07T00:00:00Z
+    #[arg(long, conflicts_with_all = ["consistency", "zookie"])]
+    pub at: Option<String>,
 }
 
 #[derive(Args)]
@@ -37,9 +86,75 @@ pub struct GetEdgesCommand {
     #[arg(long, short)]
     pub edge_type: String,
 
-    /// Optional consistency requirement  
+    /// Optional consistency requirement (`full` or `minimum`)
+    #[arg(long)]
+    pub consistency: Option<String>,
+
+    /// Read at least as fresh as this zookie, e.g. one returned by a write
+    #[arg(long, conflicts_with = "consistency")]
+    pub zookie: Option<String>,
+
+    /// Read the graph as it was at this RFC 3339 time, e.g. 2024-05-07T00:00:00Z
+    #[arg(long, conflicts_with_all = ["consistency", "zookie"])]
+    pub at: Option<String>,
+
+    /// Only return edges whose metadata contains this JSON document
+    #[arg(long)]
+    pub contains: Option<String>,
+
+    /// Only return edges whose metadata has this key set to `--equals`
+    #[arg(long, requires = "equals")]
+    pub key: Option<String>,
+
+    /// Value the metadata key given by `--key` must equal
+    #[arg(long, requires = "key")]
+    pub equals: Option<String>,
+
+    /// Compare `--equals` with the metadata value without regard to case
+    #[arg(long, requires = "equals")]
+    pub ignore_case: bool,
+
+    /// Order of the returned edges
+    #[arg(long, value_enum, default_value_t = EdgesOrder::Id)]
+    pub order: EdgesOrder,
+
+    /// Return at most this many edges
+    #[arg(long, value_parser = clap::value_parser!(i32).range(1..))]
+    pub limit: Option<i32>,
+}
+
+/// Order of the edges returned by `get-edges`
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum EdgesOrder {
+    /// Edge ID, oldest edge first
+    Id,
+    /// Creation time, oldest first
+    Oldest,
+    /// Creation time, most recent first
+    Newest,
+}
+
+#[derive(Args)]
+pub struct GetRelatedCommand {
+    /// Source object ID
+    #[arg(long)]
+    pub object_id: i64,
+
+    /// Relation to follow
+    #[arg(long, short)]
+    pub relation: String,
+
+    /// Optional consistency requirement (`full` or `minimum`)
     #[arg(long)]
     pub consistency: Option<String>,
+
+    /// Read at least as fresh as this zookie, e.g. one returned by a write
+    #[arg(long, conflicts_with = "consistency")]
+    pub zookie: Option<String>,
+
+    /// Read the graph as it was at this RFC 3339 time, e.g. 2024-05-07T00:00:00Z
+    #[arg(long, conflicts_with_all = ["consistency", "zookie"])]
+    pub at: Option<String>,
 }
 
 #[derive(Args)]
@@ -69,17 +184,29 @@ pub struct CreateEdgeCommand {
     pub metadata_file: Option<PathBuf>,
 }
 
+#[derive(Args)]
+pub struct DeleteEdgeCommand {
+    /// Edge ID to delete
+    #[arg(long)]
+    pub edge_id: i64,
+
+    /// Skip the confirmation prompt
+    #[arg(long, short)]
+    pub yes: bool,
+}
+
 pub async fn execute_get_edge(
     cmd: GetEdgeCommand,
     client: &mut GraphServiceClient<Channel>,
     auth: Option<String>,
+    format: OutputFormat,
 ) -> Result<()> {
-    let _consistency = parse_consistency(cmd.consistency)?;
+    let consistency = parse_consistency(cmd.consistency, cmd.zookie, cmd.at)?;
 
     let request = tonic::Request::new(GetEdgeRequest {
         object_id: cmd.object_id,
         edge_type: cmd.edge_type,
-        consistency: None,
+        consistency,
     });
 
     let request = if let Some(token) = auth {
@@ -89,7 +216,63 @@ pub async fn execute_get_edge(
     };
 
     let response = client.get_edge(request).await?;
-    println!("{:#?}", response.get_ref());
+    output::print(format, response.get_ref())?;
+
+    Ok(())
+}
+
+pub async fn execute_get_edge_by_id(
+    cmd: GetEdgeByIdCommand,
+    client: &mut GraphServiceClient<Channel>,
+    auth: Option<String>,
+    format: OutputFormat,
+) -> Result<()> {
+    let consistency = parse_consistency(cmd.consistency, cmd.zookie, cmd.at)?;
+
+    let request = tonic::Request::new(GetEdgeByIdRequest {
+        edge_id: cmd.edge_id,
+        consistency,
+    });
+
+    let request = if let Some(token) = auth {
+        request.with_bearer_token(&token)?
+    } else {
+        request
+    };
+
+    let response = client.get_edge_by_id(request).await?;
+    output::print(format, response.get_ref())?;
+
+    Ok(())
+}
+
+pub async fn execute_list_relations(
+    cmd: ListRelationsCommand,
+    client: &mut GraphServiceClient<Channel>,
+    auth: Option<String>,
+    format: OutputFormat,
+) -> Result<()> {
+    let consistency = parse_consistency(cmd.consistency, cmd.zookie, cmd.at)?;
+
+    let request = tonic::Request::new(ListRelationsRequest {
+        object_id: cmd.object_id,
+        consistency,
+    });
+
+    let request = if let Some(token) = auth {
+        request.with_bearer_token(&token)?
+    } else {
+        request
+    };
+
+    let response = client.list_relations(request).await?.into_inner();
+    if format == OutputFormat::Json {
+        return output::print(format, &response);
+    }
+
+    for relation in &response.relations {
+        println!("{}\t{}", relation.relation, relation.count);
+    }
 
     Ok(())
 }
@@ -98,13 +281,50 @@ pub async fn execute_get_edges(
     cmd: GetEdgesCommand,
     client: &mut GraphServiceClient<Channel>,
     auth: Option<String>,
+    format: OutputFormat,
 ) -> Result<()> {
-    let _consistency = parse_consistency(cmd.consistency)?;
+    let consistency = parse_consistency(cmd.consistency, cmd.zookie, cmd.at)?;
+
+    let contains = match cmd.contains {
+        Some(contains) => {
+            let contains_json: JsonValue = serde_json::from_str(&contains)?;
+
+            let mut contains_struct = Struct::default();
+            if let JsonValue::Object(map) = contains_json {
+                for (k, v) in map {
+                    contains_struct
+                        .fields
+                        .insert(k, json_value_to_prost_value(v));
+                }
+            }
+            Some(contains_struct)
+        }
+        None => None,
+    };
+
+    let metadata_filter = if contains.is_some() || cmd.key.is_some() {
+        Some(EdgeMetadataFilter {
+            contains,
+            key: cmd.key.unwrap_or_default(),
+            equals: cmd.equals.unwrap_or_default(),
+            ignore_case: cmd.ignore_case,
+        })
+    } else {
+        None
+    };
 
+    let order_by = match cmd.order {
+        EdgesOrder::Id => EdgeOrder::Id,
+        EdgesOrder::Oldest => EdgeOrder::CreatedAtAsc,
+        EdgesOrder::Newest => EdgeOrder::CreatedAtDesc,
+    };
     let request = tonic::Request::new(GetEdgesRequest {
         object_id: cmd.object_id,
         edge_type: cmd.edge_type,
-        consistency: None,
+        consistency,
+        metadata_filter,
+        order_by: order_by as i32,
+        limit: cmd.limit.unwrap_or(0),
     });
 
     let request = if let Some(token) = auth {
@@ -114,7 +334,33 @@ pub async fn execute_get_edges(
     };
 
     let response = client.get_edges(request).await?;
-    println!("{:#?}", response.get_ref());
+    output::print(format, response.get_ref())?;
+
+    Ok(())
+}
+
+pub async fn execute_get_related(
+    cmd: GetRelatedCommand,
+    client: &mut GraphServiceClient<Channel>,
+    auth: Option<String>,
+    format: OutputFormat,
+) -> Result<()> {
+    let consistency = parse_consistency(cmd.consistency, cmd.zookie, cmd.at)?;
+
+    let request = tonic::Request::new(GetRelatedObjectsRequest {
+        object_id: cmd.object_id,
+        relation: cmd.relation,
+        consistency,
+    });
+
+    let request = if let Some(token) = auth {
+        request.with_bearer_token(&token)?
+    } else {
+        request
+    };
+
+    let response = client.get_related_objects(request).await?;
+    output::print(format, response.get_ref())?;
 
     Ok(())
 }
@@ -123,6 +369,7 @@ pub async fn execute_create_edge(
     cmd: CreateEdgeCommand,
     client: &mut GraphServiceClient<Channel>,
     auth: Option<String>,
+    format: OutputFormat,
 ) -> Result<()> {
     let metadata = if let Some(path) = cmd.metadata_file {
         let metadata_json: JsonValue = serde_json::from_str(&fs::read_to_string(path)?)?;
@@ -156,7 +403,41 @@ pub async fn execute_create_edge(
     };
 
     let response = client.create_edge(request).await?;
-    println!("{:#?}", response.get_ref());
+    output::print(format, response.get_ref())?;
+
+    Ok(())
+}
+
+pub async fn execute_delete_edge(
+    cmd: DeleteEdgeCommand,
+    client: &mut GraphServiceClient<Channel>,
+    auth: Option<String>,
+    format: OutputFormat,
+) -> Result<()> {
+    if !cmd.yes && !confirm(&format!("Delete edge {}?", cmd.edge_id))? {
+        println!("Aborted");
+        return Ok(());
+    }
+
+    let request = tonic::Request::new(DeleteEdgeRequest {
+        edge_id: cmd.edge_id,
+    });
+
+    let request = if let Some(token) = auth {
+        request.with_bearer_token(&token)?
+    } else {
+        request
+    };
+
+    let response = client.delete_edge(request).await?;
+    if format == OutputFormat::Json {
+        return output::print(format, response.get_ref());
+    }
+
+    println!("Deleted edge {}", cmd.edge_id);
+    if let Some(revision) = &response.get_ref().revision {
+        println!("Revision: {}", revision.value);
+    }
 
     Ok(())
 }
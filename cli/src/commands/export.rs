@@ -0,0 +1,80 @@
+use anyhow::Result;
+use clap::Args;
+use ent_proto::ent::{
+    export_graph_response::Record, graph_service_client::GraphServiceClient, ExportGraphRequest,
+};
+use ent_server::auth::RequestExt;
+use serde_json::Value as JsonValue;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::PathBuf;
+use tonic::transport::Channel;
+
+use super::output::ToJson;
+
+#[derive(Args)]
+pub struct ExportCommand {
+    /// Write the NDJSON dump to this file instead of stdout
+    #[arg(long, short)]
+    pub output: Option<PathBuf>,
+}
+
+/// One NDJSON line: the record's JSON form tagged with its `kind`
+fn tagged(kind: &str, mut record: JsonValue) -> JsonValue {
+    if let JsonValue::Object(fields) = &mut record {
+        // Edges are exported at the dump's revision, not their own
+        fields.remove("revision");
+        fields.insert("kind".to_string(), JsonValue::String(kind.to_string()));
+    }
+    record
+}
+
+pub async fn execute_export(
+    cmd: ExportCommand,
+    client: &mut GraphServiceClient<Channel>,
+    auth: Option<String>,
+) -> Result<()> {
+    let request = tonic::Request::new(ExportGraphRequest {});
+
+    let request = if let Some(token) = auth {
+        request.with_bearer_token(&token)?
+    } else {
+        request
+    };
+
+    let mut out: Box<dyn Write> = match cmd.output {
+        Some(path) => Box::new(BufWriter::new(File::create(path)?)),
+        None => Box::new(BufWriter::new(io::stdout().lock())),
+    };
+
+    let mut stream = client.export_graph(request).await?.into_inner();
+    let (mut objects, mut edges, mut revision) = (0, 0, None);
+    while let Some(response) = stream.message().await? {
+        let line = match response.record {
+            Some(Record::Revision(zookie)) => {
+                revision = Some(zookie.value);
+                continue;
+            }
+            Some(Record::Object(object)) => {
+                objects += 1;
+                tagged("object", object.to_json())
+            }
+            Some(Record::Edge(edge)) => {
+                edges += 1;
+                tagged("edge", edge.to_json())
+            }
+            None => continue,
+        };
+        writeln!(out, "{}", serde_json::to_string(&line)?)?;
+    }
+    out.flush()?;
+
+    eprintln!(
+        "Exported {} objects and {} edges at revision {}",
+        objects,
+        edges,
+        revision.unwrap_or_default()
+    );
+
+    Ok(())
+}
@@ -0,0 +1,275 @@
+use anyhow::Result;
+use clap::Args;
+use ent_proto::ent::{
+    graph_service_client::GraphServiceClient, schema_service_client::SchemaServiceClient,
+    BatchCreateEdgesRequest, BatchCreateObjectsRequest, CreateEdgeRequest, CreateObjectRequest,
+    GetSchemaRequest,
+};
+use ent_server::db::schema::{apply_computed_fields, computed_fields, schema_violations};
+use ent_server::server::json_value_to_prost_value;
+use prost_types::Struct;
+use serde::Deserialize;
+use serde_json::Value as JsonValue;
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+use std::path::PathBuf;
+use tonic::transport::Channel;
+
+use super::authorized;
+
+#[derive(Args)]
+pub struct ImportCommand {
+    /// NDJSON dump produced by `ent export`
+    #[arg(long, short)]
+    pub file: PathBuf,
+
+    /// How many rows to create per request. A request that fails creates none of its rows
+    #[arg(long, default_value_t = 100, value_parser = clap::value_parser!(u32).range(1..=1000))]
+    pub batch_size: u32,
+}
+
+/// One line of an export
+#[derive(Deserialize)]
+#[serde(tag = "kind", rename_all = "lowercase")]
+enum Record {
+    Object {
+        id: i64,
+        #[serde(rename = "type")]
+        type_name: String,
+        #[serde(default)]
+        metadata: JsonValue,
+    },
+    Edge(ExportedEdge),
+}
+
+#[derive(Deserialize)]
+struct ExportedEdge {
+    from_id: i64,
+    from_type: String,
+    to_id: i64,
+    to_type: String,
+    relation: String,
+    #[serde(default)]
+    metadata: JsonValue,
+}
+
+/// A type's latest schema and the draft it is validated under
+struct TypeSchema {
+    schema: JsonValue,
+    draft: Option<String>,
+}
+
+#[derive(Default)]
+struct Summary {
+    created: usize,
+    failed: usize,
+}
+
+fn to_struct(metadata: JsonValue) -> Option<Struct> {
+    match json_value_to_prost_value(metadata).kind {
+        Some(prost_types::value::Kind::StructValue(metadata)) => Some(metadata),
+        _ => None,
+    }
+}
+
+/// The latest schema of `type_name`, or `None` if the type has none or the
+/// caller may not read schemas. Without one, objects go to the server as they
+/// are and its own checks report any that fail
+async fn fetch_schema(
+    client: &mut SchemaServiceClient<Channel>,
+    auth: &Option<String>,
+    type_name: &str,
+) -> Result<Option<TypeSchema>> {
+    let request = authorized(
+        GetSchemaRequest {
+            type_name: type_name.to_string(),
+        },
+        auth,
+    )?;
+
+    match client.get_schema(request).await {
+        Ok(response) => Ok(response
+            .into_inner()
+            .schema
+            .map(|schema| {
+                Ok::<_, serde_json::Error>(TypeSchema {
+                    schema: serde_json::from_str(&schema.schema)?,
+                    draft: (!schema.draft.is_empty()).then_some(schema.draft),
+                })
+            })
+            .transpose()?),
+        Err(status)
+            if matches!(
+                status.code(),
+                tonic::Code::NotFound
+                    | tonic::Code::PermissionDenied
+                    | tonic::Code::Unauthenticated
+            ) =>
+        {
+            Ok(None)
+        }
+        Err(status) => Err(status.into()),
+    }
+}
+
+/// Drops the exported values of computed fields, which the server derives
+/// again, and checks what remains against the schema
+fn prepare_metadata(schema: &TypeSchema, metadata: &mut JsonValue) -> Result<Vec<String>> {
+    if let JsonValue::Object(fields) = metadata {
+        for (name, _) in computed_fields(&schema.schema)? {
+            fields.remove(&name);
+        }
+    }
+
+    let mut derived = metadata.clone();
+    apply_computed_fields(&schema.schema, &mut derived)?;
+    schema_violations(&schema.schema, schema.draft.as_deref(), &derived)
+}
+
+pub async fn execute_import(
+    cmd: ImportCommand,
+    client: &mut GraphServiceClient<Channel>,
+    schema_client: &mut SchemaServiceClient<Channel>,
+    auth: Option<String>,
+) -> Result<()> {
+    let batch_size = cmd.batch_size as usize;
+    let mut objects = Vec::new();
+    let mut edges = Vec::new();
+    let mut unreadable = 0;
+
+    for (index, line) in BufReader::new(File::open(&cmd.file)?).lines().enumerate() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        match serde_json::from_str::<Record>(&line) {
+            Ok(Record::Object {
+                id,
+                type_name,
+                metadata,
+            }) => objects.push((index + 1, id, type_name, metadata)),
+            Ok(Record::Edge(edge)) => edges.push((index + 1, edge)),
+            Err(e) => {
+                eprintln!("line {}: {}", index + 1, e);
+                unreadable += 1;
+            }
+        }
+    }
+
+    // Validate objects up front so one bad row doesn't sink a whole batch
+    let mut object_summary = Summary::default();
+    let mut schemas: HashMap<String, Option<TypeSchema>> = HashMap::new();
+    let mut valid = Vec::with_capacity(objects.len());
+    for (line, id, type_name, mut metadata) in objects {
+        if !schemas.contains_key(&type_name) {
+            let schema = fetch_schema(schema_client, &auth, &type_name).await?;
+            schemas.insert(type_name.clone(), schema);
+        }
+
+        if let Some(schema) = &schemas[&type_name] {
+            match prepare_metadata(schema, &mut metadata) {
+                Ok(errors) if errors.is_empty() => {}
+                Ok(errors) => {
+                    eprintln!("line {}: {}", line, errors.join("; "));
+                    object_summary.failed += 1;
+                    continue;
+                }
+                Err(e) => {
+                    eprintln!("line {}: {}", line, e);
+                    object_summary.failed += 1;
+                    continue;
+                }
+            }
+        }
+
+        let request = CreateObjectRequest {
+            r#type: type_name,
+            metadata: to_struct(metadata),
+        };
+        valid.push((line, id, request));
+    }
+
+    // Exported ids mapped to the ids the objects were created with
+    let mut ids = HashMap::new();
+    for batch in valid.chunks(batch_size) {
+        let request = authorized(
+            BatchCreateObjectsRequest {
+                objects: batch.iter().map(|(_, _, object)| object.clone()).collect(),
+                upsert: false,
+            },
+            &auth,
+        )?;
+
+        match client.batch_create_objects(request).await {
+            Ok(response) => {
+                let created = response.into_inner().objects;
+                for ((_, exported_id, _), object) in batch.iter().zip(&created) {
+                    ids.insert(*exported_id, object.id);
+                }
+                object_summary.created += created.len();
+            }
+            Err(status) => {
+                for (line, _, _) in batch {
+                    eprintln!("line {}: {}", line, status.message());
+                }
+                object_summary.failed += batch.len();
+            }
+        }
+    }
+
+    let mut edge_summary = Summary::default();
+    let mut remapped = Vec::with_capacity(edges.len());
+    for (line, edge) in edges {
+        let (Some(&new_from_id), Some(&new_to_id)) = (ids.get(&edge.from_id), ids.get(&edge.to_id))
+        else {
+            eprintln!(
+                "line {}: edge endpoints {} and {} were not both imported",
+                line, edge.from_id, edge.to_id
+            );
+            edge_summary.failed += 1;
+            continue;
+        };
+
+        let request = CreateEdgeRequest {
+            from_id: new_from_id,
+            from_type: edge.from_type,
+            to_id: new_to_id,
+            to_type: edge.to_type,
+            relation: edge.relation,
+            metadata: to_struct(edge.metadata),
+        };
+        remapped.push((line, request));
+    }
+
+    for batch in remapped.chunks(batch_size) {
+        let request = authorized(
+            BatchCreateEdgesRequest {
+                edges: batch.iter().map(|(_, edge)| edge.clone()).collect(),
+                skip_existing: false,
+            },
+            &auth,
+        )?;
+
+        match client.batch_create_edges(request).await {
+            Ok(response) => edge_summary.created += response.into_inner().edges.len(),
+            Err(status) => {
+                for (line, _) in batch {
+                    eprintln!("line {}: {}", line, status.message());
+                }
+                edge_summary.failed += batch.len();
+            }
+        }
+    }
+
+    println!(
+        "Objects: {} created, {} failed. Edges: {} created, {} failed. Unreadable lines: {}",
+        object_summary.created,
+        object_summary.failed,
+        edge_summary.created,
+        edge_summary.failed,
+        unreadable
+    );
+
+    Ok(())
+}
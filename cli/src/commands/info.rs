@@ -0,0 +1,25 @@
+use anyhow::Result;
+use ent_proto::ent::{graph_service_client::GraphServiceClient, ServerInfoRequest};
+use tonic::transport::Channel;
+
+use super::output::{self, OutputFormat};
+
+pub async fn execute_server_info(
+    client: &mut GraphServiceClient<Channel>,
+    format: OutputFormat,
+) -> Result<()> {
+    let response = client
+        .server_info(tonic::Request::new(ServerInfoRequest {}))
+        .await?;
+    let info = response.get_ref();
+
+    let cli_version = env!("CARGO_PKG_VERSION");
+    if info.version != cli_version {
+        eprintln!(
+            "warning: this CLI is version {} but the server is version {}",
+            cli_version, info.version
+        );
+    }
+
+    output::print(format, info)
+}
@@ -1,24 +1,20 @@
-use clap::{Parser, Subcommand};
+use anyhow::Result;
+use clap::Subcommand;
+use ent_proto::ent::{
+    graph_service_client::GraphServiceClient, schema_service_client::SchemaServiceClient,
+};
+use ent_server::auth::RequestExt;
+use tonic::transport::Channel;
+
+use output::OutputFormat;
 
 pub mod admin;
 pub mod edge;
+pub mod export;
+pub mod import;
+pub mod info;
 pub mod object;
-
-#[derive(Parser)]
-#[command(name = "ent")]
-#[command(about = "CLI for Ent graph database", long_about = None)]
-pub struct Cli {
-    #[command(subcommand)]
-    pub command: Commands,
-
-    /// The endpoint to connect to
-    #[arg(long, default_value = "http2://127.0.0.1:50051")]
-    pub endpoint: String,
-
-    /// The authentication token
-    #[arg(long)]
-    pub auth: Option<String>,
-}
+pub mod output;
 
 #[derive(Subcommand)]
 pub enum Commands {
@@ -31,12 +27,93 @@ pub enum Commands {
     /// Get an edge from an object
     GetEdge(edge::GetEdgeCommand),
 
+    /// Get an edge by its ID
+    GetEdgeById(edge::GetEdgeByIdCommand),
+
+    /// List the relations leaving an object, with edge counts
+    ListRelations(edge::ListRelationsCommand),
+
     /// Get multiple edges from an object
     GetEdges(edge::GetEdgesCommand),
 
+    /// Get the objects an object points at through a relation
+    GetRelated(edge::GetRelatedCommand),
+
     /// Create a new object
     CreateObject(object::CreateObjectCommand),
 
     /// Create a new edge
     CreateEdge(edge::CreateEdgeCommand),
+
+    /// Create an object, or update the one with the same x-key value
+    UpsertObject(object::UpsertObjectCommand),
+
+    /// Replace an object's metadata
+    UpdateObject(object::UpdateObjectCommand),
+
+    /// Delete an object and its edges
+    DeleteObject(object::DeleteObjectCommand),
+
+    /// Delete an edge
+    DeleteEdge(edge::DeleteEdgeCommand),
+
+    /// Grant another user read or write access to an object you own
+    ShareObject(object::ShareObjectCommand),
+
+    /// Show the server's version and capabilities
+    ServerInfo,
+
+    /// Dump all of your objects and edges as NDJSON at one revision
+    Export(export::ExportCommand),
+
+    /// Recreate objects and edges from an NDJSON export
+    Import(import::ImportCommand),
+}
+
+/// Route a parsed command to its handler
+pub async fn execute(
+    command: Commands,
+    client: &mut GraphServiceClient<Channel>,
+    schema_client: &mut SchemaServiceClient<Channel>,
+    auth: Option<String>,
+    format: OutputFormat,
+) -> Result<()> {
+    match command {
+        Commands::Admin(cmd) => admin::execute(cmd, schema_client, auth, format).await,
+        Commands::GetObject(cmd) => object::execute_get_object(cmd, client, auth, format).await,
+        Commands::GetEdge(cmd) => edge::execute_get_edge(cmd, client, auth, format).await,
+        Commands::GetEdgeById(cmd) => edge::execute_get_edge_by_id(cmd, client, auth, format).await,
+        Commands::ListRelations(cmd) => {
+            edge::execute_list_relations(cmd, client, auth, format).await
+        }
+        Commands::GetEdges(cmd) => edge::execute_get_edges(cmd, client, auth, format).await,
+        Commands::GetRelated(cmd) => edge::execute_get_related(cmd, client, auth, format).await,
+        Commands::CreateObject(cmd) => {
+            object::execute_create_object(cmd, client, auth, format).await
+        }
+        Commands::CreateEdge(cmd) => edge::execute_create_edge(cmd, client, auth, format).await,
+        Commands::UpsertObject(cmd) => {
+            object::execute_upsert_object(cmd, client, auth, format).await
+        }
+        Commands::UpdateObject(cmd) => {
+            object::execute_update_object(cmd, client, auth, format).await
+        }
+        Commands::DeleteObject(cmd) => {
+            object::execute_delete_object(cmd, client, auth, format).await
+        }
+        Commands::DeleteEdge(cmd) => edge::execute_delete_edge(cmd, client, auth, format).await,
+        Commands::ShareObject(cmd) => object::execute_share_object(cmd, client, auth, format).await,
+        Commands::ServerInfo => info::execute_server_info(client, format).await,
+        Commands::Export(cmd) => export::execute_export(cmd, client, auth).await,
+        Commands::Import(cmd) => import::execute_import(cmd, client, schema_client, auth).await,
+    }
+}
+
+/// A request carrying the bearer token, if one was given
+fn authorized<T>(message: T, auth: &Option<String>) -> Result<tonic::Request<T>> {
+    let request = tonic::Request::new(message);
+    Ok(match auth {
+        Some(token) => request.with_bearer_token(token)?,
+        None => request,
+    })
 }
@@ -1,25 +1,44 @@
 use anyhow::Result;
-use clap::Args;
+use clap::{Args, ValueEnum};
 use ent_proto::ent::{
     consistency_requirement::Requirement, graph_service_client::GraphServiceClient,
-    ConsistencyRequirement, CreateObjectRequest, GetObjectRequest,
+    ConsistencyRequirement, CreateObjectRequest, DeleteObjectRequest, GetObjectRequest, Permission,
+    ShareObjectRequest, UpdateObjectRequest, UpsertObjectRequest, Zookie,
 };
 use ent_server::auth::RequestExt;
-use prost_types::{Struct, Value as ProstValue};
-use serde_json::Value as JsonValue;
+pub(super) use ent_server::server::json_value_to_prost_value;
+use prost_types::Struct;
+use serde_json::{json, Value as JsonValue};
 use std::fs;
+use std::io::{self, BufRead, Write};
 use std::path::PathBuf;
+use time::format_description::well_known::Rfc3339;
+use time::OffsetDateTime;
 use tonic::transport::Channel;
 
+use super::output::{self, OutputFormat};
+
 #[derive(Args)]
 pub struct GetObjectCommand {
     /// Object ID to retrieve
     #[arg(long)]
     pub object_id: i64,
 
-    /// Optional consistency requirement
+    /// Optional consistency requirement (`full` or `minimum`)
     #[arg(long)]
     pub consistency: Option<String>,
+
+    /// Read at least as fresh as this zookie, e.g. one returned by a write
+    #[arg(long, conflicts_with = "consistency")]
+    pub zookie: Option<String>,
+
+    /// Read the graph as it was at this RFC 3339 time, e.g. 2024-05-07T00:00:00Z
+    #[arg(long, conflicts_with_all = ["consistency", "zookie"])]
+    pub at: Option<String>,
+
+    /// Check the object's metadata against its type's current schema
+    #[arg(long)]
+    pub validate: bool,
 }
 
 #[derive(Args)]
@@ -33,16 +52,83 @@ pub struct CreateObjectCommand {
     pub r#type: String,
 }
 
-pub async fn execute(
+#[derive(Args)]
+pub struct UpsertObjectCommand {
+    /// Path to JSON file containing object metadata, including the type's x-key field
+    #[arg(long, short)]
+    pub file: PathBuf,
+
+    /// Type of object to create or update
+    #[arg(long, short)]
+    pub r#type: String,
+}
+
+#[derive(Args)]
+pub struct UpdateObjectCommand {
+    /// Object ID to update
+    #[arg(long)]
+    pub object_id: i64,
+
+    /// Path to JSON file containing the new object metadata
+    #[arg(long, short)]
+    pub file: PathBuf,
+
+    /// Treat the file as a JSON Merge Patch (RFC 7386) of the current metadata
+    #[arg(long)]
+    pub merge_patch: bool,
+
+    /// Only update if the object hasn't changed since this zookie
+    #[arg(long)]
+    pub expected_revision: Option<String>,
+}
+
+#[derive(Args)]
+pub struct DeleteObjectCommand {
+    /// Object ID to delete
+    #[arg(long)]
+    pub object_id: i64,
+
+    /// Skip the confirmation prompt
+    #[arg(long, short)]
+    pub yes: bool,
+}
+
+#[derive(Args)]
+pub struct ShareObjectCommand {
+    /// Object ID to share; you must own it
+    #[arg(long)]
+    pub object_id: i64,
+
+    /// User to grant access to
+    #[arg(long)]
+    pub grantee: String,
+
+    /// What the user may do with the object
+    #[arg(long, value_enum, default_value_t = SharePermission::Read)]
+    pub permission: SharePermission,
+}
+
+/// Access granted by `share-object`
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum SharePermission {
+    /// Read the object and its edges
+    Read,
+    /// Also update and delete the object and its edges
+    Write,
+}
+
+pub async fn execute_get_object(
     cmd: GetObjectCommand,
     client: &mut GraphServiceClient<Channel>,
     auth: Option<String>,
+    format: OutputFormat,
 ) -> Result<()> {
-    let _consistency = parse_consistency(cmd.consistency)?;
+    let consistency = parse_consistency(cmd.consistency, cmd.zookie, cmd.at)?;
 
     let request = tonic::Request::new(GetObjectRequest {
         object_id: cmd.object_id,
-        consistency: None,
+        consistency,
+        validate: cmd.validate,
     });
 
     let request = if let Some(token) = auth {
@@ -52,58 +138,16 @@ pub async fn execute(
     };
 
     let response = client.get_object(request).await?;
-    println!("{:#?}", response.get_ref());
+    output::print(format, response.get_ref())?;
 
     Ok(())
 }
 
-pub(super) fn json_value_to_prost_value(json_value: JsonValue) -> ProstValue {
-    match json_value {
-        JsonValue::Null => ProstValue {
-            kind: Some(prost_types::value::Kind::NullValue(0)),
-        },
-        JsonValue::Bool(b) => ProstValue {
-            kind: Some(prost_types::value::Kind::BoolValue(b)),
-        },
-        JsonValue::Number(n) => {
-            if let Some(f) = n.as_f64() {
-                ProstValue {
-                    kind: Some(prost_types::value::Kind::NumberValue(f)),
-                }
-            } else {
-                // Handle integers that don't fit in f64
-                ProstValue {
-                    kind: Some(prost_types::value::Kind::StringValue(n.to_string())),
-                }
-            }
-        }
-        JsonValue::String(s) => ProstValue {
-            kind: Some(prost_types::value::Kind::StringValue(s)),
-        },
-        JsonValue::Array(arr) => {
-            let values: Vec<ProstValue> = arr.into_iter().map(json_value_to_prost_value).collect();
-            ProstValue {
-                kind: Some(prost_types::value::Kind::ListValue(
-                    prost_types::ListValue { values },
-                )),
-            }
-        }
-        JsonValue::Object(map) => {
-            let mut fields = std::collections::BTreeMap::new();
-            for (k, v) in map {
-                fields.insert(k, json_value_to_prost_value(v));
-            }
-            ProstValue {
-                kind: Some(prost_types::value::Kind::StructValue(Struct { fields })),
-            }
-        }
-    }
-}
-
 pub async fn execute_create_object(
     cmd: CreateObjectCommand,
     client: &mut GraphServiceClient<Channel>,
     auth: Option<String>,
+    format: OutputFormat,
 ) -> Result<()> {
     let metadata_json: JsonValue = serde_json::from_str(&fs::read_to_string(cmd.file)?)?;
 
@@ -128,14 +172,204 @@ pub async fn execute_create_object(
     };
 
     let response = client.create_object(request).await?;
-    println!("{:#?}", response.get_ref());
+    output::print(format, response.get_ref())?;
+
+    Ok(())
+}
+
+pub async fn execute_upsert_object(
+    cmd: UpsertObjectCommand,
+    client: &mut GraphServiceClient<Channel>,
+    auth: Option<String>,
+    format: OutputFormat,
+) -> Result<()> {
+    let metadata_json: JsonValue = serde_json::from_str(&fs::read_to_string(cmd.file)?)?;
+
+    let mut metadata_struct = Struct::default();
+    if let JsonValue::Object(map) = metadata_json {
+        for (k, v) in map {
+            metadata_struct
+                .fields
+                .insert(k, json_value_to_prost_value(v));
+        }
+    }
+
+    let request = tonic::Request::new(UpsertObjectRequest {
+        r#type: cmd.r#type,
+        metadata: Some(metadata_struct),
+    });
+
+    let request = if let Some(token) = auth {
+        request.with_bearer_token(&token)?
+    } else {
+        request
+    };
+
+    let response = client.upsert_object(request).await?;
+    output::print(format, response.get_ref())?;
 
     Ok(())
 }
 
+pub async fn execute_update_object(
+    cmd: UpdateObjectCommand,
+    client: &mut GraphServiceClient<Channel>,
+    auth: Option<String>,
+    format: OutputFormat,
+) -> Result<()> {
+    let metadata_json: JsonValue = serde_json::from_str(&fs::read_to_string(cmd.file)?)?;
+
+    let mut metadata_struct = Struct::default();
+    if let JsonValue::Object(map) = metadata_json {
+        for (k, v) in map {
+            metadata_struct
+                .fields
+                .insert(k, json_value_to_prost_value(v));
+        }
+    }
+
+    let request = tonic::Request::new(UpdateObjectRequest {
+        object_id: cmd.object_id,
+        metadata: Some(metadata_struct),
+        merge_patch: cmd.merge_patch,
+        expected_revision: cmd.expected_revision.map(|value| Zookie { value }),
+    });
+
+    let request = if let Some(token) = auth {
+        request.with_bearer_token(&token)?
+    } else {
+        request
+    };
+
+    let response = client.update_object(request).await?;
+    output::print(format, response.get_ref())?;
+
+    // The zookie can be passed back to read at least this fresh
+    if format == OutputFormat::Pretty {
+        if let Some(revision) = &response.get_ref().revision {
+            println!("Revision: {}", revision.value);
+        }
+    }
+
+    Ok(())
+}
+
+pub async fn execute_delete_object(
+    cmd: DeleteObjectCommand,
+    client: &mut GraphServiceClient<Channel>,
+    auth: Option<String>,
+    format: OutputFormat,
+) -> Result<()> {
+    if !cmd.yes
+        && !confirm(&format!(
+            "Delete object {} and all of its edges?",
+            cmd.object_id
+        ))?
+    {
+        println!("Aborted");
+        return Ok(());
+    }
+
+    let request = tonic::Request::new(DeleteObjectRequest {
+        object_id: cmd.object_id,
+    });
+
+    let request = if let Some(token) = auth {
+        request.with_bearer_token(&token)?
+    } else {
+        request
+    };
+
+    let response = client.delete_object(request).await?;
+    if format == OutputFormat::Json {
+        return output::print(format, response.get_ref());
+    }
+
+    println!("Deleted object {}", cmd.object_id);
+    if let Some(revision) = &response.get_ref().revision {
+        println!("Revision: {}", revision.value);
+    }
+
+    Ok(())
+}
+
+pub async fn execute_share_object(
+    cmd: ShareObjectCommand,
+    client: &mut GraphServiceClient<Channel>,
+    auth: Option<String>,
+    format: OutputFormat,
+) -> Result<()> {
+    let permission = match cmd.permission {
+        SharePermission::Read => Permission::Read,
+        SharePermission::Write => Permission::Write,
+    };
+    let request = tonic::Request::new(ShareObjectRequest {
+        object_id: cmd.object_id,
+        grantee_user_id: cmd.grantee.clone(),
+        permission: permission as i32,
+    });
+
+    let request = if let Some(token) = auth {
+        request.with_bearer_token(&token)?
+    } else {
+        request
+    };
+
+    client.share_object(request).await?;
+    if format == OutputFormat::Json {
+        println!(
+            "{}",
+            json!({
+                "object_id": cmd.object_id,
+                "grantee": cmd.grantee,
+                "permission": permission.as_str_name(),
+            })
+        );
+        return Ok(());
+    }
+
+    println!(
+        "Shared object {} with {} ({})",
+        cmd.object_id,
+        cmd.grantee,
+        permission.as_str_name()
+    );
+
+    Ok(())
+}
+
+// Asks a yes/no question on the terminal; anything but "y" or "yes" is a no
+pub(super) fn confirm(prompt: &str) -> Result<bool> {
+    print!("{} [y/N] ", prompt);
+    io::stdout().flush()?;
+
+    let mut answer = String::new();
+    io::stdin().lock().read_line(&mut answer)?;
+
+    Ok(matches!(answer.trim().to_lowercase().as_str(), "y" | "yes"))
+}
+
 pub(super) fn parse_consistency(
     consistency: Option<String>,
+    zookie: Option<String>,
+    at: Option<String>,
 ) -> Result<Option<ConsistencyRequirement>> {
+    if let Some(value) = zookie {
+        return Ok(Some(ConsistencyRequirement {
+            requirement: Some(Requirement::AtLeastAsFresh(Zookie { value })),
+        }));
+    }
+    if let Some(at) = at {
+        let at = OffsetDateTime::parse(&at, &Rfc3339)
+            .map_err(|e| anyhow::anyhow!("Invalid --at time {:?}: {}", at, e))?;
+        return Ok(Some(ConsistencyRequirement {
+            requirement: Some(Requirement::AtTimestamp(prost_types::Timestamp {
+                seconds: at.unix_timestamp(),
+                nanos: at.nanosecond() as i32,
+            })),
+        }));
+    }
+
     match consistency {
         None => Ok(None),
         Some(c) => match c.as_str() {
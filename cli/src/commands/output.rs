@@ -0,0 +1,237 @@
+use anyhow::Result;
+use clap::ValueEnum;
+use ent_proto::ent::{
+    CreateEdgeResponse, CreateObjectResponse, DeleteEdgeResponse, DeleteObjectResponse, Edge,
+    GetEdgeByIdResponse, GetEdgeResponse, GetEdgesResponse, GetObjectResponse,
+    GetRelatedObjectsResponse, GetSchemaResponse, ListRelationsResponse, ListSchemasResponse,
+    Object, Schema, SchemaValidation, ServerInfoResponse, UpdateObjectResponse,
+    UpsertObjectResponse, Zookie,
+};
+use ent_server::server::prost_value_to_json_value;
+use prost_types::{Struct, Value as ProstValue};
+use serde_json::{json, Value as JsonValue};
+
+/// How command responses are printed
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, ValueEnum)]
+pub enum OutputFormat {
+    /// Human readable debug output
+    #[default]
+    Pretty,
+    /// One JSON document per response, for piping into tools like `jq`
+    Json,
+}
+
+/// Responses that can be printed as JSON
+pub trait ToJson {
+    fn to_json(&self) -> JsonValue;
+}
+
+pub fn print<T: ToJson + std::fmt::Debug>(format: OutputFormat, response: &T) -> Result<()> {
+    match format {
+        OutputFormat::Pretty => println!("{:#?}", response),
+        OutputFormat::Json => println!("{}", serde_json::to_string(&response.to_json())?),
+    }
+
+    Ok(())
+}
+
+fn metadata_to_json(metadata: &Option<Struct>) -> JsonValue {
+    match metadata {
+        Some(metadata) => prost_value_to_json_value(ProstValue {
+            kind: Some(prost_types::value::Kind::StructValue(metadata.clone())),
+        }),
+        None => json!({}),
+    }
+}
+
+fn optional<T: ToJson>(value: &Option<T>) -> JsonValue {
+    value.as_ref().map_or(JsonValue::Null, ToJson::to_json)
+}
+
+impl ToJson for Zookie {
+    fn to_json(&self) -> JsonValue {
+        JsonValue::String(self.value.clone())
+    }
+}
+
+impl ToJson for ServerInfoResponse {
+    fn to_json(&self) -> JsonValue {
+        json!({
+            "version": self.version,
+            "consistency_modes": self.consistency_modes,
+            "features": self.features,
+        })
+    }
+}
+
+impl ToJson for Object {
+    fn to_json(&self) -> JsonValue {
+        json!({
+            "id": self.id,
+            "type": self.r#type,
+            "metadata": metadata_to_json(&self.metadata),
+            "created_by": self.created_by,
+            "updated_by": self.updated_by,
+            "created_at": self.created_at.as_ref().map(|ts| ts.to_string()),
+            "updated_at": self.updated_at.as_ref().map(|ts| ts.to_string()),
+        })
+    }
+}
+
+impl ToJson for Edge {
+    fn to_json(&self) -> JsonValue {
+        json!({
+            "id": self.id,
+            "from_id": self.from_id,
+            "from_type": self.from_type,
+            "to_id": self.to_id,
+            "to_type": self.to_type,
+            "relation": self.relation,
+            "metadata": metadata_to_json(&self.metadata),
+            "revision": self.revision,
+            "created_by": self.created_by,
+            "created_at": self.created_at.as_ref().map(|ts| ts.to_string()),
+            "updated_at": self.updated_at.as_ref().map(|ts| ts.to_string()),
+        })
+    }
+}
+
+impl ToJson for SchemaValidation {
+    fn to_json(&self) -> JsonValue {
+        json!({
+            "valid": self.valid,
+            "errors": self.errors,
+        })
+    }
+}
+
+impl ToJson for Schema {
+    fn to_json(&self) -> JsonValue {
+        json!({
+            "id": self.id,
+            "type_name": self.type_name,
+            "description": self.description,
+            "draft": (!self.draft.is_empty()).then_some(&self.draft),
+            "schema": serde_json::from_str::<JsonValue>(&self.schema).unwrap_or(JsonValue::Null),
+            "created_at": self.created_at.as_ref().map(|ts| ts.to_string()),
+        })
+    }
+}
+
+impl ToJson for ListSchemasResponse {
+    fn to_json(&self) -> JsonValue {
+        json!({
+            "schemas": self.schemas.iter().map(ToJson::to_json).collect::<Vec<_>>(),
+        })
+    }
+}
+
+impl ToJson for GetSchemaResponse {
+    fn to_json(&self) -> JsonValue {
+        json!({ "schema": optional(&self.schema) })
+    }
+}
+
+impl ToJson for GetObjectResponse {
+    fn to_json(&self) -> JsonValue {
+        json!({
+            "object": optional(&self.object),
+            "validation": optional(&self.validation),
+        })
+    }
+}
+
+impl ToJson for GetEdgeResponse {
+    fn to_json(&self) -> JsonValue {
+        json!({
+            "edge": optional(&self.edge),
+            "object": optional(&self.object),
+        })
+    }
+}
+
+impl ToJson for GetEdgeByIdResponse {
+    fn to_json(&self) -> JsonValue {
+        json!({
+            "edge": optional(&self.edge),
+        })
+    }
+}
+
+impl ToJson for ListRelationsResponse {
+    fn to_json(&self) -> JsonValue {
+        json!({
+            "relations": self
+                .relations
+                .iter()
+                .map(|r| json!({ "relation": r.relation, "count": r.count }))
+                .collect::<Vec<_>>(),
+        })
+    }
+}
+
+impl ToJson for GetEdgesResponse {
+    fn to_json(&self) -> JsonValue {
+        json!({
+            "objects": self.objects.iter().map(ToJson::to_json).collect::<Vec<_>>(),
+            "edges": self.edges.iter().map(ToJson::to_json).collect::<Vec<_>>(),
+        })
+    }
+}
+
+impl ToJson for GetRelatedObjectsResponse {
+    fn to_json(&self) -> JsonValue {
+        json!({
+            "objects": self.objects.iter().map(ToJson::to_json).collect::<Vec<_>>(),
+        })
+    }
+}
+
+impl ToJson for UpsertObjectResponse {
+    fn to_json(&self) -> JsonValue {
+        json!({
+            "object": optional(&self.object),
+            "revision": optional(&self.revision),
+            "created": self.created,
+        })
+    }
+}
+
+impl ToJson for CreateObjectResponse {
+    fn to_json(&self) -> JsonValue {
+        json!({
+            "object": optional(&self.object),
+            "revision": optional(&self.revision),
+        })
+    }
+}
+
+impl ToJson for CreateEdgeResponse {
+    fn to_json(&self) -> JsonValue {
+        json!({
+            "edge": optional(&self.edge),
+            "revision": optional(&self.revision),
+        })
+    }
+}
+
+impl ToJson for UpdateObjectResponse {
+    fn to_json(&self) -> JsonValue {
+        json!({
+            "object": optional(&self.object),
+            "revision": optional(&self.revision),
+        })
+    }
+}
+
+impl ToJson for DeleteObjectResponse {
+    fn to_json(&self) -> JsonValue {
+        json!({ "revision": optional(&self.revision) })
+    }
+}
+
+impl ToJson for DeleteEdgeResponse {
+    fn to_json(&self) -> JsonValue {
+        json!({ "revision": optional(&self.revision) })
+    }
+}
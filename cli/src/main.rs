@@ -1,10 +1,12 @@
-use anyhow::Result;
-use clap::Parser;
+use anyhow::{Context, Result};
+use clap::{Parser, ValueEnum};
 use ent_proto::ent::{
     graph_service_client::GraphServiceClient, schema_service_client::SchemaServiceClient,
 };
 
-use commands::{admin, edge, object};
+use commands::output::OutputFormat;
+use std::path::PathBuf;
+use tracing_subscriber::EnvFilter;
 
 #[derive(Parser)]
 #[command(author, version, about, long_about = None)]
@@ -13,39 +15,88 @@ pub struct Cli {
     #[arg(long, default_value = "http2://127.0.0.1:50051")]
     endpoint: String,
 
-    /// The authentication token
-    #[arg(long)]
+    /// The authentication token. Prefer --auth-env or --auth-file, which keep
+    /// the token out of shell history and process listings
+    #[arg(long, conflicts_with_all = ["auth_env", "auth_file"])]
     auth: Option<String>,
 
+    /// Read the authentication token from this environment variable
+    #[arg(long, value_name = "VAR", conflicts_with = "auth_file")]
+    auth_env: Option<String>,
+
+    /// Read the authentication token from this file
+    #[arg(long, value_name = "PATH")]
+    auth_file: Option<PathBuf>,
+
+    /// How to print responses
+    #[arg(long, value_enum, global = true, default_value_t = OutputFormat::Pretty)]
+    format: OutputFormat,
+
+    /// How to print logs. `RUST_LOG` sets which are printed, errors only by
+    /// default
+    #[arg(long, value_enum, global = true, default_value_t = LogFormat::Pretty)]
+    log_format: LogFormat,
+
     #[command(subcommand)]
     command: commands::Commands,
 }
 
+/// How log lines are printed
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, ValueEnum)]
+enum LogFormat {
+    /// Human readable lines
+    #[default]
+    Pretty,
+    /// One JSON object per line, for log pipelines
+    Json,
+}
+
 mod commands;
 
+impl Cli {
+    /// The bearer token to send, from whichever of the auth flags was given
+    fn resolve_auth(&self) -> Result<Option<String>> {
+        if let Some(var) = &self.auth_env {
+            let token = std::env::var(var)
+                .with_context(|| format!("Failed to read token from ${}", var))?;
+            return Ok(Some(token.trim().to_string()));
+        }
+
+        if let Some(path) = &self.auth_file {
+            let token = std::fs::read_to_string(path)
+                .with_context(|| format!("Failed to read token from {}", path.display()))?;
+            return Ok(Some(token.trim().to_string()));
+        }
+
+        Ok(self.auth.clone())
+    }
+}
+
 #[tokio::main]
 async fn main() -> Result<()> {
-    tracing_subscriber::fmt::init();
-
     let cli = Cli::parse();
 
+    let log_filter = match EnvFilter::try_from_default_env() {
+        Ok(filter) => filter,
+        Err(_) => EnvFilter::new("error"),
+    };
+    let logs = tracing_subscriber::fmt().with_env_filter(log_filter);
+    match cli.log_format {
+        LogFormat::Pretty => logs.init(),
+        LogFormat::Json => logs.json().init(),
+    }
+
+    let auth = cli.resolve_auth()?;
+
     let mut client = GraphServiceClient::connect(cli.endpoint.clone()).await?;
     let mut schema_client = SchemaServiceClient::connect(cli.endpoint).await?;
 
-    match cli.command {
-        commands::Commands::Admin(cmd) => admin::execute(cmd, &mut schema_client).await,
-        commands::Commands::GetObject(cmd) => object::execute(cmd, &mut client, cli.auth).await,
-        commands::Commands::GetEdge(cmd) => {
-            edge::execute_get_edge(cmd, &mut client, cli.auth).await
-        }
-        commands::Commands::GetEdges(cmd) => {
-            edge::execute_get_edges(cmd, &mut client, cli.auth).await
-        }
-        commands::Commands::CreateObject(cmd) => {
-            object::execute_create_object(cmd, &mut client, cli.auth).await
-        }
-        commands::Commands::CreateEdge(cmd) => {
-            edge::execute_create_edge(cmd, &mut client, cli.auth).await
-        }
-    }
+    commands::execute(
+        cli.command,
+        &mut client,
+        &mut schema_client,
+        auth,
+        cli.format,
+    )
+    .await
 }
@@ -0,0 +1,2 @@
+-- Supports reverse lookups of edges by target object and relation
+CREATE INDEX IF NOT EXISTS idx_triples_to_relation ON triples(to_id, relation);
@@ -0,0 +1,2 @@
+-- Supports containment filters on edge metadata
+CREATE INDEX IF NOT EXISTS idx_edge_metadata_history_metadata ON edge_metadata_history USING GIN (metadata jsonb_path_ops);
@@ -0,0 +1,2 @@
+-- Keeps the description supplied when a schema is registered
+ALTER TABLE schemata ADD COLUMN IF NOT EXISTS description TEXT NOT NULL DEFAULT '';
@@ -0,0 +1,2 @@
+-- The JSON Schema draft a schema was registered under; NULL detects it from `$schema`
+ALTER TABLE schemata ADD COLUMN IF NOT EXISTS draft TEXT;
@@ -0,0 +1,9 @@
+-- `user_id` is rewritten by every update, so the creator is kept separately.
+-- Existing rows only know their last writer, which is the best guess we have.
+ALTER TABLE objects ADD COLUMN IF NOT EXISTS created_by TEXT;
+UPDATE objects SET created_by = user_id WHERE created_by IS NULL;
+ALTER TABLE objects ALTER COLUMN created_by SET NOT NULL;
+
+ALTER TABLE triples ADD COLUMN IF NOT EXISTS created_by TEXT;
+UPDATE triples SET created_by = user_id WHERE created_by IS NULL;
+ALTER TABLE triples ALTER COLUMN created_by SET NOT NULL;
@@ -0,0 +1,6 @@
+-- Types whose schema was deprecated: their objects stay readable, but no new
+-- objects of the type may be created
+CREATE TABLE IF NOT EXISTS deprecated_types (
+    type_name TEXT PRIMARY KEY,
+    deprecated_at TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
@@ -0,0 +1,11 @@
+-- Access to an object granted by its owner to another user. `write` implies `read`.
+CREATE TABLE IF NOT EXISTS object_grants (
+    object_id BIGINT NOT NULL REFERENCES objects(id) ON DELETE CASCADE,
+    grantee TEXT NOT NULL,
+    permission TEXT NOT NULL CHECK (permission IN ('read', 'write')),
+    granted_by TEXT NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    PRIMARY KEY (object_id, grantee)
+);
+
+CREATE INDEX IF NOT EXISTS idx_object_grants_grantee ON object_grants(grantee);
@@ -0,0 +1,3 @@
+-- Ownership lives in `created_by`; `user_id` only records the last writer.
+COMMENT ON COLUMN objects.user_id IS 'Last user to write or delete the object; the owner is created_by';
+COMMENT ON COLUMN triples.user_id IS 'Last user to write or delete the edge; the owner is created_by';
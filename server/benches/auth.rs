@@ -0,0 +1,58 @@
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use ent_server::auth::{Claims, JwtValidator};
+use jsonwebtoken::{decode, encode, Algorithm, DecodingKey, EncodingKey, Header, Validation};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+const ISSUER: &str = "ent";
+
+fn token() -> String {
+    let private_key = std::fs::read_to_string("../test/data/private.pem").unwrap();
+    let exp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_secs() as usize
+        + 3600;
+    let claims = Claims {
+        sub: "bench-user".to_string(),
+        exp,
+        iss: ISSUER.to_string(),
+        aud: None,
+        scopes: vec![],
+    };
+    encode(
+        &Header::new(Algorithm::RS256),
+        &claims,
+        &EncodingKey::from_rsa_pem(private_key.as_bytes()).unwrap(),
+    )
+    .unwrap()
+}
+
+// What `validate_token` used to do on every call before decoding
+fn fresh_validation() -> Validation {
+    let mut validation = Validation::new(Algorithm::RS256);
+    validation.set_issuer(&[ISSUER]);
+    validation.validate_aud = false;
+    validation
+}
+
+fn bench_validate_token(c: &mut Criterion) {
+    let public_key = std::fs::read_to_string("../test/data/public.pem").unwrap();
+    let validator = JwtValidator::new(&public_key, ISSUER.to_string()).unwrap();
+    let decoding_key = DecodingKey::from_rsa_pem(public_key.as_bytes()).unwrap();
+    let token = token();
+
+    let mut group = c.benchmark_group("validate_token");
+    group.bench_function("fresh_validation", |b| {
+        b.iter(|| decode::<Claims>(black_box(&token), &decoding_key, &fresh_validation()).unwrap())
+    });
+    group.bench_function("cached_validation", |b| {
+        b.iter(|| validator.validate_token(black_box(&token)).unwrap())
+    });
+    group.finish();
+
+    // The setup alone, which the cached path no longer pays per token
+    c.bench_function("build_validation", |b| b.iter(fresh_validation));
+}
+
+criterion_group!(benches, bench_validate_token);
+criterion_main!(benches);
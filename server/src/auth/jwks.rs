@@ -0,0 +1,231 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::{Duration, Instant};
+
+use anyhow::{anyhow, Result};
+use jsonwebtoken::{jwk::JwkSet, DecodingKey};
+use tracing::{info, warn};
+
+// A fetch that takes longer than this is abandoned
+const FETCH_TIMEOUT: Duration = Duration::from_secs(10);
+
+// Tokens with an unknown kid trigger a fetch at most this often, so a flood of
+// them can't hammer the JWKS endpoint
+const MIN_FETCH_INTERVAL: Duration = Duration::from_secs(30);
+
+// Signing keys fetched from a JWKS endpoint, indexed by `kid`
+#[derive(Clone)]
+pub struct JwksCache {
+    url: String,
+    client: reqwest::Client,
+    keys: Arc<RwLock<HashMap<String, DecodingKey>>>,
+    // When the last fetch started, if any
+    last_fetch: Arc<Mutex<Option<Instant>>>,
+}
+
+impl JwksCache {
+    // Fetch the key set once, then keep refreshing it in the background every `refresh_interval`
+    pub async fn start(url: String, refresh_interval: Duration) -> Result<Self> {
+        let cache = Self {
+            url,
+            client: reqwest::Client::builder().timeout(FETCH_TIMEOUT).build()?,
+            keys: Arc::new(RwLock::new(HashMap::new())),
+            last_fetch: Arc::new(Mutex::new(None)),
+        };
+        cache.refresh().await?;
+
+        let background = cache.clone();
+        tokio::spawn(async move {
+            loop {
+                tokio::time::sleep(refresh_interval).await;
+                if let Err(e) = background.refresh().await {
+                    warn!("Failed to refresh JWKS from {}: {}", background.url, e);
+                }
+            }
+        });
+
+        Ok(cache)
+    }
+
+    pub async fn refresh(&self) -> Result<()> {
+        *self
+            .last_fetch
+            .lock()
+            .map_err(|_| anyhow!("JWKS cache lock poisoned"))? = Some(Instant::now());
+
+        let body = self
+            .client
+            .get(&self.url)
+            .send()
+            .await?
+            .error_for_status()?
+            .bytes()
+            .await?;
+        let keys = index_keys(&serde_json::from_slice(&body)?);
+
+        info!(url = %self.url, keys = keys.len(), "Loaded JWKS");
+
+        *self
+            .keys
+            .write()
+            .map_err(|_| anyhow!("JWKS cache lock poisoned"))? = keys;
+        Ok(())
+    }
+
+    // An unknown kid may be a key rotated in since the last fetch, so it
+    // schedules an early refresh. The token at hand is still rejected
+    pub fn get(&self, kid: &str) -> Result<DecodingKey> {
+        let key = self
+            .keys
+            .read()
+            .map_err(|_| anyhow!("JWKS cache lock poisoned"))?
+            .get(kid)
+            .cloned();
+
+        key.ok_or_else(|| {
+            self.refresh_soon();
+            anyhow!("No signing key found for kid {}", kid)
+        })
+    }
+
+    // Fetches the key set in the background unless a fetch started within
+    // `MIN_FETCH_INTERVAL`
+    fn refresh_soon(&self) {
+        let Ok(runtime) = tokio::runtime::Handle::try_current() else {
+            return;
+        };
+        let Ok(mut last_fetch) = self.last_fetch.lock() else {
+            return;
+        };
+        if last_fetch.is_some_and(|at| at.elapsed() < MIN_FETCH_INTERVAL) {
+            return;
+        }
+        *last_fetch = Some(Instant::now());
+
+        let cache = self.clone();
+        runtime.spawn(async move {
+            if let Err(e) = cache.refresh().await {
+                warn!("Failed to refresh JWKS from {}: {}", cache.url, e);
+            }
+        });
+    }
+}
+
+// The usable keys of `jwks` by kid. Keys without a kid can't be picked by a
+// token, so they are left out
+fn index_keys(jwks: &JwkSet) -> HashMap<String, DecodingKey> {
+    let mut keys = HashMap::new();
+    for jwk in &jwks.keys {
+        let Some(kid) = &jwk.common.key_id else {
+            continue;
+        };
+        match DecodingKey::from_jwk(jwk) {
+            Ok(key) => {
+                keys.insert(kid.clone(), key);
+            }
+            Err(e) => warn!("Skipping unusable JWK {}: {}", kid, e),
+        }
+    }
+    keys
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use serde_json::json;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+
+    fn jwks(kids: &[&str]) -> serde_json::Value {
+        let keys: Vec<_> = kids
+            .iter()
+            .map(|kid| json!({"kty": "oct", "k": "c2VjcmV0", "kid": kid}))
+            .collect();
+        json!({ "keys": keys })
+    }
+
+    // Serves whatever `body` holds at the time of each request, counting them
+    async fn serve(body: Arc<Mutex<String>>, hits: Arc<AtomicUsize>) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/jwks.json", listener.local_addr().unwrap());
+        tokio::spawn(async move {
+            loop {
+                let (mut socket, _) = listener.accept().await.unwrap();
+                let mut request = [0; 1024];
+                let _ = socket.read(&mut request).await;
+                hits.fetch_add(1, Ordering::SeqCst);
+                let body = body.lock().unwrap().clone();
+                let response = format!(
+                    "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                    body.len(),
+                    body
+                );
+                let _ = socket.write_all(response.as_bytes()).await;
+            }
+        });
+        url
+    }
+
+    #[test]
+    fn test_index_keys() {
+        let jwks: JwkSet = serde_json::from_value(json!({
+            "keys": [
+                {"kty": "oct", "k": "c2VjcmV0", "kid": "a"},
+                {"kty": "oct", "k": "c2VjcmV0"},
+                {"kty": "RSA", "n": "!", "e": "AQAB", "kid": "broken"},
+                {"kty": "oct", "k": "b3RoZXI", "kid": "b"},
+            ]
+        }))
+        .unwrap();
+
+        let mut kids: Vec<_> = index_keys(&jwks).into_keys().collect();
+        kids.sort();
+        assert_eq!(kids, vec!["a", "b"]);
+    }
+
+    #[tokio::test]
+    async fn test_refresh_loads_keys() {
+        let body = Arc::new(Mutex::new(jwks(&["a"]).to_string()));
+        let url = serve(body.clone(), Arc::new(AtomicUsize::new(0))).await;
+        let cache = JwksCache::start(url, Duration::from_secs(3600))
+            .await
+            .unwrap();
+        assert!(cache.get("a").is_ok());
+
+        // A refresh replaces the key set, dropping retired keys
+        *body.lock().unwrap() = jwks(&["b"]).to_string();
+        cache.refresh().await.unwrap();
+        assert!(cache.get("a").is_err());
+        assert!(cache.get("b").is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_unknown_kid_refreshes() {
+        let body = Arc::new(Mutex::new(jwks(&["a"]).to_string()));
+        let hits = Arc::new(AtomicUsize::new(0));
+        let url = serve(body.clone(), hits.clone()).await;
+        let cache = JwksCache::start(url, Duration::from_secs(3600))
+            .await
+            .unwrap();
+        *body.lock().unwrap() = jwks(&["a", "b"]).to_string();
+
+        // Right after a fetch an unknown kid is rejected without another one
+        assert!(cache.get("b").is_err());
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        assert_eq!(hits.load(Ordering::SeqCst), 1);
+
+        // Once the interval has passed it picks up the rotated key
+        *cache.last_fetch.lock().unwrap() = Some(Instant::now() - MIN_FETCH_INTERVAL);
+        assert!(cache.get("b").is_err());
+        for _ in 0..50 {
+            if cache.get("b").is_ok() {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+        assert!(cache.get("b").is_ok());
+        assert_eq!(hits.load(Ordering::SeqCst), 2);
+    }
+}
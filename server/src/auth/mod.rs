@@ -1,61 +1,170 @@
-use anyhow::Result;
-use jsonwebtoken::{decode, Algorithm, DecodingKey, Validation};
+use anyhow::{anyhow, Result};
+use jsonwebtoken::{decode, decode_header, Algorithm, DecodingKey, Validation};
 use once_cell::sync::OnceCell;
 use serde::{Deserialize, Serialize};
+use tonic::metadata::MetadataMap;
 use tonic::{Request, Status};
 
+mod jwks;
+
+pub use jwks::JwksCache;
+
 static JWT_VALIDATOR: OnceCell<JwtValidator> = OnceCell::new();
 
-#[derive(Debug, Serialize, Deserialize)]
+// Matches the `jsonwebtoken` default
+pub const DEFAULT_LEEWAY_SECONDS: u64 = 60;
+
+// Where clients put their token unless configured otherwise
+pub const DEFAULT_TOKEN_HEADER: &str = "authorization";
+pub const DEFAULT_TOKEN_SCHEME: &str = "Bearer";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Claims {
     pub sub: String,
     pub exp: usize,
     pub iss: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub aud: Option<Audience>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub scopes: Vec<String>,
 }
 
-// Extension trait for adding bearer token to requests
-pub trait RequestExt<T> {
-    fn with_bearer_token(self, token: &str) -> Result<Request<T>>;
+impl Claims {
+    pub fn has_scope(&self, scope: &str) -> bool {
+        self.scopes.iter().any(|s| s == scope)
+    }
 }
 
-impl<T> RequestExt<T> for Request<T> {
-    fn with_bearer_token(mut self, token: &str) -> Result<Request<T>> {
-        let auth_header = format!("Bearer {}", token);
-        let auth_value = auth_header.parse()?;
-        self.metadata_mut().insert("authorization", auth_value);
-        Ok(self)
-    }
+// The `aud` claim may be a single string or an array of strings
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum Audience {
+    Single(String),
+    Multiple(Vec<String>),
+}
+
+#[derive(Clone)]
+enum KeySource {
+    Static(DecodingKey),
+    Jwks(JwksCache),
 }
 
 #[derive(Clone)]
 pub struct JwtValidator {
-    decoding_key: DecodingKey,
+    keys: KeySource,
     issuer: String,
+    audience: Option<String>,
+    leeway_seconds: u64,
+    header: String,
+    scheme: String,
+    // Built from the settings above whenever they change, not per token
+    validation: Validation,
 }
 
 impl JwtValidator {
     pub fn new(public_key_pem: &str, issuer: String) -> Result<Self> {
         let decoding_key = DecodingKey::from_rsa_pem(public_key_pem.as_bytes())?;
-        Ok(Self {
-            decoding_key,
+        Ok(Self::with_keys(KeySource::Static(decoding_key), issuer))
+    }
+
+    // Validate tokens against a rotating key set, picking the key named by the token's `kid`
+    pub fn from_jwks(jwks: JwksCache, issuer: String) -> Self {
+        Self::with_keys(KeySource::Jwks(jwks), issuer)
+    }
+
+    fn with_keys(keys: KeySource, issuer: String) -> Self {
+        let mut validator = Self {
+            keys,
             issuer,
-        })
+            audience: None,
+            leeway_seconds: DEFAULT_LEEWAY_SECONDS,
+            header: DEFAULT_TOKEN_HEADER.to_string(),
+            scheme: DEFAULT_TOKEN_SCHEME.to_string(),
+            validation: Validation::new(Algorithm::RS256),
+        };
+        validator.validation = validator.build_validation();
+        validator
     }
 
-    pub fn validate_token(&self, token: &str) -> Result<Claims> {
+    // Require tokens to carry this audience in their `aud` claim
+    pub fn with_audience(mut self, audience: Option<String>) -> Self {
+        self.audience = audience;
+        self.validation = self.build_validation();
+        self
+    }
+
+    // Tolerate this much clock skew when checking `exp`
+    pub fn with_leeway(mut self, leeway_seconds: u64) -> Self {
+        self.leeway_seconds = leeway_seconds;
+        self.validation = self.build_validation();
+        self
+    }
+
+    // Read tokens from this metadata key, optionally prefixed by `scheme`
+    // (e.g. `Bearer`). An empty scheme means the header holds the bare token
+    pub fn with_token_header(mut self, header: &str, scheme: &str) -> Self {
+        self.header = header.to_ascii_lowercase();
+        self.scheme = scheme.to_string();
+        self
+    }
+
+    // Metadata key requests carry their token under
+    pub fn token_header(&self) -> &str {
+        &self.header
+    }
+
+    // The token in a header value, with the scheme stripped if present
+    fn strip_scheme<'a>(&self, value: &'a str) -> &'a str {
+        value
+            .strip_prefix(self.scheme.as_str())
+            .and_then(|rest| rest.strip_prefix(' '))
+            .filter(|_| !self.scheme.is_empty())
+            .unwrap_or(value)
+    }
+
+    // The checks every token must pass, per the current settings
+    fn build_validation(&self) -> Validation {
         let mut validation = Validation::new(Algorithm::RS256);
+        validation.leeway = self.leeway_seconds;
         validation.set_issuer(&[&self.issuer]);
 
-        let token_data = decode::<Claims>(token, &self.decoding_key, &validation)?;
+        match &self.audience {
+            Some(audience) => {
+                validation.set_audience(&[audience]);
+                // A missing `aud` is only rejected when it is a required claim
+                validation.set_required_spec_claims(&["exp", "aud"]);
+            }
+            None => validation.validate_aud = false,
+        }
+
+        validation
+    }
+
+    pub fn validate_token(&self, token: &str) -> Result<Claims> {
+        let token_data = match &self.keys {
+            KeySource::Static(decoding_key) => {
+                decode::<Claims>(token, decoding_key, &self.validation)?
+            }
+            KeySource::Jwks(jwks) => {
+                let kid = decode_header(token)?
+                    .kid
+                    .ok_or_else(|| anyhow!("Token header is missing kid"))?;
+                decode::<Claims>(token, &jwks.get(&kid)?, &self.validation)?
+            }
+        };
         Ok(token_data.claims)
     }
 
     // Initialize the global JWT validator
     pub fn init(public_key_pem: &str, issuer: String) -> Result<()> {
+        Self::init_with(JwtValidator::new(public_key_pem, issuer)?)
+    }
+
+    // Initialize the global JWT validator with a fully configured instance
+    pub fn init_with(validator: JwtValidator) -> Result<()> {
         if JWT_VALIDATOR.get().is_some() {
-            return Ok(());
+            Ok(())
         } else {
-            let validator = JwtValidator::new(public_key_pem, issuer)?;
             JWT_VALIDATOR
                 .set(validator)
                 .map_err(|_| anyhow::anyhow!("JWT Validator has already been initialized"))
@@ -68,28 +177,217 @@ impl JwtValidator {
     }
 }
 
-pub trait AuthenticatedRequest {
-    fn user_id(&self) -> Result<String, Status>;
-}
+// Token handling for both ends of a call: clients attach their token with
+// `with_bearer_token`, handlers read the caller back with `claims` and the
+// helpers built on it
+pub trait RequestExt: Sized {
+    // Sends `token` under the default header and scheme
+    fn with_bearer_token(self, token: &str) -> Result<Self>;
+
+    fn claims(&self) -> Result<Claims, Status>;
 
-impl<T> AuthenticatedRequest for Request<T> {
     fn user_id(&self) -> Result<String, Status> {
-        let token = self
-            .metadata()
-            .get("authorization")
-            .ok_or_else(|| Status::unauthenticated("Missing authorization token"))?
-            .to_str()
-            .map_err(|_| Status::unauthenticated("Invalid authorization token"))?;
+        Ok(self.claims()?.sub)
+    }
+
+    // Fails with `permission_denied` unless the token grants `scope`
+    fn require_scope(&self, scope: &str) -> Result<(), Status> {
+        if self.claims()?.has_scope(scope) {
+            Ok(())
+        } else {
+            Err(Status::permission_denied(format!(
+                "Missing required scope: {}",
+                scope
+            )))
+        }
+    }
+}
+
+// Metadata key tokens are read from, as configured on the global validator
+pub fn token_header() -> &'static str {
+    JwtValidator::get().map_or(DEFAULT_TOKEN_HEADER, JwtValidator::token_header)
+}
+
+// Outcome of checking a request's token, cached in its extensions by
+// `interceptor` so handlers don't verify the token again
+#[derive(Clone)]
+struct Authentication(Result<Claims, Status>);
+
+// Verifies the token carried in `metadata` against the global validator
+fn authenticate(metadata: &MetadataMap) -> Result<Claims, Status> {
+    let validator =
+        JwtValidator::get().ok_or_else(|| Status::internal("JWT validator not configured"))?;
+
+    let value = metadata
+        .get(validator.token_header())
+        .ok_or_else(|| Status::unauthenticated("Missing authorization token"))?
+        .to_str()
+        .map_err(|_| Status::unauthenticated("Invalid authorization token"))?;
+    let token = validator.strip_scheme(value);
+
+    validator
+        .validate_token(token)
+        .map_err(|_| Status::unauthenticated("Invalid token"))
+}
+
+// Verifies the request's token once, up front, for every handler to reuse.
+// Requests without a valid token still pass; handlers decide whether they
+// need one
+pub fn interceptor(mut request: Request<()>) -> Result<Request<()>, Status> {
+    let authentication = Authentication(authenticate(request.metadata()));
+    request.extensions_mut().insert(authentication);
+    Ok(request)
+}
+
+impl<T> RequestExt for Request<T> {
+    fn with_bearer_token(mut self, token: &str) -> Result<Self> {
+        let value = format!("{} {}", DEFAULT_TOKEN_SCHEME, token).parse()?;
+        self.metadata_mut().insert(DEFAULT_TOKEN_HEADER, value);
+        Ok(self)
+    }
+
+    fn claims(&self) -> Result<Claims, Status> {
+        match self.extensions().get::<Authentication>() {
+            Some(Authentication(claims)) => claims.clone(),
+            None => authenticate(self.metadata()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use jsonwebtoken::{encode, EncodingKey, Header};
+    use std::time::{SystemTime, UNIX_EPOCH};
+
+    fn validator() -> JwtValidator {
+        let public_key = std::fs::read_to_string("../test/data/public.pem").unwrap();
+        JwtValidator::new(&public_key, "ent".to_string()).unwrap()
+    }
+
+    fn token(aud: Option<Audience>) -> String {
+        token_expiring_in(aud, 3600)
+    }
+
+    fn token_expiring_in(aud: Option<Audience>, seconds: i64) -> String {
+        token_with(aud, vec![], seconds)
+    }
+
+    fn token_with(aud: Option<Audience>, scopes: Vec<String>, seconds: i64) -> String {
+        let private_key = std::fs::read_to_string("../test/data/private.pem").unwrap();
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_secs() as i64;
+        let exp = (now + seconds) as usize;
+        let claims = Claims {
+            sub: "test-user".to_string(),
+            exp,
+            iss: "ent".to_string(),
+            aud,
+            scopes,
+        };
+        encode(
+            &Header::new(Algorithm::RS256),
+            &claims,
+            &EncodingKey::from_rsa_pem(private_key.as_bytes()).unwrap(),
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_audience_validation() {
+        let validator = validator().with_audience(Some("ent".to_string()));
+
+        let valid = token(Some(Audience::Single("ent".to_string())));
+        assert_eq!(validator.validate_token(&valid).unwrap().sub, "test-user");
+
+        let multiple = token(Some(Audience::Multiple(vec![
+            "ent".to_string(),
+            "billing".to_string(),
+        ])));
+        assert!(validator.validate_token(&multiple).is_ok());
+
+        let mismatched = token(Some(Audience::Single("billing".to_string())));
+        assert!(validator.validate_token(&mismatched).is_err());
+
+        let missing = token(None);
+        assert!(validator.validate_token(&missing).is_err());
+    }
+
+    #[test]
+    fn test_audience_not_configured() {
+        let validator = validator();
+
+        assert!(validator.validate_token(&token(None)).is_ok());
+        assert!(validator
+            .validate_token(&token(Some(Audience::Single("billing".to_string()))))
+            .is_ok());
+    }
+
+    #[test]
+    fn test_expiry_leeway() {
+        let expired = token_expiring_in(None, -10);
+
+        assert!(validator().with_leeway(30).validate_token(&expired).is_ok());
+        assert!(validator().with_leeway(0).validate_token(&expired).is_err());
+    }
+
+    #[test]
+    fn test_token_header() {
+        let default = validator();
+        assert_eq!(default.token_header(), "authorization");
+        assert_eq!(default.strip_scheme("Bearer abc"), "abc");
+        assert_eq!(default.strip_scheme("abc"), "abc");
+
+        let gateway = validator().with_token_header("X-Ent-Token", "");
+        assert_eq!(gateway.token_header(), "x-ent-token");
+        assert_eq!(gateway.strip_scheme("abc"), "abc");
+
+        let custom = validator().with_token_header("x-ent-token", "Token");
+        assert_eq!(custom.strip_scheme("Token abc"), "abc");
+        assert_eq!(custom.strip_scheme("Bearer abc"), "Bearer abc");
+    }
+
+    #[test]
+    fn test_interceptor_caches_claims() {
+        JwtValidator::init_with(validator()).unwrap();
+
+        let request = Request::new(()).with_bearer_token(&token(None)).unwrap();
+        let mut request = interceptor(request).unwrap();
+        assert_eq!(request.user_id().unwrap(), "test-user");
+
+        // The cached outcome stands even once the token is gone
+        request.metadata_mut().remove("authorization");
+        assert_eq!(request.user_id().unwrap(), "test-user");
+
+        let request = interceptor(Request::new(())).unwrap();
+        assert_eq!(
+            request.user_id().unwrap_err().code(),
+            tonic::Code::Unauthenticated
+        );
+    }
 
-        let token = token.strip_prefix("Bearer ").unwrap_or(token);
+    #[test]
+    fn test_require_scope() {
+        JwtValidator::init_with(validator()).unwrap();
 
-        let validator =
-            JwtValidator::get().ok_or_else(|| Status::internal("JWT validator not configured"))?;
+        let admin = token_with(None, vec!["admin".to_string()], 3600);
+        let request = Request::new(()).with_bearer_token(&admin).unwrap();
+        assert_eq!(request.user_id().unwrap(), "test-user");
+        assert!(request.require_scope("admin").is_ok());
 
-        let claims = validator
-            .validate_token(token)
-            .map_err(|_| Status::unauthenticated("Invalid token"))?;
+        let user = token_with(None, vec!["read".to_string()], 3600);
+        let request = Request::new(()).with_bearer_token(&user).unwrap();
+        assert_eq!(
+            request.require_scope("admin").unwrap_err().code(),
+            tonic::Code::PermissionDenied
+        );
 
-        Ok(claims.sub)
+        let request = Request::new(());
+        assert_eq!(
+            request.require_scope("admin").unwrap_err().code(),
+            tonic::Code::Unauthenticated
+        );
     }
 }
@@ -1,11 +1,50 @@
 use config::{Config, ConfigError, Environment, File};
 use serde::Deserialize;
 use std::env;
+use std::path::Path;
 
 #[derive(Debug, Deserialize)]
 pub struct JwtConfig {
+    #[serde(default)]
     pub public_key_path: String,
     pub issuer: String,
+    /// JWKS endpoint to fetch signing keys from instead of `public_key_path`
+    #[serde(default)]
+    pub jwks_url: Option<String>,
+    /// How often the JWKS key set is refreshed. A token with an unknown `kid`
+    /// also triggers a refresh, at most every 30 seconds
+    #[serde(default = "default_jwks_refresh_seconds")]
+    pub jwks_refresh_seconds: u64,
+    /// Allowed clock skew when checking token expiry
+    #[serde(default = "default_leeway_seconds")]
+    pub leeway_seconds: u64,
+    /// Expected `aud` claim; tokens minted for other audiences are rejected
+    #[serde(default)]
+    pub audience: Option<String>,
+    /// Metadata key clients send their token under, for gateways that
+    /// forward it as something like `x-ent-token`
+    #[serde(default = "default_token_header")]
+    pub header: String,
+    /// Prefix before the token in that header; empty when it holds the bare
+    /// token
+    #[serde(default = "default_token_scheme")]
+    pub scheme: String,
+}
+
+fn default_token_header() -> String {
+    crate::auth::DEFAULT_TOKEN_HEADER.to_string()
+}
+
+fn default_token_scheme() -> String {
+    crate::auth::DEFAULT_TOKEN_SCHEME.to_string()
+}
+
+fn default_jwks_refresh_seconds() -> u64 {
+    300
+}
+
+fn default_leeway_seconds() -> u64 {
+    crate::auth::DEFAULT_LEEWAY_SECONDS
 }
 
 #[derive(Debug, Deserialize)]
@@ -13,13 +52,230 @@ pub struct ServerConfig {
     pub host: String,
     pub port: u16,
     pub max_connections: u32,
+    /// How often the health service probes the database
+    #[serde(default = "default_health_check_interval_seconds")]
+    pub health_check_interval_seconds: u64,
+    /// Largest request message accepted, in bytes. It caps the memory one
+    /// request can claim; raise it when bulk calls like `BatchCreateObjects`
+    /// legitimately need more than the 4 MiB default
+    #[serde(default = "default_max_decoding_message_size")]
+    pub max_decoding_message_size: usize,
+    /// Largest response message sent, in bytes. Unlimited by default; a cap
+    /// turns oversized responses like a huge `ListObjects` page into errors
+    #[serde(default = "default_max_encoding_message_size")]
+    pub max_encoding_message_size: usize,
+}
+
+fn default_health_check_interval_seconds() -> u64 {
+    5
+}
+
+fn default_max_decoding_message_size() -> usize {
+    4 * 1024 * 1024
+}
+
+fn default_max_encoding_message_size() -> usize {
+    usize::MAX
+}
+
+/// Where Prometheus metrics are served over plain HTTP at `/metrics`
+#[derive(Debug, Clone, Deserialize)]
+pub struct MetricsConfig {
+    #[serde(default = "default_metrics_host")]
+    pub host: String,
+    pub port: u16,
+}
+
+fn default_metrics_host() -> String {
+    "0.0.0.0".to_string()
+}
+
+impl MetricsConfig {
+    pub fn address(&self) -> String {
+        format!("{}:{}", self.host, self.port)
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LogFormat {
+    /// Human readable lines
+    #[default]
+    Pretty,
+    /// One JSON object per line, for log pipelines
+    Json,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct LoggingConfig {
+    #[serde(default)]
+    pub format: LogFormat,
+    /// `RUST_LOG`-style filter, e.g. `info` or `ent_server=debug,sqlx=warn`.
+    /// `RUST_LOG` itself takes precedence when set.
+    #[serde(default = "default_log_level")]
+    pub level: String,
+}
+
+impl Default for LoggingConfig {
+    fn default() -> Self {
+        Self {
+            format: LogFormat::default(),
+            level: default_log_level(),
+        }
+    }
+}
+
+fn default_log_level() -> String {
+    "info".to_string()
+}
+
+/// Export traces to an OpenTelemetry collector over OTLP/gRPC
+#[derive(Debug, Clone, Deserialize)]
+pub struct TelemetryConfig {
+    pub otlp_endpoint: String,
+    #[serde(default = "default_service_name")]
+    pub service_name: String,
+}
+
+fn default_service_name() -> String {
+    "ent-server".to_string()
+}
+
+/// Per-user token bucket applied to graph RPCs
+#[derive(Debug, Clone, Deserialize)]
+pub struct RateLimitConfig {
+    /// Sustained rate each user may make requests at
+    pub requests_per_second: f64,
+    /// Requests a user may make at once after being idle; defaults to one
+    /// second's worth
+    #[serde(default)]
+    pub burst: Option<u32>,
+}
+
+/// Read RPCs that serve objects flagged public in their metadata to anyone,
+/// without a token or ownership. Writes always require a token.
+#[derive(Debug, Clone, Deserialize)]
+pub struct PublicReadsConfig {
+    pub rpcs: Vec<PublicRpc>,
+    /// Metadata field that marks an object public when set to `true`
+    #[serde(default = "default_public_field")]
+    pub field: String,
+}
+
+fn default_public_field() -> String {
+    "public".to_string()
+}
+
+/// RPCs that can be opened up with `public_reads`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PublicRpc {
+    GetObject,
+    /// Also opens GetEdge and GetRelatedObjects, which return the same edges
+    GetEdges,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct AccessConfig {
+    /// Answer `not_found` both for objects that don't exist and for objects
+    /// the caller may not access, so ids can't be probed for existence
+    #[serde(default)]
+    pub uniform_not_found: bool,
+}
+
+/// Serve gRPC over TLS with this certificate and private key (PEM files)
+#[derive(Debug, Clone, Deserialize)]
+pub struct TlsConfig {
+    pub cert_path: String,
+    pub key_path: String,
+    /// When set, clients must present a certificate signed by one of these CAs
+    #[serde(default)]
+    pub client_ca_path: Option<String>,
 }
 
 #[derive(Debug, Deserialize)]
 pub struct DatabaseConfig {
     pub url: String,
     pub max_connections: u32,
-    pub timeout_seconds: u64,
+    /// Connections kept open even when idle
+    #[serde(default)]
+    pub min_connections: u32,
+    /// How long a request waits for a free connection before failing
+    #[serde(default = "default_acquire_timeout_seconds", alias = "timeout_seconds")]
+    pub acquire_timeout_seconds: u64,
+    /// Idle connections above `min_connections` are closed after this long
+    #[serde(default)]
+    pub idle_timeout_seconds: Option<u64>,
+    /// Size of the pool serving writes; defaults to `max_connections`
+    #[serde(default)]
+    pub write_max_connections: Option<u32>,
+    /// When set, read-only RPCs get their own pool of this size instead of
+    /// sharing the write pool
+    #[serde(default)]
+    pub read_max_connections: Option<u32>,
+    /// Postgres cancels any statement, lock waits included, running longer
+    /// than this. Unlimited unless set
+    #[serde(default)]
+    pub statement_timeout_seconds: Option<u64>,
+}
+
+fn default_acquire_timeout_seconds() -> u64 {
+    3
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct SchemaConfig {
+    /// Allow dot-separated type names such as `billing.invoice`
+    #[serde(default)]
+    pub allow_namespaced_types: bool,
+    /// When set, every type name must live under this namespace (e.g. `billing`).
+    /// Implies `allow_namespaced_types`.
+    #[serde(default)]
+    pub required_namespace: Option<String>,
+    /// When set, every schema RPC requires a token carrying this scope. When
+    /// unset, reading, deleting, exporting and importing schemas still require
+    /// a valid token
+    #[serde(default)]
+    pub admin_scope: Option<String>,
+    /// Reject objects whose type has no registered schema instead of storing
+    /// them unvalidated
+    #[serde(default)]
+    pub strict_types: bool,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct ZookieConfig {
+    /// HMAC key zookies are signed with. When unset a random key is generated
+    /// at startup, so zookies are only valid until the server restarts.
+    #[serde(default)]
+    pub secret: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct DiagnosticsConfig {
+    /// Statements running at least this long are reported as slow queries
+    #[serde(default = "default_slow_query_threshold_ms")]
+    pub slow_query_threshold_ms: u64,
+    /// When set, reading diagnostics requires a token carrying this scope
+    #[serde(default)]
+    pub admin_scope: Option<String>,
+    /// Report the snapshot each read saw in an `x-ent-snapshot` response header
+    #[serde(default)]
+    pub snapshot_header: bool,
+}
+
+impl Default for DiagnosticsConfig {
+    fn default() -> Self {
+        Self {
+            slow_query_threshold_ms: default_slow_query_threshold_ms(),
+            admin_scope: None,
+            snapshot_header: false,
+        }
+    }
+}
+
+fn default_slow_query_threshold_ms() -> u64 {
+    500
 }
 
 #[derive(Debug, Deserialize)]
@@ -27,6 +283,31 @@ pub struct Settings {
     pub server: ServerConfig,
     pub database: DatabaseConfig,
     pub jwt: JwtConfig,
+    #[serde(default)]
+    pub schema: SchemaConfig,
+    #[serde(default)]
+    pub diagnostics: DiagnosticsConfig,
+    #[serde(default)]
+    pub zookie: ZookieConfig,
+    /// Plaintext unless set
+    #[serde(default)]
+    pub tls: Option<TlsConfig>,
+    /// No metrics endpoint unless set
+    #[serde(default)]
+    pub metrics: Option<MetricsConfig>,
+    /// Traces stay local unless set
+    #[serde(default)]
+    pub telemetry: Option<TelemetryConfig>,
+    /// Users are not rate limited unless set
+    #[serde(default)]
+    pub rate_limit: Option<RateLimitConfig>,
+    /// Every read requires a token and ownership unless set
+    #[serde(default)]
+    pub public_reads: Option<PublicReadsConfig>,
+    #[serde(default)]
+    pub access: AccessConfig,
+    #[serde(default)]
+    pub logging: LoggingConfig,
 }
 
 impl Settings {
@@ -46,12 +327,193 @@ impl Settings {
             .add_source(File::with_name(&format!("{}/config/local", prefix)).required(false))
             // Add environment variables with prefix "ENT_"
             .add_source(Environment::with_prefix("ENT").separator("_"))
+            // The conventional DATABASE_URL, also read by sqlx at build time,
+            // wins over everything else
+            .set_override_option("database.url", env::var("DATABASE_URL").ok())?
             .build()?;
 
         s.try_deserialize()
     }
 
+    /// Sanity-checks values that deserialize fine but would fail later, or
+    /// worse, mid-boot. Every problem found is reported at once.
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        let mut problems = Vec::new();
+
+        if self.server.host.is_empty() {
+            problems.push("server.host must not be empty".to_string());
+        }
+        if self.server.port == 0 {
+            problems.push("server.port must be between 1 and 65535".to_string());
+        }
+        if self.server.max_connections == 0 {
+            problems.push("server.max_connections must be at least 1".to_string());
+        }
+        if self.server.health_check_interval_seconds == 0 {
+            problems.push("server.health_check_interval_seconds must be at least 1".to_string());
+        }
+
+        if self.database.url.is_empty() {
+            problems.push("database.url must not be empty; set it or DATABASE_URL".to_string());
+        }
+        if self.database.max_connections == 0 {
+            problems.push("database.max_connections must be at least 1".to_string());
+        }
+        if self.database.min_connections > self.database.max_connections {
+            problems.push(format!(
+                "database.min_connections ({}) exceeds database.max_connections ({})",
+                self.database.min_connections, self.database.max_connections
+            ));
+        }
+        for (key, size) in [
+            ("write_max_connections", self.database.write_max_connections),
+            ("read_max_connections", self.database.read_max_connections),
+        ] {
+            if size == Some(0) {
+                problems.push(format!("database.{} must be at least 1", key));
+            }
+        }
+
+        if self.jwt.issuer.is_empty() {
+            problems.push("jwt.issuer must not be empty".to_string());
+        }
+        if tonic::metadata::AsciiMetadataKey::from_bytes(self.jwt.header.as_bytes()).is_err() {
+            problems.push(format!(
+                "jwt.header ({:?}) is not a valid metadata key",
+                self.jwt.header
+            ));
+        }
+        if self.jwt.jwks_url.is_none() {
+            if self.jwt.public_key_path.is_empty() {
+                problems.push("one of jwt.public_key_path or jwt.jwks_url is required".to_string());
+            } else {
+                check_file(
+                    "jwt.public_key_path",
+                    &self.jwt.public_key_path,
+                    &mut problems,
+                );
+            }
+        } else if self.jwt.jwks_refresh_seconds == 0 {
+            problems.push("jwt.jwks_refresh_seconds must be at least 1".to_string());
+        }
+
+        if let Some(tls) = &self.tls {
+            check_file("tls.cert_path", &tls.cert_path, &mut problems);
+            check_file("tls.key_path", &tls.key_path, &mut problems);
+            if let Some(client_ca_path) = &tls.client_ca_path {
+                check_file("tls.client_ca_path", client_ca_path, &mut problems);
+            }
+        }
+        if let Some(metrics) = &self.metrics {
+            if metrics.port == 0 {
+                problems.push("metrics.port must be between 1 and 65535".to_string());
+            } else if metrics.port == self.server.port {
+                problems.push(format!(
+                    "metrics.port ({}) clashes with server.port",
+                    metrics.port
+                ));
+            }
+        }
+        if let Some(rate_limit) = &self.rate_limit {
+            let rate = rate_limit.requests_per_second;
+            if rate.is_nan() || rate <= 0.0 {
+                problems.push("rate_limit.requests_per_second must be positive".to_string());
+            }
+        }
+        if let Some(public_reads) = &self.public_reads {
+            if public_reads.field.is_empty() {
+                problems.push("public_reads.field must not be empty".to_string());
+            }
+        }
+
+        if problems.is_empty() {
+            Ok(())
+        } else {
+            Err(ConfigError::Message(format!(
+                "Invalid configuration: {}",
+                problems.join("; ")
+            )))
+        }
+    }
+
     pub fn server_address(&self) -> String {
         format!("{}:{}", self.server.host, self.server.port)
     }
 }
+
+fn check_file(key: &str, path: &str, problems: &mut Vec<String>) {
+    if !Path::new(path).is_file() {
+        problems.push(format!(
+            "{} points at {}, which is not a readable file",
+            key, path
+        ));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use config::FileFormat;
+
+    fn settings(yaml: &str) -> Settings {
+        Config::builder()
+            .add_source(File::from_str(yaml, FileFormat::Yaml))
+            .build()
+            .unwrap()
+            .try_deserialize()
+            .unwrap()
+    }
+
+    const VALID: &str = r#"
+server:
+  host: "0.0.0.0"
+  port: 50051
+  max_connections: 10
+database:
+  url: "postgres://localhost/ent"
+  max_connections: 5
+jwt:
+  public_key_path: "../test/data/public.pem"
+  issuer: "ent"
+"#;
+
+    #[test]
+    fn test_validate_accepts_valid_settings() {
+        settings(VALID).validate().unwrap();
+    }
+
+    #[test]
+    fn test_validate_reports_every_problem() {
+        let yaml = VALID
+            .replace("port: 50051", "port: 0")
+            .replace(
+                "max_connections: 10",
+                "max_connections: 10\n  health_check_interval_seconds: 0",
+            )
+            .replace("\"postgres://localhost/ent\"", "\"\"")
+            .replace("../test/data/public.pem", "/does/not/exist.pem");
+        let message = settings(&yaml).validate().unwrap_err().to_string();
+
+        assert!(message.contains("server.port"), "{}", message);
+        assert!(
+            message.contains("server.health_check_interval_seconds"),
+            "{}",
+            message
+        );
+        assert!(message.contains("database.url"), "{}", message);
+        assert!(message.contains("/does/not/exist.pem"), "{}", message);
+    }
+
+    #[test]
+    fn test_validate_skips_key_file_with_jwks() {
+        let yaml = VALID.replace(
+            "public_key_path: \"../test/data/public.pem\"",
+            "jwks_url: \"https://example.com/jwks.json\"",
+        );
+        settings(&yaml).validate().unwrap();
+
+        let yaml = yaml.replace("issuer:", "jwks_refresh_seconds: 0\n  issuer:");
+        let message = settings(&yaml).validate().unwrap_err().to_string();
+        assert!(message.contains("jwt.jwks_refresh_seconds"), "{}", message);
+    }
+}
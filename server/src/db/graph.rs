@@ -1,24 +1,35 @@
-use anyhow::{anyhow, Result};
+use anyhow::{anyhow, bail, Result};
 use ent_proto::ent::{
     CreateEdgeRequest, CreateObjectRequest, Edge as ProtoEdge, Object as ProtoObject,
 };
 use prost_types::{Struct, Value as ProstValue};
 use serde_json::Value;
 use sqlx::PgPool;
+use std::collections::HashMap;
 use time::OffsetDateTime;
+use tokio::sync::mpsc;
+use tokio_stream::{wrappers::ReceiverStream, Stream, StreamExt};
 use tracing::{info, instrument};
 
 use crate::{
     db::xid::Xid8,
-    server::{json_value_to_prost_value, prost_value_to_json_value},
+    server::{json_value_to_prost_value, offset_date_time_to_timestamp, prost_value_to_json_value},
 };
 
-use super::transaction::{ConsistencyMode, Revision, Transaction};
+use super::query_error;
+use super::schema::{allowed_edges, is_indexable_field, relation_rules};
+use super::transaction::{ConsistencyMode, PgSnapshot, Revision, Transaction, TransactionMetadata};
+
+/// How many rows `GraphRepository::stream_objects` and `export_graph` read
+/// ahead of their consumer.
+const STREAM_OBJECTS_BUFFER: usize = 64;
 
 #[derive(Debug, sqlx::FromRow)]
 pub struct Object {
     pub id: i64,
     pub type_name: String,
+    pub created_by: String,
+    pub updated_by: String,
     pub created_at: Option<OffsetDateTime>,
     pub updated_at: Option<OffsetDateTime>,
 }
@@ -28,54 +39,424 @@ pub struct ObjectWithMetadata {
     pub id: i64,
     pub type_name: String,
     pub metadata: Value,
+    pub created_by: String,
+    /// The last user to write the object, the creator until it is updated
+    pub updated_by: String,
     pub created_at: Option<OffsetDateTime>,
     pub updated_at: Option<OffsetDateTime>,
 }
 
 impl ObjectWithMetadata {
     pub fn to_pb(&self) -> ProtoObject {
-        let fields: std::collections::BTreeMap<String, ProstValue> = match &self.metadata {
-            Value::Object(map) => map
-                .into_iter()
-                .map(|(k, v)| (k.clone(), json_value_to_prost_value(v.clone())))
-                .collect(),
-            _ => std::collections::BTreeMap::new(),
-        };
-
-        let metadata = if fields.is_empty() {
-            None
-        } else {
-            Some(Struct { fields })
-        };
-
         ProtoObject {
             id: self.id,
             r#type: self.type_name.clone(),
-            metadata,
+            metadata: metadata_to_pb(&self.metadata),
+            created_by: self.created_by.clone(),
+            updated_by: self.updated_by.clone(),
+            created_at: self.created_at.map(offset_date_time_to_timestamp),
+            updated_at: self.updated_at.map(offset_date_time_to_timestamp),
         }
     }
 }
 
 impl Object {
     pub fn to_pb(&self, metadata: Value) -> ProtoObject {
-        let fields: std::collections::BTreeMap<String, ProstValue> = match metadata {
-            Value::Object(map) => map
-                .into_iter()
-                .map(|(k, v)| (k, json_value_to_prost_value(v)))
-                .collect(),
-            _ => std::collections::BTreeMap::new(),
-        };
+        ProtoObject {
+            id: self.id,
+            r#type: self.type_name.clone(),
+            metadata: metadata_to_pb(&metadata),
+            created_by: self.created_by.clone(),
+            updated_by: self.updated_by.clone(),
+            created_at: self.created_at.map(offset_date_time_to_timestamp),
+            updated_at: self.updated_at.map(offset_date_time_to_timestamp),
+        }
+    }
+}
+
+/// Applies an RFC 7386 JSON Merge Patch: objects in `patch` are merged key by
+/// key, a `null` removes the key, and anything else replaces the target value.
+pub fn merge_patch(target: &mut Value, patch: &Value) {
+    let Value::Object(patch) = patch else {
+        *target = patch.clone();
+        return;
+    };
 
-        let metadata = if fields.is_empty() {
-            None
+    if !target.is_object() {
+        *target = Value::Object(serde_json::Map::new());
+    }
+    let Value::Object(target) = target else {
+        unreachable!()
+    };
+
+    for (key, value) in patch {
+        if value.is_null() {
+            target.remove(key);
         } else {
-            Some(Struct { fields })
+            merge_patch(target.entry(key.clone()).or_insert(Value::Null), value);
+        }
+    }
+}
+
+/// Converts stored metadata into its protobuf form. Objects and edges without
+/// metadata (an empty JSON object or a non-object value) are both sent as `None`.
+fn metadata_to_pb(metadata: &Value) -> Option<Struct> {
+    match metadata {
+        Value::Object(map) if !map.is_empty() => Some(Struct {
+            fields: map
+                .iter()
+                .map(|(k, v)| (k.clone(), json_value_to_prost_value(v.clone())))
+                .collect(),
+        }),
+        _ => None,
+    }
+}
+
+/// Restricts `GraphRepository::get_edges` and `get_related_objects` to edges
+/// whose current metadata matches. Both conditions must hold when both are set.
+#[derive(Debug, Clone, Default)]
+pub struct EdgeMetadataFilter {
+    /// Metadata must contain this JSON document (`@>`)
+    pub contains: Option<Value>,
+    /// The text value of a top-level metadata key must equal the given string
+    pub key_equals: Option<(String, String)>,
+    /// Compare `key_equals` values without regard to case
+    pub ignore_case: bool,
+}
+
+/// Which targets `GraphRepository::get_related_objects` returns, so readability
+/// is decided in the query and `limit` counts only readable targets.
+#[derive(Debug, Clone, Copy)]
+pub enum TargetAccess<'a> {
+    /// Targets the user owns or was granted access to
+    User(&'a str),
+    /// Targets whose metadata sets this field to `true`
+    PublicField(&'a str),
+}
+
+/// The objects `GraphRepository::find_objects` returns: those where
+/// `metadata->>field` equals `value` as text. With `ignore_case` both sides
+/// are lowered first, which an `x-indexed` index on the field can't serve.
+#[derive(Debug, Clone, Copy)]
+pub struct FieldMatch<'a> {
+    pub field: &'a str,
+    pub value: &'a str,
+    pub ignore_case: bool,
+}
+
+/// The order `GraphRepository::get_related_objects` returns edges in. Ties on
+/// `created_at` are broken by edge ID in the same direction.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum EdgeOrder {
+    #[default]
+    Id,
+    CreatedAtAsc,
+    CreatedAtDesc,
+}
+
+impl EdgeOrder {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            EdgeOrder::Id => "id",
+            EdgeOrder::CreatedAtAsc => "created_at_asc",
+            EdgeOrder::CreatedAtDesc => "created_at_desc",
+        }
+    }
+}
+
+/// One version of an object's metadata, as returned by
+/// `GraphRepository::get_object_history`.
+#[derive(Debug)]
+pub struct MetadataVersion {
+    pub metadata: Value,
+    pub created_xid: Xid8,
+    /// `None` while this is the current version
+    pub deleted_xid: Option<Xid8>,
+    /// Revision of the transaction that wrote this version
+    pub revision: Revision,
+}
+
+/// What a batch write did with one of its items.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BatchOutcome {
+    Created,
+    /// Replaced the metadata of the existing item it matched
+    Updated,
+    /// Matched an existing item exactly, so nothing was written
+    Skipped,
+}
+
+/// Identifies the edge that made `GraphRepository::batch_create_edges` fail.
+#[derive(Debug)]
+pub struct BatchEdgeError {
+    pub index: usize,
+    pub reason: String,
+}
+
+impl std::fmt::Display for BatchEdgeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "edge {}: {}", self.index, self.reason)
+    }
+}
+
+impl std::error::Error for BatchEdgeError {}
+
+/// An edge whose declared endpoint type is not the type of the object it
+/// points at.
+#[derive(Debug)]
+pub struct EndpointTypeError {
+    pub end: &'static str,
+    pub id: i64,
+    pub expected: String,
+    pub actual: String,
+}
+
+impl std::fmt::Display for EndpointTypeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} object {} has type '{}', not '{}'",
+            self.end, self.id, self.actual, self.expected
+        )
+    }
+}
+
+impl std::error::Error for EndpointTypeError {}
+
+/// An edge endpoint that exists but the user may not read. Callers that hide
+/// which objects exist report it with `missing`, the error a missing endpoint
+/// gets.
+#[derive(Debug)]
+pub struct UnreadableEndpointError {
+    /// Position of the edge in a batch
+    pub index: Option<usize>,
+    pub end: &'static str,
+    pub id: i64,
+}
+
+impl UnreadableEndpointError {
+    pub fn missing(&self) -> anyhow::Error {
+        missing_endpoint(self.index, self.end, self.id)
+    }
+}
+
+impl std::fmt::Display for UnreadableEndpointError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if let Some(index) = self.index {
+            write!(f, "edge {}: ", index)?;
+        }
+        write!(f, "you may not read {} object {}", self.end, self.id)
+    }
+}
+
+impl std::error::Error for UnreadableEndpointError {}
+
+// A `BatchEdgeError` for an edge of a batch, a `ForeignKey` error otherwise
+fn missing_endpoint(index: Option<usize>, end: &str, id: i64) -> anyhow::Error {
+    let reason = format!("{} object {} does not exist", end, id);
+    match index {
+        Some(index) => BatchEdgeError { index, reason }.into(),
+        None => GraphError::ForeignKey(reason).into(),
+    }
+}
+
+/// An edge its source type's `x-edges` list does not allow.
+#[derive(Debug)]
+pub struct RelationNotAllowedError {
+    pub from_type: String,
+    pub relation: String,
+    pub to_type: String,
+}
+
+impl std::fmt::Display for RelationNotAllowedError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "type '{}' does not allow '{}' edges to type '{}'",
+            self.from_type, self.relation, self.to_type
+        )
+    }
+}
+
+impl std::error::Error for RelationNotAllowedError {}
+
+/// An edge write that would exceed a relation's `x-relations` `max`.
+#[derive(Debug)]
+pub struct CardinalityError {
+    pub from_id: i64,
+    pub relation: String,
+    pub max: i64,
+}
+
+impl std::fmt::Display for CardinalityError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "object {} may have at most {} '{}' edge(s)",
+            self.from_id, self.max, self.relation
+        )
+    }
+}
+
+impl std::error::Error for CardinalityError {}
+
+/// What a grant lets a user other than the owner do with an object. `Write`
+/// implies `Read`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Permission {
+    Read,
+    Write,
+}
+
+impl Permission {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Permission::Read => "read",
+            Permission::Write => "write",
+        }
+    }
+}
+
+/// A write the database refused, classified so callers can tell clients why
+/// without exposing the underlying SQL error.
+#[derive(Debug)]
+pub enum GraphError {
+    /// The row the write expected to find does not exist
+    NotFound(String),
+    /// The write collides with a row that already exists
+    Conflict(String),
+    /// The write refers to an object that does not exist
+    ForeignKey(String),
+    /// The caller may not change the row the write targets
+    Forbidden(String),
+    /// Any other database failure
+    Db(sqlx::Error),
+}
+
+/// SQLSTATEs for the constraint violations `GraphError` distinguishes
+const FOREIGN_KEY_VIOLATION: &str = "23503";
+const UNIQUE_VIOLATION: &str = "23505";
+
+impl From<sqlx::Error> for GraphError {
+    fn from(error: sqlx::Error) -> Self {
+        let code = match &error {
+            sqlx::Error::RowNotFound => return GraphError::NotFound("row not found".to_string()),
+            sqlx::Error::Database(e) => e.code().map(|code| code.into_owned()),
+            _ => None,
         };
 
-        ProtoObject {
-            id: self.id,
-            r#type: self.type_name.clone(),
-            metadata,
+        match code.as_deref() {
+            Some(FOREIGN_KEY_VIOLATION) => {
+                GraphError::ForeignKey("referenced object does not exist".to_string())
+            }
+            Some(UNIQUE_VIOLATION) => GraphError::Conflict("row already exists".to_string()),
+            _ => GraphError::Db(error),
+        }
+    }
+}
+
+impl std::fmt::Display for GraphError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            GraphError::NotFound(message)
+            | GraphError::Conflict(message)
+            | GraphError::ForeignKey(message)
+            | GraphError::Forbidden(message) => write!(f, "{}", message),
+            GraphError::Db(e) => write!(f, "database error: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for GraphError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            GraphError::Db(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+/// A conditional update of an object changed since the revision the client
+/// expected.
+#[derive(Debug)]
+pub struct RevisionConflictError {
+    pub object_id: i64,
+}
+
+impl std::fmt::Display for RevisionConflictError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "revision conflict: object {} changed since the expected revision",
+            self.object_id
+        )
+    }
+}
+
+impl std::error::Error for RevisionConflictError {}
+
+/// Why `GraphRepository::get_object` found nothing for an ID.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ObjectMiss {
+    NeverExisted,
+    Deleted,
+    NotVisible,
+}
+
+/// An object reached by `GraphRepository::traverse`, with the hop count of the
+/// shortest path from the start object.
+#[derive(Debug)]
+pub struct TraversedObject {
+    pub object: ObjectWithMetadata,
+    pub depth: i32,
+}
+
+/// A relation leaving an object and how many live edges use it, as returned
+/// by `GraphRepository::list_relations`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RelationCount {
+    pub relation: String,
+    pub count: i64,
+}
+
+/// One hop of a path returned by `GraphRepository::shortest_path`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PathStep {
+    pub edge_id: i64,
+    pub relation: String,
+}
+
+#[derive(Debug, sqlx::FromRow)]
+struct PathRecord {
+    edge_ids: Vec<i64>,
+    relations: Vec<String>,
+}
+
+// One object reached by `traverse`, joined with its metadata
+struct TraversedObjectRow {
+    id: i64,
+    type_name: String,
+    metadata: Value,
+    created_by: String,
+    updated_by: String,
+    created_at: Option<OffsetDateTime>,
+    updated_at: Option<OffsetDateTime>,
+    depth: i32,
+}
+
+impl From<TraversedObjectRow> for TraversedObject {
+    fn from(row: TraversedObjectRow) -> Self {
+        TraversedObject {
+            object: ObjectWithMetadata {
+                id: row.id,
+                type_name: row.type_name,
+                metadata: row.metadata,
+                created_by: row.created_by,
+                updated_by: row.updated_by,
+                created_at: row.created_at,
+                updated_at: row.updated_at,
+            },
+            depth: row.depth,
         }
     }
 }
@@ -99,6 +480,7 @@ pub struct Edge {
     pub relation: String,
     pub to_type: String,
     pub to_id: i64,
+    pub created_by: String,
     pub created_at: Option<OffsetDateTime>,
     pub updated_at: Option<OffsetDateTime>,
 }
@@ -112,13 +494,28 @@ pub struct EdgeWithMetadata {
     pub to_type: String,
     pub to_id: i64,
     pub metadata: Value,
+    pub created_by: String,
     pub created_at: Option<OffsetDateTime>,
     pub updated_at: Option<OffsetDateTime>,
 }
 
 impl EdgeWithMetadata {
+    fn from_edge(edge: Edge, metadata: Value) -> Self {
+        EdgeWithMetadata {
+            id: edge.id,
+            from_type: edge.from_type,
+            from_id: edge.from_id,
+            relation: edge.relation,
+            to_type: edge.to_type,
+            to_id: edge.to_id,
+            metadata,
+            created_by: edge.created_by,
+            created_at: edge.created_at,
+            updated_at: edge.updated_at,
+        }
+    }
+
     pub fn to_pb(&self) -> ProtoEdge {
-        let json_value = self.metadata.clone();
         ProtoEdge {
             id: self.id,
             relation: self.relation.clone(),
@@ -126,11 +523,90 @@ impl EdgeWithMetadata {
             from_type: self.from_type.clone(),
             to_id: self.to_id,
             to_type: self.to_type.clone(),
-            metadata: match json_value_to_prost_value(json_value).kind {
-                Some(prost_types::value::Kind::StructValue(v)) => Some(v),
-                _ => None,
-            },
+            metadata: metadata_to_pb(&self.metadata),
             revision: String::new(), // Empty revision since it's handled separately in responses
+            created_by: self.created_by.clone(),
+            created_at: self.created_at.map(offset_date_time_to_timestamp),
+            updated_at: self.updated_at.map(offset_date_time_to_timestamp),
+        }
+    }
+}
+
+/// An object reached through an edge, as returned by
+/// `GraphRepository::get_related_objects`.
+#[derive(Debug)]
+pub struct RelatedObject {
+    pub edge: EdgeWithMetadata,
+    pub object: ObjectWithMetadata,
+}
+
+// One row of the edge-and-target join behind `get_related_objects`
+struct RelatedObjectRow {
+    edge_id: i64,
+    from_type: String,
+    from_id: i64,
+    relation: String,
+    edge_metadata: Value,
+    edge_created_by: String,
+    edge_created_at: Option<OffsetDateTime>,
+    edge_updated_at: Option<OffsetDateTime>,
+    id: i64,
+    type_name: String,
+    metadata: Value,
+    created_by: String,
+    updated_by: String,
+    created_at: Option<OffsetDateTime>,
+    updated_at: Option<OffsetDateTime>,
+}
+
+impl From<RelatedObjectRow> for RelatedObject {
+    fn from(row: RelatedObjectRow) -> Self {
+        RelatedObject {
+            edge: EdgeWithMetadata {
+                id: row.edge_id,
+                from_type: row.from_type,
+                from_id: row.from_id,
+                relation: row.relation,
+                to_type: row.type_name.clone(),
+                to_id: row.id,
+                metadata: row.edge_metadata,
+                created_by: row.edge_created_by,
+                created_at: row.edge_created_at,
+                updated_at: row.edge_updated_at,
+            },
+            object: ObjectWithMetadata {
+                id: row.id,
+                type_name: row.type_name,
+                metadata: row.metadata,
+                created_by: row.created_by,
+                updated_by: row.updated_by,
+                created_at: row.created_at,
+                updated_at: row.updated_at,
+            },
+        }
+    }
+}
+
+/// One row of `GraphRepository::export_graph`.
+#[derive(Debug)]
+pub enum GraphRecord {
+    Object(ObjectWithMetadata),
+    Edge(EdgeWithMetadata),
+}
+
+/// Sends `rows` to `sender` until they run out, one fails, or the receiver
+/// goes away. A failure is sent on as the last item.
+async fn forward_rows<T>(
+    rows: impl Stream<Item = sqlx::Result<T>>,
+    sender: mpsc::Sender<Result<T>>,
+    context: &str,
+) {
+    tokio::pin!(rows);
+    while let Some(row) = rows.next().await {
+        let row = row.map_err(|e| anyhow!("{}: {}", context, e));
+        let failed = row.is_err();
+        if sender.send(row).await.is_err() || failed {
+            break;
         }
     }
 }
@@ -147,73 +623,65 @@ impl GraphRepository {
 
     pub async fn create_object(
         &self,
-        user_id: String,
+        tx_metadata: TransactionMetadata,
         request: CreateObjectRequest,
     ) -> Result<(ObjectWithMetadata, Revision)> {
-        let metadata: Value = match request.metadata {
-            Some(v) => {
-                let prost_value = ProstValue {
-                    kind: Some(prost_types::value::Kind::StructValue(v)),
-                };
-                prost_value_to_json_value(prost_value)
-            }
-            None => Value::Object(serde_json::Map::new()),
-        };
+        let mut tx = self.pool.begin().await?;
+        let transaction = Transaction::create_with_metadata(&mut tx, (&tx_metadata).into()).await?;
+
+        let revision = transaction.revision();
+
+        let object =
+            Self::insert_object(&mut tx, &transaction, &tx_metadata.user_id, request).await?;
+
+        // Commit the transaction
+        tx.commit().await?;
+
+        Ok((object, revision))
+    }
 
+    /// Creates an object of the request's type, or replaces the metadata of
+    /// the live one whose `key` field already holds `value`, in one
+    /// transaction. Upserts of the same key are serialized. Returns whether
+    /// the object was created. Updating needs write access to the object.
+    pub async fn upsert_object(
+        &self,
+        tx_metadata: TransactionMetadata,
+        request: CreateObjectRequest,
+        key: &str,
+        value: &str,
+    ) -> Result<(ObjectWithMetadata, Revision, bool)> {
         let mut tx = self.pool.begin().await?;
-        let transaction = Transaction::create(&mut tx).await?;
 
+        let existing =
+            Self::keyed_object(&mut tx, &request.r#type, key, value, &tx_metadata.user_id).await?;
+
+        let transaction = Transaction::create_with_metadata(&mut tx, (&tx_metadata).into()).await?;
         let revision = transaction.revision();
 
-        // Create the object with transaction tracking
-        let object = sqlx::query_as!(
-            Object,
-            r#"
-                INSERT INTO objects (
-                    type, 
-                    user_id,
-                    created_xid,
-                    deleted_xid
-                )
-                VALUES ($1, $2, $3, $4)
-                RETURNING 
-                    id, 
-                    type as type_name, 
-                    created_at as "created_at?: OffsetDateTime",
-                    updated_at as "updated_at?: OffsetDateTime"
-            "#,
-            request.r#type,
-            user_id,
-            transaction.xid as _, // The current transaction's XID
-            Xid8::max() as _,     // Max XID value for "not deleted"
-        )
-        .fetch_one(&mut *tx)
-        .await
-        .map_err(|e| anyhow!("Failed to create object: {}", e))?;
+        let Some(existing_id) = existing else {
+            let object =
+                Self::insert_object(&mut tx, &transaction, &tx_metadata.user_id, request).await?;
+            tx.commit().await?;
 
-        // Create initial metadata entry
-        sqlx::query!(
-            r#"
-                INSERT INTO object_metadata_history (
-                    object_id,
-                    metadata,
-                    created_xid,
-                    deleted_xid
-                )
-                VALUES ($1, $2, $3, $4)
-            "#,
-            object.id,
-            metadata,
-            transaction.xid as _,
-            Xid8::max() as _,
-        )
-        .execute(&mut *tx)
-        .await
-        .map_err(|e| anyhow!("Failed to create metadata: {}", e))?;
+            return Ok((object, revision, true));
+        };
 
-        info!("Created object: {:?}", object);
+        let metadata = match request.metadata {
+            Some(v) => prost_value_to_json_value(ProstValue {
+                kind: Some(prost_types::value::Kind::StructValue(v)),
+            }),
+            None => Value::Object(serde_json::Map::new()),
+        };
+        let object = Self::replace_metadata(
+            &mut tx,
+            transaction.xid,
+            &tx_metadata.user_id,
+            existing_id,
+            &metadata,
+        )
+        .await?;
 
-        // Commit the transaction
         tx.commit().await?;
 
         Ok((
@@ -221,32 +689,528 @@ impl GraphRepository {
                 id: object.id,
                 type_name: object.type_name,
                 metadata,
+                created_by: object.created_by,
+                updated_by: object.updated_by,
                 created_at: object.created_at,
                 updated_at: object.updated_at,
             },
             revision,
+            false,
         ))
     }
 
-    pub async fn create_edge(
+    /// Writes all objects in one transaction, so they share a single revision.
+    /// An object given with a key field and value is matched like
+    /// `upsert_object` does: the live object holding that value has its
+    /// metadata replaced, or is left alone if the metadata is unchanged.
+    pub async fn batch_create_objects(
         &self,
-        user_id: String,
-        request: CreateEdgeRequest,
-    ) -> Result<(EdgeWithMetadata, Revision)> {
-        let metadata: Value = match request.metadata {
-            Some(v) => {
-                let prost_value = ProstValue {
-                    kind: Some(prost_types::value::Kind::StructValue(v)),
+        tx_metadata: TransactionMetadata,
+        requests: Vec<(CreateObjectRequest, Option<(String, String)>)>,
+    ) -> Result<(Vec<(ObjectWithMetadata, BatchOutcome)>, Revision)> {
+        let mut tx = self.pool.begin().await?;
+        let transaction = Transaction::create_with_metadata(&mut tx, (&tx_metadata).into()).await?;
+
+        let revision = transaction.revision();
+        let user_id = tx_metadata.user_id.as_str();
+
+        let mut objects = Vec::with_capacity(requests.len());
+        for (request, key) in requests {
+            let existing = match &key {
+                Some((key, value)) => {
+                    Self::keyed_object(&mut tx, &request.r#type, key, value, user_id).await?
+                }
+                None => None,
+            };
+            let Some(existing_id) = existing else {
+                let object = Self::insert_object(&mut tx, &transaction, user_id, request).await?;
+                objects.push((object, BatchOutcome::Created));
+                continue;
+            };
+
+            let metadata = match request.metadata {
+                Some(v) => prost_value_to_json_value(ProstValue {
+                    kind: Some(prost_types::value::Kind::StructValue(v)),
+                }),
+                None => Value::Object(serde_json::Map::new()),
+            };
+            let (object, outcome) =
+                if Self::current_metadata(&mut tx, existing_id).await? == metadata {
+                    (
+                        Self::fetch_object(&mut tx, existing_id).await?,
+                        BatchOutcome::Skipped,
+                    )
+                } else {
+                    let object = Self::replace_metadata(
+                        &mut tx,
+                        transaction.xid,
+                        user_id,
+                        existing_id,
+                        &metadata,
+                    )
+                    .await?;
+                    (object, BatchOutcome::Updated)
+                };
+            objects.push((
+                ObjectWithMetadata {
+                    id: object.id,
+                    type_name: object.type_name,
+                    metadata,
+                    created_by: object.created_by,
+                    updated_by: object.updated_by,
+                    created_at: object.created_at,
+                    updated_at: object.updated_at,
+                },
+                outcome,
+            ));
+        }
+
+        // Commit the transaction
+        tx.commit().await?;
+
+        Ok((objects, revision))
+    }
+
+    // The live object of `type_name` whose `key` field holds `value`, locking
+    // the key until commit so writes of the same key are serialized. Fails
+    // with `GraphError::Forbidden` if the user may not write to it.
+    async fn keyed_object(
+        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+        type_name: &str,
+        key: &str,
+        value: &str,
+        user_id: &str,
+    ) -> Result<Option<i64>> {
+        sqlx::query!(
+            "SELECT pg_advisory_xact_lock(hashtext($1 || ':' || $2 || ':' || $3))",
+            type_name,
+            key,
+            value
+        )
+        .execute(&mut **tx)
+        .await
+        .map_err(|e| anyhow!("Failed to lock key: {}", e))?;
+
+        let existing = sqlx::query!(
+            r#"
+            SELECT
+                o.id,
+                (
+                    o.created_by = $4
+                    OR EXISTS(
+                        SELECT 1
+                        FROM object_grants g
+                        WHERE g.object_id = o.id
+                        AND g.grantee = $4
+                        AND g.permission = 'write'
+                    )
+                ) as "writable!"
+            FROM objects o
+            JOIN object_metadata_history m ON m.object_id = o.id AND m.deleted_xid = $5
+            WHERE o.type = $1
+            AND o.deleted_xid = $5
+            AND m.metadata->>$2 = $3
+            ORDER BY o.id
+            LIMIT 1
+            "#,
+            type_name,
+            key,
+            value,
+            user_id,
+            Xid8::max() as _,
+        )
+        .fetch_optional(&mut **tx)
+        .await
+        .map_err(|e| anyhow!("Failed to look up object by key: {}", e))?;
+
+        match existing {
+            Some(existing) if !existing.writable => Err(GraphError::Forbidden(format!(
+                "Object {} with {} '{}' belongs to someone else",
+                existing.id, key, value
+            ))
+            .into()),
+            existing => Ok(existing.map(|existing| existing.id)),
+        }
+    }
+
+    async fn current_metadata(
+        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+        object_id: i64,
+    ) -> Result<Value> {
+        sqlx::query_scalar!(
+            r#"
+            SELECT metadata as "metadata: Value"
+            FROM object_metadata_history
+            WHERE object_id = $1
+            AND deleted_xid = $2
+            "#,
+            object_id,
+            Xid8::max() as _,
+        )
+        .fetch_optional(&mut **tx)
+        .await
+        .map_err(|e| anyhow!("Failed to fetch metadata: {}", e))?
+        .ok_or_else(|| anyhow!("Object {} has no current metadata", object_id))
+    }
+
+    async fn fetch_object(
+        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+        object_id: i64,
+    ) -> Result<Object> {
+        let object = sqlx::query_as!(
+            Object,
+            r#"
+            SELECT
+                id,
+                type as type_name,
+                created_by,
+                user_id as updated_by,
+                created_at as "created_at?: OffsetDateTime",
+                updated_at as "updated_at?: OffsetDateTime"
+            FROM objects
+            WHERE id = $1
+            "#,
+            object_id,
+        )
+        .fetch_one(&mut **tx)
+        .await
+        .map_err(|e| anyhow!("Failed to fetch object: {}", e))?;
+
+        Ok(object)
+    }
+
+    async fn insert_object(
+        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+        transaction: &Transaction,
+        user_id: &str,
+        request: CreateObjectRequest,
+    ) -> Result<ObjectWithMetadata> {
+        let metadata: Value = match request.metadata {
+            Some(v) => {
+                let prost_value = ProstValue {
+                    kind: Some(prost_types::value::Kind::StructValue(v)),
                 };
                 prost_value_to_json_value(prost_value)
             }
             None => Value::Object(serde_json::Map::new()),
         };
 
+        // Create the object with transaction tracking
+        let object = sqlx::query_as!(
+            Object,
+            r#"
+                INSERT INTO objects (
+                    type, 
+                    user_id,
+                    created_by,
+                    created_xid,
+                    deleted_xid
+                )
+                VALUES ($1, $2, $2, $3, $4)
+                RETURNING 
+                    id, 
+                    type as type_name, 
+                    created_by,
+                    user_id as updated_by,
+                    created_at as "created_at?: OffsetDateTime",
+                    updated_at as "updated_at?: OffsetDateTime"
+            "#,
+            request.r#type,
+            user_id,
+            transaction.xid as _, // The current transaction's XID
+            Xid8::max() as _,     // Max XID value for "not deleted"
+        )
+        .fetch_one(&mut **tx)
+        .await
+        .map_err(GraphError::from)?;
+
+        // Create initial metadata entry
+        sqlx::query!(
+            r#"
+                INSERT INTO object_metadata_history (
+                    object_id,
+                    metadata,
+                    created_xid,
+                    deleted_xid
+                )
+                VALUES ($1, $2, $3, $4)
+            "#,
+            object.id,
+            metadata,
+            transaction.xid as _,
+            Xid8::max() as _,
+        )
+        .execute(&mut **tx)
+        .await
+        .map_err(GraphError::from)?;
+
+        info!("Created object: {:?}", object);
+
+        Ok(ObjectWithMetadata {
+            id: object.id,
+            type_name: object.type_name,
+            metadata,
+            created_by: object.created_by,
+            updated_by: object.updated_by,
+            created_at: object.created_at,
+            updated_at: object.updated_at,
+        })
+    }
+
+    pub async fn create_edge(
+        &self,
+        tx_metadata: TransactionMetadata,
+        request: CreateEdgeRequest,
+    ) -> Result<(EdgeWithMetadata, Revision)> {
+        let mut tx = self.pool.begin().await?;
+        let transaction = Transaction::create_with_metadata(&mut tx, (&tx_metadata).into()).await?;
+
+        let revision = transaction.revision();
+
+        // The foreign keys still accept edges to deleted objects. An endpoint
+        // the user can't read fails before its type can give it away
+        for (id, expected_type, end) in [
+            (request.from_id, &request.from_type, "from"),
+            (request.to_id, &request.to_type, "to"),
+        ] {
+            let actual = Self::live_object_type(&mut tx, id)
+                .await?
+                .ok_or_else(|| missing_endpoint(None, end, id))?;
+            if !Self::can_access(&mut tx, id, &tx_metadata.user_id, Permission::Read).await? {
+                return Err(UnreadableEndpointError {
+                    index: None,
+                    end,
+                    id,
+                }
+                .into());
+            }
+            if &actual != expected_type {
+                return Err(EndpointTypeError {
+                    end,
+                    id,
+                    expected: expected_type.clone(),
+                    actual,
+                }
+                .into());
+            }
+        }
+
+        if !Self::can_access(
+            &mut tx,
+            request.from_id,
+            &tx_metadata.user_id,
+            Permission::Write,
+        )
+        .await?
+        {
+            return Err(GraphError::Forbidden(format!(
+                "You may not add edges to object {}",
+                request.from_id
+            ))
+            .into());
+        }
+
+        let edge = Self::insert_edge(&mut tx, &transaction, &tx_metadata.user_id, request).await?;
+
+        // Commit the transaction
+        tx.commit().await?;
+
+        Ok((edge, revision))
+    }
+
+    /// Creates all edges in one transaction, so they share a single revision.
+    /// Every endpoint must be a live object of the declared type; otherwise
+    /// nothing is written and the error is a `BatchEdgeError` naming the first
+    /// offending edge, or an `UnreadableEndpointError` if the user may not read
+    /// it. The user must be able to write to every source object.
+    /// With `skip_existing`, an edge identical to a live one, metadata
+    /// included, is not created again and the live edge is returned instead.
+    pub async fn batch_create_edges(
+        &self,
+        tx_metadata: TransactionMetadata,
+        requests: Vec<CreateEdgeRequest>,
+        skip_existing: bool,
+    ) -> Result<(Vec<(EdgeWithMetadata, BatchOutcome)>, Revision)> {
         let mut tx = self.pool.begin().await?;
-        let transaction = Transaction::create(&mut tx).await?;
+        let transaction = Transaction::create_with_metadata(&mut tx, (&tx_metadata).into()).await?;
 
         let revision = transaction.revision();
+        let user_id = tx_metadata.user_id.as_str();
+
+        let mut edges = Vec::with_capacity(requests.len());
+        for (index, request) in requests.into_iter().enumerate() {
+            for (id, expected_type, end) in [
+                (request.from_id, &request.from_type, "from"),
+                (request.to_id, &request.to_type, "to"),
+            ] {
+                let actual = Self::live_object_type(&mut tx, id)
+                    .await?
+                    .ok_or_else(|| missing_endpoint(Some(index), end, id))?;
+                if !Self::can_access(&mut tx, id, user_id, Permission::Read).await? {
+                    return Err(UnreadableEndpointError {
+                        index: Some(index),
+                        end,
+                        id,
+                    }
+                    .into());
+                }
+                if &actual != expected_type {
+                    return Err(BatchEdgeError {
+                        index,
+                        reason: format!(
+                            "{} object {} has type '{}', not '{}'",
+                            end, id, actual, expected_type
+                        ),
+                    }
+                    .into());
+                }
+            }
+
+            if !Self::can_access(&mut tx, request.from_id, user_id, Permission::Write).await? {
+                return Err(GraphError::Forbidden(format!(
+                    "edge {}: you may not add edges to object {}",
+                    index, request.from_id
+                ))
+                .into());
+            }
+
+            if skip_existing {
+                if let Some(edge) = Self::identical_edge(&mut tx, &request).await? {
+                    edges.push((edge, BatchOutcome::Skipped));
+                    continue;
+                }
+            }
+            let edge = Self::insert_edge(&mut tx, &transaction, user_id, request).await?;
+            edges.push((edge, BatchOutcome::Created));
+        }
+
+        // Commit the transaction
+        tx.commit().await?;
+
+        Ok((edges, revision))
+    }
+
+    // Whether the user owns the object or was granted the permission on it
+    async fn can_access(
+        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+        object_id: i64,
+        user_id: &str,
+        permission: Permission,
+    ) -> Result<bool> {
+        let allowed = sqlx::query_scalar!(
+            r#"
+            SELECT EXISTS(
+                SELECT 1
+                FROM objects o
+                WHERE o.id = $1
+                AND (
+                    o.created_by = $2
+                    OR EXISTS(
+                        SELECT 1
+                        FROM object_grants g
+                        WHERE g.object_id = o.id
+                        AND g.grantee = $2
+                        AND (g.permission = 'write' OR g.permission = $3)
+                    )
+                )
+            ) as "allowed!"
+            "#,
+            object_id,
+            user_id,
+            permission.as_str(),
+        )
+        .fetch_one(&mut **tx)
+        .await
+        .map_err(|e| anyhow!("Failed to check object access: {}", e))?;
+
+        Ok(allowed)
+    }
+
+    // The first live edge with the request's endpoints, relation and metadata
+    async fn identical_edge(
+        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+        request: &CreateEdgeRequest,
+    ) -> Result<Option<EdgeWithMetadata>> {
+        let metadata = match request.metadata.clone() {
+            Some(v) => prost_value_to_json_value(ProstValue {
+                kind: Some(prost_types::value::Kind::StructValue(v)),
+            }),
+            None => Value::Object(serde_json::Map::new()),
+        };
+
+        let edge = sqlx::query_as!(
+            Edge,
+            r#"
+            SELECT
+                t.id,
+                t.from_type,
+                t.from_id,
+                t.relation,
+                t.to_type,
+                t.to_id,
+                t.created_by,
+                t.created_at as "created_at?: OffsetDateTime",
+                t.updated_at as "updated_at?: OffsetDateTime"
+            FROM triples t
+            JOIN edge_metadata_history m ON m.edge_id = t.id AND m.deleted_xid = $5
+            WHERE t.from_id = $1
+            AND t.relation = $2
+            AND t.to_id = $3
+            AND t.deleted_xid = $5
+            AND m.metadata = $4
+            ORDER BY t.id
+            LIMIT 1
+            "#,
+            request.from_id,
+            request.relation,
+            request.to_id,
+            metadata,
+            Xid8::max() as _,
+        )
+        .fetch_optional(&mut **tx)
+        .await
+        .map_err(|e| anyhow!("Failed to look up edge: {}", e))?;
+
+        Ok(edge.map(|edge| EdgeWithMetadata::from_edge(edge, metadata)))
+    }
+
+    // Type of the object if it exists and has not been deleted
+    async fn live_object_type(
+        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+        id: i64,
+    ) -> Result<Option<String>> {
+        let object_type = sqlx::query_scalar!(
+            r#"
+            SELECT type
+            FROM objects
+            WHERE id = $1
+            AND deleted_xid = $2
+            "#,
+            id,
+            Xid8::max() as _,
+        )
+        .fetch_optional(&mut **tx)
+        .await
+        .map_err(|e| anyhow!("Failed to fetch object: {}", e))?;
+
+        Ok(object_type)
+    }
+
+    async fn insert_edge(
+        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+        transaction: &Transaction,
+        user_id: &str,
+        request: CreateEdgeRequest,
+    ) -> Result<EdgeWithMetadata> {
+        let metadata: Value = match request.metadata {
+            Some(v) => {
+                let prost_value = ProstValue {
+                    kind: Some(prost_types::value::Kind::StructValue(v)),
+                };
+                prost_value_to_json_value(prost_value)
+            }
+            None => Value::Object(serde_json::Map::new()),
+        };
 
         // Create the edge with transaction tracking
         let edge = sqlx::query_as!(
@@ -255,6 +1219,7 @@ impl GraphRepository {
                 INSERT INTO triples (
                     relation, 
                     user_id,
+                    created_by,
                     from_id,
                     from_type,
                     to_id,
@@ -262,7 +1227,7 @@ impl GraphRepository {
                     created_xid,
                     deleted_xid
                 )
-                VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+                VALUES ($1, $2, $2, $3, $4, $5, $6, $7, $8)
                 RETURNING 
                     id, 
                     from_type,
@@ -270,6 +1235,7 @@ impl GraphRepository {
                     relation, 
                     to_type,
                     to_id,
+                    created_by,
                     created_at as "created_at?: OffsetDateTime",
                     updated_at as "updated_at?: OffsetDateTime"
             "#,
@@ -282,9 +1248,9 @@ impl GraphRepository {
             transaction.xid as _, // The current transaction's XID
             Xid8::max() as _,     // Max XID value for "not deleted"
         )
-        .fetch_one(&mut *tx)
+        .fetch_one(&mut **tx)
         .await
-        .map_err(|e| anyhow!("Failed to create edge: {}", e))?;
+        .map_err(GraphError::from)?;
 
         // Create initial metadata entry
         sqlx::query!(
@@ -302,106 +1268,377 @@ impl GraphRepository {
             transaction.xid as _,
             Xid8::max() as _,
         )
-        .execute(&mut *tx)
+        .execute(&mut **tx)
         .await
-        .map_err(|e| anyhow!("Failed to create edge metadata: {}", e))?;
+        .map_err(GraphError::from)?;
 
-        info!("Created edge: {:?}", edge);
+        Self::check_relation_rules(tx, &edge).await?;
 
-        // Commit the transaction
-        tx.commit().await?;
+        info!("Created edge: {:?}", edge);
 
-        Ok((
-            EdgeWithMetadata {
-                id: edge.id,
-                from_type: edge.from_type,
-                from_id: edge.from_id,
-                relation: edge.relation,
-                to_type: edge.to_type,
-                to_id: edge.to_id,
-                metadata,
-                created_at: edge.created_at,
-                updated_at: edge.updated_at,
-            },
-            revision,
-        ))
+        Ok(EdgeWithMetadata::from_edge(edge, metadata))
     }
 
-    pub async fn update_object(
-        &self,
-        user_id: String,
-        object_id: i64,
-        metadata: Value,
-    ) -> Result<(ObjectWithMetadata, Revision)> {
-        let mut tx = self.pool.begin().await?;
-        let transaction = Transaction::create(&mut tx).await?;
+    // Enforces the `x-edges` allow-list and `x-relations` rules declared by the
+    // source type's schema, if any, the latter against the source object's
+    // live edges including the new one
+    async fn check_relation_rules(
+        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+        edge: &Edge,
+    ) -> Result<()> {
+        let Some(schema) = Self::source_schema(tx, &edge.from_type).await? else {
+            return Ok(());
+        };
 
-        let revision = transaction.revision();
+        Self::check_edge_allowed(&schema, edge)?;
 
-        // Mark the current metadata version as deleted
+        let Some(rule) = relation_rules(&schema)?
+            .into_iter()
+            .find(|rule| rule.relation == edge.relation)
+        else {
+            return Ok(());
+        };
+
+        // Serialize concurrent edge writes from the same object so the counts
+        // below cannot miss an edge being added alongside this one
         sqlx::query!(
+            "SELECT id FROM objects WHERE id = $1 FOR UPDATE",
+            edge.from_id
+        )
+        .fetch_optional(&mut **tx)
+        .await
+        .map_err(|e| query_error(e, "Failed to lock object"))?;
+
+        let counts = sqlx::query!(
             r#"
-            UPDATE object_metadata_history
-            SET deleted_xid = $1
-            WHERE object_id = $2
-            AND deleted_xid = $3
+            SELECT
+                COUNT(*) as "count!",
+                COUNT(*) FILTER (WHERE to_id = $3) as "duplicates!"
+            FROM triples
+            WHERE from_id = $1
+            AND relation = $2
+            AND deleted_xid = $4
             "#,
-            transaction.xid as _,
-            object_id,
+            edge.from_id,
+            edge.relation,
+            edge.to_id,
             Xid8::max() as _,
         )
-        .execute(&mut *tx)
+        .fetch_one(&mut **tx)
         .await
-        .map_err(|e| anyhow!("Failed to update metadata: {}", e))?;
+        .map_err(|e| anyhow!("Failed to count edges: {}", e))?;
 
-        // Create new metadata version
-        sqlx::query!(
-            r#"
-            INSERT INTO object_metadata_history (
-                object_id,
-                metadata,
-                created_xid,
-                deleted_xid
-            )
-            VALUES ($1, $2, $3, $4)
+        if rule.unique && counts.duplicates > 1 {
+            return Err(GraphError::Conflict(format!(
+                "object {} already has a '{}' edge to object {}",
+                edge.from_id, edge.relation, edge.to_id
+            ))
+            .into());
+        }
+        if let Some(max) = rule.max.filter(|&max| counts.count > max) {
+            return Err(CardinalityError {
+                from_id: edge.from_id,
+                relation: edge.relation.clone(),
+                max,
+            }
+            .into());
+        }
+
+        Ok(())
+    }
+
+    // The latest schema registered for the type edges leave from, if any
+    async fn source_schema(
+        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+        from_type: &str,
+    ) -> Result<Option<Value>> {
+        sqlx::query_scalar!(
+            r#"
+            SELECT schema as "schema: Value"
+            FROM schemata
+            WHERE type_name = $1
+            ORDER BY id DESC
+            LIMIT 1
+            "#,
+            from_type
+        )
+        .fetch_optional(&mut **tx)
+        .await
+        .map_err(|e| anyhow!("Failed to fetch schema: {}", e))
+    }
+
+    // Rejects an edge the source schema's `x-edges` list, if set, leaves out
+    fn check_edge_allowed(schema: &Value, edge: &Edge) -> Result<()> {
+        if let Some(allowed) = allowed_edges(schema)? {
+            let permitted = allowed
+                .get(&edge.relation)
+                .is_some_and(|targets| targets.contains(&edge.to_type));
+            if !permitted {
+                return Err(RelationNotAllowedError {
+                    from_type: edge.from_type.clone(),
+                    relation: edge.relation.clone(),
+                    to_type: edge.to_type.clone(),
+                }
+                .into());
+            }
+        }
+        Ok(())
+    }
+
+    pub async fn update_object(
+        &self,
+        tx_metadata: TransactionMetadata,
+        object_id: i64,
+        metadata: Value,
+        expected_revision: Option<&Revision>,
+    ) -> Result<(ObjectWithMetadata, Revision)> {
+        let mut tx = self.pool.begin().await?;
+        Self::lock_object(&mut tx, object_id).await?;
+        if let Some(expected) = expected_revision {
+            Self::check_expected_revision(&mut tx, object_id, expected).await?;
+        }
+
+        let transaction = Transaction::create_with_metadata(&mut tx, (&tx_metadata).into()).await?;
+
+        let revision = transaction.revision();
+
+        let object = Self::replace_metadata(
+            &mut tx,
+            transaction.xid,
+            &tx_metadata.user_id,
+            object_id,
+            &metadata,
+        )
+        .await?;
+
+        // Commit the transaction
+        tx.commit().await?;
+
+        info!(
+            user_id = %tx_metadata.user_id,
+            object_id = object.id,
+            "Updated object"
+        );
+
+        Ok((
+            ObjectWithMetadata {
+                id: object.id,
+                type_name: object.type_name,
+                metadata,
+                created_by: object.created_by,
+                updated_by: object.updated_by,
+                created_at: object.created_at,
+                updated_at: object.updated_at,
+            },
+            revision,
+        ))
+    }
+
+    /// Applies `patch` to the object's current metadata as an RFC 7386 JSON
+    /// Merge Patch and stores the result as a new version. Concurrent patches
+    /// of the same object are serialized, so none of them loses another's
+    /// edits. `prepare` sees the merged document before it is written and may
+    /// adjust or reject it.
+    pub async fn patch_object<F>(
+        &self,
+        tx_metadata: TransactionMetadata,
+        object_id: i64,
+        patch: &Value,
+        expected_revision: Option<&Revision>,
+        prepare: F,
+    ) -> Result<(ObjectWithMetadata, Revision)>
+    where
+        F: FnOnce(&mut Value) -> Result<()>,
+    {
+        let mut tx = self.pool.begin().await?;
+
+        // Hold the object until commit so the metadata read below is the
+        // version this patch replaces
+        Self::lock_object(&mut tx, object_id).await?;
+        if let Some(expected) = expected_revision {
+            Self::check_expected_revision(&mut tx, object_id, expected).await?;
+        }
+
+        let mut metadata = Self::current_metadata(&mut tx, object_id).await?;
+
+        merge_patch(&mut metadata, patch);
+        prepare(&mut metadata)?;
+
+        let transaction = Transaction::create_with_metadata(&mut tx, (&tx_metadata).into()).await?;
+        let revision = transaction.revision();
+
+        let object = Self::replace_metadata(
+            &mut tx,
+            transaction.xid,
+            &tx_metadata.user_id,
+            object_id,
+            &metadata,
+        )
+        .await?;
+
+        tx.commit().await?;
+
+        info!(
+            user_id = %tx_metadata.user_id,
+            object_id = object.id,
+            "Patched object"
+        );
+
+        Ok((
+            ObjectWithMetadata {
+                id: object.id,
+                type_name: object.type_name,
+                metadata,
+                created_by: object.created_by,
+                updated_by: object.updated_by,
+                created_at: object.created_at,
+                updated_at: object.updated_at,
+            },
+            revision,
+        ))
+    }
+
+    /// Changes the object's type to `type_name`, along with the types recorded
+    /// on its live edges. `check` sees the object's current metadata with the
+    /// object locked, and may reject the change. The retyped edges must satisfy
+    /// the `x-edges` and `x-relations` rules of their source types, or the
+    /// change is rejected with `RelationNotAllowedError` or `CardinalityError`.
+    /// Types are not versioned, so reads at earlier revisions see the object
+    /// and its live edges with the new type; deleted edges keep the old one.
+    /// The writing user is recorded as the object's last writer.
+    pub async fn retype_object<F>(
+        &self,
+        tx_metadata: TransactionMetadata,
+        object_id: i64,
+        type_name: &str,
+        check: F,
+    ) -> Result<(ObjectWithMetadata, Revision)>
+    where
+        F: FnOnce(&Value) -> Result<()>,
+    {
+        let mut tx = self.pool.begin().await?;
+        Self::lock_object(&mut tx, object_id).await?;
+
+        let metadata = sqlx::query_scalar!(
+            r#"
+            SELECT metadata as "metadata: Value"
+            FROM object_metadata_history
+            WHERE object_id = $1
+            AND deleted_xid = $2
             "#,
             object_id,
-            metadata,
-            transaction.xid as _,
             Xid8::max() as _,
         )
-        .execute(&mut *tx)
+        .fetch_optional(&mut *tx)
         .await
-        .map_err(|e| anyhow!("Failed to create metadata: {}", e))?;
+        .map_err(|e| anyhow!("Failed to fetch metadata: {}", e))?
+        .ok_or_else(|| GraphError::NotFound(format!("object {} has been deleted", object_id)))?;
+
+        check(&metadata)?;
+
+        let transaction = Transaction::create_with_metadata(&mut tx, (&tx_metadata).into()).await?;
+        let revision = transaction.revision();
 
-        // Update the object's updated_at timestamp
         let object = sqlx::query_as!(
             Object,
             r#"
             UPDATE objects
-            SET updated_at = NOW(),
-                user_id = $1
+            SET type = $1,
+                updated_at = NOW(),
+                user_id = $3
             WHERE id = $2
-            RETURNING 
+            RETURNING
                 id,
                 type as type_name,
+                created_by,
+                user_id as updated_by,
                 created_at as "created_at?: OffsetDateTime",
                 updated_at as "updated_at?: OffsetDateTime"
             "#,
-            user_id,
+            type_name,
             object_id,
+            tx_metadata.user_id,
         )
         .fetch_one(&mut *tx)
         .await
-        .map_err(|e| anyhow!("Failed to update object: {}", e))?;
+        .map_err(|e| anyhow!("Failed to retype object: {}", e))?;
+
+        let outgoing = sqlx::query_as!(
+            Edge,
+            r#"
+            UPDATE triples
+            SET from_type = $1
+            WHERE from_id = $2
+            AND deleted_xid = $3
+            RETURNING
+                id,
+                from_type,
+                from_id,
+                relation,
+                to_type,
+                to_id,
+                created_by,
+                created_at as "created_at?: OffsetDateTime",
+                updated_at as "updated_at?: OffsetDateTime"
+            "#,
+            type_name,
+            object_id,
+            Xid8::max() as _,
+        )
+        .fetch_all(&mut *tx)
+        .await
+        .map_err(|e| anyhow!("Failed to retype edges: {}", e))?;
+
+        let incoming = sqlx::query_as!(
+            Edge,
+            r#"
+            UPDATE triples
+            SET to_type = $1
+            WHERE to_id = $2
+            AND deleted_xid = $3
+            RETURNING
+                id,
+                from_type,
+                from_id,
+                relation,
+                to_type,
+                to_id,
+                created_by,
+                created_at as "created_at?: OffsetDateTime",
+                updated_at as "updated_at?: OffsetDateTime"
+            "#,
+            type_name,
+            object_id,
+            Xid8::max() as _,
+        )
+        .fetch_all(&mut *tx)
+        .await
+        .map_err(|e| anyhow!("Failed to retype edges: {}", e))?;
+
+        // Outgoing edges now answer to the new type's rules in full. Incoming
+        // ones keep their source's cardinality, so only its allow-list can
+        // newly reject them
+        for edge in &outgoing {
+            Self::check_relation_rules(&mut tx, edge).await?;
+        }
+        let mut schemas = HashMap::new();
+        for edge in &incoming {
+            if !schemas.contains_key(&edge.from_type) {
+                let schema = Self::source_schema(&mut tx, &edge.from_type).await?;
+                schemas.insert(edge.from_type.clone(), schema);
+            }
+            if let Some(schema) = &schemas[&edge.from_type] {
+                Self::check_edge_allowed(schema, edge)?;
+            }
+        }
 
-        // Commit the transaction
         tx.commit().await?;
 
         info!(
-            user_id = %user_id,
+            user_id = %tx_metadata.user_id,
             object_id = object.id,
-            "Updated object"
+            type_name = %object.type_name,
+            "Retyped object"
         );
 
         Ok((
@@ -409,6 +1646,8 @@ impl GraphRepository {
                 id: object.id,
                 type_name: object.type_name,
                 metadata,
+                created_by: object.created_by,
+                updated_by: object.updated_by,
                 created_at: object.created_at,
                 updated_at: object.updated_at,
             },
@@ -416,14 +1655,132 @@ impl GraphRepository {
         ))
     }
 
+    // Serializes writers of the object until the transaction ends
+    async fn lock_object(
+        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+        object_id: i64,
+    ) -> Result<()> {
+        sqlx::query!("SELECT id FROM objects WHERE id = $1 FOR UPDATE", object_id)
+            .fetch_optional(&mut **tx)
+            .await
+            .map_err(|e| query_error(e, "Failed to lock object"))?;
+
+        Ok(())
+    }
+
+    // Fails with `RevisionConflictError` if the object's current metadata was
+    // written by a transaction `expected` doesn't include. Call with the object
+    // locked so the answer holds until commit.
+    async fn check_expected_revision(
+        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+        object_id: i64,
+        expected: &Revision,
+    ) -> Result<()> {
+        let created_xid = sqlx::query_scalar!(
+            r#"
+            SELECT created_xid as "created_xid: Xid8"
+            FROM object_metadata_history
+            WHERE object_id = $1
+            AND deleted_xid = $2
+            "#,
+            object_id,
+            Xid8::max() as _,
+        )
+        .fetch_optional(&mut **tx)
+        .await
+        .map_err(|e| anyhow!("Failed to fetch metadata: {}", e))?;
+
+        match created_xid {
+            Some(xid) if !expected.includes(xid.value()) => {
+                Err(RevisionConflictError { object_id }.into())
+            }
+            _ => Ok(()),
+        }
+    }
+
+    // Closes the object's current metadata version and opens `metadata` as the
+    // next one at `xid`. `user_id` is recorded as the last writer, reported as
+    // `updated_by`; the object stays owned by `created_by`
+    async fn replace_metadata(
+        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+        xid: Xid8,
+        user_id: &str,
+        object_id: i64,
+        metadata: &Value,
+    ) -> Result<Object> {
+        // Mark the current metadata version as deleted
+        sqlx::query!(
+            r#"
+            UPDATE object_metadata_history
+            SET deleted_xid = $1
+            WHERE object_id = $2
+            AND deleted_xid = $3
+            "#,
+            xid as _,
+            object_id,
+            Xid8::max() as _,
+        )
+        .execute(&mut **tx)
+        .await
+        .map_err(|e| anyhow!("Failed to update metadata: {}", e))?;
+
+        // Create new metadata version
+        sqlx::query!(
+            r#"
+            INSERT INTO object_metadata_history (
+                object_id,
+                metadata,
+                created_xid,
+                deleted_xid
+            )
+            VALUES ($1, $2, $3, $4)
+            "#,
+            object_id,
+            metadata,
+            xid as _,
+            Xid8::max() as _,
+        )
+        .execute(&mut **tx)
+        .await
+        .map_err(|e| anyhow!("Failed to create metadata: {}", e))?;
+
+        // Update the object's updated_at timestamp
+        let object = sqlx::query_as!(
+            Object,
+            r#"
+            UPDATE objects
+            SET updated_at = NOW(),
+                user_id = $1
+            WHERE id = $2
+            AND deleted_xid = $3
+            RETURNING 
+                id,
+                type as type_name,
+                created_by,
+                user_id as updated_by,
+                created_at as "created_at?: OffsetDateTime",
+                updated_at as "updated_at?: OffsetDateTime"
+            "#,
+            user_id,
+            object_id,
+            Xid8::max() as _,
+        )
+        .fetch_optional(&mut **tx)
+        .await
+        .map_err(|e| anyhow!("Failed to update object: {}", e))?
+        .ok_or_else(|| GraphError::NotFound(format!("object {} has been deleted", object_id)))?;
+
+        Ok(object)
+    }
+
     pub async fn update_edge(
         &self,
-        user_id: String,
+        tx_metadata: TransactionMetadata,
         edge_id: i64,
         metadata: Value,
     ) -> Result<(EdgeWithMetadata, Revision)> {
         let mut tx = self.pool.begin().await?;
-        let transaction = Transaction::create(&mut tx).await?;
+        let transaction = Transaction::create_with_metadata(&mut tx, (&tx_metadata).into()).await?;
 
         let revision = transaction.revision();
 
@@ -471,6 +1828,7 @@ impl GraphRepository {
             SET updated_at = NOW(),
                 user_id = $1
             WHERE id = $2
+            AND deleted_xid = $3
             RETURNING 
                 id,
                 from_type,
@@ -478,15 +1836,18 @@ impl GraphRepository {
                 relation,
                 to_type,
                 to_id,
+                created_by,
                 created_at as "created_at?: OffsetDateTime",
                 updated_at as "updated_at?: OffsetDateTime"
             "#,
-            user_id,
+            tx_metadata.user_id,
             edge_id,
+            Xid8::max() as _,
         )
-        .fetch_one(&mut *tx)
+        .fetch_optional(&mut *tx)
         .await
-        .map_err(|e| anyhow!("Failed to update edge: {}", e))?;
+        .map_err(|e| anyhow!("Failed to update edge: {}", e))?
+        .ok_or_else(|| GraphError::NotFound(format!("edge {} has been deleted", edge_id)))?;
 
         // Commit the transaction
         tx.commit().await?;
@@ -500,6 +1861,7 @@ impl GraphRepository {
                 to_type: edge.to_type,
                 to_id: edge.to_id,
                 metadata,
+                created_by: edge.created_by,
                 created_at: edge.created_at,
                 updated_at: edge.updated_at,
             },
@@ -507,19 +1869,204 @@ impl GraphRepository {
         ))
     }
 
-    #[instrument(skip(self))]
-    pub async fn get_object(
+    /// Tombstones every live `relation` edge leaving `from_id` in a single
+    /// transaction, returning how many were deleted. Each edge records the
+    /// writing user as its last writer.
+    pub async fn delete_edges_by(
+        &self,
+        tx_metadata: TransactionMetadata,
+        from_id: i64,
+        relation: &str,
+    ) -> Result<(u64, Revision)> {
+        let mut tx = self.pool.begin().await?;
+        let transaction = Transaction::create_with_metadata(&mut tx, (&tx_metadata).into()).await?;
+
+        let revision = transaction.revision();
+
+        let deleted = sqlx::query!(
+            r#"
+            UPDATE triples
+            SET deleted_xid = $1,
+                updated_at = NOW(),
+                user_id = $2
+            WHERE from_id = $3
+            AND relation = $4
+            AND deleted_xid = $5
+            "#,
+            transaction.xid as _,
+            tx_metadata.user_id,
+            from_id,
+            relation,
+            Xid8::max() as _,
+        )
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| anyhow!("Failed to delete edges: {}", e))?
+        .rows_affected();
+
+        info!("Deleted {} '{}' edges from {}", deleted, relation, from_id);
+
+        // Commit the transaction
+        tx.commit().await?;
+
+        Ok((deleted, revision))
+    }
+
+    /// Tombstones an object, its current metadata and every live edge from or
+    /// to it. Returns `None` if the object is not live. The writing user is
+    /// recorded as the last writer of each row, leaving `created_by` alone.
+    pub async fn delete_object(
         &self,
+        tx_metadata: TransactionMetadata,
         id: i64,
-        consistency: ConsistencyMode,
-    ) -> Result<Option<ObjectWithMetadata>> {
-        let object = match &consistency {
-            ConsistencyMode::Full => sqlx::query_as!(
-                Object,
+    ) -> Result<Option<Revision>> {
+        let mut tx = self.pool.begin().await?;
+        let transaction = Transaction::create_with_metadata(&mut tx, (&tx_metadata).into()).await?;
+
+        let revision = transaction.revision();
+
+        let deleted = sqlx::query!(
+            r#"
+            UPDATE objects
+            SET deleted_xid = $1,
+                updated_at = NOW(),
+                user_id = $2
+            WHERE id = $3
+            AND deleted_xid = $4
+            "#,
+            transaction.xid as _,
+            tx_metadata.user_id,
+            id,
+            Xid8::max() as _,
+        )
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| anyhow!("Failed to delete object: {}", e))?
+        .rows_affected();
+
+        if deleted == 0 {
+            return Ok(None);
+        }
+
+        sqlx::query!(
+            r#"
+            UPDATE object_metadata_history
+            SET deleted_xid = $1
+            WHERE object_id = $2
+            AND deleted_xid = $3
+            "#,
+            transaction.xid as _,
+            id,
+            Xid8::max() as _,
+        )
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| anyhow!("Failed to delete object metadata: {}", e))?;
+
+        let edges = sqlx::query!(
+            r#"
+            UPDATE triples
+            SET deleted_xid = $1,
+                updated_at = NOW(),
+                user_id = $2
+            WHERE (from_id = $3 OR to_id = $3)
+            AND deleted_xid = $4
+            "#,
+            transaction.xid as _,
+            tx_metadata.user_id,
+            id,
+            Xid8::max() as _,
+        )
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| anyhow!("Failed to delete object edges: {}", e))?
+        .rows_affected();
+
+        info!("Deleted object {} and {} edges", id, edges);
+
+        // Commit the transaction
+        tx.commit().await?;
+
+        Ok(Some(revision))
+    }
+
+    /// Tombstones a single edge. Returns `None` if the edge is not live.
+    pub async fn delete_edge(
+        &self,
+        tx_metadata: TransactionMetadata,
+        edge_id: i64,
+    ) -> Result<Option<Revision>> {
+        let mut tx = self.pool.begin().await?;
+        let transaction = Transaction::create_with_metadata(&mut tx, (&tx_metadata).into()).await?;
+
+        let revision = transaction.revision();
+
+        let deleted = sqlx::query!(
+            r#"
+            UPDATE triples
+            SET deleted_xid = $1,
+                updated_at = NOW(),
+                user_id = $2
+            WHERE id = $3
+            AND deleted_xid = $4
+            "#,
+            transaction.xid as _,
+            tx_metadata.user_id,
+            edge_id,
+            Xid8::max() as _,
+        )
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| anyhow!("Failed to delete edge: {}", e))?
+        .rows_affected();
+
+        if deleted == 0 {
+            return Ok(None);
+        }
+
+        info!("Deleted edge {}", edge_id);
+
+        // Commit the transaction
+        tx.commit().await?;
+
+        Ok(Some(revision))
+    }
+
+    /// Source object of a live edge
+    pub async fn edge_source(&self, edge_id: i64) -> Result<Option<i64>> {
+        let from_id = sqlx::query_scalar!(
+            r#"
+            SELECT from_id
+            FROM triples
+            WHERE id = $1
+            AND deleted_xid = $2
+            "#,
+            edge_id,
+            Xid8::max() as _,
+        )
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(|e| anyhow!("Failed to fetch edge: {}", e))?;
+
+        Ok(from_id)
+    }
+
+    #[instrument(skip(self))]
+    pub async fn get_object(
+        &self,
+        id: i64,
+        consistency: ConsistencyMode,
+    ) -> Result<Option<ObjectWithMetadata>> {
+        let consistency = self.resolve_consistency(consistency).await?;
+        let object = match &consistency {
+            ConsistencyMode::Full => sqlx::query_as!(
+                Object,
                 r#"
                     SELECT 
                         o.id,
                         o.type as type_name,
+                        o.created_by,
+                        o.user_id as updated_by,
                         o.created_at as "created_at?: OffsetDateTime",
                         o.updated_at as "updated_at?: OffsetDateTime"
                     FROM objects o
@@ -538,12 +2085,16 @@ impl GraphRepository {
                     SELECT 
                         o.id,
                         o.type as type_name,
+                        o.created_by,
+                        o.user_id as updated_by,
                         o.created_at as "created_at?: OffsetDateTime",
                         o.updated_at as "updated_at?: OffsetDateTime"
                     FROM objects o
                     WHERE o.id = $1
+                    AND o.deleted_xid = $2
                     "#,
-                id
+                id,
+                Xid8::max() as _,
             )
             .fetch_optional(&self.pool)
             .await
@@ -558,15 +2109,17 @@ impl GraphRepository {
                     SELECT 
                         o.id,
                         o.type as type_name,
+                        o.created_by,
+                        o.user_id as updated_by,
                         o.created_at as "created_at?: OffsetDateTime",
                         o.updated_at as "updated_at?: OffsetDateTime"
                     FROM objects o, snapshot s
                     WHERE o.id = $1
-                    AND o.created_xid <= pg_snapshot_xmax(s.snapshot)
-                    AND o.deleted_xid > pg_snapshot_xmax(s.snapshot)
+                    AND pg_visible_in_snapshot(o.created_xid, s.snapshot)
+                    AND NOT pg_visible_in_snapshot(o.deleted_xid, s.snapshot)
                     "#,
                     id,
-                    _revision.snapshot_string()
+                    _revision.included().to_string()
                 )
                 .fetch_optional(&self.pool)
                 .await
@@ -597,10 +2150,10 @@ impl GraphRepository {
                         SELECT metadata
                         FROM object_metadata_history
                         WHERE object_id = $1
-                        ORDER BY created_xid DESC
-                        LIMIT 1
+                        AND deleted_xid = $2
                         "#,
-                    id
+                    id,
+                    Xid8::max() as _,
                 )
                 .fetch_one(&self.pool)
                 .await
@@ -609,42 +2162,1043 @@ impl GraphRepository {
                 | ConsistencyMode::ExactlyAt(_revision) => sqlx::query_as!(
                     MetadataRecord,
                     r#"
-                        WITH snapshot AS (
-                            SELECT $2::text::pg_snapshot as snapshot
-                        )
-                        SELECT metadata
-                        FROM object_metadata_history h, snapshot s
-                        WHERE h.object_id = $1
-                        AND h.created_xid <= pg_snapshot_xmax(s.snapshot)
-                        AND h.deleted_xid > pg_snapshot_xmax(s.snapshot)
-                        "#,
-                    id,
-                    _revision.snapshot_string()
+                        WITH snapshot AS (
+                            SELECT $2::text::pg_snapshot as snapshot
+                        )
+                        SELECT metadata
+                        FROM object_metadata_history h, snapshot s
+                        WHERE h.object_id = $1
+                        AND pg_visible_in_snapshot(h.created_xid, s.snapshot)
+                        AND NOT pg_visible_in_snapshot(h.deleted_xid, s.snapshot)
+                        "#,
+                    id,
+                    _revision.included().to_string()
+                )
+                .fetch_one(&self.pool)
+                .await
+                .map_err(|e| anyhow!("Failed to fetch metadata: {}", e))?,
+            };
+
+            Ok(Some(ObjectWithMetadata {
+                id: object.id,
+                type_name: object.type_name,
+                metadata: metadata.into_value(),
+                created_by: object.created_by,
+                updated_by: object.updated_by,
+                created_at: object.created_at,
+                updated_at: object.updated_at,
+            }))
+        } else {
+            Ok(None)
+        }
+    }
+
+    /// Several objects in one query, in the order of `ids`, which must not
+    /// repeat. Ids that don't resolve to a visible object are left out.
+    #[instrument(skip(self))]
+    pub async fn get_objects(
+        &self,
+        ids: &[i64],
+        consistency: ConsistencyMode,
+    ) -> Result<Vec<ObjectWithMetadata>> {
+        let consistency = self.resolve_consistency(consistency).await?;
+        let objects = match &consistency {
+            ConsistencyMode::Full => sqlx::query_as!(
+                ObjectWithMetadata,
+                r#"
+                    SELECT
+                        o.id,
+                        o.type as type_name,
+                        h.metadata as "metadata: Value",
+                        o.created_by,
+                        o.user_id as updated_by,
+                        o.created_at as "created_at?: OffsetDateTime",
+                        o.updated_at as "updated_at?: OffsetDateTime"
+                    FROM objects o
+                    JOIN object_metadata_history h ON o.id = h.object_id
+                    WHERE o.id = ANY($1)
+                    AND o.created_xid <= pg_current_xact_id()
+                    AND o.deleted_xid > pg_current_xact_id()
+                    AND h.created_xid <= pg_current_xact_id()
+                    AND h.deleted_xid > pg_current_xact_id()
+                    "#,
+                ids
+            )
+            .fetch_all(&self.pool)
+            .await
+            .map_err(|e| anyhow!("Failed to fetch objects: {}", e))?,
+            ConsistencyMode::MinimizeLatency => sqlx::query_as!(
+                ObjectWithMetadata,
+                r#"
+                    SELECT
+                        o.id,
+                        o.type as type_name,
+                        h.metadata as "metadata!: Value",
+                        o.created_by,
+                        o.user_id as updated_by,
+                        o.created_at as "created_at?: OffsetDateTime",
+                        o.updated_at as "updated_at?: OffsetDateTime"
+                    FROM objects o
+                    JOIN LATERAL (
+                        SELECT metadata
+                        FROM object_metadata_history
+                        WHERE object_id = o.id
+                        ORDER BY created_xid DESC
+                        LIMIT 1
+                    ) h ON true
+                    WHERE o.id = ANY($1)
+                    AND o.deleted_xid = $2
+                    "#,
+                ids,
+                Xid8::max() as _,
+            )
+            .fetch_all(&self.pool)
+            .await
+            .map_err(|e| anyhow!("Failed to fetch objects: {}", e))?,
+            ConsistencyMode::AtLeastAsFresh(_revision) | ConsistencyMode::ExactlyAt(_revision) => {
+                sqlx::query_as!(
+                    ObjectWithMetadata,
+                    r#"
+                    WITH snapshot AS (
+                        SELECT $2::text::pg_snapshot as snapshot
+                    )
+                    SELECT
+                        o.id,
+                        o.type as type_name,
+                        h.metadata as "metadata: Value",
+                        o.created_by,
+                        o.user_id as updated_by,
+                        o.created_at as "created_at?: OffsetDateTime",
+                        o.updated_at as "updated_at?: OffsetDateTime"
+                    FROM snapshot s, objects o
+                    JOIN object_metadata_history h ON o.id = h.object_id
+                    WHERE o.id = ANY($1)
+                    AND pg_visible_in_snapshot(o.created_xid, s.snapshot)
+                    AND NOT pg_visible_in_snapshot(o.deleted_xid, s.snapshot)
+                    AND pg_visible_in_snapshot(h.created_xid, s.snapshot)
+                    AND NOT pg_visible_in_snapshot(h.deleted_xid, s.snapshot)
+                    "#,
+                    ids,
+                    _revision.included().to_string()
+                )
+                .fetch_all(&self.pool)
+                .await
+                .map_err(|e| anyhow!("Failed to fetch objects: {}", e))?
+            }
+        };
+
+        let mut by_id: HashMap<i64, ObjectWithMetadata> = objects
+            .into_iter()
+            .map(|object| (object.id, object))
+            .collect();
+        Ok(ids.iter().filter_map(|id| by_id.remove(id)).collect())
+    }
+
+    /// Up to `limit` objects of `type_name` owned by `user_id` with IDs above
+    /// `cursor`, in ID order. Pages by key rather than offset so late pages of
+    /// large types stay cheap.
+    pub async fn list_objects(
+        &self,
+        type_name: &str,
+        user_id: &str,
+        limit: i64,
+        cursor: i64,
+        consistency: ConsistencyMode,
+    ) -> Result<Vec<ObjectWithMetadata>> {
+        let consistency = self.resolve_consistency(consistency).await?;
+        let objects = match &consistency {
+            ConsistencyMode::Full => sqlx::query_as!(
+                ObjectWithMetadata,
+                r#"
+                    SELECT
+                        o.id,
+                        o.type as type_name,
+                        h.metadata as "metadata: Value",
+                        o.created_by,
+                        o.user_id as updated_by,
+                        o.created_at as "created_at?: OffsetDateTime",
+                        o.updated_at as "updated_at?: OffsetDateTime"
+                    FROM objects o
+                    JOIN object_metadata_history h ON o.id = h.object_id
+                    WHERE o.type = $1
+                    AND o.created_by = $2
+                    AND o.id > $3
+                    AND o.created_xid <= pg_current_xact_id()
+                    AND o.deleted_xid > pg_current_xact_id()
+                    AND h.created_xid <= pg_current_xact_id()
+                    AND h.deleted_xid > pg_current_xact_id()
+                    ORDER BY o.id
+                    LIMIT $4
+                    "#,
+                type_name,
+                user_id,
+                cursor,
+                limit
+            )
+            .fetch_all(&self.pool)
+            .await
+            .map_err(|e| anyhow!("Failed to list objects: {}", e))?,
+            ConsistencyMode::MinimizeLatency => sqlx::query_as!(
+                ObjectWithMetadata,
+                r#"
+                    SELECT
+                        o.id,
+                        o.type as type_name,
+                        h.metadata as "metadata!: Value",
+                        o.created_by,
+                        o.user_id as updated_by,
+                        o.created_at as "created_at?: OffsetDateTime",
+                        o.updated_at as "updated_at?: OffsetDateTime"
+                    FROM objects o
+                    JOIN LATERAL (
+                        SELECT metadata
+                        FROM object_metadata_history
+                        WHERE object_id = o.id
+                        ORDER BY created_xid DESC
+                        LIMIT 1
+                    ) h ON true
+                    WHERE o.type = $1
+                    AND o.created_by = $2
+                    AND o.id > $3
+                    AND o.deleted_xid = $5
+                    ORDER BY o.id
+                    LIMIT $4
+                    "#,
+                type_name,
+                user_id,
+                cursor,
+                limit,
+                Xid8::max() as _,
+            )
+            .fetch_all(&self.pool)
+            .await
+            .map_err(|e| anyhow!("Failed to list objects: {}", e))?,
+            ConsistencyMode::AtLeastAsFresh(revision) | ConsistencyMode::ExactlyAt(revision) => {
+                sqlx::query_as!(
+                    ObjectWithMetadata,
+                    r#"
+                    WITH snapshot AS (
+                        SELECT $5::text::pg_snapshot as snapshot
+                    )
+                    SELECT
+                        o.id,
+                        o.type as type_name,
+                        h.metadata as "metadata: Value",
+                        o.created_by,
+                        o.user_id as updated_by,
+                        o.created_at as "created_at?: OffsetDateTime",
+                        o.updated_at as "updated_at?: OffsetDateTime"
+                    FROM snapshot s, objects o
+                    JOIN object_metadata_history h ON o.id = h.object_id
+                    WHERE o.type = $1
+                    AND o.created_by = $2
+                    AND o.id > $3
+                    AND pg_visible_in_snapshot(o.created_xid, s.snapshot)
+                    AND NOT pg_visible_in_snapshot(o.deleted_xid, s.snapshot)
+                    AND pg_visible_in_snapshot(h.created_xid, s.snapshot)
+                    AND NOT pg_visible_in_snapshot(h.deleted_xid, s.snapshot)
+                    ORDER BY o.id
+                    LIMIT $4
+                    "#,
+                    type_name,
+                    user_id,
+                    cursor,
+                    limit,
+                    revision.included().to_string()
+                )
+                .fetch_all(&self.pool)
+                .await
+                .map_err(|e| anyhow!("Failed to list objects: {}", e))?
+            }
+        };
+
+        Ok(objects)
+    }
+
+    /// A page of the objects of `type_name` owned by `user_id`, in ID order
+    /// after `cursor`, whose metadata matches `field_match`. The field name is
+    /// inlined into the query so that an `x-indexed` index on it applies, and
+    /// must pass `is_indexable_field`.
+    pub async fn find_objects(
+        &self,
+        type_name: &str,
+        user_id: &str,
+        field_match: FieldMatch<'_>,
+        limit: i64,
+        cursor: i64,
+        consistency: ConsistencyMode,
+    ) -> Result<Vec<ObjectWithMetadata>> {
+        let FieldMatch {
+            field,
+            value,
+            ignore_case,
+        } = field_match;
+        if !is_indexable_field(field) {
+            bail!("Cannot look objects up by field '{}'", field);
+        }
+
+        let consistency = self.resolve_consistency(consistency).await?;
+        let matches = if ignore_case {
+            format!("lower(h.metadata->>'{field}') = lower($3)")
+        } else {
+            format!("h.metadata->>'{field}' = $3")
+        };
+        let columns = r#"
+            o.id,
+            o.type as type_name,
+            h.metadata,
+            o.created_by,
+            o.user_id as updated_by,
+            o.created_at,
+            o.updated_at
+        "#;
+        let query = match &consistency {
+            ConsistencyMode::Full => format!(
+                r#"
+                SELECT {columns}
+                FROM objects o
+                JOIN object_metadata_history h ON o.id = h.object_id
+                WHERE o.type = $1
+                AND o.created_by = $2
+                AND {matches}
+                AND o.id > $4
+                AND o.created_xid <= pg_current_xact_id()
+                AND o.deleted_xid > pg_current_xact_id()
+                AND h.created_xid <= pg_current_xact_id()
+                AND h.deleted_xid > pg_current_xact_id()
+                ORDER BY o.id
+                LIMIT $5
+                "#
+            ),
+            ConsistencyMode::MinimizeLatency => format!(
+                r#"
+                SELECT {columns}
+                FROM objects o
+                JOIN LATERAL (
+                    SELECT metadata
+                    FROM object_metadata_history
+                    WHERE object_id = o.id
+                    ORDER BY created_xid DESC
+                    LIMIT 1
+                ) h ON true
+                WHERE o.type = $1
+                AND o.created_by = $2
+                AND {matches}
+                AND o.id > $4
+                AND o.deleted_xid = $6
+                ORDER BY o.id
+                LIMIT $5
+                "#
+            ),
+            ConsistencyMode::AtLeastAsFresh(_) | ConsistencyMode::ExactlyAt(_) => format!(
+                r#"
+                SELECT {columns}
+                FROM objects o
+                JOIN object_metadata_history h ON o.id = h.object_id
+                WHERE o.type = $1
+                AND o.created_by = $2
+                AND {matches}
+                AND o.id > $4
+                AND pg_visible_in_snapshot(o.created_xid, $6::text::pg_snapshot)
+                AND NOT pg_visible_in_snapshot(o.deleted_xid, $6::text::pg_snapshot)
+                AND pg_visible_in_snapshot(h.created_xid, $6::text::pg_snapshot)
+                AND NOT pg_visible_in_snapshot(h.deleted_xid, $6::text::pg_snapshot)
+                ORDER BY o.id
+                LIMIT $5
+                "#
+            ),
+        };
+
+        let query = sqlx::query_as::<_, ObjectWithMetadata>(&query)
+            .bind(type_name)
+            .bind(user_id)
+            .bind(value)
+            .bind(cursor)
+            .bind(limit);
+        let query = match &consistency {
+            ConsistencyMode::Full => query,
+            ConsistencyMode::MinimizeLatency => query.bind(Xid8::max()),
+            ConsistencyMode::AtLeastAsFresh(revision) | ConsistencyMode::ExactlyAt(revision) => {
+                query.bind(revision.included().to_string())
+            }
+        };
+
+        query
+            .fetch_all(&self.pool)
+            .await
+            .map_err(|e| query_error(e, "Failed to find objects"))
+    }
+
+    /// Streams every object of `type_name` owned by `user_id` in ID order, as
+    /// seen at the given consistency. Rows are read from a single query on a
+    /// background task and handed over through a bounded channel, so neither
+    /// side buffers the whole type. The task stops as soon as the receiver is
+    /// dropped.
+    pub async fn stream_objects(
+        &self,
+        type_name: String,
+        user_id: String,
+        consistency: ConsistencyMode,
+    ) -> Result<ReceiverStream<Result<ObjectWithMetadata>>> {
+        let consistency = self.resolve_consistency(consistency).await?;
+        let pool = self.pool.clone();
+        let (sender, receiver) = mpsc::channel(STREAM_OBJECTS_BUFFER);
+
+        tokio::spawn(async move {
+            let rows = match &consistency {
+                ConsistencyMode::Full => sqlx::query_as!(
+                    ObjectWithMetadata,
+                    r#"
+                    SELECT
+                        o.id,
+                        o.type as type_name,
+                        h.metadata as "metadata: Value",
+                        o.created_by,
+                        o.user_id as updated_by,
+                        o.created_at as "created_at?: OffsetDateTime",
+                        o.updated_at as "updated_at?: OffsetDateTime"
+                    FROM objects o
+                    JOIN object_metadata_history h ON o.id = h.object_id
+                    WHERE o.type = $1
+                    AND o.created_by = $2
+                    AND o.created_xid <= pg_current_xact_id()
+                    AND o.deleted_xid > pg_current_xact_id()
+                    AND h.created_xid <= pg_current_xact_id()
+                    AND h.deleted_xid > pg_current_xact_id()
+                    ORDER BY o.id
+                    "#,
+                    type_name,
+                    user_id
+                )
+                .fetch(&pool),
+                ConsistencyMode::MinimizeLatency => sqlx::query_as!(
+                    ObjectWithMetadata,
+                    r#"
+                    SELECT
+                        o.id,
+                        o.type as type_name,
+                        h.metadata as "metadata!: Value",
+                        o.created_by,
+                        o.user_id as updated_by,
+                        o.created_at as "created_at?: OffsetDateTime",
+                        o.updated_at as "updated_at?: OffsetDateTime"
+                    FROM objects o
+                    JOIN LATERAL (
+                        SELECT metadata
+                        FROM object_metadata_history
+                        WHERE object_id = o.id
+                        ORDER BY created_xid DESC
+                        LIMIT 1
+                    ) h ON true
+                    WHERE o.type = $1
+                    AND o.created_by = $2
+                    AND o.deleted_xid = $3
+                    ORDER BY o.id
+                    "#,
+                    type_name,
+                    user_id,
+                    Xid8::max() as _,
+                )
+                .fetch(&pool),
+                ConsistencyMode::AtLeastAsFresh(revision)
+                | ConsistencyMode::ExactlyAt(revision) => sqlx::query_as!(
+                    ObjectWithMetadata,
+                    r#"
+                    WITH snapshot AS (
+                        SELECT $3::text::pg_snapshot as snapshot
+                    )
+                    SELECT
+                        o.id,
+                        o.type as type_name,
+                        h.metadata as "metadata: Value",
+                        o.created_by,
+                        o.user_id as updated_by,
+                        o.created_at as "created_at?: OffsetDateTime",
+                        o.updated_at as "updated_at?: OffsetDateTime"
+                    FROM snapshot s, objects o
+                    JOIN object_metadata_history h ON o.id = h.object_id
+                    WHERE o.type = $1
+                    AND o.created_by = $2
+                    AND pg_visible_in_snapshot(o.created_xid, s.snapshot)
+                    AND NOT pg_visible_in_snapshot(o.deleted_xid, s.snapshot)
+                    AND pg_visible_in_snapshot(h.created_xid, s.snapshot)
+                    AND NOT pg_visible_in_snapshot(h.deleted_xid, s.snapshot)
+                    ORDER BY o.id
+                    "#,
+                    type_name,
+                    user_id,
+                    revision.included().to_string()
+                )
+                .fetch(&pool),
+            };
+
+            forward_rows(rows, sender, "Failed to stream objects").await;
+        });
+
+        Ok(ReceiverStream::new(receiver))
+    }
+
+    /// Streams every object owned by `user_id`, then every edge, exactly as
+    /// they were at `revision`. Like `stream_objects`, rows are produced by a
+    /// background task that stops when the receiver is dropped.
+    pub fn export_graph(
+        &self,
+        user_id: String,
+        revision: Revision,
+    ) -> ReceiverStream<Result<GraphRecord>> {
+        let pool = self.pool.clone();
+        let (sender, receiver) = mpsc::channel(STREAM_OBJECTS_BUFFER);
+
+        tokio::spawn(async move {
+            let snapshot = revision.included().to_string();
+            let objects = sqlx::query_as!(
+                ObjectWithMetadata,
+                r#"
+                WITH snapshot AS (
+                    SELECT $2::text::pg_snapshot as snapshot
+                )
+                SELECT
+                    o.id,
+                    o.type as type_name,
+                    h.metadata as "metadata: Value",
+                    o.created_by,
+                    o.user_id as updated_by,
+                    o.created_at as "created_at?: OffsetDateTime",
+                    o.updated_at as "updated_at?: OffsetDateTime"
+                FROM snapshot s, objects o
+                JOIN object_metadata_history h ON o.id = h.object_id
+                WHERE o.created_by = $1
+                AND pg_visible_in_snapshot(o.created_xid, s.snapshot)
+                AND NOT pg_visible_in_snapshot(o.deleted_xid, s.snapshot)
+                AND pg_visible_in_snapshot(h.created_xid, s.snapshot)
+                AND NOT pg_visible_in_snapshot(h.deleted_xid, s.snapshot)
+                ORDER BY o.id
+                "#,
+                user_id,
+                snapshot
+            )
+            .fetch(&pool)
+            .map(|row| row.map(GraphRecord::Object));
+            let edges = sqlx::query_as!(
+                EdgeWithMetadata,
+                r#"
+                WITH snapshot AS (
+                    SELECT $2::text::pg_snapshot as snapshot
+                )
+                SELECT
+                    t.id,
+                    t.from_type,
+                    t.from_id,
+                    t.relation,
+                    t.to_type,
+                    t.to_id,
+                    eh.metadata as "metadata: Value",
+                    t.created_by,
+                    t.created_at as "created_at?: OffsetDateTime",
+                    t.updated_at as "updated_at?: OffsetDateTime"
+                FROM snapshot s, triples t
+                JOIN edge_metadata_history eh ON t.id = eh.edge_id
+                WHERE t.created_by = $1
+                AND pg_visible_in_snapshot(t.created_xid, s.snapshot)
+                AND NOT pg_visible_in_snapshot(t.deleted_xid, s.snapshot)
+                AND pg_visible_in_snapshot(eh.created_xid, s.snapshot)
+                AND NOT pg_visible_in_snapshot(eh.deleted_xid, s.snapshot)
+                ORDER BY t.id
+                "#,
+                user_id,
+                snapshot
+            )
+            .fetch(&pool)
+            .map(|row| row.map(GraphRecord::Edge));
+
+            forward_rows(objects.chain(edges), sender, "Failed to export graph").await;
+        });
+
+        ReceiverStream::new(receiver)
+    }
+
+    /// Counts the objects of `type_name` owned by `user_id` that are visible at
+    /// the given consistency.
+    pub async fn count_objects(
+        &self,
+        type_name: &str,
+        user_id: &str,
+        consistency: ConsistencyMode,
+    ) -> Result<i64> {
+        let consistency = self.resolve_consistency(consistency).await?;
+        let count = match &consistency {
+            ConsistencyMode::Full => sqlx::query_scalar!(
+                r#"
+                    SELECT COUNT(*) as "count!"
+                    FROM objects
+                    WHERE type = $1
+                    AND created_by = $2
+                    AND created_xid <= pg_current_xact_id()
+                    AND deleted_xid > pg_current_xact_id()
+                    "#,
+                type_name,
+                user_id
+            )
+            .fetch_one(&self.pool)
+            .await
+            .map_err(|e| anyhow!("Failed to count objects: {}", e))?,
+            ConsistencyMode::MinimizeLatency => sqlx::query_scalar!(
+                r#"
+                    SELECT COUNT(*) as "count!"
+                    FROM objects
+                    WHERE type = $1
+                    AND created_by = $2
+                    AND deleted_xid = $3
+                    "#,
+                type_name,
+                user_id,
+                Xid8::max() as _,
+            )
+            .fetch_one(&self.pool)
+            .await
+            .map_err(|e| anyhow!("Failed to count objects: {}", e))?,
+            ConsistencyMode::AtLeastAsFresh(revision) | ConsistencyMode::ExactlyAt(revision) => {
+                sqlx::query_scalar!(
+                    r#"
+                    WITH snapshot AS (
+                        SELECT $3::text::pg_snapshot as snapshot
+                    )
+                    SELECT COUNT(*) as "count!"
+                    FROM snapshot s, objects o
+                    WHERE o.type = $1
+                    AND o.created_by = $2
+                    AND pg_visible_in_snapshot(o.created_xid, s.snapshot)
+                    AND NOT pg_visible_in_snapshot(o.deleted_xid, s.snapshot)
+                    "#,
+                    type_name,
+                    user_id,
+                    revision.included().to_string()
+                )
+                .fetch_one(&self.pool)
+                .await
+                .map_err(|e| anyhow!("Failed to count objects: {}", e))?
+            }
+        };
+
+        Ok(count)
+    }
+
+    /// The distinct relations of the edges leaving `from_id`, with their edge
+    /// counts, ordered by relation.
+    #[instrument(skip(self))]
+    pub async fn list_relations(
+        &self,
+        from_id: i64,
+        consistency: ConsistencyMode,
+    ) -> Result<Vec<RelationCount>> {
+        let consistency = self.resolve_consistency(consistency).await?;
+        let relations = match &consistency {
+            ConsistencyMode::Full => sqlx::query_as!(
+                RelationCount,
+                r#"
+                    SELECT relation, COUNT(*) as "count!"
+                    FROM triples
+                    WHERE from_id = $1
+                    AND created_xid <= pg_current_xact_id()
+                    AND deleted_xid > pg_current_xact_id()
+                    GROUP BY relation
+                    ORDER BY relation
+                    "#,
+                from_id
+            )
+            .fetch_all(&self.pool)
+            .await
+            .map_err(|e| anyhow!("Failed to list relations: {}", e))?,
+            ConsistencyMode::MinimizeLatency => sqlx::query_as!(
+                RelationCount,
+                r#"
+                    SELECT relation, COUNT(*) as "count!"
+                    FROM triples
+                    WHERE from_id = $1
+                    AND deleted_xid = $2
+                    GROUP BY relation
+                    ORDER BY relation
+                    "#,
+                from_id,
+                Xid8::max() as _,
+            )
+            .fetch_all(&self.pool)
+            .await
+            .map_err(|e| anyhow!("Failed to list relations: {}", e))?,
+            ConsistencyMode::AtLeastAsFresh(revision) | ConsistencyMode::ExactlyAt(revision) => {
+                sqlx::query_as!(
+                    RelationCount,
+                    r#"
+                    WITH snapshot AS (
+                        SELECT $2::text::pg_snapshot as snapshot
+                    )
+                    SELECT t.relation, COUNT(*) as "count!"
+                    FROM snapshot s, triples t
+                    WHERE t.from_id = $1
+                    AND pg_visible_in_snapshot(t.created_xid, s.snapshot)
+                    AND NOT pg_visible_in_snapshot(t.deleted_xid, s.snapshot)
+                    GROUP BY t.relation
+                    ORDER BY t.relation
+                    "#,
+                    from_id,
+                    revision.included().to_string()
+                )
+                .fetch_all(&self.pool)
+                .await
+                .map_err(|e| anyhow!("Failed to list relations: {}", e))?
+            }
+        };
+
+        Ok(relations)
+    }
+
+    pub async fn get_edge(
+        &self,
+        from_id: i64,
+        relation: &str,
+        consistency: ConsistencyMode,
+    ) -> Result<Option<EdgeWithMetadata>> {
+        let consistency = self.resolve_consistency(consistency).await?;
+        let edge = match &consistency {
+            ConsistencyMode::Full => sqlx::query_as!(
+                Edge,
+                r#"
+                    SELECT 
+                        t.id,
+                        t.from_type,
+                        t.from_id,
+                        t.relation,
+                        t.to_type,
+                        t.to_id,
+                        t.created_by,
+                        t.created_at as "created_at?: OffsetDateTime",
+                        t.updated_at as "updated_at?: OffsetDateTime"
+                    FROM triples t
+                    WHERE t.from_id = $1 AND t.relation = $2
+                    AND t.created_xid <= pg_current_xact_id()
+                    AND t.deleted_xid > pg_current_xact_id()
+                    LIMIT 1
+                    "#,
+                from_id,
+                relation
+            )
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(|e| anyhow!("Failed to fetch edge: {}", e))?,
+            ConsistencyMode::MinimizeLatency => sqlx::query_as!(
+                Edge,
+                r#"
+                    SELECT 
+                        t.id,
+                        t.from_type,
+                        t.from_id,
+                        t.relation,
+                        t.to_type,
+                        t.to_id,
+                        t.created_by,
+                        t.created_at as "created_at?: OffsetDateTime",
+                        t.updated_at as "updated_at?: OffsetDateTime"
+                    FROM triples t
+                    WHERE t.from_id = $1 AND t.relation = $2
+                    AND t.deleted_xid = $3
+                    LIMIT 1
+                    "#,
+                from_id,
+                relation,
+                Xid8::max() as _,
+            )
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(|e| anyhow!("Failed to fetch edge: {}", e))?,
+            ConsistencyMode::AtLeastAsFresh(_revision) | ConsistencyMode::ExactlyAt(_revision) => {
+                sqlx::query_as!(
+                    Edge,
+                    r#"
+                    WITH snapshot AS (
+                        SELECT $3::text::pg_snapshot as snapshot
+                    )
+                    SELECT 
+                        t.id,
+                        t.from_type,
+                        t.from_id,
+                        t.relation,
+                        t.to_type,
+                        t.to_id,
+                        t.created_by,
+                        t.created_at as "created_at?: OffsetDateTime",
+                        t.updated_at as "updated_at?: OffsetDateTime"
+                    FROM triples t, snapshot s
+                    WHERE t.from_id = $1 AND t.relation = $2
+                    AND pg_visible_in_snapshot(t.created_xid, s.snapshot)
+                    AND NOT pg_visible_in_snapshot(t.deleted_xid, s.snapshot)
+                    LIMIT 1
+                    "#,
+                    from_id,
+                    relation,
+                    _revision.included().to_string()
+                )
+                .fetch_optional(&self.pool)
+                .await
+                .map_err(|e| anyhow!("Failed to fetch edge: {}", e))?
+            }
+        };
+
+        match edge {
+            Some(edge) => {
+                let metadata = self.get_edge_metadata(edge.id, &consistency).await?;
+                Ok(Some(EdgeWithMetadata::from_edge(edge, metadata)))
+            }
+            None => Ok(None),
+        }
+    }
+
+    /// The edge with `id`, with its metadata, as visible under `consistency`.
+    #[instrument(skip(self))]
+    pub async fn get_edge_by_id(
+        &self,
+        id: i64,
+        consistency: ConsistencyMode,
+    ) -> Result<Option<EdgeWithMetadata>> {
+        let consistency = self.resolve_consistency(consistency).await?;
+        let edge = match &consistency {
+            ConsistencyMode::Full => sqlx::query_as!(
+                Edge,
+                r#"
+                    SELECT 
+                        t.id,
+                        t.from_type,
+                        t.from_id,
+                        t.relation,
+                        t.to_type,
+                        t.to_id,
+                        t.created_by,
+                        t.created_at as "created_at?: OffsetDateTime",
+                        t.updated_at as "updated_at?: OffsetDateTime"
+                    FROM triples t
+                    WHERE t.id = $1
+                    AND t.created_xid <= pg_current_xact_id()
+                    AND t.deleted_xid > pg_current_xact_id()
+                    "#,
+                id
+            )
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(|e| anyhow!("Failed to fetch edge: {}", e))?,
+            ConsistencyMode::MinimizeLatency => sqlx::query_as!(
+                Edge,
+                r#"
+                    SELECT 
+                        t.id,
+                        t.from_type,
+                        t.from_id,
+                        t.relation,
+                        t.to_type,
+                        t.to_id,
+                        t.created_by,
+                        t.created_at as "created_at?: OffsetDateTime",
+                        t.updated_at as "updated_at?: OffsetDateTime"
+                    FROM triples t
+                    WHERE t.id = $1
+                    AND t.deleted_xid = $2
+                    "#,
+                id,
+                Xid8::max() as _,
+            )
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(|e| anyhow!("Failed to fetch edge: {}", e))?,
+            ConsistencyMode::AtLeastAsFresh(_revision) | ConsistencyMode::ExactlyAt(_revision) => {
+                sqlx::query_as!(
+                    Edge,
+                    r#"
+                    WITH snapshot AS (
+                        SELECT $2::text::pg_snapshot as snapshot
+                    )
+                    SELECT 
+                        t.id,
+                        t.from_type,
+                        t.from_id,
+                        t.relation,
+                        t.to_type,
+                        t.to_id,
+                        t.created_by,
+                        t.created_at as "created_at?: OffsetDateTime",
+                        t.updated_at as "updated_at?: OffsetDateTime"
+                    FROM triples t, snapshot s
+                    WHERE t.id = $1
+                    AND pg_visible_in_snapshot(t.created_xid, s.snapshot)
+                    AND NOT pg_visible_in_snapshot(t.deleted_xid, s.snapshot)
+                    "#,
+                    id,
+                    _revision.included().to_string()
+                )
+                .fetch_optional(&self.pool)
+                .await
+                .map_err(|e| anyhow!("Failed to fetch edge: {}", e))?
+            }
+        };
+
+        match edge {
+            Some(edge) => {
+                let metadata = self.get_edge_metadata(edge.id, &consistency).await?;
+                Ok(Some(EdgeWithMetadata::from_edge(edge, metadata)))
+            }
+            None => Ok(None),
+        }
+    }
+
+    pub async fn get_edges(
+        &self,
+        from_id: i64,
+        relation: &str,
+        filter: &EdgeMetadataFilter,
+        consistency: ConsistencyMode,
+    ) -> Result<Vec<EdgeWithMetadata>> {
+        let consistency = self.resolve_consistency(consistency).await?;
+        let contains = filter.contains.as_ref();
+        let (key, value) = match &filter.key_equals {
+            Some((key, value)) => (Some(key.as_str()), Some(value.as_str())),
+            None => (None, None),
+        };
+
+        let edges = match &consistency {
+            ConsistencyMode::Full => sqlx::query_as!(
+                Edge,
+                r#"
+                    SELECT 
+                        t.id,
+                        t.from_type,
+                        t.from_id,
+                        t.relation,
+                        t.to_type,
+                        t.to_id,
+                        t.created_by,
+                        t.created_at as "created_at?: OffsetDateTime",
+                        t.updated_at as "updated_at?: OffsetDateTime"
+                    FROM triples t
+                    WHERE t.from_id = $1 AND t.relation = $2
+                    AND t.created_xid <= pg_current_xact_id()
+                    AND t.deleted_xid > pg_current_xact_id()
+                    AND ($3::jsonb IS NULL AND $4::text IS NULL OR EXISTS (
+                        SELECT 1
+                        FROM edge_metadata_history h
+                        WHERE h.edge_id = t.id
+                        AND h.created_xid <= pg_current_xact_id()
+                        AND h.deleted_xid > pg_current_xact_id()
+                        AND ($3::jsonb IS NULL OR h.metadata @> $3)
+                        AND ($4::text IS NULL OR h.metadata ->> $4 = $5
+                            OR $6 AND lower(h.metadata ->> $4) = lower($5))
+                    ))
+                    "#,
+                from_id,
+                relation,
+                contains,
+                key,
+                value,
+                filter.ignore_case
+            )
+            .fetch_all(&self.pool)
+            .await
+            .map_err(|e| anyhow!("Failed to fetch edges: {}", e))?,
+            ConsistencyMode::MinimizeLatency => sqlx::query_as!(
+                Edge,
+                r#"
+                    SELECT 
+                        t.id,
+                        t.from_type,
+                        t.from_id,
+                        t.relation,
+                        t.to_type,
+                        t.to_id,
+                        t.created_by,
+                        t.created_at as "created_at?: OffsetDateTime",
+                        t.updated_at as "updated_at?: OffsetDateTime"
+                    FROM triples t
+                    WHERE t.from_id = $1 AND t.relation = $2
+                    AND t.deleted_xid = $6
+                    AND ($3::jsonb IS NULL AND $4::text IS NULL OR EXISTS (
+                        SELECT 1
+                        FROM (
+                            SELECT metadata
+                            FROM edge_metadata_history
+                            WHERE edge_id = t.id
+                            ORDER BY created_xid DESC
+                            LIMIT 1
+                        ) h
+                        WHERE ($3::jsonb IS NULL OR h.metadata @> $3)
+                        AND ($4::text IS NULL OR h.metadata ->> $4 = $5
+                            OR $7 AND lower(h.metadata ->> $4) = lower($5))
+                    ))
+                    "#,
+                from_id,
+                relation,
+                contains,
+                key,
+                value,
+                Xid8::max() as _,
+                filter.ignore_case,
+            )
+            .fetch_all(&self.pool)
+            .await
+            .map_err(|e| anyhow!("Failed to fetch edges: {}", e))?,
+            ConsistencyMode::AtLeastAsFresh(_revision) | ConsistencyMode::ExactlyAt(_revision) => {
+                sqlx::query_as!(
+                    Edge,
+                    r#"
+                    WITH snapshot AS (
+                        SELECT $3::text::pg_snapshot as snapshot
+                    )
+                    SELECT 
+                        t.id,
+                        t.from_type,
+                        t.from_id,
+                        t.relation,
+                        t.to_type,
+                        t.to_id,
+                        t.created_by,
+                        t.created_at as "created_at?: OffsetDateTime",
+                        t.updated_at as "updated_at?: OffsetDateTime"
+                    FROM triples t, snapshot s
+                    WHERE t.from_id = $1 AND t.relation = $2
+                    AND pg_visible_in_snapshot(t.created_xid, s.snapshot)
+                    AND NOT pg_visible_in_snapshot(t.deleted_xid, s.snapshot)
+                    AND ($4::jsonb IS NULL AND $5::text IS NULL OR EXISTS (
+                        SELECT 1
+                        FROM edge_metadata_history h
+                        WHERE h.edge_id = t.id
+                        AND pg_visible_in_snapshot(h.created_xid, s.snapshot)
+                        AND NOT pg_visible_in_snapshot(h.deleted_xid, s.snapshot)
+                        AND ($4::jsonb IS NULL OR h.metadata @> $4)
+                        AND ($5::text IS NULL OR h.metadata ->> $5 = $6
+                            OR $7 AND lower(h.metadata ->> $5) = lower($6))
+                    ))
+                    "#,
+                    from_id,
+                    relation,
+                    _revision.included().to_string(),
+                    contains,
+                    key,
+                    value,
+                    filter.ignore_case
                 )
-                .fetch_one(&self.pool)
+                .fetch_all(&self.pool)
                 .await
-                .map_err(|e| anyhow!("Failed to fetch metadata: {}", e))?,
-            };
+                .map_err(|e| anyhow!("Failed to fetch edges: {}", e))?
+            }
+        };
 
-            Ok(Some(ObjectWithMetadata {
-                id: object.id,
-                type_name: object.type_name,
-                metadata: metadata.into_value(),
-                created_at: object.created_at,
-                updated_at: object.updated_at,
-            }))
-        } else {
-            Ok(None)
-        }
+        self.with_edge_metadata(edges, &consistency).await
     }
 
-    pub async fn get_edge(
+    // Edges pointing at `to_id` through `relation`, i.e. the reverse of `get_edges`
+    pub async fn get_incoming_edges(
         &self,
-        from_id: i64,
+        to_id: i64,
         relation: &str,
         consistency: ConsistencyMode,
-    ) -> Result<Option<EdgeWithMetadata>> {
-        let edge = match &consistency {
+    ) -> Result<Vec<EdgeWithMetadata>> {
+        let consistency = self.resolve_consistency(consistency).await?;
+        let edges = match &consistency {
             ConsistencyMode::Full => sqlx::query_as!(
                 Edge,
                 r#"
@@ -655,20 +3209,20 @@ impl GraphRepository {
                         t.relation,
                         t.to_type,
                         t.to_id,
+                        t.created_by,
                         t.created_at as "created_at?: OffsetDateTime",
                         t.updated_at as "updated_at?: OffsetDateTime"
                     FROM triples t
-                    WHERE t.from_id = $1 AND t.relation = $2
+                    WHERE t.to_id = $1 AND t.relation = $2
                     AND t.created_xid <= pg_current_xact_id()
                     AND t.deleted_xid > pg_current_xact_id()
-                    LIMIT 1
                     "#,
-                from_id,
+                to_id,
                 relation
             )
-            .fetch_optional(&self.pool)
+            .fetch_all(&self.pool)
             .await
-            .map_err(|e| anyhow!("Failed to fetch edge: {}", e))?,
+            .map_err(|e| anyhow!("Failed to fetch incoming edges: {}", e))?,
             ConsistencyMode::MinimizeLatency => sqlx::query_as!(
                 Edge,
                 r#"
@@ -679,18 +3233,20 @@ impl GraphRepository {
                         t.relation,
                         t.to_type,
                         t.to_id,
+                        t.created_by,
                         t.created_at as "created_at?: OffsetDateTime",
                         t.updated_at as "updated_at?: OffsetDateTime"
                     FROM triples t
-                    WHERE t.from_id = $1 AND t.relation = $2
-                    LIMIT 1
+                    WHERE t.to_id = $1 AND t.relation = $2
+                    AND t.deleted_xid = $3
                     "#,
-                from_id,
-                relation
+                to_id,
+                relation,
+                Xid8::max() as _,
             )
-            .fetch_optional(&self.pool)
+            .fetch_all(&self.pool)
             .await
-            .map_err(|e| anyhow!("Failed to fetch edge: {}", e))?,
+            .map_err(|e| anyhow!("Failed to fetch incoming edges: {}", e))?,
             ConsistencyMode::AtLeastAsFresh(_revision) | ConsistencyMode::ExactlyAt(_revision) => {
                 sqlx::query_as!(
                     Edge,
@@ -705,308 +3261,1102 @@ impl GraphRepository {
                         t.relation,
                         t.to_type,
                         t.to_id,
+                        t.created_by,
                         t.created_at as "created_at?: OffsetDateTime",
                         t.updated_at as "updated_at?: OffsetDateTime"
                     FROM triples t, snapshot s
-                    WHERE t.from_id = $1 AND t.relation = $2
-                    AND t.created_xid <= pg_snapshot_xmax(s.snapshot)
-                    AND t.deleted_xid > pg_snapshot_xmax(s.snapshot)
-                    LIMIT 1
+                    WHERE t.to_id = $1 AND t.relation = $2
+                    AND pg_visible_in_snapshot(t.created_xid, s.snapshot)
+                    AND NOT pg_visible_in_snapshot(t.deleted_xid, s.snapshot)
                     "#,
-                    from_id,
+                    to_id,
                     relation,
-                    _revision.snapshot_string()
+                    _revision.included().to_string()
                 )
-                .fetch_optional(&self.pool)
+                .fetch_all(&self.pool)
                 .await
-                .map_err(|e| anyhow!("Failed to fetch edge: {}", e))?
+                .map_err(|e| anyhow!("Failed to fetch incoming edges: {}", e))?
             }
         };
 
-        if let Some(edge) = edge {
-            // Get the metadata for the edge based on consistency mode
-            let metadata = match &consistency {
-                ConsistencyMode::Full => sqlx::query_as!(
+        self.with_edge_metadata(edges, &consistency).await
+    }
+
+    /// The snapshot of everything committed as of now.
+    async fn current_snapshot(&self) -> Result<PgSnapshot> {
+        sqlx::query_scalar!(
+            r#"
+            SELECT pg_current_snapshot()::text as "snapshot!"
+            "#
+        )
+        .fetch_one(&self.pool)
+        .await
+        .map_err(|e| anyhow!("Failed to fetch current snapshot: {}", e))?
+        .parse()
+        .map_err(Into::into)
+    }
+
+    /// A revision of everything committed so far, for pinning several reads
+    /// to the same point in time.
+    pub async fn current_revision(&self) -> Result<Revision> {
+        Ok(Revision::from_snapshot(self.current_snapshot().await?))
+    }
+
+    /// The snapshot a read under `consistency` sees: the pinned revision's, or
+    /// for reads of the latest data the current one, taken here rather than
+    /// by the read itself. For debugging only.
+    pub async fn read_snapshot(&self, consistency: &ConsistencyMode) -> Result<PgSnapshot> {
+        match self.resolve_consistency(consistency.clone()).await? {
+            ConsistencyMode::AtLeastAsFresh(revision) | ConsistencyMode::ExactlyAt(revision) => {
+                Ok(revision.included())
+            }
+            ConsistencyMode::Full | ConsistencyMode::MinimizeLatency => {
+                self.current_snapshot().await
+            }
+        }
+    }
+
+    /// Resolves `AtLeastAsFresh` to a concrete read mode: the current head when
+    /// it has caught up with the requested revision, and the revision's own
+    /// snapshot otherwise. Other modes are returned unchanged.
+    async fn resolve_consistency(&self, consistency: ConsistencyMode) -> Result<ConsistencyMode> {
+        let ConsistencyMode::AtLeastAsFresh(revision) = consistency else {
+            return Ok(consistency);
+        };
+
+        let head = self.current_snapshot().await?;
+        if head.sees_all_of(&revision.included()) {
+            Ok(ConsistencyMode::Full)
+        } else {
+            Ok(ConsistencyMode::ExactlyAt(revision))
+        }
+    }
+
+    async fn with_edge_metadata(
+        &self,
+        edges: Vec<Edge>,
+        consistency: &ConsistencyMode,
+    ) -> Result<Vec<EdgeWithMetadata>> {
+        let mut result = Vec::with_capacity(edges.len());
+        for edge in edges {
+            let metadata = self.get_edge_metadata(edge.id, consistency).await?;
+            result.push(EdgeWithMetadata::from_edge(edge, metadata));
+        }
+
+        Ok(result)
+    }
+
+    // Get the metadata for an edge based on consistency mode
+    async fn get_edge_metadata(
+        &self,
+        edge_id: i64,
+        consistency: &ConsistencyMode,
+    ) -> Result<Value> {
+        let metadata = match consistency {
+            ConsistencyMode::Full => sqlx::query_as!(
+                MetadataRecord,
+                r#"
+                    SELECT metadata
+                    FROM edge_metadata_history
+                    WHERE edge_id = $1
+                    AND created_xid <= pg_current_xact_id()
+                    AND deleted_xid > pg_current_xact_id()
+                    "#,
+                edge_id
+            )
+            .fetch_one(&self.pool)
+            .await
+            .map_err(|e| anyhow!("Failed to fetch edge metadata: {}", e))?,
+            ConsistencyMode::MinimizeLatency => sqlx::query_as!(
+                MetadataRecord,
+                r#"
+                    SELECT metadata
+                    FROM edge_metadata_history
+                    WHERE edge_id = $1
+                    AND deleted_xid = $2
+                    "#,
+                edge_id,
+                Xid8::max() as _,
+            )
+            .fetch_one(&self.pool)
+            .await
+            .map_err(|e| anyhow!("Failed to fetch edge metadata: {}", e))?,
+            ConsistencyMode::AtLeastAsFresh(_revision) | ConsistencyMode::ExactlyAt(_revision) => {
+                sqlx::query_as!(
                     MetadataRecord,
                     r#"
-                        SELECT metadata
-                        FROM edge_metadata_history
-                        WHERE edge_id = $1
-                        AND created_xid <= pg_current_xact_id()
-                        AND deleted_xid > pg_current_xact_id()
-                        "#,
-                    edge.id
+                    WITH snapshot AS (
+                        SELECT $2::text::pg_snapshot as snapshot
+                    )
+                    SELECT metadata
+                    FROM edge_metadata_history h, snapshot s
+                    WHERE h.edge_id = $1
+                    AND pg_visible_in_snapshot(h.created_xid, s.snapshot)
+                    AND NOT pg_visible_in_snapshot(h.deleted_xid, s.snapshot)
+                    "#,
+                    edge_id,
+                    _revision.included().to_string()
                 )
                 .fetch_one(&self.pool)
                 .await
-                .map_err(|e| anyhow!("Failed to fetch edge metadata: {}", e))?,
-                ConsistencyMode::MinimizeLatency => sqlx::query_as!(
-                    MetadataRecord,
-                    r#"
+                .map_err(|e| anyhow!("Failed to fetch edge metadata: {}", e))?
+            }
+        };
+
+        Ok(metadata.into_value())
+    }
+
+    /// Walks `relation` edges breadth-first from `start_id`, up to `max_depth`
+    /// hops. Objects already reached are not expanded again, so cycles
+    /// terminate, and each reachable object is returned once with its shortest
+    /// depth.
+    #[instrument(skip(self))]
+    pub async fn traverse(
+        &self,
+        start_id: i64,
+        relation: &str,
+        max_depth: i32,
+        consistency: ConsistencyMode,
+    ) -> Result<Vec<TraversedObject>> {
+        let consistency = self.resolve_consistency(consistency).await?;
+        // Each node is kept once per depth it is reached at, rather than once
+        // per path, so the recursion is bounded by nodes × depth. The start
+        // object itself is never revisited
+        let rows = match &consistency {
+            ConsistencyMode::Full => sqlx::query_as!(
+                TraversedObjectRow,
+                r#"
+                    WITH RECURSIVE reachable(id, depth) AS (
+                        SELECT t.to_id, 1
+                        FROM triples t
+                        WHERE t.from_id = $1 AND t.relation = $2
+                        AND t.created_xid <= pg_current_xact_id()
+                        AND t.deleted_xid > pg_current_xact_id()
+                        UNION
+                        SELECT t.to_id, r.depth + 1
+                        FROM reachable r
+                        JOIN triples t ON t.from_id = r.id
+                        WHERE t.relation = $2
+                        AND r.depth < $3
+                        AND t.to_id <> $1
+                        AND t.created_xid <= pg_current_xact_id()
+                        AND t.deleted_xid > pg_current_xact_id()
+                    ),
+                    nearest AS (
+                        SELECT id, MIN(depth) as depth
+                        FROM reachable
+                        GROUP BY id
+                    )
+                    SELECT
+                        o.id,
+                        o.type as type_name,
+                        h.metadata as "metadata: Value",
+                        o.created_by,
+                        o.user_id as updated_by,
+                        o.created_at as "created_at?: OffsetDateTime",
+                        o.updated_at as "updated_at?: OffsetDateTime",
+                        n.depth as "depth!"
+                    FROM nearest n
+                    JOIN objects o ON o.id = n.id
+                    JOIN object_metadata_history h ON h.object_id = o.id
+                    WHERE o.created_xid <= pg_current_xact_id()
+                    AND o.deleted_xid > pg_current_xact_id()
+                    AND h.created_xid <= pg_current_xact_id()
+                    AND h.deleted_xid > pg_current_xact_id()
+                    ORDER BY n.depth, o.id
+                    "#,
+                start_id,
+                relation,
+                max_depth
+            )
+            .fetch_all(&self.pool)
+            .await
+            .map_err(|e| query_error(e, "Failed to traverse graph"))?,
+            ConsistencyMode::MinimizeLatency => sqlx::query_as!(
+                TraversedObjectRow,
+                r#"
+                    WITH RECURSIVE reachable(id, depth) AS (
+                        SELECT t.to_id, 1
+                        FROM triples t
+                        WHERE t.from_id = $1 AND t.relation = $2
+                        AND t.deleted_xid = $4
+                        UNION
+                        SELECT t.to_id, r.depth + 1
+                        FROM reachable r
+                        JOIN triples t ON t.from_id = r.id
+                        WHERE t.relation = $2
+                        AND t.deleted_xid = $4
+                        AND r.depth < $3
+                        AND t.to_id <> $1
+                    ),
+                    nearest AS (
+                        SELECT id, MIN(depth) as depth
+                        FROM reachable
+                        GROUP BY id
+                    )
+                    SELECT
+                        o.id,
+                        o.type as type_name,
+                        h.metadata as "metadata!: Value",
+                        o.created_by,
+                        o.user_id as updated_by,
+                        o.created_at as "created_at?: OffsetDateTime",
+                        o.updated_at as "updated_at?: OffsetDateTime",
+                        n.depth as "depth!"
+                    FROM nearest n
+                    JOIN objects o ON o.id = n.id
+                    JOIN LATERAL (
                         SELECT metadata
-                        FROM edge_metadata_history
-                        WHERE edge_id = $1
+                        FROM object_metadata_history
+                        WHERE object_id = o.id
                         ORDER BY created_xid DESC
                         LIMIT 1
-                        "#,
-                    edge.id
+                    ) h ON true
+                    WHERE o.deleted_xid = $4
+                    ORDER BY n.depth, o.id
+                    "#,
+                start_id,
+                relation,
+                max_depth,
+                Xid8::max() as _,
+            )
+            .fetch_all(&self.pool)
+            .await
+            .map_err(|e| query_error(e, "Failed to traverse graph"))?,
+            ConsistencyMode::AtLeastAsFresh(_revision) | ConsistencyMode::ExactlyAt(_revision) => {
+                sqlx::query_as!(
+                    TraversedObjectRow,
+                    r#"
+                    WITH RECURSIVE reachable(id, depth) AS (
+                        SELECT t.to_id, 1
+                        FROM triples t
+                        WHERE t.from_id = $1 AND t.relation = $2
+                        AND pg_visible_in_snapshot(t.created_xid, $4::text::pg_snapshot)
+                        AND NOT pg_visible_in_snapshot(t.deleted_xid, $4::text::pg_snapshot)
+                        UNION
+                        SELECT t.to_id, r.depth + 1
+                        FROM reachable r
+                        JOIN triples t ON t.from_id = r.id
+                        WHERE t.relation = $2
+                        AND r.depth < $3
+                        AND t.to_id <> $1
+                        AND pg_visible_in_snapshot(t.created_xid, $4::text::pg_snapshot)
+                        AND NOT pg_visible_in_snapshot(t.deleted_xid, $4::text::pg_snapshot)
+                    ),
+                    nearest AS (
+                        SELECT id, MIN(depth) as depth
+                        FROM reachable
+                        GROUP BY id
+                    )
+                    SELECT
+                        o.id,
+                        o.type as type_name,
+                        h.metadata as "metadata: Value",
+                        o.created_by,
+                        o.user_id as updated_by,
+                        o.created_at as "created_at?: OffsetDateTime",
+                        o.updated_at as "updated_at?: OffsetDateTime",
+                        n.depth as "depth!"
+                    FROM nearest n
+                    JOIN objects o ON o.id = n.id
+                    JOIN object_metadata_history h ON h.object_id = o.id
+                    WHERE pg_visible_in_snapshot(o.created_xid, $4::text::pg_snapshot)
+                    AND NOT pg_visible_in_snapshot(o.deleted_xid, $4::text::pg_snapshot)
+                    AND pg_visible_in_snapshot(h.created_xid, $4::text::pg_snapshot)
+                    AND NOT pg_visible_in_snapshot(h.deleted_xid, $4::text::pg_snapshot)
+                    ORDER BY n.depth, o.id
+                    "#,
+                    start_id,
+                    relation,
+                    max_depth,
+                    _revision.included().to_string()
                 )
-                .fetch_one(&self.pool)
+                .fetch_all(&self.pool)
                 .await
-                .map_err(|e| anyhow!("Failed to fetch edge metadata: {}", e))?,
-                ConsistencyMode::AtLeastAsFresh(_revision)
-                | ConsistencyMode::ExactlyAt(_revision) => sqlx::query_as!(
-                    MetadataRecord,
+                .map_err(|e| query_error(e, "Failed to traverse graph"))?
+            }
+        };
+
+        // Objects that are not visible at this consistency level drop out of
+        // the joins above
+        Ok(rows.into_iter().map(TraversedObject::from).collect())
+    }
+
+    /// Finds the shortest chain of edges, of any relation, leading from
+    /// `from_id` to `to_id` within `max_depth` hops. Returns `None` when
+    /// `to_id` is not reachable within that limit.
+    #[instrument(skip(self))]
+    pub async fn shortest_path(
+        &self,
+        from_id: i64,
+        to_id: i64,
+        max_depth: i32,
+        consistency: ConsistencyMode,
+    ) -> Result<Option<Vec<PathStep>>> {
+        let consistency = self.resolve_consistency(consistency).await?;
+        if from_id == to_id {
+            return Ok(Some(Vec::new()));
+        }
+
+        // Breadth first: each node is kept once per depth it is reached at,
+        // then walked back from `to_id` through one predecessor a hop nearer
+        // the start, so the work is bounded by nodes × depth rather than the
+        // number of paths
+        let path = match &consistency {
+            ConsistencyMode::Full => sqlx::query_as!(
+                PathRecord,
+                r#"
+                    WITH RECURSIVE reachable(id, depth) AS (
+                        SELECT $1::bigint, 0
+                        UNION
+                        SELECT t.to_id, r.depth + 1
+                        FROM reachable r
+                        JOIN triples t ON t.from_id = r.id
+                        WHERE r.depth < $3
+                        AND r.id <> $2
+                        AND t.created_xid <= pg_current_xact_id()
+                        AND t.deleted_xid > pg_current_xact_id()
+                    ),
+                    nearest AS (
+                        SELECT id, MIN(depth) as depth
+                        FROM reachable
+                        GROUP BY id
+                    ),
+                    parents AS (
+                        SELECT DISTINCT ON (n.id) n.id, t.from_id, t.id as edge_id, t.relation
+                        FROM nearest n
+                        JOIN triples t ON t.to_id = n.id
+                        JOIN nearest p ON p.id = t.from_id AND p.depth = n.depth - 1
+                        WHERE n.depth > 0
+                        AND t.created_xid <= pg_current_xact_id()
+                        AND t.deleted_xid > pg_current_xact_id()
+                        ORDER BY n.id, t.id
+                    ),
+                    path(node, edge_ids, relations) AS (
+                        SELECT from_id, ARRAY[edge_id], ARRAY[relation]
+                        FROM parents
+                        WHERE id = $2
+                        UNION ALL
+                        SELECT p.from_id, array_prepend(p.edge_id, path.edge_ids), array_prepend(p.relation, path.relations)
+                        FROM path
+                        JOIN parents p ON p.id = path.node
+                    )
+                    SELECT edge_ids as "edge_ids!", relations as "relations!"
+                    FROM path
+                    WHERE node = $1
+                    "#,
+                from_id,
+                to_id,
+                max_depth
+            )
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(|e| query_error(e, "Failed to find shortest path"))?,
+            ConsistencyMode::MinimizeLatency => sqlx::query_as!(
+                PathRecord,
+                r#"
+                    WITH RECURSIVE reachable(id, depth) AS (
+                        SELECT $1::bigint, 0
+                        UNION
+                        SELECT t.to_id, r.depth + 1
+                        FROM reachable r
+                        JOIN triples t ON t.from_id = r.id
+                        WHERE r.depth < $3
+                        AND r.id <> $2
+                        AND t.deleted_xid = $4
+                    ),
+                    nearest AS (
+                        SELECT id, MIN(depth) as depth
+                        FROM reachable
+                        GROUP BY id
+                    ),
+                    parents AS (
+                        SELECT DISTINCT ON (n.id) n.id, t.from_id, t.id as edge_id, t.relation
+                        FROM nearest n
+                        JOIN triples t ON t.to_id = n.id
+                        JOIN nearest p ON p.id = t.from_id AND p.depth = n.depth - 1
+                        WHERE n.depth > 0
+                        AND t.deleted_xid = $4
+                        ORDER BY n.id, t.id
+                    ),
+                    path(node, edge_ids, relations) AS (
+                        SELECT from_id, ARRAY[edge_id], ARRAY[relation]
+                        FROM parents
+                        WHERE id = $2
+                        UNION ALL
+                        SELECT p.from_id, array_prepend(p.edge_id, path.edge_ids), array_prepend(p.relation, path.relations)
+                        FROM path
+                        JOIN parents p ON p.id = path.node
+                    )
+                    SELECT edge_ids as "edge_ids!", relations as "relations!"
+                    FROM path
+                    WHERE node = $1
+                    "#,
+                from_id,
+                to_id,
+                max_depth,
+                Xid8::max() as _,
+            )
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(|e| query_error(e, "Failed to find shortest path"))?,
+            ConsistencyMode::AtLeastAsFresh(_revision) | ConsistencyMode::ExactlyAt(_revision) => {
+                sqlx::query_as!(
+                    PathRecord,
                     r#"
-                        WITH snapshot AS (
-                            SELECT $2::text::pg_snapshot as snapshot
-                        )
-                        SELECT metadata
-                        FROM edge_metadata_history h, snapshot s
-                        WHERE h.edge_id = $1
-                        AND h.created_xid <= pg_snapshot_xmax(s.snapshot)
-                        AND h.deleted_xid > pg_snapshot_xmax(s.snapshot)
-                        "#,
-                    edge.id,
-                    _revision.snapshot_string()
+                    WITH RECURSIVE reachable(id, depth) AS (
+                        SELECT $1::bigint, 0
+                        UNION
+                        SELECT t.to_id, r.depth + 1
+                        FROM reachable r
+                        JOIN triples t ON t.from_id = r.id
+                        WHERE r.depth < $3
+                        AND r.id <> $2
+                        AND pg_visible_in_snapshot(t.created_xid, $4::text::pg_snapshot)
+                        AND NOT pg_visible_in_snapshot(t.deleted_xid, $4::text::pg_snapshot)
+                    ),
+                    nearest AS (
+                        SELECT id, MIN(depth) as depth
+                        FROM reachable
+                        GROUP BY id
+                    ),
+                    parents AS (
+                        SELECT DISTINCT ON (n.id) n.id, t.from_id, t.id as edge_id, t.relation
+                        FROM nearest n
+                        JOIN triples t ON t.to_id = n.id
+                        JOIN nearest p ON p.id = t.from_id AND p.depth = n.depth - 1
+                        WHERE n.depth > 0
+                        AND pg_visible_in_snapshot(t.created_xid, $4::text::pg_snapshot)
+                        AND NOT pg_visible_in_snapshot(t.deleted_xid, $4::text::pg_snapshot)
+                        ORDER BY n.id, t.id
+                    ),
+                    path(node, edge_ids, relations) AS (
+                        SELECT from_id, ARRAY[edge_id], ARRAY[relation]
+                        FROM parents
+                        WHERE id = $2
+                        UNION ALL
+                        SELECT p.from_id, array_prepend(p.edge_id, path.edge_ids), array_prepend(p.relation, path.relations)
+                        FROM path
+                        JOIN parents p ON p.id = path.node
+                    )
+                    SELECT edge_ids as "edge_ids!", relations as "relations!"
+                    FROM path
+                    WHERE node = $1
+                    "#,
+                    from_id,
+                    to_id,
+                    max_depth,
+                    _revision.included().to_string()
+                )
+                .fetch_optional(&self.pool)
+                .await
+                .map_err(|e| query_error(e, "Failed to find shortest path"))?
+            }
+        };
+
+        Ok(path.map(|path| {
+            path.edge_ids
+                .into_iter()
+                .zip(path.relations)
+                .map(|(edge_id, relation)| PathStep { edge_id, relation })
+                .collect()
+        }))
+    }
+
+    /// The chain of edges by which `subject_id` holds `permission` on
+    /// `object_id`, if any: an edge of relation `permission` into the object,
+    /// either from the subject itself or from an object the subject reaches
+    /// through `via` relations (e.g. `member` of a group that `owns` it).
+    /// `max_depth` bounds the whole chain.
+    #[instrument(skip(self))]
+    pub async fn check_permission(
+        &self,
+        subject_id: i64,
+        permission: &str,
+        object_id: i64,
+        via: &[String],
+        max_depth: i32,
+        consistency: ConsistencyMode,
+    ) -> Result<Option<Vec<PathStep>>> {
+        let consistency = self.resolve_consistency(consistency).await?;
+
+        let path = match &consistency {
+            ConsistencyMode::Full => sqlx::query_as!(
+                PathRecord,
+                r#"
+                WITH RECURSIVE holders(node, depth, nodes, edge_ids, relations) AS (
+                    SELECT $1::bigint, 0, ARRAY[$1::bigint], ARRAY[]::bigint[], ARRAY[]::text[]
+                    UNION ALL
+                    SELECT
+                        t.to_id,
+                        h.depth + 1,
+                        h.nodes || t.to_id,
+                        h.edge_ids || t.id,
+                        h.relations || t.relation
+                    FROM holders h
+                    JOIN triples t ON t.from_id = h.node
+                    WHERE h.depth < $5 - 1
+                    AND t.relation = ANY($4)
+                    AND NOT t.to_id = ANY(h.nodes)
+                    AND t.created_xid <= pg_current_xact_id()
+                    AND t.deleted_xid > pg_current_xact_id()
+                )
+                SELECT h.edge_ids || t.id as "edge_ids!", h.relations || t.relation as "relations!"
+                FROM holders h
+                JOIN triples t ON t.from_id = h.node
+                WHERE t.relation = $2
+                AND t.to_id = $3
+                AND t.created_xid <= pg_current_xact_id()
+                AND t.deleted_xid > pg_current_xact_id()
+                ORDER BY h.depth
+                LIMIT 1
+                "#,
+                subject_id,
+                permission,
+                object_id,
+                via,
+                max_depth
+            )
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(|e| query_error(e, "Failed to check permission"))?,
+            ConsistencyMode::MinimizeLatency => sqlx::query_as!(
+                PathRecord,
+                r#"
+                WITH RECURSIVE holders(node, depth, nodes, edge_ids, relations) AS (
+                    SELECT $1::bigint, 0, ARRAY[$1::bigint], ARRAY[]::bigint[], ARRAY[]::text[]
+                    UNION ALL
+                    SELECT
+                        t.to_id,
+                        h.depth + 1,
+                        h.nodes || t.to_id,
+                        h.edge_ids || t.id,
+                        h.relations || t.relation
+                    FROM holders h
+                    JOIN triples t ON t.from_id = h.node
+                    WHERE h.depth < $5 - 1
+                    AND t.relation = ANY($4)
+                    AND t.deleted_xid = $6
+                    AND NOT t.to_id = ANY(h.nodes)
+                )
+                SELECT h.edge_ids || t.id as "edge_ids!", h.relations || t.relation as "relations!"
+                FROM holders h
+                JOIN triples t ON t.from_id = h.node
+                WHERE t.relation = $2
+                AND t.to_id = $3
+                AND t.deleted_xid = $6
+                ORDER BY h.depth
+                LIMIT 1
+                "#,
+                subject_id,
+                permission,
+                object_id,
+                via,
+                max_depth,
+                Xid8::max() as _,
+            )
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(|e| query_error(e, "Failed to check permission"))?,
+            ConsistencyMode::AtLeastAsFresh(_revision) | ConsistencyMode::ExactlyAt(_revision) => {
+                sqlx::query_as!(
+                    PathRecord,
+                    r#"
+                WITH RECURSIVE holders(node, depth, nodes, edge_ids, relations) AS (
+                    SELECT $1::bigint, 0, ARRAY[$1::bigint], ARRAY[]::bigint[], ARRAY[]::text[]
+                    UNION ALL
+                    SELECT
+                        t.to_id,
+                        h.depth + 1,
+                        h.nodes || t.to_id,
+                        h.edge_ids || t.id,
+                        h.relations || t.relation
+                    FROM holders h
+                    JOIN triples t ON t.from_id = h.node
+                    WHERE h.depth < $5 - 1
+                    AND t.relation = ANY($4)
+                    AND NOT t.to_id = ANY(h.nodes)
+                    AND pg_visible_in_snapshot(t.created_xid, $6::text::pg_snapshot)
+                    AND NOT pg_visible_in_snapshot(t.deleted_xid, $6::text::pg_snapshot)
                 )
-                .fetch_one(&self.pool)
+                SELECT h.edge_ids || t.id as "edge_ids!", h.relations || t.relation as "relations!"
+                FROM holders h
+                JOIN triples t ON t.from_id = h.node
+                WHERE t.relation = $2
+                AND t.to_id = $3
+                AND pg_visible_in_snapshot(t.created_xid, $6::text::pg_snapshot)
+                AND NOT pg_visible_in_snapshot(t.deleted_xid, $6::text::pg_snapshot)
+                ORDER BY h.depth
+                LIMIT 1
+                "#,
+                    subject_id,
+                    permission,
+                    object_id,
+                    via,
+                    max_depth,
+                    _revision.included().to_string()
+                )
+                .fetch_optional(&self.pool)
                 .await
-                .map_err(|e| anyhow!("Failed to fetch edge metadata: {}", e))?,
-            };
+                .map_err(|e| query_error(e, "Failed to check permission"))?
+            }
+        };
 
-            Ok(Some(EdgeWithMetadata {
-                id: edge.id,
-                from_type: edge.from_type,
-                from_id: edge.from_id,
-                relation: edge.relation,
-                to_type: edge.to_type,
-                to_id: edge.to_id,
-                metadata: metadata.into_value(),
-                created_at: edge.created_at,
-                updated_at: edge.updated_at,
-            }))
-        } else {
-            Ok(None)
-        }
+        Ok(path.map(|path| {
+            path.edge_ids
+                .into_iter()
+                .zip(path.relations)
+                .map(|(edge_id, relation)| PathStep { edge_id, relation })
+                .collect()
+        }))
     }
 
-    pub async fn get_edges(
+    /// The objects `from_id` points at through `relation`, each with the edge
+    /// leading to it, fetched in a single query. Only edges matching `filter`
+    /// to targets `access` allows are followed, and at most `limit` of them in
+    /// `order` are returned.
+    #[allow(clippy::too_many_arguments)]
+    #[instrument(skip(self))]
+    pub async fn get_related_objects(
         &self,
         from_id: i64,
         relation: &str,
+        filter: &EdgeMetadataFilter,
+        access: TargetAccess<'_>,
+        order: EdgeOrder,
+        limit: Option<i64>,
         consistency: ConsistencyMode,
-    ) -> Result<Vec<EdgeWithMetadata>> {
-        let edges = match &consistency {
+    ) -> Result<Vec<RelatedObject>> {
+        let consistency = self.resolve_consistency(consistency).await?;
+        let contains = filter.contains.as_ref();
+        let (key, value) = match &filter.key_equals {
+            Some((key, value)) => (Some(key.as_str()), Some(value.as_str())),
+            None => (None, None),
+        };
+        let (reader, public_field) = match access {
+            TargetAccess::User(user_id) => (Some(user_id), None),
+            TargetAccess::PublicField(field) => (None, Some(field)),
+        };
+
+        let rows = match &consistency {
             ConsistencyMode::Full => sqlx::query_as!(
-                Edge,
+                RelatedObjectRow,
                 r#"
-                    SELECT 
-                        t.id,
+                    SELECT
+                        t.id as edge_id,
                         t.from_type,
                         t.from_id,
                         t.relation,
-                        t.to_type,
-                        t.to_id,
-                        t.created_at as "created_at?: OffsetDateTime",
-                        t.updated_at as "updated_at?: OffsetDateTime"
+                        eh.metadata as "edge_metadata: Value",
+                        t.created_by as edge_created_by,
+                        t.created_at as "edge_created_at?: OffsetDateTime",
+                        t.updated_at as "edge_updated_at?: OffsetDateTime",
+                        o.id,
+                        o.type as type_name,
+                        h.metadata as "metadata: Value",
+                        o.created_by,
+                        o.user_id as updated_by,
+                        o.created_at as "created_at?: OffsetDateTime",
+                        o.updated_at as "updated_at?: OffsetDateTime"
                     FROM triples t
+                    JOIN edge_metadata_history eh ON t.id = eh.edge_id
+                    JOIN objects o ON t.to_id = o.id
+                    JOIN object_metadata_history h ON o.id = h.object_id
                     WHERE t.from_id = $1 AND t.relation = $2
                     AND t.created_xid <= pg_current_xact_id()
                     AND t.deleted_xid > pg_current_xact_id()
+                    AND eh.created_xid <= pg_current_xact_id()
+                    AND eh.deleted_xid > pg_current_xact_id()
+                    AND o.created_xid <= pg_current_xact_id()
+                    AND o.deleted_xid > pg_current_xact_id()
+                    AND h.created_xid <= pg_current_xact_id()
+                    AND h.deleted_xid > pg_current_xact_id()
+                    AND ($3::jsonb IS NULL OR eh.metadata @> $3)
+                    AND ($4::text IS NULL OR eh.metadata ->> $4 = $5
+                        OR $8 AND lower(eh.metadata ->> $4) = lower($5))
+                    AND ($9::text IS NULL OR o.created_by = $9 OR EXISTS(
+                        SELECT 1 FROM object_grants g WHERE g.object_id = o.id AND g.grantee = $9
+                    ))
+                    AND ($10::text IS NULL OR h.metadata -> $10 = 'true'::jsonb)
+                    ORDER BY
+                        CASE WHEN $6 = 'created_at_asc' THEN t.created_at END ASC,
+                        CASE WHEN $6 = 'created_at_desc' THEN t.created_at END DESC,
+                        CASE WHEN $6 = 'created_at_desc' THEN t.id END DESC,
+                        t.id
+                    LIMIT $7
                     "#,
                 from_id,
-                relation
+                relation,
+                contains,
+                key,
+                value,
+                order.as_str(),
+                limit,
+                filter.ignore_case,
+                reader,
+                public_field
             )
             .fetch_all(&self.pool)
             .await
-            .map_err(|e| anyhow!("Failed to fetch edges: {}", e))?,
+            .map_err(|e| anyhow!("Failed to fetch related objects: {}", e))?,
             ConsistencyMode::MinimizeLatency => sqlx::query_as!(
-                Edge,
+                RelatedObjectRow,
                 r#"
-                    SELECT 
-                        t.id,
+                    SELECT
+                        t.id as edge_id,
                         t.from_type,
                         t.from_id,
                         t.relation,
-                        t.to_type,
-                        t.to_id,
-                        t.created_at as "created_at?: OffsetDateTime",
-                        t.updated_at as "updated_at?: OffsetDateTime"
+                        eh.metadata as "edge_metadata!: Value",
+                        t.created_by as edge_created_by,
+                        t.created_at as "edge_created_at?: OffsetDateTime",
+                        t.updated_at as "edge_updated_at?: OffsetDateTime",
+                        o.id,
+                        o.type as type_name,
+                        h.metadata as "metadata!: Value",
+                        o.created_by,
+                        o.user_id as updated_by,
+                        o.created_at as "created_at?: OffsetDateTime",
+                        o.updated_at as "updated_at?: OffsetDateTime"
                     FROM triples t
+                    JOIN LATERAL (
+                        SELECT metadata
+                        FROM edge_metadata_history
+                        WHERE edge_id = t.id
+                        ORDER BY created_xid DESC
+                        LIMIT 1
+                    ) eh ON true
+                    JOIN objects o ON t.to_id = o.id
+                    JOIN LATERAL (
+                        SELECT metadata
+                        FROM object_metadata_history
+                        WHERE object_id = o.id
+                        ORDER BY created_xid DESC
+                        LIMIT 1
+                    ) h ON true
                     WHERE t.from_id = $1 AND t.relation = $2
+                    AND t.deleted_xid = $8
+                    AND o.deleted_xid = $8
+                    AND ($3::jsonb IS NULL OR eh.metadata @> $3)
+                    AND ($4::text IS NULL OR eh.metadata ->> $4 = $5
+                        OR $9 AND lower(eh.metadata ->> $4) = lower($5))
+                    AND ($10::text IS NULL OR o.created_by = $10 OR EXISTS(
+                        SELECT 1 FROM object_grants g WHERE g.object_id = o.id AND g.grantee = $10
+                    ))
+                    AND ($11::text IS NULL OR h.metadata -> $11 = 'true'::jsonb)
+                    ORDER BY
+                        CASE WHEN $6 = 'created_at_asc' THEN t.created_at END ASC,
+                        CASE WHEN $6 = 'created_at_desc' THEN t.created_at END DESC,
+                        CASE WHEN $6 = 'created_at_desc' THEN t.id END DESC,
+                        t.id
+                    LIMIT $7
                     "#,
                 from_id,
-                relation
+                relation,
+                contains,
+                key,
+                value,
+                order.as_str(),
+                limit,
+                Xid8::max() as _,
+                filter.ignore_case,
+                reader,
+                public_field,
             )
             .fetch_all(&self.pool)
             .await
-            .map_err(|e| anyhow!("Failed to fetch edges: {}", e))?,
+            .map_err(|e| anyhow!("Failed to fetch related objects: {}", e))?,
             ConsistencyMode::AtLeastAsFresh(_revision) | ConsistencyMode::ExactlyAt(_revision) => {
                 sqlx::query_as!(
-                    Edge,
+                    RelatedObjectRow,
                     r#"
                     WITH snapshot AS (
                         SELECT $3::text::pg_snapshot as snapshot
                     )
-                    SELECT 
-                        t.id,
+                    SELECT
+                        t.id as edge_id,
                         t.from_type,
                         t.from_id,
                         t.relation,
-                        t.to_type,
-                        t.to_id,
-                        t.created_at as "created_at?: OffsetDateTime",
-                        t.updated_at as "updated_at?: OffsetDateTime"
-                    FROM triples t, snapshot s
+                        eh.metadata as "edge_metadata: Value",
+                        t.created_by as edge_created_by,
+                        t.created_at as "edge_created_at?: OffsetDateTime",
+                        t.updated_at as "edge_updated_at?: OffsetDateTime",
+                        o.id,
+                        o.type as type_name,
+                        h.metadata as "metadata: Value",
+                        o.created_by,
+                        o.user_id as updated_by,
+                        o.created_at as "created_at?: OffsetDateTime",
+                        o.updated_at as "updated_at?: OffsetDateTime"
+                    FROM snapshot s, triples t
+                    JOIN edge_metadata_history eh ON t.id = eh.edge_id
+                    JOIN objects o ON t.to_id = o.id
+                    JOIN object_metadata_history h ON o.id = h.object_id
                     WHERE t.from_id = $1 AND t.relation = $2
-                    AND t.created_xid <= pg_snapshot_xmax(s.snapshot)
-                    AND t.deleted_xid > pg_snapshot_xmax(s.snapshot)
+                    AND pg_visible_in_snapshot(t.created_xid, s.snapshot)
+                    AND NOT pg_visible_in_snapshot(t.deleted_xid, s.snapshot)
+                    AND pg_visible_in_snapshot(eh.created_xid, s.snapshot)
+                    AND NOT pg_visible_in_snapshot(eh.deleted_xid, s.snapshot)
+                    AND pg_visible_in_snapshot(o.created_xid, s.snapshot)
+                    AND NOT pg_visible_in_snapshot(o.deleted_xid, s.snapshot)
+                    AND pg_visible_in_snapshot(h.created_xid, s.snapshot)
+                    AND NOT pg_visible_in_snapshot(h.deleted_xid, s.snapshot)
+                    AND ($4::jsonb IS NULL OR eh.metadata @> $4)
+                    AND ($5::text IS NULL OR eh.metadata ->> $5 = $6
+                        OR $9 AND lower(eh.metadata ->> $5) = lower($6))
+                    AND ($10::text IS NULL OR o.created_by = $10 OR EXISTS(
+                        SELECT 1 FROM object_grants g WHERE g.object_id = o.id AND g.grantee = $10
+                    ))
+                    AND ($11::text IS NULL OR h.metadata -> $11 = 'true'::jsonb)
+                    ORDER BY
+                        CASE WHEN $7 = 'created_at_asc' THEN t.created_at END ASC,
+                        CASE WHEN $7 = 'created_at_desc' THEN t.created_at END DESC,
+                        CASE WHEN $7 = 'created_at_desc' THEN t.id END DESC,
+                        t.id
+                    LIMIT $8
                     "#,
                     from_id,
                     relation,
-                    _revision.snapshot_string()
+                    _revision.included().to_string(),
+                    contains,
+                    key,
+                    value,
+                    order.as_str(),
+                    limit,
+                    filter.ignore_case,
+                    reader,
+                    public_field
                 )
                 .fetch_all(&self.pool)
                 .await
-                .map_err(|e| anyhow!("Failed to fetch edges: {}", e))?
+                .map_err(|e| anyhow!("Failed to fetch related objects: {}", e))?
             }
         };
 
-        let mut result = Vec::with_capacity(edges.len());
-        for edge in edges {
-            // Get the metadata for each edge based on consistency mode
-            let metadata = match &consistency {
-                ConsistencyMode::Full => sqlx::query_as!(
-                    MetadataRecord,
-                    r#"
-                        SELECT metadata
-                        FROM edge_metadata_history
-                        WHERE edge_id = $1
-                        AND created_xid <= pg_current_xact_id()
-                        AND deleted_xid > pg_current_xact_id()
-                        "#,
-                    edge.id
-                )
-                .fetch_one(&self.pool)
-                .await
-                .map_err(|e| anyhow!("Failed to fetch edge metadata: {}", e))?,
-                ConsistencyMode::MinimizeLatency => sqlx::query_as!(
-                    MetadataRecord,
+        Ok(rows.into_iter().map(RelatedObject::from).collect())
+    }
+
+    /// The revision of the latest committed transaction that started at or
+    /// before `at`, or `None` if every transaction is newer. Transactions are
+    /// stamped when they start, so one that commits after `at` can still be
+    /// picked, and one that commits before `at` is missed if the picked one
+    /// started earlier.
+    #[instrument(skip(self))]
+    pub async fn revision_at(&self, at: OffsetDateTime) -> Result<Option<Revision>> {
+        // Transaction timestamps are stored as UTC without a zone
+        let at = at.to_offset(time::UtcOffset::UTC);
+        let at = time::PrimitiveDateTime::new(at.date(), at.time());
+
+        let row = sqlx::query!(
+            r#"
+            SELECT
+                xid as "xid!: Xid8",
+                snapshot::text as "snapshot!: PgSnapshot"
+            FROM relation_tuple_transaction
+            WHERE timestamp <= $1
+            -- Old enough transactions have their status discarded, and only
+            -- committed ones leave a row behind that long
+            AND COALESCE(pg_xact_status(xid), 'committed') = 'committed'
+            ORDER BY timestamp DESC, xid DESC
+            LIMIT 1
+            "#,
+            at
+        )
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(|e| anyhow!("Failed to resolve revision: {}", e))?;
+
+        Ok(row.map(|row| Revision::new(row.snapshot, row.xid)))
+    }
+
+    /// Every metadata version of an object, oldest first, regardless of visibility.
+    #[instrument(skip(self))]
+    pub async fn get_object_history(&self, id: i64) -> Result<Vec<MetadataVersion>> {
+        let rows = sqlx::query!(
+            r#"
+            SELECT
+                h.metadata,
+                h.created_xid as "created_xid!: Xid8",
+                h.deleted_xid as "deleted_xid!: Xid8",
+                t.snapshot::text as "snapshot!: PgSnapshot"
+            FROM object_metadata_history h
+            JOIN relation_tuple_transaction t ON t.xid = h.created_xid
+            WHERE h.object_id = $1
+            ORDER BY h.created_xid
+            "#,
+            id
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| anyhow!("Failed to fetch object history: {}", e))?;
+
+        Ok(rows
+            .into_iter()
+            .map(|row| MetadataVersion {
+                metadata: row.metadata,
+                created_xid: row.created_xid,
+                deleted_xid: (row.deleted_xid != Xid8::max()).then_some(row.deleted_xid),
+                revision: Revision::new(row.snapshot, row.created_xid),
+            })
+            .collect())
+    }
+
+    /// Classifies a `get_object` miss by looking at the object row regardless
+    /// of its visibility at the requested consistency level.
+    pub async fn classify_object_miss(
+        &self,
+        id: i64,
+        consistency: ConsistencyMode,
+    ) -> Result<ObjectMiss> {
+        let consistency = self.resolve_consistency(consistency).await?;
+        let deleted = match &consistency {
+            ConsistencyMode::Full | ConsistencyMode::MinimizeLatency => sqlx::query_scalar!(
+                r#"
+                    SELECT deleted_xid <= pg_current_xact_id() as "deleted!"
+                    FROM objects
+                    WHERE id = $1
+                    "#,
+                id
+            )
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(|e| anyhow!("Failed to classify object miss: {}", e))?,
+            ConsistencyMode::AtLeastAsFresh(_revision) | ConsistencyMode::ExactlyAt(_revision) => {
+                sqlx::query_scalar!(
                     r#"
-                        SELECT metadata
-                        FROM edge_metadata_history
-                        WHERE edge_id = $1
-                        ORDER BY created_xid DESC
-                        LIMIT 1
-                        "#,
-                    edge.id
+                    SELECT pg_visible_in_snapshot(deleted_xid, $2::text::pg_snapshot) as "deleted!"
+                    FROM objects
+                    WHERE id = $1
+                    "#,
+                    id,
+                    _revision.included().to_string()
                 )
-                .fetch_one(&self.pool)
+                .fetch_optional(&self.pool)
                 .await
-                .map_err(|e| anyhow!("Failed to fetch edge metadata: {}", e))?,
-                ConsistencyMode::AtLeastAsFresh(_revision)
-                | ConsistencyMode::ExactlyAt(_revision) => sqlx::query_as!(
-                    MetadataRecord,
-                    r#"
-                        WITH snapshot AS (
-                            SELECT $2::text::pg_snapshot as snapshot
-                        )
-                        SELECT metadata
-                        FROM edge_metadata_history h, snapshot s
-                        WHERE h.edge_id = $1
-                        AND h.created_xid <= pg_snapshot_xmax(s.snapshot)
-                        AND h.deleted_xid > pg_snapshot_xmax(s.snapshot)
-                        "#,
-                    edge.id,
-                    _revision.snapshot_string()
+                .map_err(|e| anyhow!("Failed to classify object miss: {}", e))?
+            }
+        };
+
+        Ok(match deleted {
+            None => ObjectMiss::NeverExisted,
+            Some(true) => ObjectMiss::Deleted,
+            Some(false) => ObjectMiss::NotVisible,
+        })
+    }
+
+    /// Whether `user_id` owns the object or was granted `permission` on it,
+    /// `None` if the object doesn't exist
+    pub async fn check_object_ownership(
+        &self,
+        object_id: i64,
+        user_id: &str,
+        permission: Permission,
+    ) -> Result<Option<bool>> {
+        let allowed = sqlx::query_scalar!(
+            r#"
+            SELECT (
+                o.created_by = $2
+                OR EXISTS(
+                    SELECT 1
+                    FROM object_grants g
+                    WHERE g.object_id = o.id
+                    AND g.grantee = $2
+                    AND (g.permission = 'write' OR g.permission = $3)
                 )
-                .fetch_one(&self.pool)
-                .await
-                .map_err(|e| anyhow!("Failed to fetch edge metadata: {}", e))?,
-            };
+            ) as "allowed!"
+            FROM objects o
+            WHERE o.id = $1
+            "#,
+            object_id,
+            user_id,
+            permission.as_str()
+        )
+        .fetch_optional(&self.pool)
+        .await?;
 
-            result.push(EdgeWithMetadata {
-                id: edge.id,
-                from_type: edge.from_type,
-                from_id: edge.from_id,
-                relation: edge.relation,
-                to_type: edge.to_type,
-                to_id: edge.to_id,
-                metadata: metadata.into_value(),
-                created_at: edge.created_at,
-                updated_at: edge.updated_at,
-            });
-        }
+        Ok(allowed)
+    }
 
-        Ok(result)
+    /// The user who created, and so owns, the object
+    pub async fn object_owner(&self, object_id: i64) -> Result<Option<String>> {
+        let owner = sqlx::query_scalar!("SELECT created_by FROM objects WHERE id = $1", object_id)
+            .fetch_optional(&self.pool)
+            .await?;
+
+        Ok(owner)
     }
 
-    #[instrument(skip(self))]
-    pub async fn get_related_objects(
+    /// Grants `grantee` `permission` on the object, replacing any earlier grant
+    pub async fn share_object(
         &self,
-        from_id: i64,
-        relation: &str,
-    ) -> Result<Vec<ProtoObject>> {
-        let query_result = sqlx::query!(
+        object_id: i64,
+        grantee: &str,
+        permission: Permission,
+        granted_by: &str,
+    ) -> Result<()> {
+        sqlx::query!(
             r#"
-            SELECT 
-                o.id,
-                o.type as "type_name",
-                o.created_at as "created_at?: OffsetDateTime",
-                o.updated_at as "updated_at?: OffsetDateTime",
-                h.metadata as "metadata: Value"
-            FROM triples t
-            JOIN objects o ON t.to_id = o.id
-            JOIN object_metadata_history h ON o.id = h.object_id
-            WHERE t.from_id = $1 AND t.relation = $2
-            AND h.created_xid <= pg_current_xact_id()
-            AND h.deleted_xid > pg_current_xact_id()
+            INSERT INTO object_grants (object_id, grantee, permission, granted_by)
+            VALUES ($1, $2, $3, $4)
+            ON CONFLICT (object_id, grantee) DO UPDATE
+            SET permission = EXCLUDED.permission,
+                granted_by = EXCLUDED.granted_by,
+                created_at = CURRENT_TIMESTAMP
             "#,
-            from_id,
-            relation
+            object_id,
+            grantee,
+            permission.as_str(),
+            granted_by
         )
-        .fetch_all(&self.pool)
-        .await;
+        .execute(&self.pool)
+        .await
+        .map_err(|e| anyhow!("Failed to share object: {}", e))?;
 
-        match query_result {
-            Ok(rows) => {
-                let objects = rows
-                    .into_iter()
-                    .map(|row| {
-                        let obj = ObjectWithMetadata {
-                            id: row.id,
-                            type_name: row.type_name,
-                            metadata: row.metadata,
-                            created_at: row.created_at,
-                            updated_at: row.updated_at,
-                        };
-                        obj.to_pb()
-                    })
-                    .collect();
-
-                Ok(objects)
-            }
-            Err(e) => {
-                tracing::error!("Failed to get edges: {:?}", e);
-                Err(anyhow!("Failed to get edges"))
-            }
-        }
+        Ok(())
     }
 
-    pub async fn check_object_ownership(&self, object_id: i64, user_id: &str) -> Result<bool> {
-        let result = sqlx::query!(
+    /// Whether the object's current metadata sets `field` to `true`
+    pub async fn is_object_public(&self, object_id: i64, field: &str) -> Result<bool> {
+        let public = sqlx::query_scalar!(
             r#"
-            SELECT user_id
-            FROM objects
-            WHERE id = $1
+            SELECT EXISTS(
+                SELECT 1
+                FROM object_metadata_history
+                WHERE object_id = $1
+                AND deleted_xid = $2
+                AND metadata -> $3 = 'true'::jsonb
+            ) as "public!"
             "#,
-            object_id
+            object_id,
+            Xid8::max() as _,
+            field
         )
         .fetch_one(&self.pool)
         .await?;
 
-        Ok(result.user_id == user_id)
+        Ok(public)
+    }
+
+    /// The subset of `object_ids` that exist but neither belong to nor are
+    /// shared with `user_id`
+    pub async fn foreign_objects(&self, object_ids: &[i64], user_id: &str) -> Result<Vec<i64>> {
+        let ids = sqlx::query_scalar!(
+            r#"
+            SELECT id
+            FROM objects
+            WHERE id = ANY($1) AND created_by <> $2
+            AND NOT EXISTS(
+                SELECT 1 FROM object_grants g WHERE g.object_id = objects.id AND g.grantee = $2
+            )
+            "#,
+            object_ids,
+            user_id
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(ids)
     }
 }
 
@@ -1014,6 +4364,7 @@ impl GraphRepository {
 mod tests {
     use super::*;
     use prost_types::Struct;
+    use serde_json::json;
     use sqlx::postgres::PgPoolOptions;
 
     async fn setup() -> PgPool {
@@ -1027,6 +4378,47 @@ mod tests {
             .expect("Failed to create connection pool")
     }
 
+    #[test]
+    fn test_merge_patch() {
+        // Examples from RFC 7386, appendix A
+        let cases = [
+            (json!({"a": "b"}), json!({"a": "c"}), json!({"a": "c"})),
+            (
+                json!({"a": "b"}),
+                json!({"b": "c"}),
+                json!({"a": "b", "b": "c"}),
+            ),
+            (json!({"a": "b"}), json!({"a": null}), json!({})),
+            (
+                json!({"a": {"b": "c"}}),
+                json!({"a": {"b": "d", "c": null}}),
+                json!({"a": {"b": "d"}}),
+            ),
+            (json!({"a": ["b"]}), json!({"a": "c"}), json!({"a": "c"})),
+            (json!({"a": "foo"}), json!(null), json!(null)),
+            (
+                json!({"e": null}),
+                json!({"a": 1}),
+                json!({"e": null, "a": 1}),
+            ),
+            (
+                json!([1, 2]),
+                json!({"a": "b", "c": null}),
+                json!({"a": "b"}),
+            ),
+            (
+                json!({}),
+                json!({"a": {"bb": {"ccc": null}}}),
+                json!({"a": {"bb": {}}}),
+            ),
+        ];
+
+        for (mut target, patch, expected) in cases {
+            merge_patch(&mut target, &patch);
+            assert_eq!(target, expected, "patch {}", patch);
+        }
+    }
+
     #[tokio::test]
     async fn test_object_operations() {
         let pool = setup().await;
@@ -1066,6 +4458,100 @@ mod tests {
         // Add assertions here if needed
     }
 
+    #[tokio::test]
+    async fn test_transaction_metadata() {
+        let pool = setup().await;
+        let repo = GraphRepository::new(pool.clone());
+
+        let tx_metadata = TransactionMetadata::new("user_id")
+            .with_request_id("req-1")
+            .with_reason("backfill");
+        let (object, _) = repo
+            .create_object(
+                tx_metadata,
+                CreateObjectRequest {
+                    r#type: "test_type".to_string(),
+                    metadata: None,
+                },
+            )
+            .await
+            .unwrap();
+
+        let recorded = sqlx::query_scalar!(
+            r#"
+            SELECT t.metadata as "metadata: Value"
+            FROM relation_tuple_transaction t
+            JOIN objects o ON o.created_xid = t.xid
+            WHERE o.id = $1
+            "#,
+            object.id
+        )
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+
+        assert_eq!(
+            recorded,
+            json!({"user_id": "user_id", "request_id": "req-1", "reason": "backfill"})
+        );
+
+        // Deletes record theirs too
+        repo.delete_object(
+            TransactionMetadata::new("user_id").with_reason("cleanup"),
+            object.id,
+        )
+        .await
+        .unwrap();
+
+        let recorded = sqlx::query_scalar!(
+            r#"
+            SELECT t.metadata as "metadata: Value"
+            FROM relation_tuple_transaction t
+            JOIN objects o ON o.deleted_xid = t.xid
+            WHERE o.id = $1
+            "#,
+            object.id
+        )
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+
+        assert_eq!(recorded, json!({"user_id": "user_id", "reason": "cleanup"}));
+
+        // And so do batches
+        let (objects, _) = repo
+            .batch_create_objects(
+                TransactionMetadata::new("user_id").with_request_id("req-2"),
+                vec![(
+                    CreateObjectRequest {
+                        r#type: "test_type".to_string(),
+                        metadata: None,
+                    },
+                    None,
+                )],
+            )
+            .await
+            .unwrap();
+
+        let recorded = sqlx::query_scalar!(
+            r#"
+            SELECT t.metadata as "metadata: Value"
+            FROM relation_tuple_transaction t
+            JOIN objects o ON o.created_xid = t.xid
+            WHERE o.id = $1
+            "#,
+            objects[0].0.id
+        )
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+
+        assert_eq!(
+            recorded,
+            json!({"user_id": "user_id", "request_id": "req-2"})
+        );
+    }
+
     async fn insert_object(
         repo: &GraphRepository,
         user_id: String,
@@ -1073,7 +4559,7 @@ mod tests {
     ) -> (ObjectWithMetadata, Revision) {
         return repo
             .create_object(
-                user_id,
+                TransactionMetadata::new(user_id),
                 CreateObjectRequest {
                     r#type: "test_type".to_string(),
                     metadata: Some(Struct {
@@ -1099,7 +4585,7 @@ mod tests {
     ) -> (EdgeWithMetadata, Revision) {
         return repo
             .create_edge(
-                user_id,
+                TransactionMetadata::new(user_id),
                 CreateEdgeRequest {
                     relation: relation.clone(),
                     from_id: from.id,
@@ -1,9 +1,13 @@
-use anyhow::Result;
-use sqlx::postgres::{PgPool, PgPoolOptions};
+use anyhow::{anyhow, Result};
+use sqlx::postgres::{PgConnectOptions, PgPool, PgPoolOptions};
+use sqlx::Executor;
+use std::str::FromStr;
 use std::time::Duration;
 use tokio::time::sleep;
 use tracing::{info, instrument, warn};
 
+use crate::config::DatabaseConfig;
+
 // Export the schema module
 pub mod graph;
 pub mod schema;
@@ -17,44 +21,102 @@ pub struct Database {
 impl Database {
     #[instrument(skip(database_url))]
     pub async fn new(database_url: &str) -> Result<Self> {
-        let pool = Self::create_pool_with_retry(database_url).await?;
+        let options = PgPoolOptions::new()
+            .max_connections(5)
+            .acquire_timeout(Duration::from_secs(3));
+        let pool =
+            create_pool_with_retry(options, PgConnectOptions::from_str(database_url)?).await?;
         Ok(Self { pool })
     }
 
-    async fn create_pool_with_retry(database_url: &str) -> Result<PgPool> {
-        let mut retry_count = 0;
-        let max_retries = 5;
-        let retry_delay = Duration::from_secs(5);
-
-        loop {
-            match PgPoolOptions::new()
-                .max_connections(5)
-                .acquire_timeout(Duration::from_secs(3))
-                .connect(database_url)
-                .await
-            {
-                Ok(pool) => {
-                    info!("Successfully connected to database");
-                    return Ok(pool);
-                }
-                Err(e) => {
-                    retry_count += 1;
-                    if retry_count >= max_retries {
-                        return Err(e.into());
-                    }
-                    warn!(
-                        "Failed to connect to database, retrying in {} seconds (attempt {}/{})",
-                        retry_delay.as_secs(),
-                        retry_count,
-                        max_retries
-                    );
-                    sleep(retry_delay).await;
-                }
-            }
+    pub fn pool(&self) -> &PgPool {
+        &self.pool
+    }
+}
+
+/// Pool options from the `database` config section, sized to `max_connections`
+pub fn pool_options(config: &DatabaseConfig, max_connections: u32) -> PgPoolOptions {
+    let mut options = PgPoolOptions::new()
+        .max_connections(max_connections)
+        .min_connections(config.min_connections)
+        .acquire_timeout(Duration::from_secs(config.acquire_timeout_seconds));
+
+    // Without an explicit setting keep sqlx's default idle timeout
+    if let Some(seconds) = config.idle_timeout_seconds {
+        options = options.idle_timeout(Duration::from_secs(seconds));
+    }
+
+    if let Some(seconds) = config.statement_timeout_seconds {
+        let statement = format!("SET statement_timeout = {}", seconds * 1000);
+        options = options.after_connect(move |conn, _meta| {
+            let statement = statement.clone();
+            Box::pin(async move {
+                conn.execute(statement.as_str()).await?;
+                Ok(())
+            })
+        });
+    }
+
+    options
+}
+
+/// SQLSTATE Postgres reports when it cancels a statement, as it does once
+/// `statement_timeout` runs out
+const QUERY_CANCELED: &str = "57014";
+
+/// Postgres cancelled a query for running past `statement_timeout`. Worth
+/// retrying: the usual cause is a lock held by another request.
+#[derive(Debug)]
+pub struct StatementTimeoutError;
+
+impl std::fmt::Display for StatementTimeoutError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "query cancelled after exceeding the statement timeout")
+    }
+}
+
+impl std::error::Error for StatementTimeoutError {}
+
+/// `error` prefixed with `context`, or a `StatementTimeoutError` if the query
+/// was cancelled
+pub fn query_error(error: sqlx::Error, context: &str) -> anyhow::Error {
+    match &error {
+        sqlx::Error::Database(e) if e.code().as_deref() == Some(QUERY_CANCELED) => {
+            StatementTimeoutError.into()
         }
+        _ => anyhow!("{}: {}", context, error),
     }
+}
 
-    pub fn pool(&self) -> &PgPool {
-        &self.pool
+/// Connects, retrying for a while so a database that is still starting up
+/// doesn't take the server down with it
+pub async fn create_pool_with_retry(
+    options: PgPoolOptions,
+    connect_options: PgConnectOptions,
+) -> Result<PgPool> {
+    let mut retry_count = 0;
+    let max_retries = 5;
+    let retry_delay = Duration::from_secs(5);
+
+    loop {
+        match options.clone().connect_with(connect_options.clone()).await {
+            Ok(pool) => {
+                info!("Successfully connected to database");
+                return Ok(pool);
+            }
+            Err(e) => {
+                retry_count += 1;
+                if retry_count >= max_retries {
+                    return Err(e.into());
+                }
+                warn!(
+                    "Failed to connect to database, retrying in {} seconds (attempt {}/{})",
+                    retry_delay.as_secs(),
+                    retry_count,
+                    max_retries
+                );
+                sleep(retry_delay).await;
+            }
+        }
     }
 }
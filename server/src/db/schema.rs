@@ -1,19 +1,152 @@
-use anyhow::{anyhow, Result};
-use jsonschema::Validator;
-use serde_json::Value;
+use anyhow::{anyhow, bail, Result};
+use jsonschema::{Draft, Validator};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
 use sqlx::PgPool;
+use std::collections::HashMap;
+use time::format_description::well_known::Rfc3339;
 use time::OffsetDateTime;
-use tracing::instrument;
+use tracing::{instrument, warn};
+
+use super::xid::Xid8;
 
 #[derive(Debug)]
 pub struct Schema {
     pub id: i64,
     pub type_name: String,
     pub schema: Value,
+    pub description: String,
+    /// Draft the schema is validated under; `None` detects it from `$schema`
+    pub draft: Option<String>,
     pub created_at: Option<OffsetDateTime>,
     pub updated_at: Option<OffsetDateTime>,
 }
 
+impl Schema {
+    /// Every way `object` violates this schema, empty when it matches.
+    pub fn violations(&self, object: &Value) -> Result<Vec<String>> {
+        schema_violations(&self.schema, self.draft.as_deref(), object)
+    }
+}
+
+/// JSON Schema drafts a schema can be registered under, by name.
+pub const DRAFTS: [(&str, Draft); 5] = [
+    ("draft-04", Draft::Draft4),
+    ("draft-06", Draft::Draft6),
+    ("draft-07", Draft::Draft7),
+    ("2019-09", Draft::Draft201909),
+    ("2020-12", Draft::Draft202012),
+];
+
+/// Schema keyword declaring a property whose value the server derives from
+/// other top-level fields, e.g. `"full_name": { "x-computed": "{first} {last}" }`.
+pub const COMPUTED_KEYWORD: &str = "x-computed";
+
+/// Schema keyword declaring per-relation rules for edges leaving objects of
+/// the type, e.g. `"x-relations": { "parent": { "max": 1 } }`. A rule sets a
+/// cardinality `max`, `"unique": true` to forbid duplicate edges, or both.
+pub const RELATIONS_KEYWORD: &str = "x-relations";
+
+/// Schema keyword restricting edges leaving objects of the type to the listed
+/// relations and target types, e.g. `"x-edges": { "references": ["document"] }`.
+pub const EDGES_KEYWORD: &str = "x-edges";
+
+/// Schema keyword naming the top-level property that identifies objects of the
+/// type to `UpsertObject`, e.g. `"x-key": "external_id"`.
+pub const KEY_KEYWORD: &str = "x-key";
+
+/// Schema keyword listing top-level properties to index for lookups by value,
+/// e.g. `"x-indexed": ["email"]`.
+pub const INDEXED_KEYWORD: &str = "x-indexed";
+
+/// Longest property name `x-indexed` accepts, so its index name fits in
+/// Postgres' 63 byte identifiers
+pub const MAX_INDEXED_FIELD_LEN: usize = 63 - METADATA_INDEX_PREFIX.len();
+
+const METADATA_INDEX_PREFIX: &str = "idx_object_metadata_";
+
+/// Every type's latest schema, as exchanged by `ExportSchemas`/`ImportSchemas`.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct SchemaBundle {
+    pub schemas: Vec<BundledSchema>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct BundledSchema {
+    pub type_name: String,
+    #[serde(default)]
+    pub description: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub draft: Option<String>,
+    pub schema: Value,
+}
+
+/// A `delete_schema` call for a type that still has live objects.
+#[derive(Debug)]
+pub struct SchemaInUseError {
+    pub type_name: String,
+    pub objects: i64,
+}
+
+impl std::fmt::Display for SchemaInUseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} object(s) of type '{}' still exist; deprecate the schema instead",
+            self.objects, self.type_name
+        )
+    }
+}
+
+impl std::error::Error for SchemaInUseError {}
+
+/// A `create_schema` call for a type that already has a schema.
+#[derive(Debug)]
+pub struct SchemaExistsError {
+    pub type_name: String,
+}
+
+impl std::fmt::Display for SchemaExistsError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "a schema for type '{}' already exists", self.type_name)
+    }
+}
+
+impl std::error::Error for SchemaExistsError {}
+
+/// Metadata rejected because it doesn't match its type's schema.
+#[derive(Debug)]
+pub struct SchemaViolationError {
+    pub errors: Vec<String>,
+}
+
+impl std::fmt::Display for SchemaViolationError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Object does not match schema: {}",
+            self.errors.join("; ")
+        )
+    }
+}
+
+impl std::error::Error for SchemaViolationError {}
+
+/// What `import_schemas` does with a type that already has a schema.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ImportMode {
+    SkipExisting,
+    UpdateExisting,
+}
+
+/// Type names touched by `import_schemas`, by outcome.
+#[derive(Debug, Default)]
+pub struct ImportSummary {
+    pub created: Vec<String>,
+    pub updated: Vec<String>,
+    pub skipped: Vec<String>,
+}
+
 #[derive(Debug)]
 pub struct SchemaRepository {
     pool: PgPool,
@@ -25,35 +158,335 @@ impl SchemaRepository {
     }
 
     #[instrument(skip(self, schema))]
-    pub async fn create_schema(&self, type_name: &str, schema: &str) -> Result<Schema> {
+    pub async fn create_schema(
+        &self,
+        type_name: &str,
+        schema: &str,
+        description: &str,
+        draft: Option<&str>,
+    ) -> Result<Schema> {
         // First validate that the schema string is valid JSON
         let schema_json: serde_json::Value = serde_json::from_str(schema)?;
 
-        // Validate that it's a valid JSON Schema
-        Validator::new(&schema_json).map_err(|e| anyhow!("Invalid JSON Schema: {}", e))?;
+        validate_schema(&schema_json, draft)?;
+
+        let mut tx = self.pool.begin().await?;
 
-        // Insert the schema into the database
+        // Serialize concurrent registrations of the same type so the check
+        // below cannot miss a schema being created alongside this one
+        Self::lock_type(&mut tx, type_name).await?;
+
+        if let Some(existing) = Self::registered_type_name(&mut tx, type_name).await? {
+            return Err(SchemaExistsError {
+                type_name: existing,
+            }
+            .into());
+        }
+
+        let schema =
+            Self::insert_schema(&mut tx, type_name, &schema_json, description, draft).await?;
+        tx.commit().await?;
+
+        self.create_field_indexes(&schema.schema).await;
+
+        Ok(schema)
+    }
+
+    // Held until the transaction ends; taken by anything registering or
+    // removing a type's schema
+    async fn lock_type(
+        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+        type_name: &str,
+    ) -> Result<()> {
+        sqlx::query!(
+            "SELECT pg_advisory_xact_lock(hashtext(lower($1)))",
+            type_name
+        )
+        .execute(&mut **tx)
+        .await?;
+
+        Ok(())
+    }
+
+    // The name the type's schema is registered under. Types differing only in
+    // case, like `Doc` and `doc`, count as the same
+    async fn registered_type_name(
+        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+        type_name: &str,
+    ) -> Result<Option<String>> {
+        let existing = sqlx::query_scalar!(
+            "SELECT type_name FROM schemata WHERE lower(type_name) = lower($1) LIMIT 1",
+            type_name
+        )
+        .fetch_optional(&mut **tx)
+        .await?;
+
+        Ok(existing)
+    }
+
+    async fn insert_schema(
+        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+        type_name: &str,
+        schema: &Value,
+        description: &str,
+        draft: Option<&str>,
+    ) -> Result<Schema> {
         let schema = sqlx::query_as!(
             Schema,
             r#"
-            INSERT INTO schemata (type_name, schema, created_at, updated_at)
-            VALUES ($1, $2, CURRENT_TIMESTAMP, CURRENT_TIMESTAMP)
+            INSERT INTO schemata (type_name, schema, description, draft, created_at, updated_at)
+            VALUES ($1, $2, $3, $4, CURRENT_TIMESTAMP, CURRENT_TIMESTAMP)
             RETURNING 
                 id, 
                 type_name,
                 schema as "schema: serde_json::Value",
+                description,
+                draft,
                 created_at as "created_at?: OffsetDateTime",
                 updated_at as "updated_at?: OffsetDateTime"
             "#,
             type_name,
-            schema_json
+            schema,
+            description,
+            draft
         )
-        .fetch_one(&self.pool)
+        .fetch_one(&mut **tx)
         .await?;
 
         Ok(schema)
     }
 
+    // Indexes each `x-indexed` property's text value across object metadata.
+    // Indexes are named after the property alone, so types sharing a property
+    // name share its index. Concurrent builds can't run in a transaction, so
+    // this follows the registration, and a failure is only logged
+    async fn create_field_indexes(&self, schema: &Value) {
+        // Registered schemas have passed `validate_schema`, which checks these
+        let fields = indexed_fields(schema).unwrap_or_default();
+        for field in fields {
+            if let Err(e) = self.create_field_index(&field).await {
+                warn!("Failed to index field '{}': {:?}", field, e);
+            }
+        }
+    }
+
+    // A failed concurrent build leaves an invalid index behind that `IF NOT
+    // EXISTS` would skip from then on, so it is dropped and built once more
+    async fn create_field_index(&self, field: &str) -> Result<()> {
+        // `indexed_fields` only lets through names that are safe to inline
+        let name = format!("{}{}", METADATA_INDEX_PREFIX, field);
+        let create = format!(
+            r#"CREATE INDEX CONCURRENTLY IF NOT EXISTS "{}" ON object_metadata_history ((metadata->>'{}'))"#,
+            name, field
+        );
+        let drop = format!(r#"DROP INDEX CONCURRENTLY IF EXISTS "{}""#, name);
+
+        let mut failure = anyhow!("index '{}' was left invalid", name);
+        for _ in 0..2 {
+            match sqlx::query(&create).execute(&self.pool).await {
+                Ok(_) if self.is_index_valid(&name).await? => return Ok(()),
+                Ok(_) => failure = anyhow!("index '{}' was left invalid", name),
+                Err(e) => failure = e.into(),
+            }
+            sqlx::query(&drop).execute(&self.pool).await?;
+        }
+
+        Err(failure)
+    }
+
+    async fn is_index_valid(&self, name: &str) -> Result<bool> {
+        let valid = sqlx::query_scalar!(
+            r#"
+            SELECT i.indisvalid
+            FROM pg_index i
+            JOIN pg_class c ON c.oid = i.indexrelid
+            WHERE c.relname = $1
+            "#,
+            name
+        )
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(valid == Some(true))
+    }
+
+    /// The latest schema of every registered type, ordered by type name.
+    #[instrument(skip(self))]
+    pub async fn export_schemas(&self) -> Result<SchemaBundle> {
+        let schemas = sqlx::query_as!(
+            BundledSchema,
+            r#"
+            SELECT DISTINCT ON (type_name)
+                type_name,
+                description,
+                draft,
+                schema as "schema: serde_json::Value"
+            FROM schemata
+            ORDER BY type_name, id DESC
+            "#
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(SchemaBundle { schemas })
+    }
+
+    /// The latest schema version of every registered type, ordered by type name.
+    #[instrument(skip(self))]
+    pub async fn list_schemas(&self) -> Result<Vec<Schema>> {
+        let schemas = sqlx::query_as!(
+            Schema,
+            r#"
+            SELECT DISTINCT ON (type_name)
+                id,
+                type_name,
+                schema as "schema: serde_json::Value",
+                description,
+                draft,
+                created_at as "created_at?: OffsetDateTime",
+                updated_at as "updated_at?: OffsetDateTime"
+            FROM schemata
+            ORDER BY type_name, id DESC
+            "#
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(schemas)
+    }
+
+    /// Registers every schema in `bundle` in one transaction. All schemas are
+    /// validated before anything is written.
+    #[instrument(skip(self, bundle))]
+    pub async fn import_schemas(
+        &self,
+        bundle: SchemaBundle,
+        mode: ImportMode,
+    ) -> Result<ImportSummary> {
+        for bundled in &bundle.schemas {
+            validate_schema(&bundled.schema, bundled.draft.as_deref())
+                .map_err(|e| anyhow!("Schema for '{}': {}", bundled.type_name, e))?;
+        }
+
+        let mut tx = self.pool.begin().await?;
+        let mut summary = ImportSummary::default();
+        let mut imported = Vec::new();
+
+        for bundled in bundle.schemas {
+            Self::lock_type(&mut tx, &bundled.type_name).await?;
+            let existing = Self::registered_type_name(&mut tx, &bundled.type_name).await?;
+
+            if existing.is_some() && mode == ImportMode::SkipExisting {
+                summary.skipped.push(bundled.type_name);
+                continue;
+            }
+
+            // A new version of an existing type keeps the name it has
+            let type_name = existing.as_deref().unwrap_or(&bundled.type_name);
+            let schema = Self::insert_schema(
+                &mut tx,
+                type_name,
+                &bundled.schema,
+                &bundled.description,
+                bundled.draft.as_deref(),
+            )
+            .await?;
+            imported.push(schema.schema);
+
+            match existing {
+                Some(existing) => summary.updated.push(existing),
+                None => summary.created.push(bundled.type_name),
+            }
+        }
+
+        tx.commit().await?;
+
+        for schema in &imported {
+            self.create_field_indexes(schema).await;
+        }
+
+        Ok(summary)
+    }
+
+    /// Removes every version of `type_name`'s schema. Fails with
+    /// `SchemaInUseError` while live objects of the type exist. Returns false
+    /// if the type has no schema.
+    #[instrument(skip(self))]
+    pub async fn delete_schema(&self, type_name: &str) -> Result<bool> {
+        let mut tx = self.pool.begin().await?;
+
+        // Serialize with registrations of the same type
+        Self::lock_type(&mut tx, type_name).await?;
+
+        let objects = sqlx::query_scalar!(
+            r#"
+            SELECT COUNT(*) as "count!"
+            FROM objects
+            WHERE type = $1
+            AND deleted_xid = $2
+            "#,
+            type_name,
+            Xid8::max() as _,
+        )
+        .fetch_one(&mut *tx)
+        .await?;
+        if objects > 0 {
+            return Err(SchemaInUseError {
+                type_name: type_name.to_string(),
+                objects,
+            }
+            .into());
+        }
+
+        let deleted = sqlx::query!("DELETE FROM schemata WHERE type_name = $1", type_name)
+            .execute(&mut *tx)
+            .await?
+            .rows_affected();
+        sqlx::query!(
+            "DELETE FROM deprecated_types WHERE type_name = $1",
+            type_name
+        )
+        .execute(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+
+        Ok(deleted > 0)
+    }
+
+    /// Keeps `type_name`'s schema, and its objects readable, but marks the
+    /// type deprecated so no new objects of it are created. Returns false if
+    /// the type has no schema.
+    #[instrument(skip(self))]
+    pub async fn deprecate_schema(&self, type_name: &str) -> Result<bool> {
+        let deprecated = sqlx::query!(
+            r#"
+            INSERT INTO deprecated_types (type_name)
+            SELECT $1
+            WHERE EXISTS(SELECT 1 FROM schemata WHERE type_name = $1)
+            ON CONFLICT (type_name) DO NOTHING
+            "#,
+            type_name
+        )
+        .execute(&self.pool)
+        .await?
+        .rows_affected();
+
+        Ok(deprecated > 0 || self.is_deprecated(type_name).await?)
+    }
+
+    #[instrument(skip(self))]
+    pub async fn is_deprecated(&self, type_name: &str) -> Result<bool> {
+        let deprecated = sqlx::query_scalar!(
+            r#"SELECT EXISTS(SELECT 1 FROM deprecated_types WHERE type_name = $1) as "exists!""#,
+            type_name
+        )
+        .fetch_one(&self.pool)
+        .await?;
+
+        Ok(deprecated)
+    }
+
     #[instrument(skip(self))]
     pub async fn get_schema(&self, id: i64) -> Result<Option<Schema>> {
         let schema = sqlx::query_as!(
@@ -63,6 +496,8 @@ impl SchemaRepository {
                 id, 
                 type_name,
                 schema as "schema: serde_json::Value",
+                description,
+                draft,
                 created_at as "created_at?: OffsetDateTime",
                 updated_at as "updated_at?: OffsetDateTime"
             FROM schemata
@@ -85,10 +520,14 @@ impl SchemaRepository {
                 id, 
                 type_name,
                 schema as "schema: serde_json::Value",
+                description,
+                draft,
                 created_at as "created_at?: OffsetDateTime",
                 updated_at as "updated_at?: OffsetDateTime"
             FROM schemata
             WHERE type_name = $1
+            ORDER BY id DESC
+            LIMIT 1
             "#,
             type_name
         )
@@ -104,16 +543,333 @@ impl SchemaRepository {
         type_name: &str,
         object: &serde_json::Value,
     ) -> Result<bool> {
-        if let Some(schema) = self.get_schema_by_type(type_name).await? {
-            let validator = Validator::new(&schema.schema)
-                .map_err(|e| anyhow!("Invalid JSON Schema: {}", e))?;
+        Ok(self.validation_errors(type_name, object).await?.is_empty())
+    }
 
-            Ok(validator.validate(object).is_ok())
+    /// Every way `object` violates the current schema of `type_name`, empty
+    /// when it matches.
+    #[instrument(skip(self, object))]
+    pub async fn validation_errors(
+        &self,
+        type_name: &str,
+        object: &serde_json::Value,
+    ) -> Result<Vec<String>> {
+        if let Some(schema) = self.get_schema_by_type(type_name).await? {
+            schema.violations(object)
         } else {
             // If no schema exists, we consider it valid
-            Ok(true)
+            Ok(Vec::new())
+        }
+    }
+}
+
+/// `"format": "date-time"` checked as an RFC 3339 timestamp, e.g.
+/// `2024-03-18T12:00:00Z` or `2024-03-18T12:00:00.5+01:00`.
+fn is_rfc3339_date_time(value: &str) -> bool {
+    OffsetDateTime::parse(value, &Rfc3339).is_ok()
+}
+
+/// The draft called `name` in [`DRAFTS`].
+pub fn parse_draft(name: &str) -> Result<Draft> {
+    DRAFTS
+        .iter()
+        .find(|(known, _)| *known == name)
+        .map(|(_, draft)| *draft)
+        .ok_or_else(|| {
+            let known: Vec<_> = DRAFTS.iter().map(|(known, _)| *known).collect();
+            anyhow!(
+                "Unknown JSON Schema draft '{}', expected one of {}",
+                name,
+                known.join(", ")
+            )
+        })
+}
+
+// Formats are only annotations by default; assert them so a schema's
+// `"format"` keywords actually reject malformed strings
+fn validator(schema: &Value, draft: Option<&str>) -> Result<Validator> {
+    let mut options = jsonschema::options()
+        .should_validate_formats(true)
+        .with_format("date-time", is_rfc3339_date_time);
+    if let Some(draft) = draft {
+        options = options.with_draft(parse_draft(draft)?);
+    }
+
+    options
+        .build(schema)
+        .map_err(|e| anyhow!("Invalid JSON Schema: {}", e))
+}
+
+/// Every way `object` violates `schema` under `draft`, empty when it matches.
+/// Without a draft it is detected from `$schema`, defaulting to 2020-12.
+pub fn schema_violations(
+    schema: &Value,
+    draft: Option<&str>,
+    object: &Value,
+) -> Result<Vec<String>> {
+    let validator = validator(schema, draft)?;
+
+    Ok(validator
+        .iter_errors(object)
+        .map(|e| format!("{}: {}", e.instance_path, e))
+        .collect())
+}
+
+/// Checks that `schema` is a valid JSON Schema under `draft` whose `x-`
+/// keywords are well formed.
+pub fn validate_schema(schema: &Value, draft: Option<&str>) -> Result<()> {
+    validator(schema, draft)?;
+    computed_fields(schema)?;
+    relation_rules(schema)?;
+    allowed_edges(schema)?;
+    external_key(schema)?;
+    indexed_fields(schema)?;
+
+    Ok(())
+}
+
+/// Returns the `(property, template)` pairs declared with `x-computed` in a
+/// schema's top-level properties.
+pub fn computed_fields(schema: &Value) -> Result<Vec<(String, String)>> {
+    let Some(properties) = schema.get("properties").and_then(Value::as_object) else {
+        return Ok(Vec::new());
+    };
+
+    let mut fields = Vec::new();
+    for (name, property) in properties {
+        match property.get(COMPUTED_KEYWORD) {
+            None => continue,
+            Some(Value::String(template)) => {
+                // Reject malformed templates when the schema is created
+                render_template(template, &Map::new())?;
+                fields.push((name.clone(), template.clone()));
+            }
+            Some(_) => bail!(
+                "{} on '{}' must be a string template",
+                COMPUTED_KEYWORD,
+                name
+            ),
         }
     }
+
+    Ok(fields)
+}
+
+/// A rule declared with `x-relations` for one outgoing relation.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RelationRule {
+    pub relation: String,
+    /// Objects of the type may have at most this many live edges of the
+    /// relation
+    pub max: Option<i64>,
+    /// Objects of the type may have at most one live edge of the relation to
+    /// any given object
+    pub unique: bool,
+}
+
+/// Returns the rules declared with `x-relations`, one per relation.
+pub fn relation_rules(schema: &Value) -> Result<Vec<RelationRule>> {
+    let Some(relations) = schema.get(RELATIONS_KEYWORD) else {
+        return Ok(Vec::new());
+    };
+    let Some(relations) = relations.as_object() else {
+        bail!("{} must be an object keyed by relation", RELATIONS_KEYWORD);
+    };
+
+    let mut rules = Vec::new();
+    for (relation, rule) in relations {
+        let max = match rule.get("max") {
+            None => None,
+            Some(max) => match max.as_i64() {
+                Some(max) if max >= 0 => Some(max),
+                _ => bail!(
+                    "{} rule for '{}' must set \"max\" to a non-negative integer",
+                    RELATIONS_KEYWORD,
+                    relation
+                ),
+            },
+        };
+        let unique = match rule.get("unique") {
+            None => false,
+            Some(unique) => match unique.as_bool() {
+                Some(unique) => unique,
+                None => bail!(
+                    "{} rule for '{}' must set \"unique\" to a boolean",
+                    RELATIONS_KEYWORD,
+                    relation
+                ),
+            },
+        };
+        if max.is_none() && !unique {
+            bail!(
+                "{} rule for '{}' must set \"max\" or \"unique\"",
+                RELATIONS_KEYWORD,
+                relation
+            );
+        }
+
+        rules.push(RelationRule {
+            relation: relation.clone(),
+            max,
+            unique,
+        });
+    }
+
+    Ok(rules)
+}
+
+/// Returns the target types of each relation declared with `x-edges`, or
+/// `None` if the type accepts any edge. With `x-edges` set, edges leaving
+/// objects of the type must use a listed relation and point at one of its
+/// target types.
+pub fn allowed_edges(schema: &Value) -> Result<Option<HashMap<String, Vec<String>>>> {
+    let Some(edges) = schema.get(EDGES_KEYWORD) else {
+        return Ok(None);
+    };
+    let Some(edges) = edges.as_object() else {
+        bail!("{} must be an object keyed by relation", EDGES_KEYWORD);
+    };
+
+    let mut allowed = HashMap::new();
+    for (relation, targets) in edges {
+        let targets: Option<Vec<String>> = targets.as_array().and_then(|targets| {
+            targets
+                .iter()
+                .map(|target| target.as_str().map(str::to_string))
+                .collect()
+        });
+        match targets {
+            Some(targets) if !targets.is_empty() => {
+                allowed.insert(relation.clone(), targets);
+            }
+            _ => bail!(
+                "{} entry for '{}' must be a non-empty array of type names",
+                EDGES_KEYWORD,
+                relation
+            ),
+        }
+    }
+
+    Ok(Some(allowed))
+}
+
+/// Returns the property declared with `x-key`, or `None` if the type has no
+/// external key. The property must be one of the schema's `properties`.
+pub fn external_key(schema: &Value) -> Result<Option<String>> {
+    let Some(key) = schema.get(KEY_KEYWORD) else {
+        return Ok(None);
+    };
+    let Some(key) = key.as_str().filter(|key| !key.is_empty()) else {
+        bail!("{} must be a property name", KEY_KEYWORD);
+    };
+
+    let declared = schema
+        .get("properties")
+        .and_then(Value::as_object)
+        .is_some_and(|properties| properties.contains_key(key));
+    if !declared {
+        bail!(
+            "{} names '{}', which is not a declared property",
+            KEY_KEYWORD,
+            key
+        );
+    }
+
+    Ok(Some(key.to_string()))
+}
+
+/// Whether `field` may be indexed and looked up by value: letters, digits and
+/// underscores only, not starting with a digit, and short enough for an index
+/// name. Such names are safe to inline into SQL.
+pub fn is_indexable_field(field: &str) -> bool {
+    field.len() <= MAX_INDEXED_FIELD_LEN
+        && field.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_')
+        && field.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+/// Returns the properties listed under `x-indexed`. Names are restricted to
+/// letters, digits and underscores since they end up in index definitions.
+pub fn indexed_fields(schema: &Value) -> Result<Vec<String>> {
+    let Some(fields) = schema.get(INDEXED_KEYWORD) else {
+        return Ok(Vec::new());
+    };
+    let Some(fields) = fields.as_array() else {
+        bail!("{} must be an array of property names", INDEXED_KEYWORD);
+    };
+
+    let mut indexed = Vec::with_capacity(fields.len());
+    for field in fields {
+        match field.as_str().filter(|field| is_indexable_field(field)) {
+            Some(field) => indexed.push(field.to_string()),
+            None => bail!(
+                "{} entry {} must be a property name of letters, digits and underscores, at most {} long",
+                INDEXED_KEYWORD,
+                field,
+                MAX_INDEXED_FIELD_LEN
+            ),
+        }
+    }
+
+    Ok(indexed)
+}
+
+/// Fills in the schema's computed fields on `metadata`, replacing whatever the
+/// object previously stored for them. A computed field whose template refers
+/// to a missing or null field is left unset. Fails if the client supplied a
+/// value for a computed field.
+pub fn apply_computed_fields(schema: &Value, metadata: &mut Value) -> Result<()> {
+    let Value::Object(map) = metadata else {
+        return Ok(());
+    };
+
+    let fields = computed_fields(schema)?;
+    for (name, _) in &fields {
+        if map.contains_key(name) {
+            bail!(
+                "Field '{}' is computed by the server and cannot be set",
+                name
+            );
+        }
+    }
+
+    for (name, template) in fields {
+        if let Some(value) = render_template(&template, map)? {
+            map.insert(name, Value::String(value));
+        }
+    }
+
+    Ok(())
+}
+
+// Substitutes `{field}` placeholders with top-level metadata values
+fn render_template(template: &str, metadata: &Map<String, Value>) -> Result<Option<String>> {
+    let mut rendered = String::new();
+    let mut rest = template;
+    let mut complete = true;
+
+    while let Some(start) = rest.find('{') {
+        rendered.push_str(&rest[..start]);
+        let end = rest[start..]
+            .find('}')
+            .ok_or_else(|| anyhow!("Unclosed placeholder in template '{}'", template))?;
+        let field = &rest[start + 1..start + end];
+        if field.is_empty() || field.contains('{') {
+            bail!("Invalid placeholder in template '{}'", template);
+        }
+
+        match metadata.get(field) {
+            Some(Value::String(s)) => rendered.push_str(s),
+            Some(Value::Null) | None => complete = false,
+            Some(other) => rendered.push_str(&other.to_string()),
+        }
+        rest = &rest[start + end + 1..];
+    }
+    if rest.contains('}') {
+        bail!("Unmatched '}}' in template '{}'", template);
+    }
+    rendered.push_str(rest);
+
+    Ok(complete.then_some(rendered))
 }
 
 #[cfg(test)]
@@ -149,7 +905,10 @@ mod tests {
         let type_name = format!("test_type_{}", Uuid::new_v4());
 
         // Test creating schema
-        let created = repo.create_schema(&type_name, test_schema).await.unwrap();
+        let created = repo
+            .create_schema(&type_name, test_schema, "", None)
+            .await
+            .unwrap();
         assert!(created.id > 0);
         assert_eq!(created.type_name, type_name);
 
@@ -162,6 +921,13 @@ mod tests {
         let retrieved = repo.get_schema_by_type(&type_name).await.unwrap().unwrap();
         assert_eq!(created.id, retrieved.id);
         assert_eq!(created.schema, retrieved.schema);
+
+        // Registering the same type again is rejected
+        let err = repo
+            .create_schema(&type_name, test_schema, "", None)
+            .await
+            .unwrap_err();
+        assert!(err.is::<SchemaExistsError>());
     }
 
     #[tokio::test]
@@ -181,7 +947,9 @@ mod tests {
         let type_name = format!("person_{}", Uuid::new_v4());
 
         // Create schema
-        repo.create_schema(&type_name, test_schema).await.unwrap();
+        repo.create_schema(&type_name, test_schema, "", None)
+            .await
+            .unwrap();
 
         // Test valid object
         let valid_object = serde_json::json!({
@@ -203,4 +971,254 @@ mod tests {
             .await
             .unwrap());
     }
+
+    #[tokio::test]
+    async fn test_schema_draft() {
+        let pool = setup().await;
+        let repo = SchemaRepository::new(pool);
+
+        // `prefixItems` only exists since 2020-12; earlier drafts ignore it
+        let test_schema = r#"{
+            "type": "object",
+            "properties": {
+                "pair": { "prefixItems": [{ "type": "string" }, { "type": "number" }] }
+            }
+        }"#;
+        let swapped = serde_json::json!({ "pair": [1, "one"] });
+
+        let current = format!("pair_{}", Uuid::new_v4());
+        let created = repo
+            .create_schema(&current, test_schema, "", Some("2020-12"))
+            .await
+            .unwrap();
+        assert_eq!(created.draft.as_deref(), Some("2020-12"));
+        assert!(!repo.validate_object(&current, &swapped).await.unwrap());
+
+        let legacy = format!("pair_{}", Uuid::new_v4());
+        repo.create_schema(&legacy, test_schema, "", Some("draft-07"))
+            .await
+            .unwrap();
+        let stored = repo.get_schema_by_type(&legacy).await.unwrap().unwrap();
+        assert_eq!(stored.draft.as_deref(), Some("draft-07"));
+        assert!(repo.validate_object(&legacy, &swapped).await.unwrap());
+
+        let unknown = format!("pair_{}", Uuid::new_v4());
+        assert!(repo
+            .create_schema(&unknown, test_schema, "", Some("draft-99"))
+            .await
+            .is_err());
+    }
+
+    #[test]
+    fn test_date_time_format() {
+        let schema = serde_json::json!({
+            "type": "object",
+            "properties": { "due": { "type": "string", "format": "date-time" } }
+        });
+
+        for valid in ["2024-03-18T12:00:00Z", "2024-03-18T12:00:00.5+01:00"] {
+            let object = serde_json::json!({ "due": valid });
+            assert!(schema_violations(&schema, None, &object)
+                .unwrap()
+                .is_empty());
+        }
+
+        for invalid in [
+            "2024-03-18",
+            "2024-03-18 12:00:00",
+            "2024-03-18T12:00:00",
+            "2024-02-30T12:00:00Z",
+            "yesterday",
+        ] {
+            let object = serde_json::json!({ "due": invalid });
+            let violations = schema_violations(&schema, None, &object).unwrap();
+            assert_eq!(violations.len(), 1, "{} should be rejected", invalid);
+        }
+    }
+
+    #[test]
+    fn test_email_and_uri_formats() {
+        let schema = serde_json::json!({
+            "type": "object",
+            "properties": {
+                "email": { "type": "string", "format": "email" },
+                "homepage": { "type": "string", "format": "uri" }
+            }
+        });
+
+        let object = serde_json::json!({
+            "email": "ada@example.com",
+            "homepage": "https://example.com/ada"
+        });
+        assert!(schema_violations(&schema, None, &object)
+            .unwrap()
+            .is_empty());
+
+        for invalid in [
+            serde_json::json!({ "email": "not-an-email" }),
+            serde_json::json!({ "email": "ada@" }),
+            serde_json::json!({ "homepage": "not a uri" }),
+            serde_json::json!({ "homepage": "/relative/path" }),
+        ] {
+            let violations = schema_violations(&schema, None, &invalid).unwrap();
+            assert_eq!(violations.len(), 1, "{} should be rejected", invalid);
+        }
+    }
+
+    #[test]
+    fn test_apply_computed_fields() {
+        let schema = serde_json::json!({
+            "type": "object",
+            "properties": {
+                "first": { "type": "string" },
+                "last": { "type": "string" },
+                "age": { "type": "integer" },
+                "full_name": { "type": "string", "x-computed": "{first} {last}" },
+                "label": { "type": "string", "x-computed": "{last} ({age})" }
+            }
+        });
+
+        let mut metadata = serde_json::json!({ "first": "Ada", "last": "Lovelace" });
+        apply_computed_fields(&schema, &mut metadata).unwrap();
+        assert_eq!(metadata["full_name"], "Ada Lovelace");
+        // `age` is missing, so `label` cannot be computed
+        assert!(metadata.get("label").is_none());
+
+        let mut metadata =
+            serde_json::json!({ "first": "Ada", "last": "Lovelace", "full_name": "Countess" });
+        assert!(apply_computed_fields(&schema, &mut metadata).is_err());
+    }
+
+    #[test]
+    fn test_invalid_computed_templates() {
+        for template in [
+            serde_json::json!("{first"),
+            serde_json::json!("first}"),
+            serde_json::json!("{}"),
+            serde_json::json!(42),
+        ] {
+            let schema = serde_json::json!({
+                "properties": { "full_name": { "x-computed": template } }
+            });
+            assert!(computed_fields(&schema).is_err());
+        }
+    }
+
+    #[test]
+    fn test_allowed_edges() {
+        let schema = serde_json::json!({
+            "x-edges": { "references": ["document", "comment"] }
+        });
+        assert_eq!(
+            allowed_edges(&schema).unwrap(),
+            Some(HashMap::from([(
+                "references".to_string(),
+                vec!["document".to_string(), "comment".to_string()]
+            )]))
+        );
+        assert_eq!(allowed_edges(&serde_json::json!({})).unwrap(), None);
+
+        for edges in [
+            serde_json::json!(["references"]),
+            serde_json::json!({ "references": [] }),
+            serde_json::json!({ "references": "document" }),
+            serde_json::json!({ "references": [1] }),
+        ] {
+            let schema = serde_json::json!({ "x-edges": edges });
+            assert!(allowed_edges(&schema).is_err());
+        }
+    }
+
+    #[test]
+    fn test_external_key() {
+        let schema = serde_json::json!({
+            "properties": { "external_id": { "type": "string" } },
+            "x-key": "external_id"
+        });
+        assert_eq!(
+            external_key(&schema).unwrap(),
+            Some("external_id".to_string())
+        );
+        assert_eq!(external_key(&serde_json::json!({})).unwrap(), None);
+
+        for key in [
+            serde_json::json!("missing"),
+            serde_json::json!(""),
+            serde_json::json!(["external_id"]),
+        ] {
+            let schema = serde_json::json!({
+                "properties": { "external_id": { "type": "string" } },
+                "x-key": key
+            });
+            assert!(external_key(&schema).is_err());
+        }
+    }
+
+    #[test]
+    fn test_indexed_fields() {
+        let schema = serde_json::json!({ "x-indexed": ["email", "_legacy_id2"] });
+        assert_eq!(
+            indexed_fields(&schema).unwrap(),
+            vec!["email".to_string(), "_legacy_id2".to_string()]
+        );
+        assert!(indexed_fields(&serde_json::json!({})).unwrap().is_empty());
+
+        for fields in [
+            serde_json::json!("email"),
+            serde_json::json!([1]),
+            serde_json::json!(["e-mail"]),
+            serde_json::json!(["2fa"]),
+            serde_json::json!(["x'); DROP TABLE objects; --"]),
+            serde_json::json!(["a".repeat(MAX_INDEXED_FIELD_LEN + 1)]),
+        ] {
+            let schema = serde_json::json!({ "x-indexed": fields });
+            assert!(indexed_fields(&schema).is_err());
+        }
+    }
+
+    #[test]
+    fn test_relation_rules() {
+        let schema = serde_json::json!({
+            "x-relations": {
+                "parent": { "max": 1 },
+                "friend": { "unique": true },
+                "tag": { "max": 5, "unique": false }
+            }
+        });
+        let mut rules = relation_rules(&schema).unwrap();
+        rules.sort_by(|a, b| a.relation.cmp(&b.relation));
+        assert_eq!(
+            rules,
+            vec![
+                RelationRule {
+                    relation: "friend".to_string(),
+                    max: None,
+                    unique: true,
+                },
+                RelationRule {
+                    relation: "parent".to_string(),
+                    max: Some(1),
+                    unique: false,
+                },
+                RelationRule {
+                    relation: "tag".to_string(),
+                    max: Some(5),
+                    unique: false,
+                },
+            ]
+        );
+        assert!(relation_rules(&serde_json::json!({})).unwrap().is_empty());
+
+        for relations in [
+            serde_json::json!(["parent"]),
+            serde_json::json!({ "parent": {} }),
+            serde_json::json!({ "parent": { "max": -1 } }),
+            serde_json::json!({ "parent": { "max": "one" } }),
+            serde_json::json!({ "parent": { "unique": "yes" } }),
+            serde_json::json!({ "parent": { "unique": false } }),
+        ] {
+            let schema = serde_json::json!({ "x-relations": relations });
+            assert!(relation_rules(&schema).is_err());
+        }
+    }
 }
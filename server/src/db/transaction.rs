@@ -1,4 +1,5 @@
 use std::{
+    cmp::Ordering,
     fmt::{Display, Formatter, Result as FmtResult},
     str::FromStr,
 };
@@ -7,7 +8,10 @@ use super::xid::Xid8;
 use anyhow::{anyhow, Result};
 use base64::{self, engine::general_purpose::URL_SAFE as base64_url, Engine};
 use ent_proto::ent::Zookie;
+use hmac::{Hmac, Mac};
+use once_cell::sync::OnceCell;
 use serde::{Deserialize, Serialize};
+use sha2::Sha256;
 use sqlx::{
     encode::IsNull,
     error::BoxDynError,
@@ -16,6 +20,28 @@ use sqlx::{
     Decode, Encode, Type,
 };
 
+type HmacSha256 = Hmac<Sha256>;
+
+// Key signing zookies; a random per-process key unless configured
+static ZOOKIE_KEY: OnceCell<Vec<u8>> = OnceCell::new();
+
+// Initialize the key zookies are signed with, so they stay valid across
+// restarts and between servers sharing the secret
+pub fn init_zookie_key(secret: &str) -> Result<()> {
+    if secret.is_empty() {
+        return Err(anyhow!("Zookie secret must not be empty"));
+    }
+
+    ZOOKIE_KEY
+        .set(secret.as_bytes().to_vec())
+        .map_err(|_| anyhow!("Zookie key has already been initialized"))
+}
+
+fn zookie_mac() -> HmacSha256 {
+    let key = ZOOKIE_KEY.get_or_init(|| rand::random::<[u8; 32]>().to_vec());
+    HmacSha256::new_from_slice(key).expect("HMAC accepts keys of any length")
+}
+
 #[derive(Debug)]
 pub struct SnapshotError(String);
 
@@ -27,13 +53,26 @@ impl Display for SnapshotError {
 
 impl std::error::Error for SnapshotError {}
 
+/// A Postgres `pg_snapshot`. `xip_list` is kept sorted and free of duplicates
+/// however the snapshot was built, so `is_visible` can binary search it.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct PgSnapshot {
     xmin: u64,
     xmax: u64,
+    #[serde(deserialize_with = "deserialize_xip_list")]
     xip_list: Vec<u64>,
 }
 
+fn deserialize_xip_list<'de, D>(deserializer: D) -> Result<Vec<u64>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    let mut xip_list = Vec::<u64>::deserialize(deserializer)?;
+    xip_list.sort_unstable();
+    xip_list.dedup();
+    Ok(xip_list)
+}
+
 impl FromStr for PgSnapshot {
     type Err = SnapshotError;
 
@@ -50,7 +89,7 @@ impl FromStr for PgSnapshot {
             .parse::<u64>()
             .map_err(|e| SnapshotError(format!("Invalid xmax: {}", e)))?;
 
-        let xip_list = if parts[2].is_empty() {
+        let mut xip_list = if parts[2].is_empty() {
             Vec::new()
         } else {
             parts[2]
@@ -61,6 +100,9 @@ impl FromStr for PgSnapshot {
                 })
                 .collect::<Result<Vec<_>, _>>()?
         };
+        // Postgres emits the list sorted, but hand-written snapshots may not be
+        xip_list.sort_unstable();
+        xip_list.dedup();
 
         Ok(PgSnapshot {
             xmin,
@@ -70,12 +112,13 @@ impl FromStr for PgSnapshot {
     }
 }
 
-impl ToString for PgSnapshot {
-    fn to_string(&self) -> String {
+impl Display for PgSnapshot {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
         if self.xip_list.is_empty() {
-            format!("{}:{}:", self.xmin, self.xmax)
+            write!(f, "{}:{}:", self.xmin, self.xmax)
         } else {
-            format!(
+            write!(
+                f,
                 "{}:{}:{}",
                 self.xmin,
                 self.xmax,
@@ -117,19 +160,59 @@ impl PgSnapshot {
         if xid >= self.xmax {
             return false;
         }
-        !self.xip_list.binary_search(&xid).is_ok()
+        self.xip_list.binary_search(&xid).is_err()
     }
 
+    /// Whether every transaction visible to `other` is also visible to this
+    /// snapshot.
+    pub fn sees_all_of(&self, other: &PgSnapshot) -> bool {
+        // Transactions hidden from us by being in progress must be hidden from
+        // `other` too
+        if self.xip_list.iter().any(|&xid| other.is_visible(xid)) {
+            return false;
+        }
+
+        // So must every transaction at or past our xmax that `other` covers,
+        // which means all of them were still in progress for `other`
+        if other.xmax > self.xmax {
+            if self.xmax < other.xmin {
+                return false;
+            }
+            let hidden = other
+                .xip_list
+                .iter()
+                .filter(|&&xid| xid >= self.xmax && xid < other.xmax)
+                .count() as u64;
+            return hidden == other.xmax - self.xmax;
+        }
+
+        true
+    }
+
+    /// Orders snapshots by the transactions they see. Snapshots that each see
+    /// a transaction the other doesn't are incomparable.
+    pub fn compare(&self, other: &PgSnapshot) -> Option<Ordering> {
+        match (self.sees_all_of(other), other.sees_all_of(self)) {
+            (true, true) => Some(Ordering::Equal),
+            (true, false) => Some(Ordering::Greater),
+            (false, true) => Some(Ordering::Less),
+            (false, false) => None,
+        }
+    }
+
+    /// The snapshot with `xid` committed and nothing else changed: any
+    /// transaction it moves past the old `xmax` stays hidden.
     pub fn mark_complete(mut self, xid: u64) -> Self {
         if xid >= self.xmax {
+            // Appending larger IDs keeps the list sorted
+            self.xip_list.extend(self.xmax..xid);
             self.xmax = xid + 1;
         }
+        // So does removing one
         if let Ok(pos) = self.xip_list.binary_search(&xid) {
             self.xip_list.remove(pos);
         }
-        if self.xip_list.is_empty() {
-            self.xmin = self.xmax;
-        }
+        self.xmin = self.xip_list.first().copied().unwrap_or(self.xmax);
         self
     }
 }
@@ -142,24 +225,96 @@ pub struct Revision {
 }
 
 impl Revision {
+    /// The revision written by the transaction `xid`, which took `snapshot`
+    pub fn new(snapshot: PgSnapshot, xid: Xid8) -> Self {
+        Revision {
+            snapshot,
+            optional_xid: Some(xid.value()),
+        }
+    }
+
+    /// A revision of everything committed as of `snapshot`, taken by a reader
+    /// rather than a writing transaction
+    pub fn from_snapshot(snapshot: PgSnapshot) -> Self {
+        Revision {
+            snapshot,
+            optional_xid: None,
+        }
+    }
+
+    /// Encodes the revision as `<payload>.<signature>`, both base64, where the
+    /// signature is an HMAC-SHA256 of the payload.
     pub fn to_zookie(&self) -> Result<Zookie> {
         let bytes = serde_json::to_vec(self)?;
+        let mut mac = zookie_mac();
+        mac.update(&bytes);
+        let signature = mac.finalize().into_bytes();
+
         Ok(Zookie {
-            value: base64_url.encode(bytes),
+            value: format!(
+                "{}.{}",
+                base64_url.encode(&bytes),
+                base64_url.encode(signature)
+            ),
         })
     }
 
+    /// Decodes a zookie issued by `to_zookie`, rejecting any whose signature
+    /// does not match.
     pub fn from_zookie(zookie: Zookie) -> Result<Self> {
+        let (payload, signature) = zookie
+            .value
+            .split_once('.')
+            .ok_or_else(|| anyhow!("Invalid zookie format"))?;
         let bytes = base64_url
-            .decode(zookie.value.as_bytes())
+            .decode(payload.as_bytes())
             .map_err(|_| anyhow!("Invalid zookie encoding"))?;
+        let signature = base64_url
+            .decode(signature.as_bytes())
+            .map_err(|_| anyhow!("Invalid zookie encoding"))?;
+
+        let mut mac = zookie_mac();
+        mac.update(&bytes);
+        mac.verify_slice(&signature)
+            .map_err(|_| anyhow!("Invalid zookie signature"))?;
 
         serde_json::from_slice(&bytes).map_err(|_| anyhow!("Invalid zookie format"))
     }
 
-    pub fn greater_than(&self, other: &Self) -> bool {
-        // A revision is greater if it can see transactions the other can't
-        self.snapshot.xmax > other.snapshot.xmax
+    /// Partial order of revisions: one is at least as fresh as another when it
+    /// includes every transaction the other includes. `None` if neither does.
+    pub fn compare(&self, other: &Self) -> Option<Ordering> {
+        self.included().compare(&other.included())
+    }
+
+    /// Whether this revision includes everything `other` does and more.
+    pub fn is_fresher_than(&self, other: &Self) -> bool {
+        self.compare(other) == Some(Ordering::Greater)
+    }
+
+    /// Whether both revisions include exactly the same transactions, however
+    /// their snapshots are written.
+    pub fn is_same_as(&self, other: &Self) -> bool {
+        self.compare(other) == Some(Ordering::Equal)
+    }
+
+    /// Whether the effects of transaction `xid` are part of this revision:
+    /// it committed before the snapshot was taken or is the writing transaction.
+    pub fn includes(&self, xid: u64) -> bool {
+        self.optional_xid == Some(xid) || self.snapshot.is_visible(xid)
+    }
+
+    /// The transactions this revision includes, as a single snapshot. Reads
+    /// at the revision are evaluated against it, so they see its own writes.
+    pub fn included(&self) -> PgSnapshot {
+        match self.optional_xid {
+            Some(xid) => self.snapshot.clone().mark_complete(xid),
+            None => self.snapshot.clone(),
+        }
+    }
+
+    pub fn snapshot(&self) -> &PgSnapshot {
+        &self.snapshot
     }
 
     pub fn snapshot_string(&self) -> String {
@@ -167,6 +322,15 @@ impl Revision {
     }
 }
 
+/// Revisions are equal when they include the same transactions.
+impl PartialEq for Revision {
+    fn eq(&self, other: &Self) -> bool {
+        self.is_same_as(other)
+    }
+}
+
+impl Eq for Revision {}
+
 /// Consistency mode for queries
 #[derive(Debug, Clone)]
 pub enum ConsistencyMode {
@@ -176,6 +340,42 @@ pub enum ConsistencyMode {
     MinimizeLatency,
 }
 
+/// Who made a write and why, stored with its transaction so the transaction
+/// log doubles as an audit trail
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct TransactionMetadata {
+    pub user_id: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub request_id: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+}
+
+impl TransactionMetadata {
+    pub fn new(user_id: impl Into<String>) -> Self {
+        Self {
+            user_id: user_id.into(),
+            ..Default::default()
+        }
+    }
+
+    pub fn with_request_id(mut self, request_id: impl Into<String>) -> Self {
+        self.request_id = Some(request_id.into());
+        self
+    }
+
+    pub fn with_reason(mut self, reason: impl Into<String>) -> Self {
+        self.reason = Some(reason.into());
+        self
+    }
+}
+
+impl From<&TransactionMetadata> for Json<serde_json::Value> {
+    fn from(metadata: &TransactionMetadata) -> Self {
+        Json(serde_json::to_value(metadata).unwrap_or_default())
+    }
+}
+
 #[derive(Debug)]
 pub struct Transaction {
     pub xid: Xid8,
@@ -185,23 +385,29 @@ pub struct Transaction {
 
 impl Transaction {
     pub fn revision(&self) -> Revision {
-        Revision {
-            snapshot: self.snapshot.clone(),
-            optional_xid: Some(self.xid.value()),
-        }
+        Revision::new(self.snapshot.clone(), self.xid)
     }
 
     pub async fn create(
         transaction: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+    ) -> Result<Transaction> {
+        Self::create_with_metadata(transaction, Json(serde_json::json!({}))).await
+    }
+
+    /// Like `create`, recording `metadata` in the transaction log
+    pub async fn create_with_metadata(
+        transaction: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+        metadata: Json<serde_json::Value>,
     ) -> Result<Transaction> {
         let row = sqlx::query!(
             r#"
-            INSERT INTO relation_tuple_transaction DEFAULT VALUES 
+            INSERT INTO relation_tuple_transaction (metadata) VALUES ($1)
             RETURNING
                     xid as "xid!: Xid8",
                     snapshot::text as "snapshot!: PgSnapshot",
                     metadata as "metadata: Json<serde_json::Value>"
-            "#
+            "#,
+            metadata as _
         )
         .fetch_one(&mut **transaction)
         .await?;
@@ -217,6 +423,7 @@ impl Transaction {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use proptest::prelude::*;
 
     #[test]
     fn test_snapshot_parsing() {
@@ -238,6 +445,11 @@ mod tests {
 
         let err = PgSnapshot::from_str("a:b:c").unwrap_err();
         assert!(err.to_string().contains("Invalid xmin"));
+
+        // Out of order and repeated in-progress transactions are normalized
+        let snapshot = PgSnapshot::from_str("100:105:103,101,103").unwrap();
+        assert_eq!(snapshot.xip_list, vec![101, 103]);
+        assert!(!snapshot.is_visible(103));
     }
 
     #[test]
@@ -256,4 +468,159 @@ mod tests {
         };
         assert_eq!(snapshot.to_string(), "100:100:");
     }
+
+    #[test]
+    fn test_zookie_signature() {
+        let revision = Revision {
+            snapshot: PgSnapshot::from_str("100:105:101").unwrap(),
+            optional_xid: Some(104),
+        };
+        let zookie = revision.to_zookie().unwrap();
+        let decoded = Revision::from_zookie(zookie.clone()).unwrap();
+        assert_eq!(decoded.snapshot_string(), "100:105:101");
+
+        // A forged payload keeps the original signature, which no longer matches
+        let (_, signature) = zookie.value.split_once('.').unwrap();
+        let forged = Revision {
+            snapshot: PgSnapshot::from_str("100:900:").unwrap(),
+            optional_xid: Some(899),
+        };
+        let forged_payload = base64_url.encode(serde_json::to_vec(&forged).unwrap());
+        let err = Revision::from_zookie(Zookie {
+            value: format!("{}.{}", forged_payload, signature),
+        })
+        .unwrap_err();
+        assert!(err.to_string().contains("signature"));
+
+        // Unsigned zookies are rejected
+        let err = Revision::from_zookie(Zookie {
+            value: forged_payload,
+        })
+        .unwrap_err();
+        assert!(err.to_string().contains("Invalid zookie format"));
+    }
+
+    #[test]
+    fn test_snapshot_ordering() {
+        let snapshot = |s: &str| PgSnapshot::from_str(s).unwrap();
+
+        assert_eq!(
+            snapshot("100:105:101").compare(&snapshot("100:105:101")),
+            Some(Ordering::Equal)
+        );
+        // Same horizon, but 101 has committed in the first one
+        assert_eq!(
+            snapshot("100:105:").compare(&snapshot("100:105:101")),
+            Some(Ordering::Greater)
+        );
+        // A later xmax only helps if nothing it adds was committed
+        assert_eq!(
+            snapshot("100:103:").compare(&snapshot("100:105:103,104")),
+            Some(Ordering::Equal)
+        );
+        assert_eq!(
+            snapshot("100:103:").compare(&snapshot("100:105:104")),
+            Some(Ordering::Less)
+        );
+        // Each sees a transaction the other doesn't
+        assert_eq!(
+            snapshot("100:105:102").compare(&snapshot("100:105:103")),
+            None
+        );
+        assert_eq!(snapshot("100:110:101").compare(&snapshot("100:105:")), None);
+    }
+
+    #[test]
+    fn test_revision_includes() {
+        let revision = Revision {
+            snapshot: PgSnapshot::from_str("100:105:102").unwrap(),
+            optional_xid: Some(105),
+        };
+
+        assert!(revision.includes(99));
+        assert!(revision.includes(101));
+        assert!(revision.includes(105));
+        assert!(!revision.includes(102));
+        assert!(!revision.includes(106));
+    }
+
+    #[test]
+    fn test_mark_complete() {
+        let snapshot = |s: &str| PgSnapshot::from_str(s).unwrap();
+
+        assert_eq!(
+            snapshot("100:105:101,103").mark_complete(101).to_string(),
+            "103:105:103"
+        );
+        // Transactions skipped over by the new xmax stay in progress
+        assert_eq!(
+            snapshot("100:105:").mark_complete(107).to_string(),
+            "105:108:105,106"
+        );
+        assert_eq!(
+            snapshot("100:105:103").mark_complete(103).to_string(),
+            "105:105:"
+        );
+    }
+
+    #[test]
+    fn test_revision_equality() {
+        let revision = |s: &str, xid: Option<u64>| Revision {
+            snapshot: PgSnapshot::from_str(s).unwrap(),
+            optional_xid: xid,
+        };
+
+        // The writing transaction counts towards what a revision includes
+        let written = revision("100:105:", Some(105));
+        let read_after = revision("106:106:", None);
+        assert_eq!(written, read_after);
+        assert!(written.is_same_as(&read_after));
+        assert!(!written.is_fresher_than(&read_after));
+
+        let read_before = revision("100:105:", None);
+        assert_ne!(written, read_before);
+        assert!(written.is_fresher_than(&read_before));
+        assert!(!read_before.is_fresher_than(&written));
+
+        // Different spellings of the same snapshot are equal
+        assert_eq!(
+            revision("100:103:", None),
+            revision("100:105:103,104", None)
+        );
+
+        // Neither is fresher when each includes something the other doesn't
+        let a = revision("100:105:102", None);
+        let b = revision("100:105:103", None);
+        assert_ne!(a, b);
+        assert!(!a.is_fresher_than(&b) && !b.is_fresher_than(&a));
+    }
+
+    proptest! {
+        // Visibility matches a linear scan of the in-progress list, whatever
+        // order it was written in, whether parsed or deserialized
+        #[test]
+        fn test_is_visible_unsorted_xip_list(
+            xmin in 0u64..50,
+            span in 0u64..50,
+            xip_list in prop::collection::vec(0u64..100, 0..10),
+            xid in 0u64..120,
+        ) {
+            let xmax = xmin + span;
+            let xip_list: Vec<u64> = xip_list.into_iter().filter(|x| (xmin..xmax).contains(x)).collect();
+            let expected = xid < xmin || (xid < xmax && !xip_list.contains(&xid));
+
+            let text = format!(
+                "{}:{}:{}",
+                xmin,
+                xmax,
+                xip_list.iter().map(|x| x.to_string()).collect::<Vec<_>>().join(",")
+            );
+            let parsed = PgSnapshot::from_str(&text).unwrap();
+            prop_assert_eq!(parsed.is_visible(xid), expected);
+
+            let json = serde_json::json!({ "xmin": xmin, "xmax": xmax, "xip_list": xip_list });
+            let deserialized: PgSnapshot = serde_json::from_value(json).unwrap();
+            prop_assert_eq!(deserialized.is_visible(xid), expected);
+        }
+    }
 }
@@ -1,67 +1,283 @@
 use std::fs;
+use std::str::FromStr;
+use std::time::Duration;
 
 use anyhow::{anyhow, Result};
 use ent_proto::ent::{
-    graph_service_server::GraphServiceServer, schema_service_server::SchemaServiceServer,
+    diagnostics_service_server::DiagnosticsServiceServer, graph_service_server::GraphServiceServer,
+    schema_service_server::SchemaServiceServer,
 };
-use sqlx::postgres::PgPoolOptions;
-use tonic::transport::Server;
-use tracing::{error, info};
+use sqlx::postgres::PgConnectOptions;
+use sqlx::ConnectOptions;
+use tokio::signal;
+use tonic::service::interceptor::InterceptedService;
+use tonic::transport::{Certificate, Identity, Server, ServerTlsConfig};
+use tracing::level_filters::LevelFilter;
+use tracing::{error, info, warn};
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::util::SubscriberInitExt;
+use tracing_subscriber::{EnvFilter, Layer};
 
-use ent_server::{auth::JwtValidator, config::Settings, GraphServer, SchemaServer};
+use ent_server::{
+    auth::{self, JwksCache, JwtValidator},
+    config::{LogFormat, Settings, TlsConfig},
+    db::{create_pool_with_retry, pool_options, transaction::init_zookie_key},
+    diagnostics::SlowQueryLog,
+    health::report_database_health,
+    metrics::Metrics,
+    rate_limit::{self, RateLimiter},
+    telemetry, DiagnosticsServer, GraphServer, SchemaServer,
+};
 
 #[tokio::main]
 async fn main() -> Result<()> {
-    tracing_subscriber::fmt::init();
+    // Logging isn't set up yet, so a bad config is reported through the error
+    let settings = Settings::new()?;
+    settings.validate()?;
 
-    let settings = Settings::new().map_err(|e| {
-        error!(error = e.to_string());
-        e
-    })?;
+    let (otel_layer, tracer_provider) = match &settings.telemetry {
+        Some(config) => {
+            let (layer, provider) = telemetry::init(config)?;
+            (Some(layer.with_filter(LevelFilter::INFO)), Some(provider))
+        }
+        None => (None, None),
+    };
+
+    let log_filter = match EnvFilter::try_from_default_env() {
+        Ok(filter) => filter,
+        Err(_) => EnvFilter::try_new(&settings.logging.level)?,
+    };
+    let log_layer = match settings.logging.format {
+        LogFormat::Pretty => tracing_subscriber::fmt::layer().boxed(),
+        LogFormat::Json => tracing_subscriber::fmt::layer().json().boxed(),
+    };
+
+    let slow_queries = SlowQueryLog::new();
+    tracing_subscriber::registry()
+        .with(log_layer.with_filter(log_filter))
+        .with(slow_queries.layer())
+        .with(otel_layer)
+        .init();
 
-    let addr = settings.server_address().parse().map_err(|e| {
+    let addr = settings.server_address().parse().inspect_err(|e| {
         error!("Error parsing server address: {}", e);
-        e
     })?;
 
-    info!(path = &settings.jwt.public_key_path);
+    let validator = match &settings.jwt.jwks_url {
+        Some(jwks_url) => {
+            info!(jwks_url = %jwks_url);
 
-    let public_key = fs::read_to_string(&settings.jwt.public_key_path).map_err(|e| {
-        error!("failed to read pem file: {}", e);
-        e
-    })?;
+            let jwks = JwksCache::start(
+                jwks_url.clone(),
+                Duration::from_secs(settings.jwt.jwks_refresh_seconds),
+            )
+            .await
+            .inspect_err(|e| {
+                error!("failed to fetch JWKS: {}", e);
+            })?;
+
+            JwtValidator::from_jwks(jwks, settings.jwt.issuer.clone())
+        }
+        None => {
+            info!(path = &settings.jwt.public_key_path);
+
+            let public_key =
+                fs::read_to_string(&settings.jwt.public_key_path).inspect_err(|e| {
+                    error!("failed to read pem file: {}", e);
+                })?;
+
+            JwtValidator::new(&public_key, settings.jwt.issuer.clone()).inspect_err(|e| {
+                error!("failed to parse JWT public key: {}", e);
+            })?
+        }
+    }
+    .with_audience(settings.jwt.audience.clone())
+    .with_leeway(settings.jwt.leeway_seconds)
+    .with_token_header(&settings.jwt.header, &settings.jwt.scheme);
 
-    JwtValidator::init(&public_key, settings.jwt.issuer.clone()).map_err(|e| {
+    JwtValidator::init_with(validator).inspect_err(|e| {
         error!("failed to initialize JWT validator: {}", e);
-        e
     })?;
 
-    let pool = PgPoolOptions::new()
-        .max_connections(settings.database.max_connections)
-        .connect(&settings.database.url)
-        .await?;
+    match &settings.zookie.secret {
+        Some(secret) => init_zookie_key(secret).inspect_err(|e| {
+            error!("failed to initialize zookie key: {}", e);
+        })?,
+        None => warn!("zookie.secret is not set; zookies will not survive a restart"),
+    }
+
+    let connect_options = PgConnectOptions::from_str(&settings.database.url)?.log_slow_statements(
+        log::LevelFilter::Warn,
+        Duration::from_millis(settings.diagnostics.slow_query_threshold_ms),
+    );
+
+    let write_max_connections = settings
+        .database
+        .write_max_connections
+        .unwrap_or(settings.database.max_connections);
+    let pool = create_pool_with_retry(
+        pool_options(&settings.database, write_max_connections),
+        connect_options.clone(),
+    )
+    .await
+    .inspect_err(|e| {
+        error!("failed to connect to database: {}", e);
+    })?;
 
     let graph_pool = pool.clone();
 
-    let (_, health) = tonic_health::server::health_reporter();
-    let graph_server = GraphServer::new(graph_pool);
-    let schema_server = SchemaServer::new(pool);
+    let (health_reporter, health) = tonic_health::server::health_reporter();
+    tokio::spawn(report_database_health(
+        pool.clone(),
+        health_reporter,
+        Duration::from_secs(settings.server.health_check_interval_seconds),
+    ));
+    let mut graph_server = GraphServer::new(graph_pool)
+        .with_schema_config(settings.schema.clone())
+        .with_public_reads(settings.public_reads.clone())
+        .with_access_config(settings.access.clone())
+        .with_snapshot_header(settings.diagnostics.snapshot_header);
+    let mut metered_pools = vec![("write", pool.clone())];
+    if let Some(read_max_connections) = settings.database.read_max_connections {
+        let read_pool = create_pool_with_retry(
+            pool_options(&settings.database, read_max_connections),
+            connect_options,
+        )
+        .await?;
+        metered_pools.push(("read", read_pool.clone()));
+        graph_server = graph_server.with_read_pool(read_pool);
+    }
+
+    let metrics = Metrics::with_pools(metered_pools)?;
+    if let Some(metrics_config) = &settings.metrics {
+        let metrics_addr = metrics_config.address().parse().inspect_err(|e| {
+            error!("Error parsing metrics address: {}", e);
+        })?;
+        let metrics = metrics.clone();
+        tokio::spawn(async move {
+            if let Err(e) = metrics.serve(metrics_addr).await {
+                error!("metrics server exited with error: {}", e);
+            }
+        });
+    }
+    let schema_server = SchemaServer::new(pool).with_config(settings.schema.clone());
+
+    let diagnostics_server =
+        DiagnosticsServer::new(slow_queries).with_config(settings.diagnostics.clone());
 
     let reflection_service = tonic_reflection::server::Builder::configure()
         .register_encoded_file_descriptor_set(ent_proto::proto::FILE_DESCRIPTOR_SET)
         .build_v1()
         .map_err(|e| anyhow!("failed to build grpc reflection service: {}", e))?;
 
+    let mut server = Server::builder()
+        .trace_fn(telemetry::grpc_request_span)
+        .layer(metrics.layer());
+    if let Some(tls) = &settings.tls {
+        server = server.tls_config(load_tls_config(tls)?)?;
+        info!(
+            cert_path = &tls.cert_path,
+            client_auth = tls.client_ca_path.is_some(),
+            "TLS enabled"
+        );
+    }
+
+    let rate_limiter = settings.rate_limit.as_ref().map(RateLimiter::new);
+    if let Some(rate_limit) = &settings.rate_limit {
+        info!(
+            requests_per_second = rate_limit.requests_per_second,
+            burst = rate_limit.burst,
+            "Rate limiting enabled"
+        );
+    }
+
     info!("Server listening on {}", addr);
 
-    Server::builder()
-        .add_service(GraphServiceServer::new(graph_server))
-        .add_service(SchemaServiceServer::new(schema_server))
+    server
+        .add_service(InterceptedService::new(
+            GraphServiceServer::new(graph_server)
+                .max_decoding_message_size(settings.server.max_decoding_message_size)
+                .max_encoding_message_size(settings.server.max_encoding_message_size),
+            rate_limit::interceptor(rate_limiter),
+        ))
+        .add_service(InterceptedService::new(
+            SchemaServiceServer::new(schema_server)
+                .max_decoding_message_size(settings.server.max_decoding_message_size)
+                .max_encoding_message_size(settings.server.max_encoding_message_size),
+            auth::interceptor,
+        ))
+        .add_service(InterceptedService::new(
+            DiagnosticsServiceServer::new(diagnostics_server)
+                .max_decoding_message_size(settings.server.max_decoding_message_size)
+                .max_encoding_message_size(settings.server.max_encoding_message_size),
+            auth::interceptor,
+        ))
         .add_service(health)
         .add_service(reflection_service)
-        .serve(addr)
+        .serve_with_shutdown(addr, shutdown_signal())
         .await
         .map_err(|e| anyhow!("tonic server exited with error: {}", e))?;
 
+    info!("Server shut down");
+
+    if let Some(provider) = tracer_provider {
+        if let Err(e) = provider.shutdown() {
+            error!("failed to flush traces: {}", e);
+        }
+    }
+
     Ok(())
 }
+
+// Resolves on SIGINT or SIGTERM; the server then stops accepting connections
+// and waits for in-flight RPCs to finish
+async fn shutdown_signal() {
+    let interrupt = async {
+        if let Err(e) = signal::ctrl_c().await {
+            error!("failed to listen for SIGINT: {}", e);
+            std::future::pending::<()>().await;
+        }
+    };
+
+    #[cfg(unix)]
+    let terminate = async {
+        match signal::unix::signal(signal::unix::SignalKind::terminate()) {
+            Ok(mut sigterm) => {
+                sigterm.recv().await;
+            }
+            Err(e) => {
+                error!("failed to listen for SIGTERM: {}", e);
+                std::future::pending::<()>().await;
+            }
+        }
+    };
+
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        _ = interrupt => info!("Received SIGINT, shutting down"),
+        _ = terminate => info!("Received SIGTERM, shutting down"),
+    }
+}
+
+fn load_tls_config(tls: &TlsConfig) -> Result<ServerTlsConfig> {
+    let cert = fs::read_to_string(&tls.cert_path).inspect_err(|e| {
+        error!("failed to read TLS certificate: {}", e);
+    })?;
+    let key = fs::read_to_string(&tls.key_path).inspect_err(|e| {
+        error!("failed to read TLS private key: {}", e);
+    })?;
+
+    let config = ServerTlsConfig::new().identity(Identity::from_pem(cert, key));
+
+    // Connections without a certificate from this bundle fail the handshake
+    let Some(client_ca_path) = &tls.client_ca_path else {
+        return Ok(config);
+    };
+    let client_ca = fs::read_to_string(client_ca_path).inspect_err(|e| {
+        error!("failed to read TLS client CA bundle: {}", e);
+    })?;
+
+    Ok(config.client_ca_root(Certificate::from_pem(client_ca)))
+}
@@ -0,0 +1,441 @@
+use std::collections::HashSet;
+use std::future::Future;
+use std::net::SocketAddr;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{ready, Context, Poll};
+use std::time::{Duration, Instant};
+
+use anyhow::Result;
+use axum::{extract::State, routing::get, Router};
+use http::HeaderMap;
+use http_body::{Body, Frame, SizeHint};
+use once_cell::sync::Lazy;
+use prometheus::{
+    Encoder, HistogramOpts, HistogramVec, IntCounterVec, IntGaugeVec, Opts, Registry, TextEncoder,
+};
+use prost::Message;
+use prost_types::FileDescriptorSet;
+use sqlx::PgPool;
+use tonic::Code;
+use tower::{Layer, Service};
+use tracing::info;
+
+/// Label used for paths that don't name an RPC we serve, so random paths
+/// can't blow up the number of series.
+const UNKNOWN_METHOD: &str = "unknown";
+
+// Paths of every RPC the server serves, `/package.Service/Method`, read from
+// the descriptor sets of its services
+static KNOWN_METHODS: Lazy<HashSet<String>> = Lazy::new(|| {
+    [
+        ent_proto::proto::FILE_DESCRIPTOR_SET,
+        tonic_health::pb::FILE_DESCRIPTOR_SET,
+        tonic_reflection::pb::v1::FILE_DESCRIPTOR_SET,
+    ]
+    .into_iter()
+    .filter_map(|encoded| FileDescriptorSet::decode(encoded).ok())
+    .flat_map(|set| set.file)
+    .flat_map(|file| {
+        let package = file.package().to_string();
+        file.service.into_iter().flat_map(move |service| {
+            let prefix = format!("/{}.{}/", package, service.name());
+            service
+                .method
+                .into_iter()
+                .map(move |method| format!("{}{}", prefix, method.name()))
+        })
+    })
+    .collect()
+});
+
+/// Prometheus metrics for the gRPC server and its database pools. Cheap to
+/// clone; clones share the same registry.
+#[derive(Clone)]
+pub struct Metrics {
+    inner: Arc<MetricsInner>,
+}
+
+struct MetricsInner {
+    registry: Registry,
+    requests: IntCounterVec,
+    latency: HistogramVec,
+    pool_connections: IntGaugeVec,
+    pools: Vec<(&'static str, PgPool)>,
+}
+
+impl Metrics {
+    pub fn new() -> Result<Self> {
+        Self::with_pools(Vec::new())
+    }
+
+    /// Also reports active and idle connections of each named pool
+    pub fn with_pools(pools: Vec<(&'static str, PgPool)>) -> Result<Self> {
+        let registry = Registry::new();
+
+        let requests = IntCounterVec::new(
+            Opts::new(
+                "ent_grpc_requests_total",
+                "gRPC requests by method and status code",
+            ),
+            &["method", "code"],
+        )?;
+        let latency = HistogramVec::new(
+            HistogramOpts::new(
+                "ent_grpc_request_duration_seconds",
+                "gRPC request latency by method",
+            ),
+            &["method"],
+        )?;
+        let pool_connections = IntGaugeVec::new(
+            Opts::new(
+                "ent_db_pool_connections",
+                "Database pool connections by pool and state",
+            ),
+            &["pool", "state"],
+        )?;
+
+        registry.register(Box::new(requests.clone()))?;
+        registry.register(Box::new(latency.clone()))?;
+        registry.register(Box::new(pool_connections.clone()))?;
+
+        Ok(Self {
+            inner: Arc::new(MetricsInner {
+                registry,
+                requests,
+                latency,
+                pool_connections,
+                pools,
+            }),
+        })
+    }
+
+    /// A tower layer recording every RPC served through it
+    pub fn layer(&self) -> MetricsLayer {
+        MetricsLayer {
+            metrics: self.clone(),
+        }
+    }
+
+    pub fn observe(&self, method: &str, code: Code, elapsed: Duration) {
+        let method = if KNOWN_METHODS.contains(method) {
+            method
+        } else {
+            UNKNOWN_METHOD
+        };
+        let code = format!("{:?}", code);
+
+        self.inner
+            .requests
+            .with_label_values(&[method, &code])
+            .inc();
+        self.inner
+            .latency
+            .with_label_values(&[method])
+            .observe(elapsed.as_secs_f64());
+    }
+
+    /// Current metrics in the Prometheus text format
+    pub fn render(&self) -> Result<String> {
+        for (name, pool) in &self.inner.pools {
+            let idle = pool.num_idle() as i64;
+            let active = pool.size() as i64 - idle;
+            self.inner
+                .pool_connections
+                .with_label_values(&[name, "active"])
+                .set(active);
+            self.inner
+                .pool_connections
+                .with_label_values(&[name, "idle"])
+                .set(idle);
+        }
+
+        let mut buffer = Vec::new();
+        TextEncoder::new().encode(&self.inner.registry.gather(), &mut buffer)?;
+        Ok(String::from_utf8(buffer)?)
+    }
+
+    /// Serves `GET /metrics` on `addr` until the task is dropped
+    pub async fn serve(self, addr: SocketAddr) -> Result<()> {
+        let app = Router::new()
+            .route("/metrics", get(render_metrics))
+            .with_state(self);
+
+        let listener = tokio::net::TcpListener::bind(addr).await?;
+        info!("Metrics listening on {}", addr);
+        axum::serve(listener, app).await?;
+
+        Ok(())
+    }
+}
+
+async fn render_metrics(State(metrics): State<Metrics>) -> Result<String, String> {
+    metrics.render().map_err(|e| e.to_string())
+}
+
+#[derive(Clone)]
+pub struct MetricsLayer {
+    metrics: Metrics,
+}
+
+impl<S> Layer<S> for MetricsLayer {
+    type Service = MetricsService<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        MetricsService {
+            inner,
+            metrics: self.metrics.clone(),
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct MetricsService<S> {
+    inner: S,
+    metrics: Metrics,
+}
+
+impl<S, ReqBody, ResBody> Service<http::Request<ReqBody>> for MetricsService<S>
+where
+    S: Service<http::Request<ReqBody>, Response = http::Response<ResBody>>,
+    S::Future: Send + 'static,
+    ResBody: Body,
+{
+    type Response = http::Response<MetricsBody<ResBody>>;
+    type Error = S::Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>> + Send>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: http::Request<ReqBody>) -> Self::Future {
+        let call = PendingCall {
+            metrics: self.metrics.clone(),
+            method: request.uri().path().to_string(),
+            start: Instant::now(),
+        };
+        let response = self.inner.call(request);
+
+        Box::pin(async move {
+            match response.await {
+                // Failed calls are "trailers-only" responses carrying the status
+                // in their headers; the others report it in trailers, so the
+                // call is recorded once the body ends
+                Ok(response) => {
+                    let code = grpc_status(response.headers()).unwrap_or(Code::Ok);
+                    Ok(response.map(|body| MetricsBody {
+                        inner: Box::pin(body),
+                        call: Some(call),
+                        code,
+                    }))
+                }
+                Err(e) => {
+                    call.record(Code::Unknown);
+                    Err(e)
+                }
+            }
+        })
+    }
+}
+
+fn grpc_status(headers: &HeaderMap) -> Option<Code> {
+    headers
+        .get("grpc-status")
+        .and_then(|status| status.to_str().ok())
+        .and_then(|status| status.parse::<i32>().ok())
+        .map(Code::from)
+}
+
+// A call waiting for its response body to end before it is recorded
+struct PendingCall {
+    metrics: Metrics,
+    method: String,
+    start: Instant,
+}
+
+impl PendingCall {
+    fn record(self, code: Code) {
+        self.metrics
+            .observe(&self.method, code, self.start.elapsed());
+    }
+}
+
+/// Response body of a call recorded by `MetricsLayer`. The call is recorded
+/// with the status from the trailers, and the latency up to them, when the
+/// body ends, or as cancelled if it is dropped before that.
+pub struct MetricsBody<B: Body> {
+    inner: Pin<Box<B>>,
+    call: Option<PendingCall>,
+    code: Code,
+}
+
+impl<B: Body> MetricsBody<B> {
+    fn record(&mut self, code: Code) {
+        if let Some(call) = self.call.take() {
+            call.record(code);
+        }
+    }
+}
+
+impl<B: Body> Body for MetricsBody<B> {
+    type Data = B::Data;
+    type Error = B::Error;
+
+    fn poll_frame(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Frame<Self::Data>, Self::Error>>> {
+        let this = self.get_mut();
+        let frame = ready!(this.inner.as_mut().poll_frame(cx));
+        match &frame {
+            // Trailers are the last frame of a response
+            Some(Ok(frame)) => {
+                if let Some(trailers) = frame.trailers_ref() {
+                    let code = grpc_status(trailers).unwrap_or(this.code);
+                    this.record(code);
+                }
+            }
+            Some(Err(_)) => this.record(Code::Unknown),
+            None => {
+                let code = this.code;
+                this.record(code);
+            }
+        }
+        Poll::Ready(frame)
+    }
+
+    fn is_end_stream(&self) -> bool {
+        self.inner.is_end_stream()
+    }
+
+    fn size_hint(&self) -> SizeHint {
+        self.inner.size_hint()
+    }
+}
+
+impl<B: Body> Drop for MetricsBody<B> {
+    fn drop(&mut self) {
+        // An empty body may be dropped without ever being polled
+        let code = if self.inner.is_end_stream() {
+            self.code
+        } else {
+            Code::Cancelled
+        };
+        self.record(code);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use http_body_util::{BodyExt, StreamBody};
+    use std::convert::Infallible;
+    use tower::ServiceExt;
+
+    type TestBody = StreamBody<
+        tokio_stream::Iter<std::vec::IntoIter<Result<Frame<&'static [u8]>, Infallible>>>,
+    >;
+
+    // Answers like a gRPC server: unary calls end with an OK trailer, GetEdges
+    // fails up front and StreamObjects fails after sending a message
+    async fn fake_server(
+        request: http::Request<()>,
+    ) -> Result<http::Response<TestBody>, Infallible> {
+        let trailers = |code: &'static str| {
+            let mut trailers = HeaderMap::new();
+            trailers.insert("grpc-status", http::HeaderValue::from_static(code));
+            Frame::trailers(trailers)
+        };
+        let frames = match request.uri().path() {
+            "/ent.GraphService/GetEdges" => Vec::new(),
+            "/ent.GraphService/StreamObjects" => {
+                vec![Ok(Frame::data(&b"object"[..])), Ok(trailers("13"))]
+            }
+            _ => vec![Ok(Frame::data(&b"object"[..])), Ok(trailers("0"))],
+        };
+
+        let mut response = http::Response::new(StreamBody::new(tokio_stream::iter(frames)));
+        if request.uri().path() == "/ent.GraphService/GetEdges" {
+            response
+                .headers_mut()
+                .insert("grpc-status", http::HeaderValue::from_static("5"));
+        }
+        Ok(response)
+    }
+
+    async fn call(metrics: &Metrics, path: &str) {
+        let request = http::Request::builder().uri(path).body(()).unwrap();
+        let response = metrics
+            .layer()
+            .layer(tower::service_fn(fake_server))
+            .oneshot(request)
+            .await
+            .unwrap();
+        response.into_body().collect().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_layer_records_requests() {
+        let metrics = Metrics::new().unwrap();
+        for path in [
+            "/ent.GraphService/GetObject",
+            "/ent.GraphService/GetObject",
+            "/ent.GraphService/GetEdges",
+            "/ent.GraphService/StreamObjects",
+        ] {
+            call(&metrics, path).await;
+        }
+
+        let rendered = metrics.render().unwrap();
+        assert!(rendered.contains(
+            r#"ent_grpc_requests_total{code="Ok",method="/ent.GraphService/GetObject"} 2"#
+        ));
+        assert!(rendered.contains(
+            r#"ent_grpc_requests_total{code="NotFound",method="/ent.GraphService/GetEdges"} 1"#
+        ));
+        // Streams that fail midway report the status from their trailers
+        assert!(rendered.contains(
+            r#"ent_grpc_requests_total{code="Internal",method="/ent.GraphService/StreamObjects"} 1"#
+        ));
+        assert!(rendered.contains("ent_grpc_request_duration_seconds_bucket"));
+    }
+
+    #[tokio::test]
+    async fn test_unread_body_counts_as_cancelled() {
+        let metrics = Metrics::new().unwrap();
+        let request = http::Request::builder()
+            .uri("/ent.GraphService/StreamObjects")
+            .body(())
+            .unwrap();
+        let response = metrics
+            .layer()
+            .layer(tower::service_fn(fake_server))
+            .oneshot(request)
+            .await
+            .unwrap();
+
+        // Nothing is recorded until the body ends or is dropped
+        assert!(!metrics.render().unwrap().contains("StreamObjects"));
+        drop(response);
+
+        assert!(metrics.render().unwrap().contains(
+            r#"ent_grpc_requests_total{code="Cancelled",method="/ent.GraphService/StreamObjects"} 1"#
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_unknown_paths_share_a_label() {
+        let metrics = Metrics::new().unwrap();
+        call(&metrics, "/random/path").await;
+        call(&metrics, "/ent.GraphService/NoSuchMethod").await;
+        metrics.observe("/another/path", Code::Unimplemented, Duration::ZERO);
+
+        let rendered = metrics.render().unwrap();
+        assert!(rendered.contains(r#"ent_grpc_requests_total{code="Ok",method="unknown"} 2"#));
+        assert!(rendered
+            .contains(r#"ent_grpc_requests_total{code="Unimplemented",method="unknown"} 1"#));
+        assert!(!rendered.contains("/random/path"));
+        assert!(!rendered.contains("NoSuchMethod"));
+    }
+}
@@ -0,0 +1,146 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+
+use tonic::service::Interceptor;
+use tonic::{Request, Status};
+
+use crate::auth::{self, RequestExt};
+use crate::config::RateLimitConfig;
+
+/// Buckets are dropped once refilled, but only when this many users are
+/// tracked, so the map is not swept on every request.
+const PRUNE_THRESHOLD: usize = 10_000;
+
+/// Token bucket rate limiting per user (`sub` claim). Cheap to clone; clones
+/// share the same buckets.
+#[derive(Clone)]
+pub struct RateLimiter {
+    rate: f64,
+    burst: f64,
+    buckets: Arc<Mutex<HashMap<String, Bucket>>>,
+}
+
+struct Bucket {
+    tokens: f64,
+    updated: Instant,
+}
+
+impl RateLimiter {
+    pub fn new(config: &RateLimitConfig) -> Self {
+        let burst = config
+            .burst
+            .map(f64::from)
+            .unwrap_or(config.requests_per_second)
+            .max(1.0);
+
+        Self {
+            rate: config.requests_per_second,
+            burst,
+            buckets: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    /// Takes a token from `user`'s bucket, false if it is empty
+    pub fn try_acquire(&self, user: &str) -> bool {
+        self.try_acquire_at(user, Instant::now())
+    }
+
+    fn try_acquire_at(&self, user: &str, now: Instant) -> bool {
+        let mut buckets = self.buckets.lock().unwrap_or_else(|e| e.into_inner());
+
+        if buckets.len() >= PRUNE_THRESHOLD {
+            buckets.retain(|_, bucket| self.refilled(bucket, now) < self.burst);
+        }
+
+        let bucket = buckets.entry(user.to_string()).or_insert(Bucket {
+            tokens: self.burst,
+            updated: now,
+        });
+        bucket.tokens = self.refilled(bucket, now);
+        bucket.updated = now;
+
+        if bucket.tokens >= 1.0 {
+            bucket.tokens -= 1.0;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn refilled(&self, bucket: &Bucket, now: Instant) -> f64 {
+        let elapsed = now.saturating_duration_since(bucket.updated).as_secs_f64();
+        (bucket.tokens + elapsed * self.rate).min(self.burst)
+    }
+}
+
+// Unauthenticated requests pass through; the handlers reject them anyway
+impl Interceptor for RateLimiter {
+    fn call(&mut self, request: Request<()>) -> Result<Request<()>, Status> {
+        match request.user_id() {
+            Ok(user_id) if !self.try_acquire(&user_id) => Err(Status::resource_exhausted(
+                "Rate limit exceeded, retry later",
+            )),
+            _ => Ok(request),
+        }
+    }
+}
+
+/// Verifies every request's token with `auth::interceptor`, so the limiter
+/// and handlers share the claims, then applies `limiter` to it, or lets
+/// everything through without one
+pub fn interceptor(mut limiter: Option<RateLimiter>) -> impl Interceptor + Clone {
+    move |request| {
+        let request = auth::interceptor(request)?;
+        match &mut limiter {
+            Some(limiter) => limiter.call(request),
+            None => Ok(request),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    fn limiter(requests_per_second: f64, burst: Option<u32>) -> RateLimiter {
+        RateLimiter::new(&RateLimitConfig {
+            requests_per_second,
+            burst,
+        })
+    }
+
+    #[test]
+    fn test_burst_then_refill() {
+        let limiter = limiter(2.0, Some(3));
+        let start = Instant::now();
+
+        for _ in 0..3 {
+            assert!(limiter.try_acquire_at("alice", start));
+        }
+        assert!(!limiter.try_acquire_at("alice", start));
+
+        // Two tokens a second: one is back after half a second
+        let later = start + Duration::from_millis(500);
+        assert!(limiter.try_acquire_at("alice", later));
+        assert!(!limiter.try_acquire_at("alice", later));
+
+        // Idle time never refills past the burst
+        let much_later = start + Duration::from_secs(60);
+        for _ in 0..3 {
+            assert!(limiter.try_acquire_at("alice", much_later));
+        }
+        assert!(!limiter.try_acquire_at("alice", much_later));
+    }
+
+    #[test]
+    fn test_users_are_limited_separately() {
+        let limiter = limiter(1.0, None);
+        let now = Instant::now();
+
+        assert!(limiter.try_acquire_at("alice", now));
+        assert!(!limiter.try_acquire_at("alice", now));
+        assert!(limiter.try_acquire_at("bob", now));
+    }
+}
@@ -0,0 +1,57 @@
+use crate::auth::RequestExt;
+use crate::config::DiagnosticsConfig;
+use crate::diagnostics::SlowQueryLog;
+use ent_proto::ent::diagnostics_service_server::DiagnosticsService;
+use ent_proto::ent::{GetSlowQueriesRequest, GetSlowQueriesResponse, SlowQuery};
+use tonic::{async_trait, Request, Response, Status};
+
+#[derive(Debug)]
+pub struct DiagnosticsServer {
+    slow_queries: SlowQueryLog,
+    config: DiagnosticsConfig,
+}
+
+impl DiagnosticsServer {
+    pub fn new(slow_queries: SlowQueryLog) -> Self {
+        DiagnosticsServer {
+            slow_queries,
+            config: DiagnosticsConfig::default(),
+        }
+    }
+
+    pub fn with_config(mut self, config: DiagnosticsConfig) -> Self {
+        self.config = config;
+        self
+    }
+}
+
+#[async_trait]
+impl DiagnosticsService for DiagnosticsServer {
+    #[tracing::instrument(skip(self))]
+    async fn get_slow_queries(
+        &self,
+        request: Request<GetSlowQueriesRequest>,
+    ) -> Result<Response<GetSlowQueriesResponse>, Status> {
+        request.user_id()?;
+        if let Some(scope) = &self.config.admin_scope {
+            request.require_scope(scope)?;
+        }
+
+        let queries = self
+            .slow_queries
+            .samples()
+            .into_iter()
+            .map(|sample| SlowQuery {
+                statement_hash: sample.statement_hash,
+                count: sample.count,
+                max_duration_ms: sample.max_duration.as_secs_f64() * 1000.0,
+                last_duration_ms: sample.last_duration.as_secs_f64() * 1000.0,
+            })
+            .collect();
+
+        Ok(Response::new(GetSlowQueriesResponse {
+            queries,
+            threshold_ms: self.config.slow_query_threshold_ms,
+        }))
+    }
+}
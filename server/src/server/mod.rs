@@ -1,7 +1,9 @@
+mod diagnostics_server;
 mod graph_server;
 mod schema_server;
 mod util;
 
-pub use graph_server::GraphServer;
+pub use diagnostics_server::DiagnosticsServer;
+pub use graph_server::{GraphServer, SNAPSHOT_HEADER};
 pub use schema_server::SchemaServer;
 pub use util::*;
@@ -1,32 +1,127 @@
-use crate::db::schema::SchemaRepository;
+use crate::auth::RequestExt;
+use crate::config::SchemaConfig;
+use crate::db::schema::{
+    indexed_fields, parse_draft, validate_schema, ImportMode, Schema, SchemaBundle,
+    SchemaExistsError, SchemaInUseError, SchemaRepository,
+};
+use ent_proto::ent::import_schemas_request::Mode;
 use ent_proto::ent::schema_service_server::SchemaService;
-use ent_proto::ent::{CreateSchemaRequest, CreateSchemaResponse};
+use ent_proto::ent::{
+    CreateSchemaRequest, CreateSchemaResponse, DeleteSchemaRequest, DeleteSchemaResponse,
+    ExportSchemasRequest, ExportSchemasResponse, GetSchemaRequest, GetSchemaResponse,
+    ImportSchemasRequest, ImportSchemasResponse, ListSchemasRequest, ListSchemasResponse,
+    Schema as ProtoSchema,
+};
+use once_cell::sync::Lazy;
 use regex::Regex;
+
+use super::offset_date_time_to_timestamp;
 use sqlx::PgPool;
 use tonic::{async_trait, Request, Response, Status};
 
+/// Longest type name a schema can be registered for, namespace included.
+pub const MAX_TYPE_NAME_LEN: usize = 64;
+
+/// Type names starting with this, in any case, are reserved for the server.
+pub const RESERVED_TYPE_PREFIX: &str = "ent_";
+
+/// A single identifier, e.g. `invoice`
+static TYPE_NAME: Lazy<Regex> = Lazy::new(|| Regex::new(r"^[a-zA-Z][a-zA-Z0-9_]*$").unwrap());
+
+/// Dot-separated identifiers, e.g. `billing.invoice`
+static NAMESPACED_TYPE_NAME: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"^[a-zA-Z][a-zA-Z0-9_]*(\.[a-zA-Z][a-zA-Z0-9_]*)*$").unwrap());
+
 #[derive(Debug)]
 pub struct SchemaServer {
     repository: SchemaRepository,
+    config: SchemaConfig,
 }
 
 impl SchemaServer {
     pub fn new(pool: PgPool) -> Self {
         let repository = SchemaRepository::new(pool);
-        SchemaServer { repository }
+        SchemaServer {
+            repository,
+            config: SchemaConfig::default(),
+        }
     }
 
-    fn validate_type_name(type_name: &str) -> Result<(), Status> {
-        let re = Regex::new(r"^[a-zA-Z][a-zA-Z0-9_]*$").unwrap();
-        if !re.is_match(type_name) {
+    pub fn with_config(mut self, config: SchemaConfig) -> Self {
+        self.config = config;
+        self
+    }
+
+    // Operations that change or reveal every schema need `admin_scope` when
+    // one is configured and at least a valid token when none is
+    fn require_admin<T>(&self, request: &Request<T>) -> Result<(), Status> {
+        match &self.config.admin_scope {
+            Some(scope) => request.require_scope(scope),
+            None => request.user_id().map(|_| ()),
+        }
+    }
+
+    fn validate_type_name(config: &SchemaConfig, type_name: &str) -> Result<(), Status> {
+        if type_name.len() > MAX_TYPE_NAME_LEN {
+            return Err(Status::invalid_argument(format!(
+                "type_name must be at most {} characters, got {}",
+                MAX_TYPE_NAME_LEN,
+                type_name.len()
+            )));
+        }
+
+        if type_name
+            .to_ascii_lowercase()
+            .starts_with(RESERVED_TYPE_PREFIX)
+        {
+            return Err(Status::invalid_argument(format!(
+                "type_name must not start with '{}', which is reserved",
+                RESERVED_TYPE_PREFIX
+            )));
+        }
+
+        if !config.allow_namespaced_types && config.required_namespace.is_none() {
+            if !TYPE_NAME.is_match(type_name) {
+                return Err(Status::invalid_argument(
+                    "type_name must start with a letter and contain only letters, numbers, and underscores"
+                ));
+            }
+            return Ok(());
+        }
+
+        // Namespaced mode
+        if !NAMESPACED_TYPE_NAME.is_match(type_name) {
             return Err(Status::invalid_argument(
-                "type_name must start with a letter and contain only letters, numbers, and underscores"
+                "type_name must be one or more dot-separated identifiers, each starting with a letter and containing only letters, numbers, and underscores"
             ));
         }
+
+        if let Some(namespace) = &config.required_namespace {
+            let prefix = format!("{}.", namespace);
+            if !type_name.starts_with(&prefix) {
+                return Err(Status::invalid_argument(format!(
+                    "type_name must be namespaced under '{}'",
+                    prefix
+                )));
+            }
+        }
+
         Ok(())
     }
 }
 
+fn schema_to_proto(schema: Schema) -> ProtoSchema {
+    ProtoSchema {
+        id: schema.id,
+        type_name: schema.type_name,
+        schema: schema.schema.to_string(),
+        description: schema.description,
+        draft: schema.draft.unwrap_or_default(),
+        created_at: schema.created_at.map(offset_date_time_to_timestamp),
+        indexed_fields: indexed_fields(&schema.schema).unwrap_or_default(),
+    }
+}
+
 #[async_trait]
 impl SchemaService for SchemaServer {
     #[tracing::instrument(skip(self))]
@@ -34,6 +129,10 @@ impl SchemaService for SchemaServer {
         &self,
         request: Request<CreateSchemaRequest>,
     ) -> Result<Response<CreateSchemaResponse>, Status> {
+        if let Some(scope) = &self.config.admin_scope {
+            request.require_scope(scope)?;
+        }
+
         let req = request.into_inner();
         let type_name = req.type_name.clone();
 
@@ -42,16 +141,215 @@ impl SchemaService for SchemaServer {
         }
 
         // Validate type name format
-        Self::validate_type_name(&type_name)?;
+        Self::validate_type_name(&self.config, &type_name)?;
+
+        let draft = (!req.draft.is_empty()).then_some(req.draft.as_str());
+        if let Some(draft) = draft {
+            parse_draft(draft).map_err(|e| Status::invalid_argument(e.to_string()))?;
+        }
 
-        match self.repository.create_schema(&type_name, &req.schema).await {
+        match self
+            .repository
+            .create_schema(&type_name, &req.schema, &req.description, draft)
+            .await
+        {
             Ok(schema) => Ok(Response::new(CreateSchemaResponse {
                 schema_id: schema.id,
+                // Stored as jsonb, so this is already normalized with sorted keys
+                schema: schema.schema.to_string(),
+                created_at: schema.created_at.map(offset_date_time_to_timestamp),
             })),
+            Err(e) if e.is::<SchemaExistsError>() => Err(Status::already_exists(e.to_string())),
             Err(e) => {
                 tracing::error!("Failed to create schema: {:?}", e);
                 Err(Status::internal("Failed to create schema"))
             }
         }
     }
+
+    #[tracing::instrument(skip(self))]
+    async fn export_schemas(
+        &self,
+        request: Request<ExportSchemasRequest>,
+    ) -> Result<Response<ExportSchemasResponse>, Status> {
+        self.require_admin(&request)?;
+
+        let bundle = self.repository.export_schemas().await.map_err(|e| {
+            tracing::error!("Failed to export schemas: {:?}", e);
+            Status::internal("Failed to export schemas")
+        })?;
+        let bundle = serde_json::to_string_pretty(&bundle)
+            .map_err(|_| Status::internal("Failed to export schemas"))?;
+
+        Ok(Response::new(ExportSchemasResponse { bundle }))
+    }
+
+    #[tracing::instrument(skip(self, request))]
+    async fn import_schemas(
+        &self,
+        request: Request<ImportSchemasRequest>,
+    ) -> Result<Response<ImportSchemasResponse>, Status> {
+        self.require_admin(&request)?;
+
+        let req = request.into_inner();
+        let mode = match req.mode() {
+            Mode::SkipExisting => ImportMode::SkipExisting,
+            Mode::UpdateExisting => ImportMode::UpdateExisting,
+        };
+
+        let bundle: SchemaBundle = serde_json::from_str(&req.bundle)
+            .map_err(|e| Status::invalid_argument(format!("Invalid schema bundle: {}", e)))?;
+        for schema in &bundle.schemas {
+            Self::validate_type_name(&self.config, &schema.type_name)?;
+            validate_schema(&schema.schema, schema.draft.as_deref()).map_err(|e| {
+                Status::invalid_argument(format!("Schema for '{}': {}", schema.type_name, e))
+            })?;
+        }
+
+        let summary = self
+            .repository
+            .import_schemas(bundle, mode)
+            .await
+            .map_err(|e| {
+                tracing::error!("Failed to import schemas: {:?}", e);
+                Status::internal("Failed to import schemas")
+            })?;
+
+        Ok(Response::new(ImportSchemasResponse {
+            created: summary.created,
+            updated: summary.updated,
+            skipped: summary.skipped,
+        }))
+    }
+
+    #[tracing::instrument(skip(self))]
+    async fn list_schemas(
+        &self,
+        request: Request<ListSchemasRequest>,
+    ) -> Result<Response<ListSchemasResponse>, Status> {
+        self.require_admin(&request)?;
+
+        let schemas = self.repository.list_schemas().await.map_err(|e| {
+            tracing::error!("Failed to list schemas: {:?}", e);
+            Status::internal("Failed to list schemas")
+        })?;
+
+        Ok(Response::new(ListSchemasResponse {
+            schemas: schemas.into_iter().map(schema_to_proto).collect(),
+        }))
+    }
+
+    #[tracing::instrument(skip(self))]
+    async fn get_schema(
+        &self,
+        request: Request<GetSchemaRequest>,
+    ) -> Result<Response<GetSchemaResponse>, Status> {
+        self.require_admin(&request)?;
+
+        let type_name = request.into_inner().type_name;
+        if type_name.is_empty() {
+            return Err(Status::invalid_argument("type_name is required"));
+        }
+
+        let schema = self
+            .repository
+            .get_schema_by_type(&type_name)
+            .await
+            .map_err(|e| {
+                tracing::error!("Failed to get schema: {:?}", e);
+                Status::internal("Failed to get schema")
+            })?
+            .ok_or_else(|| {
+                Status::not_found(format!("no schema registered for type '{}'", type_name))
+            })?;
+
+        Ok(Response::new(GetSchemaResponse {
+            schema: Some(schema_to_proto(schema)),
+        }))
+    }
+
+    #[tracing::instrument(skip(self))]
+    async fn delete_schema(
+        &self,
+        request: Request<DeleteSchemaRequest>,
+    ) -> Result<Response<DeleteSchemaResponse>, Status> {
+        self.require_admin(&request)?;
+
+        let req = request.into_inner();
+        if req.type_name.is_empty() {
+            return Err(Status::invalid_argument("type_name is required"));
+        }
+
+        let result = if req.deprecate {
+            self.repository.deprecate_schema(&req.type_name).await
+        } else {
+            self.repository.delete_schema(&req.type_name).await
+        };
+
+        match result {
+            Ok(true) => Ok(Response::new(DeleteSchemaResponse {
+                deleted: !req.deprecate,
+                deprecated: req.deprecate,
+            })),
+            Ok(false) => Err(Status::not_found(format!(
+                "no schema registered for type '{}'",
+                req.type_name
+            ))),
+            Err(e) if e.is::<SchemaInUseError>() => Err(Status::failed_precondition(e.to_string())),
+            Err(e) => {
+                tracing::error!("Failed to delete schema: {:?}", e);
+                Err(Status::internal("Failed to delete schema"))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_strict_type_names() {
+        let config = SchemaConfig::default();
+
+        assert!(SchemaServer::validate_type_name(&config, "invoice").is_ok());
+        assert!(SchemaServer::validate_type_name(&config, "billing.invoice").is_err());
+    }
+
+    #[test]
+    fn test_required_namespace() {
+        let config = SchemaConfig {
+            allow_namespaced_types: false,
+            required_namespace: Some("billing".to_string()),
+            ..Default::default()
+        };
+
+        assert!(SchemaServer::validate_type_name(&config, "billing.invoice").is_ok());
+        assert!(SchemaServer::validate_type_name(&config, "billing.tax.rate").is_ok());
+
+        let err = SchemaServer::validate_type_name(&config, "invoice").unwrap_err();
+        assert_eq!(err.code(), tonic::Code::InvalidArgument);
+
+        assert!(SchemaServer::validate_type_name(&config, "billing").is_err());
+        assert!(SchemaServer::validate_type_name(&config, "shipping.invoice").is_err());
+        assert!(SchemaServer::validate_type_name(&config, "billing..invoice").is_err());
+    }
+
+    #[test]
+    fn test_type_name_length_and_reserved_prefix() {
+        let config = SchemaConfig::default();
+
+        let longest = "a".repeat(MAX_TYPE_NAME_LEN);
+        assert!(SchemaServer::validate_type_name(&config, &longest).is_ok());
+
+        let err = SchemaServer::validate_type_name(&config, &format!("{}a", longest)).unwrap_err();
+        assert_eq!(err.code(), tonic::Code::InvalidArgument);
+        assert!(err.message().contains("at most 64 characters"));
+
+        for reserved in ["ent_user", "Ent_user", "ENT_"] {
+            let err = SchemaServer::validate_type_name(&config, reserved).unwrap_err();
+            assert!(err.message().contains("reserved"), "{}", reserved);
+        }
+        assert!(SchemaServer::validate_type_name(&config, "entity").is_ok());
+    }
 }
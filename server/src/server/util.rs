@@ -1,6 +1,51 @@
-use prost_types::{Struct, Value as ProstValue};
+use prost_types::{Struct, Timestamp, Value as ProstValue};
 use serde_json::Value as JsonValue;
+use time::OffsetDateTime;
 
+/// Largest integer magnitude an f64 represents exactly, 2^53 - 1.
+pub const MAX_SAFE_INTEGER: u64 = (1 << 53) - 1;
+
+/// Key of the single-field struct that carries an integer beyond
+/// ±`MAX_SAFE_INTEGER` through a protobuf `Value`, e.g.
+/// `{"$int": "9007199254740993"}`.
+pub const INT_TAG: &str = "$int";
+
+// protobuf `Value` only carries numbers as f64. Integers beyond
+// ±MAX_SAFE_INTEGER would lose precision there, so they travel as a tagged
+// struct holding their decimal string and are turned back into numbers on the
+// way out. A plain string is never reinterpreted, so clients' digit strings
+// stay strings.
+fn is_unsafe_integer(n: &serde_json::Number) -> bool {
+    match (n.as_i64(), n.as_u64()) {
+        (Some(i), _) => i.unsigned_abs() > MAX_SAFE_INTEGER,
+        (None, Some(_)) => true,
+        (None, None) => false,
+    }
+}
+
+// Only the exact form written by `json_value_to_prost_value` is read back, so
+// tags holding "+9007199254740993" or "09007199254740993" stay structs
+fn parse_unsafe_integer(s: &str) -> Option<serde_json::Number> {
+    let n = match s.parse::<i64>() {
+        Ok(i) => serde_json::Number::from(i),
+        Err(_) => serde_json::Number::from(s.parse::<u64>().ok()?),
+    };
+    (is_unsafe_integer(&n) && n.to_string() == s).then_some(n)
+}
+
+fn tagged_integer(obj: &Struct) -> Option<serde_json::Number> {
+    if obj.fields.len() != 1 {
+        return None;
+    }
+    match obj.fields.get(INT_TAG)?.kind.as_ref()? {
+        prost_types::value::Kind::StringValue(s) => parse_unsafe_integer(s),
+        _ => None,
+    }
+}
+
+/// Converts JSON to a protobuf `Value`. Integers outside ±`MAX_SAFE_INTEGER`
+/// become `{"$int": "<decimal>"}` structs; `prost_value_to_json_value`
+/// reverses this.
 pub fn json_value_to_prost_value(json_value: JsonValue) -> ProstValue {
     match json_value {
         JsonValue::Null => ProstValue {
@@ -9,18 +54,21 @@ pub fn json_value_to_prost_value(json_value: JsonValue) -> ProstValue {
         JsonValue::Bool(b) => ProstValue {
             kind: Some(prost_types::value::Kind::BoolValue(b)),
         },
-        JsonValue::Number(n) => {
-            if let Some(f) = n.as_f64() {
-                ProstValue {
-                    kind: Some(prost_types::value::Kind::NumberValue(f)),
-                }
-            } else {
-                // Handle integers that don't fit in f64
-                ProstValue {
+        JsonValue::Number(n) => match n.as_f64() {
+            Some(f) if !is_unsafe_integer(&n) => ProstValue {
+                kind: Some(prost_types::value::Kind::NumberValue(f)),
+            },
+            _ => {
+                let tag = ProstValue {
                     kind: Some(prost_types::value::Kind::StringValue(n.to_string())),
+                };
+                ProstValue {
+                    kind: Some(prost_types::value::Kind::StructValue(Struct {
+                        fields: [(INT_TAG.to_string(), tag)].into(),
+                    })),
                 }
             }
-        }
+        },
         JsonValue::String(s) => ProstValue {
             kind: Some(prost_types::value::Kind::StringValue(s)),
         },
@@ -44,6 +92,8 @@ pub fn json_value_to_prost_value(json_value: JsonValue) -> ProstValue {
     }
 }
 
+/// Converts a protobuf `Value` to JSON, reading integers that
+/// `json_value_to_prost_value` sent as `$int` structs back as numbers.
 pub fn prost_value_to_json_value(prost_value: ProstValue) -> JsonValue {
     match prost_value.kind {
         Some(prost_types::value::Kind::NullValue(_)) => JsonValue::Null,
@@ -52,8 +102,9 @@ pub fn prost_value_to_json_value(prost_value: ProstValue) -> JsonValue {
 
         Some(prost_types::value::Kind::NumberValue(n)) => {
             // Handle conversion of float to appropriate JSON number representation
-            if n.fract() == 0.0 && n <= i64::MAX as f64 && n >= i64::MIN as f64 {
-                // Convert to integer if it's a whole number within i64 range
+            if n.fract() == 0.0 && n.abs() <= MAX_SAFE_INTEGER as f64 {
+                // Whole numbers in the safe range are integers. Larger integers
+                // arrive as `$int` structs, so a whole f64 beyond it stays a float
                 JsonValue::Number(serde_json::Number::from(n as i64))
             } else {
                 // Otherwise keep as floating point
@@ -74,6 +125,9 @@ pub fn prost_value_to_json_value(prost_value: ProstValue) -> JsonValue {
         ),
 
         Some(prost_types::value::Kind::StructValue(obj)) => {
+            if let Some(n) = tagged_integer(&obj) {
+                return JsonValue::Number(n);
+            }
             let mut map = serde_json::Map::new();
             for (key, value) in obj.fields {
                 map.insert(key, prost_value_to_json_value(value));
@@ -85,6 +139,13 @@ pub fn prost_value_to_json_value(prost_value: ProstValue) -> JsonValue {
     }
 }
 
+pub fn offset_date_time_to_timestamp(at: OffsetDateTime) -> Timestamp {
+    Timestamp {
+        seconds: at.unix_timestamp(),
+        nanos: at.nanosecond() as i32,
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -124,15 +185,84 @@ mod tests {
 
         // Test float
         let prost_float = ProstValue {
-            kind: Some(prost_types::value::Kind::NumberValue(3.14)),
+            kind: Some(prost_types::value::Kind::NumberValue(2.5)),
         };
-        assert_eq!(prost_value_to_json_value(prost_float), json!(3.14));
+        assert_eq!(prost_value_to_json_value(prost_float), json!(2.5));
 
         // Test large integer
         let prost_large = ProstValue {
             kind: Some(prost_types::value::Kind::NumberValue(1e20)),
         };
         assert_eq!(prost_value_to_json_value(prost_large), json!(1e20));
+
+        // Whole floats beyond 2^53 stay floats rather than saturating
+        let prost_beyond_i64 = ProstValue {
+            kind: Some(prost_types::value::Kind::NumberValue(9223372036854775808.0)),
+        };
+        assert_eq!(
+            prost_value_to_json_value(prost_beyond_i64),
+            json!(9223372036854775808.0)
+        );
+    }
+
+    #[test]
+    fn test_large_integers() {
+        let safe = MAX_SAFE_INTEGER as i64;
+        for value in [json!(safe), json!(-safe)] {
+            let prost = json_value_to_prost_value(value.clone());
+            assert!(matches!(
+                prost.kind,
+                Some(prost_types::value::Kind::NumberValue(_))
+            ));
+            assert_eq!(prost_value_to_json_value(prost), value);
+        }
+
+        // Beyond 2^53 integers travel as `$int` structs and come back exactly
+        for value in [
+            json!(9007199254740993u64),
+            json!(-9007199254740993i64),
+            json!(i64::MIN),
+            json!(u64::MAX),
+        ] {
+            let prost = json_value_to_prost_value(value.clone());
+            assert_eq!(
+                prost_value_to_json_value(ProstValue {
+                    kind: prost.kind.clone(),
+                }),
+                value
+            );
+            let Some(prost_types::value::Kind::StructValue(tag)) = prost.kind else {
+                panic!("expected a tagged struct for {}", value);
+            };
+            assert_eq!(
+                tag.fields[INT_TAG].kind,
+                Some(prost_types::value::Kind::StringValue(value.to_string()))
+            );
+        }
+
+        // Tags that are not in the exact written form stay structs
+        for s in ["123", "+9007199254740993", "09007199254740993", "1e300"] {
+            let tagged = json!({ INT_TAG: s });
+            assert_eq!(
+                prost_value_to_json_value(json_value_to_prost_value(tagged.clone())),
+                tagged
+            );
+        }
+    }
+
+    #[test]
+    fn test_digit_strings_stay_strings() {
+        // A client storing an id as a string gets a string back, however
+        // large the number it spells
+        let metadata = json!({
+            "external_id": "12345678901234567890",
+            "negative": "-9007199254740993",
+            "small": "123",
+        });
+        assert_eq!(
+            prost_value_to_json_value(json_value_to_prost_value(metadata.clone())),
+            metadata
+        );
     }
 
     #[test]
@@ -0,0 +1,89 @@
+use anyhow::Result;
+use opentelemetry::propagation::Extractor;
+use opentelemetry::trace::TracerProvider as _;
+use opentelemetry::{global, KeyValue};
+use opentelemetry_otlp::WithExportConfig;
+use opentelemetry_sdk::propagation::TraceContextPropagator;
+use opentelemetry_sdk::trace::TracerProvider;
+use opentelemetry_sdk::{runtime, Resource};
+use tracing::Subscriber;
+use tracing_opentelemetry::OpenTelemetrySpanExt;
+use tracing_subscriber::registry::LookupSpan;
+use tracing_subscriber::Layer;
+
+use crate::config::TelemetryConfig;
+
+/// Sets up span export to an OTLP collector and W3C trace context
+/// propagation. Returns the layer to add to the subscriber along with the
+/// provider, which must be shut down on exit to flush pending spans.
+pub fn init<S>(config: &TelemetryConfig) -> Result<(impl Layer<S>, TracerProvider)>
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+{
+    global::set_text_map_propagator(TraceContextPropagator::new());
+
+    let exporter = opentelemetry_otlp::SpanExporter::builder()
+        .with_tonic()
+        .with_endpoint(&config.otlp_endpoint)
+        .build()?;
+    let provider = TracerProvider::builder()
+        .with_batch_exporter(exporter, runtime::Tokio)
+        .with_resource(Resource::new(vec![KeyValue::new(
+            "service.name",
+            config.service_name.clone(),
+        )]))
+        .build();
+
+    let tracer = provider.tracer("ent-server");
+    Ok((tracing_opentelemetry::layer().with_tracer(tracer), provider))
+}
+
+/// The span every gRPC request runs in. When the caller sent a `traceparent`
+/// header the span joins that trace, so handler spans link across services.
+pub fn grpc_request_span(request: &http::Request<()>) -> tracing::Span {
+    let span = tracing::info_span!("grpc_request", method = %request.uri().path());
+
+    let parent = global::get_text_map_propagator(|propagator| {
+        propagator.extract(&HeaderExtractor(request.headers()))
+    });
+    span.set_parent(parent);
+
+    span
+}
+
+struct HeaderExtractor<'a>(&'a http::HeaderMap);
+
+impl Extractor for HeaderExtractor<'_> {
+    fn get(&self, key: &str) -> Option<&str> {
+        self.0.get(key).and_then(|value| value.to_str().ok())
+    }
+
+    fn keys(&self) -> Vec<&str> {
+        self.0.keys().map(|key| key.as_str()).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use opentelemetry::propagation::TextMapPropagator;
+    use opentelemetry::trace::TraceContextExt;
+
+    #[test]
+    fn test_extracts_traceparent() {
+        let headers = http::HeaderMap::from_iter([(
+            http::header::HeaderName::from_static("traceparent"),
+            http::HeaderValue::from_static(
+                "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01",
+            ),
+        )]);
+
+        let context = TraceContextPropagator::new().extract(&HeaderExtractor(&headers));
+        let span_context = context.span().span_context().clone();
+        assert!(span_context.is_remote());
+        assert_eq!(
+            span_context.trace_id().to_string(),
+            "4bf92f3577b34da6a3ce929d0e0e4736"
+        );
+    }
+}
@@ -2,12 +2,13 @@ use anyhow::Result;
 use ent_proto::ent::{
     graph_service_server::GraphServiceServer, schema_service_server::SchemaServiceServer,
 };
-use ent_server::{config::Settings, GraphServer, SchemaServer};
+use ent_server::{auth, config::Settings, GraphServer, SchemaServer};
 use once_cell::sync::Lazy;
 use sqlx::{postgres::PgPoolOptions, Pool, Postgres as SqlxPostgres};
-use std::{net::SocketAddr, sync::Mutex};
+use std::net::SocketAddr;
 use testcontainers::{clients::Cli, Container, GenericImage};
-use tokio::net::TcpListener;
+use tokio::{net::TcpListener, sync::Mutex};
+use tonic::service::interceptor::InterceptedService;
 use tonic::transport::Server;
 use tracing::info;
 use tracing_subscriber::fmt::format::FmtSpan;
@@ -24,6 +25,12 @@ pub struct PostgresContainer<'a> {
     port: u16,
 }
 
+impl<'a> Default for PostgresContainer<'a> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl<'a> PostgresContainer<'a> {
     pub fn new() -> Self {
         let postgres_image = GenericImage::new("postgres", "15-alpine")
@@ -53,7 +60,7 @@ impl<'a> Drop for PostgresContainer<'a> {
 }
 
 pub async fn setup_test_db() -> Result<(Pool<SqlxPostgres>, PostgresContainer<'static>)> {
-    let _lock = MIGRATIONS_LOCK.lock().unwrap();
+    let _lock = MIGRATIONS_LOCK.lock().await;
 
     // Start a Postgres container
     let container = PostgresContainer::new();
@@ -151,8 +158,14 @@ pub async fn spawn_app() -> Result<(String, Pool<SqlxPostgres>, PostgresContaine
         let graph_server = GraphServer::new(graph_pool);
 
         Server::builder()
-            .add_service(SchemaServiceServer::new(schema_server))
-            .add_service(GraphServiceServer::new(graph_server))
+            .add_service(InterceptedService::new(
+                SchemaServiceServer::new(schema_server),
+                auth::interceptor,
+            ))
+            .add_service(InterceptedService::new(
+                GraphServiceServer::new(graph_server),
+                auth::interceptor,
+            ))
             .serve(addr)
             .await
             .expect("Failed to start test server");
@@ -1,12 +1,17 @@
-use crate::test_helper::*;
 use anyhow::Result;
 use ent_proto::ent::{
-    graph_service_client::GraphServiceClient, ConsistencyRequirement, GetObjectRequest,
-    UpdateObjectRequest,
+    consistency_requirement::Requirement, graph_service_client::GraphServiceClient,
+    graph_service_server::GraphService, BatchCreateEdgesRequest, ConsistencyRequirement,
+    CountObjectsRequest, CreateEdgeRequest, CreateObjectRequest, DeleteEdgeRequest,
+    FindObjectsRequest, GetEdgeByIdRequest, GetEdgesRequest, GetObjectRequest, GetObjectsRequest,
+    ListObjectsRequest, Permission, ServerInfoRequest, ShareObjectRequest, StreamObjectsRequest,
+    UpdateEdgeRequest, UpdateObjectRequest,
 };
 use ent_server::auth::RequestExt;
+use ent_server::config::{AccessConfig, PublicReadsConfig, PublicRpc};
+use ent_server::GraphServer;
 use serde_json::json;
-use std::collections::HashMap;
+use tonic::Request;
 
 use crate::{
     common::spawn_app,
@@ -49,12 +54,13 @@ async fn test_object_ownership_access_control() -> Result<()> {
     let user2_object = test_state.get_object(1).unwrap();
 
     let request = tonic::Request::new(GetObjectRequest {
-        object_id: user2_object.id.clone(),
+        object_id: user2_object.id,
         consistency: Some(ConsistencyRequirement {
             requirement: Some(
                 ent_proto::ent::consistency_requirement::Requirement::FullConsistency(true),
             ),
         }),
+        validate: false,
     })
     .with_bearer_token(user1_token)?;
 
@@ -65,10 +71,12 @@ async fn test_object_ownership_access_control() -> Result<()> {
 
     // Test: User1 trying to update User2's object
     let update_request = tonic::Request::new(UpdateObjectRequest {
-        object_id: user2_object.id.clone(),
+        object_id: user2_object.id,
         metadata: json_to_protobuf_struct(json!({
             "name": "attempted modification",
         })),
+        merge_patch: false,
+        expected_revision: None,
     })
     .with_bearer_token(user1_token)?;
 
@@ -83,17 +91,896 @@ async fn test_object_ownership_access_control() -> Result<()> {
     // Test: User2 accessing their own object (should succeed)
     let user2_token = test_state.get_user_token(1).unwrap();
     let owner_request = tonic::Request::new(GetObjectRequest {
-        object_id: user2_object.id.clone(),
+        object_id: user2_object.id,
         consistency: Some(ConsistencyRequirement {
             requirement: Some(
                 ent_proto::ent::consistency_requirement::Requirement::FullConsistency(true),
             ),
         }),
+        validate: false,
     })
     .with_bearer_token(user2_token)?;
 
-    let owner_response = client.get_object(owner_request).await;
-    assert!(owner_response.is_ok());
+    let owner_response = client.get_object(owner_request).await?.into_inner();
+    let object = owner_response.object.unwrap();
+    assert_eq!(object.created_by, "user2");
+    assert_eq!(object.updated_by, "user2");
+    assert!(object.created_at.is_some());
+    assert_eq!(object.updated_at, object.created_at);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_get_objects() -> Result<()> {
+    let (addr, _pool, _pg) = spawn_app().await?;
+
+    let test_state = EntTestBuilder::new()
+        .with_basic_schema()
+        .with_user("user1")
+        .with_user("user2")
+        .with_object(0, "basic", json!({"name": "first"}))
+        .with_object(0, "basic", json!({"name": "second"}))
+        .with_object(1, "basic", json!({"name": "someone else's"}))
+        .build(addr.clone())
+        .await?;
+
+    let mut client = GraphServiceClient::connect(addr).await?;
+    let user1_token = test_state.get_user_token(0).unwrap();
+    let ids: Vec<i64> = (0..3)
+        .map(|i| test_state.get_object(i).unwrap().id)
+        .collect();
+
+    // Objects come back in request order and unknown ids are left out
+    let request = tonic::Request::new(GetObjectsRequest {
+        object_ids: vec![ids[1], i64::MAX, ids[0]],
+        consistency: Some(ConsistencyRequirement {
+            requirement: Some(
+                ent_proto::ent::consistency_requirement::Requirement::FullConsistency(true),
+            ),
+        }),
+    })
+    .with_bearer_token(user1_token)?;
+
+    let objects = client.get_objects(request).await?.into_inner().objects;
+    assert_eq!(
+        objects.iter().map(|o| o.id).collect::<Vec<_>>(),
+        vec![ids[1], ids[0]]
+    );
+    assert_eq!(
+        objects[0].metadata,
+        json_to_protobuf_struct(json!({"name": "second"}))
+    );
+
+    // Asking for another user's object fails the whole batch
+    let request = tonic::Request::new(GetObjectsRequest {
+        object_ids: vec![ids[0], ids[2]],
+        consistency: None,
+    })
+    .with_bearer_token(user1_token)?;
+
+    let status = client.get_objects(request).await.unwrap_err();
+    assert_eq!(status.code(), tonic::Code::PermissionDenied);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_count_objects() -> Result<()> {
+    let (addr, _pool, _pg) = spawn_app().await?;
+
+    let test_state = EntTestBuilder::new()
+        .with_basic_schema()
+        .with_user("user1")
+        .with_user("user2")
+        .with_object(0, "basic", json!({"name": "first"}))
+        .with_object(0, "basic", json!({"name": "second"}))
+        .with_object(1, "basic", json!({"name": "someone else's"}))
+        .build(addr.clone())
+        .await?;
+
+    let mut client = GraphServiceClient::connect(addr).await?;
+    let user1_token = test_state.get_user_token(0).unwrap();
+    let type_name = test_state.get_object(0).unwrap().r#type.clone();
+
+    let count = |requirement: Option<Requirement>| {
+        tonic::Request::new(CountObjectsRequest {
+            r#type: type_name.clone(),
+            consistency: requirement.map(|requirement| ConsistencyRequirement {
+                requirement: Some(requirement),
+            }),
+        })
+        .with_bearer_token(user1_token)
+    };
+
+    // Only the caller's own objects are counted
+    let response = client
+        .count_objects(count(Some(Requirement::FullConsistency(true)))?)
+        .await?;
+    assert_eq!(response.into_inner().count, 2);
+
+    // A count pinned to a zookie ignores objects created afterwards
+    let create = || {
+        tonic::Request::new(CreateObjectRequest {
+            r#type: type_name.clone(),
+            metadata: json_to_protobuf_struct(json!({"name": "later"})),
+        })
+        .with_bearer_token(user1_token)
+    };
+    let revision = client
+        .create_object(create()?)
+        .await?
+        .into_inner()
+        .revision
+        .unwrap();
+    client.create_object(create()?).await?;
+
+    let response = client
+        .count_objects(count(Some(Requirement::ExactlyAt(revision)))?)
+        .await?;
+    assert_eq!(response.into_inner().count, 3);
+
+    let response = client.count_objects(count(None)?).await?;
+    assert_eq!(response.into_inner().count, 4);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_list_objects() -> Result<()> {
+    let (addr, _pool, _pg) = spawn_app().await?;
+
+    let test_state = EntTestBuilder::new()
+        .with_basic_schema()
+        .with_user("user1")
+        .with_user("user2")
+        .with_object(0, "basic", json!({"name": "first"}))
+        .with_object(1, "basic", json!({"name": "someone else's"}))
+        .with_object(0, "basic", json!({"name": "second"}))
+        .with_object(0, "basic", json!({"name": "third"}))
+        .build(addr.clone())
+        .await?;
+
+    let mut client = GraphServiceClient::connect(addr).await?;
+    let user1_token = test_state.get_user_token(0).unwrap();
+    let type_name = test_state.get_object(0).unwrap().r#type.clone();
+
+    // Page through the caller's objects two at a time
+    let mut names = Vec::new();
+    let mut cursor = 0;
+    let mut pages = 0;
+    loop {
+        let request = tonic::Request::new(ListObjectsRequest {
+            r#type: type_name.clone(),
+            limit: 2,
+            cursor,
+            consistency: None,
+        })
+        .with_bearer_token(user1_token)?;
+        let response = client.list_objects(request).await?.into_inner();
+        pages += 1;
+
+        for object in response.objects {
+            let name = object.metadata.unwrap().fields["name"].clone();
+            names.push(name.kind);
+        }
+        if response.next_cursor == 0 {
+            break;
+        }
+        cursor = response.next_cursor;
+    }
+
+    assert_eq!(pages, 2);
+    assert_eq!(
+        names,
+        ["first", "second", "third"]
+            .map(|name| Some(prost_types::value::Kind::StringValue(name.to_string())))
+    );
+
+    let request = tonic::Request::new(ListObjectsRequest {
+        r#type: type_name,
+        limit: 5000,
+        cursor: 0,
+        consistency: None,
+    })
+    .with_bearer_token(user1_token)?;
+    let status = client.list_objects(request).await.unwrap_err();
+    assert_eq!(status.code(), tonic::Code::InvalidArgument);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_find_objects() -> Result<()> {
+    let (addr, _pool, _pg) = spawn_app().await?;
+
+    let test_state = EntTestBuilder::new()
+        .with_basic_schema()
+        .with_user("user1")
+        .with_user("user2")
+        .with_object(0, "basic", json!({"name": "match"}))
+        .with_object(0, "basic", json!({"name": "other"}))
+        .with_object(1, "basic", json!({"name": "match"}))
+        .with_object(0, "basic", json!({"name": "match"}))
+        .with_object(0, "basic", json!({"name": "match"}))
+        .with_object(0, "basic", json!({"name": "john"}))
+        .build(addr.clone())
+        .await?;
+
+    let mut client = GraphServiceClient::connect(addr).await?;
+    let user1_token = test_state.get_user_token(0).unwrap();
+    let type_name = test_state.get_object(0).unwrap().r#type.clone();
+    let expected: Vec<i64> = [0, 3, 4]
+        .iter()
+        .map(|&i| test_state.get_object(i).unwrap().id)
+        .collect();
+
+    // Page through the caller's matches two at a time; user2's match and the
+    // non-matching object are skipped
+    let mut ids = Vec::new();
+    let mut cursor = 0;
+    let mut pages = 0;
+    loop {
+        let request = tonic::Request::new(FindObjectsRequest {
+            r#type: type_name.clone(),
+            field: "name".to_string(),
+            value: "match".to_string(),
+            limit: 2,
+            cursor,
+            consistency: None,
+            ignore_case: false,
+        })
+        .with_bearer_token(user1_token)?;
+        let response = client.find_objects(request).await?.into_inner();
+        pages += 1;
+
+        ids.extend(response.objects.iter().map(|object| object.id));
+        if response.next_cursor == 0 {
+            break;
+        }
+        cursor = response.next_cursor;
+    }
+    assert_eq!(pages, 2);
+    assert_eq!(ids, expected);
+
+    let request = tonic::Request::new(FindObjectsRequest {
+        r#type: type_name.clone(),
+        field: "name".to_string(),
+        value: "match".to_string(),
+        limit: 0,
+        cursor: 0,
+        consistency: Some(ConsistencyRequirement {
+            requirement: Some(Requirement::FullConsistency(true)),
+        }),
+        ignore_case: false,
+    })
+    .with_bearer_token(user1_token)?;
+    let response = client.find_objects(request).await?.into_inner();
+    assert_eq!(response.objects.len(), 3);
+
+    // Values are compared exactly unless asked to ignore case
+    let john = test_state.get_object(5).unwrap().id;
+    for (ignore_case, expected) in [(false, vec![]), (true, vec![john])] {
+        let request = tonic::Request::new(FindObjectsRequest {
+            r#type: type_name.clone(),
+            field: "name".to_string(),
+            value: "JOHN".to_string(),
+            limit: 0,
+            cursor: 0,
+            consistency: None,
+            ignore_case,
+        })
+        .with_bearer_token(user1_token)?;
+        let response = client.find_objects(request).await?.into_inner();
+        let ids: Vec<i64> = response.objects.iter().map(|object| object.id).collect();
+        assert_eq!(ids, expected);
+    }
+
+    let request = tonic::Request::new(FindObjectsRequest {
+        r#type: type_name,
+        field: "name'; --".to_string(),
+        value: "match".to_string(),
+        limit: 0,
+        cursor: 0,
+        consistency: None,
+        ignore_case: false,
+    })
+    .with_bearer_token(user1_token)?;
+    let status = client.find_objects(request).await.unwrap_err();
+    assert_eq!(status.code(), tonic::Code::InvalidArgument);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_stream_objects() -> Result<()> {
+    let (addr, _pool, _pg) = spawn_app().await?;
+
+    let test_state = EntTestBuilder::new()
+        .with_basic_schema()
+        .with_user("user1")
+        .with_user("user2")
+        .with_object(0, "basic", json!({"name": "first"}))
+        .with_object(1, "basic", json!({"name": "someone else's"}))
+        .with_object(0, "basic", json!({"name": "second"}))
+        .build(addr.clone())
+        .await?;
+
+    let mut client = GraphServiceClient::connect(addr).await?;
+    let type_name = test_state.get_object(0).unwrap().r#type.clone();
+
+    for (user, expected) in [(0, vec!["first", "second"]), (1, vec!["someone else's"])] {
+        let request = tonic::Request::new(StreamObjectsRequest {
+            r#type: type_name.clone(),
+            consistency: None,
+        })
+        .with_bearer_token(test_state.get_user_token(user).unwrap())?;
+        let mut stream = client.stream_objects(request).await?.into_inner();
+
+        let mut names = Vec::new();
+        while let Some(object) = stream.message().await? {
+            let name = object.metadata.unwrap().fields["name"].clone();
+            names.push(name.kind);
+        }
+
+        assert_eq!(
+            names,
+            expected
+                .into_iter()
+                .map(|name| Some(prost_types::value::Kind::StringValue(name.to_string())))
+                .collect::<Vec<_>>()
+        );
+    }
+
+    let request = tonic::Request::new(StreamObjectsRequest {
+        r#type: String::new(),
+        consistency: None,
+    })
+    .with_bearer_token(test_state.get_user_token(0).unwrap())?;
+    let status = client.stream_objects(request).await.unwrap_err();
+    assert_eq!(status.code(), tonic::Code::InvalidArgument);
+
+    Ok(())
+}
+
+/// Test that `public_reads` serves public objects without a token or ownership
+#[tokio::test]
+async fn test_public_reads() -> Result<()> {
+    let (addr, pool, _pg) = spawn_app().await?;
+    let state = EntTestBuilder::new()
+        .with_basic_schema()
+        .with_user("owner")
+        .with_user("stranger")
+        .with_attributed_object(0, "test_type", json!({ "public": true }))
+        .with_attributed_object(0, "test_type", json!({}))
+        .with_attributed_object(0, "test_type", json!({ "public": true }))
+        .with_edge(0, 0, 1, "links", json!({}))
+        .with_edge(0, 0, 2, "links", json!({}))
+        .build(addr)
+        .await?;
+    let owner_token = state.get_user_token(0).unwrap();
+    let stranger_token = state.get_user_token(1).unwrap();
+    let ids: Vec<i64> = (0..3).map(|i| state.get_object(i).unwrap().id).collect();
+
+    let server = GraphServer::new(pool.clone()).with_public_reads(Some(PublicReadsConfig {
+        rpcs: vec![PublicRpc::GetObject, PublicRpc::GetEdges],
+        field: "public".to_string(),
+    }));
+
+    let get_object = |object_id| {
+        Request::new(GetObjectRequest {
+            object_id,
+            consistency: None,
+            validate: false,
+        })
+    };
+    let get_edges = |object_id| {
+        Request::new(GetEdgesRequest {
+            object_id,
+            edge_type: "links".to_string(),
+            consistency: None,
+            metadata_filter: None,
+            ..Default::default()
+        })
+    };
+
+    // Anyone may read a public object, with or without a token
+    server.get_object(get_object(ids[0])).await?;
+    server
+        .get_object(get_object(ids[0]).with_bearer_token(stranger_token)?)
+        .await?;
+
+    // Private objects still need their owner
+    let status = server.get_object(get_object(ids[1])).await.unwrap_err();
+    assert_eq!(status.code(), tonic::Code::Unauthenticated);
+    let status = server
+        .get_object(get_object(ids[1]).with_bearer_token(stranger_token)?)
+        .await
+        .unwrap_err();
+    assert_eq!(status.code(), tonic::Code::PermissionDenied);
+
+    // A bad token is rejected even for public objects
+    let status = server
+        .get_object(get_object(ids[0]).with_bearer_token("not-a-token")?)
+        .await
+        .unwrap_err();
+    assert_eq!(status.code(), tonic::Code::Unauthenticated);
+
+    // Edges read through public access only lead to public objects
+    let edges = server.get_edges(get_edges(ids[0])).await?.into_inner();
+    let targets: Vec<i64> = edges.objects.iter().map(|object| object.id).collect();
+    assert_eq!(targets, vec![ids[2]]);
+
+    let edges = server
+        .get_edges(get_edges(ids[0]).with_bearer_token(owner_token)?)
+        .await?
+        .into_inner();
+    assert_eq!(edges.objects.len(), 2);
+
+    // Publishing an object doesn't open up its earlier, private versions
+    let private_revision = state.objects[1].revision.clone();
+    let request = Request::new(UpdateObjectRequest {
+        object_id: ids[1],
+        metadata: json_to_protobuf_struct(json!({ "public": true })),
+        ..Default::default()
+    })
+    .with_bearer_token(owner_token)?;
+    server.update_object(request).await?;
+
+    server.get_object(get_object(ids[1])).await?;
+    let request = Request::new(GetObjectRequest {
+        object_id: ids[1],
+        consistency: Some(ConsistencyRequirement {
+            requirement: Some(Requirement::ExactlyAt(private_revision)),
+        }),
+        validate: false,
+    });
+    let status = server.get_object(request).await.unwrap_err();
+    assert_eq!(status.code(), tonic::Code::NotFound);
+
+    // Without `public_reads` every read needs a token
+    let server = GraphServer::new(pool);
+    let status = server.get_object(get_object(ids[0])).await.unwrap_err();
+    assert_eq!(status.code(), tonic::Code::Unauthenticated);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_share_object() -> Result<()> {
+    let (addr, pool, _pg) = spawn_app().await?;
+    let state = EntTestBuilder::new()
+        .with_basic_schema()
+        .with_user("owner")
+        .with_user("reader")
+        .with_user("writer")
+        .with_attributed_object(0, "test_type", json!({ "name": "shared" }))
+        .build(addr)
+        .await?;
+    let owner_token = state.get_user_token(0).unwrap();
+    let reader_token = state.get_user_token(1).unwrap();
+    let writer_token = state.get_user_token(2).unwrap();
+    let object_id = state.get_object(0).unwrap().id;
+
+    let server = GraphServer::new(pool);
+    let share = |grantee: &str, permission: Permission| {
+        Request::new(ShareObjectRequest {
+            object_id,
+            grantee_user_id: grantee.to_string(),
+            permission: permission as i32,
+        })
+    };
+    let get_object = || {
+        Request::new(GetObjectRequest {
+            object_id,
+            consistency: None,
+            validate: false,
+        })
+    };
+    let update_object = |name: &str| {
+        Request::new(UpdateObjectRequest {
+            object_id,
+            metadata: json_to_protobuf_struct(json!({ "name": name })),
+            merge_patch: false,
+            expected_revision: None,
+        })
+    };
+
+    // Nothing is shared yet
+    let status = server
+        .get_object(get_object().with_bearer_token(reader_token)?)
+        .await
+        .unwrap_err();
+    assert_eq!(status.code(), tonic::Code::PermissionDenied);
+
+    // Only the owner can share
+    let status = server
+        .share_object(share("reader", Permission::Read).with_bearer_token(reader_token)?)
+        .await
+        .unwrap_err();
+    assert_eq!(status.code(), tonic::Code::PermissionDenied);
+
+    server
+        .share_object(share("reader", Permission::Read).with_bearer_token(owner_token)?)
+        .await?;
+    server
+        .share_object(share("writer", Permission::Write).with_bearer_token(owner_token)?)
+        .await?;
+
+    // A read grant allows reads but not writes
+    server
+        .get_object(get_object().with_bearer_token(reader_token)?)
+        .await?;
+    let status = server
+        .update_object(update_object("by reader").with_bearer_token(reader_token)?)
+        .await
+        .unwrap_err();
+    assert_eq!(status.code(), tonic::Code::PermissionDenied);
+
+    // A write grant allows both, and doesn't pass on the right to share
+    server
+        .get_object(get_object().with_bearer_token(writer_token)?)
+        .await?;
+    server
+        .update_object(update_object("by writer").with_bearer_token(writer_token)?)
+        .await?;
+    let status = server
+        .share_object(share("reader", Permission::Write).with_bearer_token(writer_token)?)
+        .await
+        .unwrap_err();
+    assert_eq!(status.code(), tonic::Code::PermissionDenied);
+
+    // The writer is recorded as the last writer, not as the owner
+    let object = server
+        .get_object(get_object().with_bearer_token(owner_token)?)
+        .await?
+        .into_inner()
+        .object
+        .unwrap();
+    assert_eq!(object.created_by, "owner");
+    assert_eq!(object.updated_by, "writer");
+    let count = |token| {
+        Request::new(CountObjectsRequest {
+            r#type: object.r#type.clone(),
+            consistency: None,
+        })
+        .with_bearer_token(token)
+    };
+    assert_eq!(
+        server
+            .count_objects(count(owner_token)?)
+            .await?
+            .into_inner()
+            .count,
+        1
+    );
+    assert_eq!(
+        server
+            .count_objects(count(writer_token)?)
+            .await?
+            .into_inner()
+            .count,
+        0
+    );
+
+    // The owner still owns the object after someone else updated it
+    server
+        .share_object(share("reader", Permission::Write).with_bearer_token(owner_token)?)
+        .await?;
+    server
+        .update_object(update_object("by reader").with_bearer_token(reader_token)?)
+        .await?;
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_server_info() -> Result<()> {
+    let (addr, pool, _pg) = spawn_app().await?;
+
+    // Needs no token
+    let mut client = GraphServiceClient::connect(addr).await?;
+    let info = client
+        .server_info(Request::new(ServerInfoRequest {}))
+        .await?
+        .into_inner();
+    assert_eq!(info.version, env!("CARGO_PKG_VERSION"));
+    assert!(info
+        .consistency_modes
+        .contains(&"minimize_latency".to_string()));
+    assert!(info.features.contains(&"batch_writes".to_string()));
+    assert!(!info.features.contains(&"public_reads".to_string()));
+
+    // Configured features are reported
+    let server = GraphServer::new(pool).with_public_reads(Some(PublicReadsConfig {
+        rpcs: vec![PublicRpc::GetObject],
+        field: "public".to_string(),
+    }));
+    let info = server
+        .server_info(Request::new(ServerInfoRequest {}))
+        .await?
+        .into_inner();
+    assert!(info.features.contains(&"public_reads".to_string()));
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_write_error_codes() -> Result<()> {
+    let (addr, pool, _pg) = spawn_app().await?;
+    let state = EntTestBuilder::new()
+        .with_basic_schema()
+        .with_user("owner")
+        .with_user("stranger")
+        .with_attributed_object(0, "test_type", json!({}))
+        .with_attributed_object(0, "test_type", json!({}))
+        .with_edge(0, 0, 1, "links", json!({}))
+        .build(addr)
+        .await?;
+    let owner_token = state.get_user_token(0).unwrap();
+    let stranger_token = state.get_user_token(1).unwrap();
+    let object_id = state.get_object(0).unwrap().id;
+    let edge_id = state.get_edge(0).unwrap().id;
+
+    let server = GraphServer::new(pool);
+    let update_object = |object_id| {
+        Request::new(UpdateObjectRequest {
+            object_id,
+            metadata: json_to_protobuf_struct(json!({})),
+            merge_patch: false,
+            expected_revision: None,
+        })
+    };
+    let update_edge = |edge_id| {
+        Request::new(UpdateEdgeRequest {
+            edge_id,
+            metadata: json_to_protobuf_struct(json!({ "weight": 1 })),
+        })
+    };
+
+    // No token is unauthenticated, someone else's object is denied and a
+    // missing one is not found
+    let status = server
+        .update_object(update_object(object_id))
+        .await
+        .unwrap_err();
+    assert_eq!(status.code(), tonic::Code::Unauthenticated);
+    let status = server
+        .update_object(update_object(object_id).with_bearer_token(stranger_token)?)
+        .await
+        .unwrap_err();
+    assert_eq!(status.code(), tonic::Code::PermissionDenied);
+    let status = server
+        .update_object(update_object(i64::MAX).with_bearer_token(owner_token)?)
+        .await
+        .unwrap_err();
+    assert_eq!(status.code(), tonic::Code::NotFound);
+
+    // Edges follow the ownership of their source object
+    let status = server
+        .update_edge(update_edge(edge_id).with_bearer_token(stranger_token)?)
+        .await
+        .unwrap_err();
+    assert_eq!(status.code(), tonic::Code::PermissionDenied);
+    let status = server
+        .update_edge(update_edge(i64::MAX).with_bearer_token(owner_token)?)
+        .await
+        .unwrap_err();
+    assert_eq!(status.code(), tonic::Code::NotFound);
+    server
+        .update_edge(update_edge(edge_id).with_bearer_token(owner_token)?)
+        .await?;
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_uniform_not_found() -> Result<()> {
+    let (addr, pool, _pg) = spawn_app().await?;
+    let state = EntTestBuilder::new()
+        .with_basic_schema()
+        .with_user("owner")
+        .with_user("stranger")
+        .with_attributed_object(0, "test_type", json!({}))
+        .with_attributed_object(1, "test_type", json!({}))
+        .with_attributed_object(0, "test_type", json!({}))
+        .with_edge(0, 0, 2, "likes", json!({}))
+        .build(addr)
+        .await?;
+    let stranger_token = state.get_user_token(1).unwrap();
+    let owned_id = state.get_object(0).unwrap().id;
+    let own_id = state.get_object(1).unwrap().id;
+    let owned_edge_id = state.get_edge(0).unwrap().id;
+
+    let server = GraphServer::new(pool).with_access_config(AccessConfig {
+        uniform_not_found: true,
+    });
+    let get_object = |object_id| {
+        Request::new(GetObjectRequest {
+            object_id,
+            consistency: None,
+            validate: false,
+        })
+    };
+
+    // Someone else's object looks exactly like a missing one
+    let foreign = server
+        .get_object(get_object(owned_id).with_bearer_token(stranger_token)?)
+        .await
+        .unwrap_err();
+    let missing = server
+        .get_object(get_object(i64::MAX).with_bearer_token(stranger_token)?)
+        .await
+        .unwrap_err();
+    assert_eq!(foreign.code(), tonic::Code::NotFound);
+    assert_eq!(foreign.code(), missing.code());
+    assert_eq!(foreign.message(), missing.message());
+    assert_eq!(foreign.details(), missing.details());
+
+    let status = server
+        .update_object(
+            Request::new(UpdateObjectRequest {
+                object_id: owned_id,
+                metadata: json_to_protobuf_struct(json!({})),
+                merge_patch: false,
+                expected_revision: None,
+            })
+            .with_bearer_token(stranger_token)?,
+        )
+        .await
+        .unwrap_err();
+    assert_eq!(status.code(), tonic::Code::NotFound);
+
+    // Batch reads leave them out just like missing ids
+    let response = server
+        .get_objects(
+            Request::new(GetObjectsRequest {
+                object_ids: vec![own_id, owned_id, i64::MAX],
+                consistency: None,
+            })
+            .with_bearer_token(stranger_token)?,
+        )
+        .await?
+        .into_inner();
+    let ids: Vec<i64> = response.objects.iter().map(|object| object.id).collect();
+    assert_eq!(ids, vec![own_id]);
+
+    // So does someone else's edge
+    let get_edge = |edge_id| {
+        Request::new(GetEdgeByIdRequest {
+            edge_id,
+            consistency: None,
+        })
+    };
+    let foreign = server
+        .get_edge_by_id(get_edge(owned_edge_id).with_bearer_token(stranger_token)?)
+        .await
+        .unwrap_err();
+    let missing = server
+        .get_edge_by_id(get_edge(i64::MAX).with_bearer_token(stranger_token)?)
+        .await
+        .unwrap_err();
+    assert_eq!(foreign.code(), tonic::Code::NotFound);
+    assert_eq!(foreign.message(), missing.message());
+    assert_eq!(foreign.details(), missing.details());
+
+    let delete_edge = |edge_id| Request::new(DeleteEdgeRequest { edge_id });
+    let foreign = server
+        .delete_edge(delete_edge(owned_edge_id).with_bearer_token(stranger_token)?)
+        .await
+        .unwrap_err();
+    let missing = server
+        .delete_edge(delete_edge(i64::MAX).with_bearer_token(stranger_token)?)
+        .await
+        .unwrap_err();
+    assert_eq!(foreign.code(), tonic::Code::NotFound);
+    assert_eq!(foreign.message(), missing.message());
+    assert_eq!(foreign.details(), missing.details());
+
+    Ok(())
+}
+
+/// Test that an edge can only point at an object the caller can read, and that
+/// the refusal gives away nothing about the target, not even its type
+#[tokio::test]
+async fn test_edge_to_unreadable_object() -> Result<()> {
+    let (addr, pool, _pg) = spawn_app().await?;
+    let state = EntTestBuilder::new()
+        .with_basic_schema()
+        .with_user("owner")
+        .with_user("stranger")
+        .with_attributed_object(0, "test_type", json!({}))
+        .with_attributed_object(1, "test_type", json!({}))
+        .build(addr.clone())
+        .await?;
+    let owner_token = state.get_user_token(0).unwrap();
+    let stranger_token = state.get_user_token(1).unwrap();
+    let from = state.get_object(0).unwrap().clone();
+    let to_id = state.get_object(1).unwrap().id;
+
+    let mut client = GraphServiceClient::connect(addr).await?;
+    let edge = |to_id, to_type: &str| CreateEdgeRequest {
+        from_id: from.id,
+        from_type: from.r#type.clone(),
+        to_id,
+        to_type: to_type.to_string(),
+        relation: "likes".to_string(),
+        metadata: None,
+    };
+
+    // Declaring the wrong type doesn't reveal the right one
+    let status = client
+        .create_edge(Request::new(edge(to_id, "other_type")).with_bearer_token(owner_token)?)
+        .await
+        .unwrap_err();
+    assert_eq!(status.code(), tonic::Code::PermissionDenied);
+    assert_eq!(
+        status.message(),
+        format!("you may not read to object {}", to_id)
+    );
+
+    let status = client
+        .batch_create_edges(
+            Request::new(BatchCreateEdgesRequest {
+                edges: vec![edge(to_id, &from.r#type)],
+                skip_existing: false,
+            })
+            .with_bearer_token(owner_token)?,
+        )
+        .await
+        .unwrap_err();
+    assert_eq!(status.code(), tonic::Code::PermissionDenied);
+    assert_eq!(
+        status.message(),
+        format!("edge 0: you may not read to object {}", to_id)
+    );
+
+    // With uniform not found the target fails like a missing one
+    let server = GraphServer::new(pool).with_access_config(AccessConfig {
+        uniform_not_found: true,
+    });
+    let foreign = server
+        .create_edge(Request::new(edge(to_id, "other_type")).with_bearer_token(owner_token)?)
+        .await
+        .unwrap_err();
+    let missing = server
+        .create_edge(Request::new(edge(i64::MAX, "other_type")).with_bearer_token(owner_token)?)
+        .await
+        .unwrap_err();
+    assert_eq!(foreign.code(), tonic::Code::FailedPrecondition);
+    assert_eq!(foreign.code(), missing.code());
+    assert_eq!(
+        foreign.message(),
+        format!("to object {} does not exist", to_id)
+    );
+
+    let foreign = server
+        .batch_create_edges(
+            Request::new(BatchCreateEdgesRequest {
+                edges: vec![edge(to_id, &from.r#type)],
+                skip_existing: false,
+            })
+            .with_bearer_token(owner_token)?,
+        )
+        .await
+        .unwrap_err();
+    assert_eq!(foreign.code(), tonic::Code::InvalidArgument);
+    assert_eq!(
+        foreign.message(),
+        format!("edge 0: to object {} does not exist", to_id)
+    );
+
+    // Once shared the target can be linked to
+    let request = Request::new(ShareObjectRequest {
+        object_id: to_id,
+        grantee_user_id: "owner".to_string(),
+        permission: Permission::Read as i32,
+    })
+    .with_bearer_token(stranger_token)?;
+    client.share_object(request).await?;
+    client
+        .create_edge(Request::new(edge(to_id, &from.r#type)).with_bearer_token(owner_token)?)
+        .await?;
 
     Ok(())
 }
@@ -0,0 +1,1905 @@
+use crate::test_helper::{json_to_protobuf_struct, EntTestBuilder};
+use anyhow::Result;
+use ent_proto::ent::{
+    consistency_requirement::Requirement, export_graph_response::Record,
+    graph_service_client::GraphServiceClient, BatchCreateEdgesRequest, BatchCreateObjectsRequest,
+    CheckPermissionRequest, ConsistencyRequirement, CountObjectsRequest, CreateEdgeRequest,
+    CreateObjectRequest, DeleteEdgeRequest, DeleteEdgesByRequest, DeleteObjectRequest,
+    EdgeMetadataFilter, EdgeOrder, ExportGraphRequest, GetEdgeByIdRequest, GetEdgeRequest,
+    GetEdgesRequest, GetIncomingEdgesRequest, GetObjectRequest, GetObjectsRequest,
+    GetRelatedObjectsRequest, ListRelationsRequest, Permission, RelationCount, ShortestPathRequest,
+    TraverseRequest, UpdateEdgeRequest,
+};
+use ent_server::auth::RequestExt;
+use serde_json::json;
+use tonic::Request;
+
+/// Test that incoming edges are looked up by target object and relation, for
+/// callers who can read the target
+#[tokio::test]
+async fn test_get_incoming_edges() -> Result<()> {
+    let (address, _pool, _container) = crate::common::spawn_app().await?;
+    let builder = EntTestBuilder::new()
+        .with_basic_schema()
+        .with_user("test_user")
+        .with_user("other_user")
+        .with_attributed_object(0, "test_type", json!({}))
+        .with_attributed_object(0, "test_type", json!({}))
+        .with_attributed_object(0, "test_type", json!({}))
+        .with_edge(0, 0, 2, "references", json!({}))
+        .with_edge(0, 1, 2, "references", json!({}))
+        .with_edge(0, 0, 1, "references", json!({}))
+        .with_edge(0, 1, 2, "mentions", json!({}));
+
+    let state = builder.build(address.clone()).await?;
+    let user_token = state.get_user_token(0).unwrap();
+    let other_token = state.get_user_token(1).unwrap();
+    let target_id = state.get_object(2).unwrap().id;
+
+    let mut client = GraphServiceClient::connect(address).await?;
+
+    let request = || {
+        Request::new(GetIncomingEdgesRequest {
+            object_id: target_id,
+            edge_type: "references".to_string(),
+            consistency: None,
+        })
+    };
+
+    let status = client.get_incoming_edges(request()).await.unwrap_err();
+    assert_eq!(status.code(), tonic::Code::Unauthenticated);
+    let status = client
+        .get_incoming_edges(request().with_bearer_token(other_token)?)
+        .await
+        .unwrap_err();
+    assert_eq!(status.code(), tonic::Code::PermissionDenied);
+
+    let edges = client
+        .get_incoming_edges(request().with_bearer_token(user_token)?)
+        .await?
+        .into_inner()
+        .edges;
+
+    let mut sources: Vec<i64> = edges.iter().map(|edge| edge.from_id).collect();
+    sources.sort();
+    let mut expected = vec![
+        state.get_object(0).unwrap().id,
+        state.get_object(1).unwrap().id,
+    ];
+    expected.sort();
+
+    assert_eq!(sources, expected);
+    assert!(edges
+        .iter()
+        .all(|edge| edge.to_id == target_id && edge.relation == "references"));
+
+    Ok(())
+}
+
+/// Test that an edge created without metadata is returned in full
+#[tokio::test]
+async fn test_create_edge_without_metadata() -> Result<()> {
+    let (address, _pool, _container) = crate::common::spawn_app().await?;
+    let builder = EntTestBuilder::new()
+        .with_basic_schema()
+        .with_user("test_user")
+        .with_attributed_object(0, "test_type", json!({}))
+        .with_attributed_object(0, "test_type", json!({}));
+
+    let state = builder.build(address.clone()).await?;
+    let user_token = state.get_user_token(0).unwrap();
+    let from = state.get_object(0).unwrap();
+    let to = state.get_object(1).unwrap();
+
+    let mut client = GraphServiceClient::connect(address).await?;
+
+    let request = Request::new(CreateEdgeRequest {
+        from_id: from.id,
+        from_type: from.r#type.clone(),
+        to_id: to.id,
+        to_type: to.r#type.clone(),
+        relation: "links_to".to_string(),
+        metadata: None,
+    })
+    .with_bearer_token(user_token)?;
+
+    let response = client.create_edge(request).await?.into_inner();
+    let edge = response.edge.expect("created edge should be returned");
+
+    assert!(edge.id > 0);
+    assert_eq!(edge.from_id, from.id);
+    assert_eq!(edge.from_type, from.r#type);
+    assert_eq!(edge.to_id, to.id);
+    assert_eq!(edge.to_type, to.r#type);
+    assert_eq!(edge.relation, "links_to");
+    assert_eq!(edge.metadata, None);
+    assert_eq!(edge.created_by, "test_user");
+    assert!(edge.created_at.is_some());
+    assert!(edge.updated_at.is_some());
+    assert!(response.revision.is_some());
+
+    // Reading the edge back represents the empty metadata the same way
+    let request = Request::new(GetEdgeRequest {
+        object_id: from.id,
+        edge_type: "links_to".to_string(),
+        consistency: None,
+    })
+    .with_bearer_token(user_token)?;
+
+    let fetched = client.get_edge(request).await?.into_inner().edge.unwrap();
+    assert_eq!(fetched.id, edge.id);
+    assert_eq!(fetched.metadata, None);
+    assert_eq!(fetched.created_by, "test_user");
+
+    Ok(())
+}
+
+/// Test that traversal follows edges across hops, stops at the depth limit,
+/// terminates on cycles and only returns objects the caller can read
+#[tokio::test]
+async fn test_traverse() -> Result<()> {
+    let (address, _pool, _container) = crate::common::spawn_app().await?;
+    let builder = EntTestBuilder::new()
+        .with_basic_schema()
+        .with_user("test_user")
+        .with_user("other_user")
+        .with_attributed_object(0, "test_type", json!({}))
+        .with_attributed_object(0, "test_type", json!({}))
+        .with_attributed_object(0, "test_type", json!({}))
+        .with_attributed_object(0, "test_type", json!({}))
+        .with_attributed_object(1, "test_type", json!({}))
+        .with_edge(0, 0, 1, "next", json!({}))
+        .with_edge(0, 1, 2, "next", json!({}))
+        .with_edge(0, 2, 0, "next", json!({}))
+        .with_edge(0, 2, 3, "next", json!({}))
+        .with_edge(0, 0, 3, "other", json!({}))
+        // Linked by the other user, who can read the target
+        .with_share(1, 1, Permission::Write)
+        .with_edge(1, 1, 4, "next", json!({}));
+
+    let state = builder.build(address.clone()).await?;
+    let user_token = state.get_user_token(0).unwrap();
+    let other_token = state.get_user_token(1).unwrap();
+    let ids: Vec<i64> = (0..4).map(|i| state.get_object(i).unwrap().id).collect();
+
+    let mut client = GraphServiceClient::connect(address).await?;
+
+    let request = |max_depth| {
+        Request::new(TraverseRequest {
+            object_id: ids[0],
+            edge_type: "next".to_string(),
+            max_depth,
+            consistency: None,
+        })
+    };
+    let traverse = |max_depth| request(max_depth).with_bearer_token(user_token);
+
+    // Only the owner of the start object may walk from it
+    let status = client.traverse(request(10)).await.unwrap_err();
+    assert_eq!(status.code(), tonic::Code::Unauthenticated);
+    let status = client
+        .traverse(request(10).with_bearer_token(other_token)?)
+        .await
+        .unwrap_err();
+    assert_eq!(status.code(), tonic::Code::PermissionDenied);
+
+    // The other user's object is reached but left out
+    let reached: Vec<(i64, i32)> = client
+        .traverse(traverse(10)?)
+        .await?
+        .into_inner()
+        .objects
+        .into_iter()
+        .map(|node| (node.object.unwrap().id, node.depth))
+        .collect();
+    assert_eq!(reached, vec![(ids[1], 1), (ids[2], 2), (ids[3], 3)]);
+
+    let reached: Vec<(i64, i32)> = client
+        .traverse(traverse(2)?)
+        .await?
+        .into_inner()
+        .objects
+        .into_iter()
+        .map(|node| (node.object.unwrap().id, node.depth))
+        .collect();
+    assert_eq!(reached, vec![(ids[1], 1), (ids[2], 2)]);
+
+    let status = client.traverse(traverse(0)?).await.unwrap_err();
+    assert_eq!(status.code(), tonic::Code::InvalidArgument);
+
+    Ok(())
+}
+
+/// Test that the shortest path follows the fewest hops, respects the revision
+/// it is evaluated at and needs read access to both ends
+#[tokio::test]
+async fn test_shortest_path() -> Result<()> {
+    let (address, _pool, _container) = crate::common::spawn_app().await?;
+    let builder = EntTestBuilder::new()
+        .with_basic_schema()
+        .with_user("test_user")
+        .with_user("other_user")
+        .with_attributed_object(0, "test_type", json!({}))
+        .with_attributed_object(0, "test_type", json!({}))
+        .with_attributed_object(0, "test_type", json!({}))
+        .with_attributed_object(0, "test_type", json!({}))
+        .with_attributed_object(0, "test_type", json!({}))
+        .with_edge(0, 0, 1, "a", json!({}))
+        .with_edge(0, 1, 2, "b", json!({}))
+        .with_edge(0, 2, 3, "c", json!({}))
+        // Keeps the shortcut strictly after the revision of the previous edge
+        .with_edge(0, 3, 4, "d", json!({}))
+        .with_edge(0, 0, 3, "shortcut", json!({}))
+        .with_attributed_object(1, "test_type", json!({}))
+        .with_share(4, 1, Permission::Write)
+        .with_edge(1, 4, 5, "e", json!({}));
+
+    let state = builder.build(address.clone()).await?;
+    let user_token = state.get_user_token(0).unwrap();
+    let other_token = state.get_user_token(1).unwrap();
+    let ids: Vec<i64> = (0..6).map(|i| state.get_object(i).unwrap().id).collect();
+    let edge_ids: Vec<i64> = (0..5).map(|i| state.get_edge(i).unwrap().id).collect();
+    let before_shortcut = state.edges[2].revision.clone();
+
+    let mut client = GraphServiceClient::connect(address).await?;
+
+    let shortest_path = |from_id, to_id, max_depth, requirement| {
+        Request::new(ShortestPathRequest {
+            from_id,
+            to_id,
+            max_depth,
+            consistency: Some(ConsistencyRequirement {
+                requirement: Some(requirement),
+            }),
+        })
+        .with_bearer_token(user_token)
+    };
+
+    let path = client
+        .shortest_path(shortest_path(
+            ids[0],
+            ids[3],
+            10,
+            Requirement::FullConsistency(true),
+        )?)
+        .await?
+        .into_inner();
+    assert!(path.found);
+    let steps: Vec<(i64, String)> = path
+        .steps
+        .into_iter()
+        .map(|step| (step.edge_id, step.relation))
+        .collect();
+    assert_eq!(steps, vec![(edge_ids[4], "shortcut".to_string())]);
+
+    // Before the shortcut existed the path goes the long way round
+    let path = client
+        .shortest_path(shortest_path(
+            ids[0],
+            ids[3],
+            10,
+            Requirement::ExactlyAt(before_shortcut.clone()),
+        )?)
+        .await?
+        .into_inner();
+    assert!(path.found);
+    let steps: Vec<i64> = path.steps.iter().map(|step| step.edge_id).collect();
+    assert_eq!(steps, vec![edge_ids[0], edge_ids[1], edge_ids[2]]);
+
+    let path = client
+        .shortest_path(shortest_path(
+            ids[0],
+            ids[3],
+            2,
+            Requirement::ExactlyAt(before_shortcut),
+        )?)
+        .await?
+        .into_inner();
+    assert!(!path.found);
+
+    // Edges are directed
+    let path = client
+        .shortest_path(shortest_path(
+            ids[3],
+            ids[0],
+            10,
+            Requirement::FullConsistency(true),
+        )?)
+        .await?
+        .into_inner();
+    assert!(!path.found);
+    assert!(path.steps.is_empty());
+
+    // Neither a caller without a token nor one who can't read both ends learns
+    // whether the objects are connected
+    let request = |from_id, to_id| {
+        Request::new(ShortestPathRequest {
+            from_id,
+            to_id,
+            max_depth: 10,
+            consistency: None,
+        })
+    };
+    let status = client
+        .shortest_path(request(ids[0], ids[3]))
+        .await
+        .unwrap_err();
+    assert_eq!(status.code(), tonic::Code::Unauthenticated);
+    let status = client
+        .shortest_path(request(ids[0], ids[3]).with_bearer_token(other_token)?)
+        .await
+        .unwrap_err();
+    assert_eq!(status.code(), tonic::Code::PermissionDenied);
+    let status = client
+        .shortest_path(request(ids[0], ids[5]).with_bearer_token(user_token)?)
+        .await
+        .unwrap_err();
+    assert_eq!(status.code(), tonic::Code::PermissionDenied);
+
+    Ok(())
+}
+
+/// Test that permissions are granted directly or through membership relations,
+/// and can only be checked by someone who can read the subject
+#[tokio::test]
+async fn test_check_permission() -> Result<()> {
+    let (address, _pool, _container) = crate::common::spawn_app().await?;
+    // alice -member-> team -member-> org -owns-> doc, and bob -owns-> doc
+    let builder = EntTestBuilder::new()
+        .with_basic_schema()
+        .with_user("test_user")
+        .with_user("other_user")
+        .with_attributed_object(0, "test_type", json!({ "name": "alice" }))
+        .with_attributed_object(0, "test_type", json!({ "name": "team" }))
+        .with_attributed_object(0, "test_type", json!({ "name": "org" }))
+        .with_attributed_object(0, "test_type", json!({ "name": "doc" }))
+        .with_attributed_object(0, "test_type", json!({ "name": "bob" }))
+        .with_edge(0, 0, 1, "member", json!({}))
+        .with_edge(0, 1, 2, "member", json!({}))
+        .with_edge(0, 2, 3, "owns", json!({}))
+        .with_edge(0, 4, 3, "owns", json!({}))
+        .with_edge(0, 4, 2, "follows", json!({}));
+
+    let state = builder.build(address.clone()).await?;
+    let user_token = state.get_user_token(0).unwrap();
+    let other_token = state.get_user_token(1).unwrap();
+    let ids: Vec<i64> = (0..5).map(|i| state.get_object(i).unwrap().id).collect();
+    let edge_ids: Vec<i64> = (0..3).map(|i| state.get_edge(i).unwrap().id).collect();
+
+    let mut client = GraphServiceClient::connect(address).await?;
+
+    let request = |subject_id, via: &[&str], max_depth| {
+        Request::new(CheckPermissionRequest {
+            subject_id,
+            permission: "owns".to_string(),
+            object_id: ids[3],
+            via: via.iter().map(|relation| relation.to_string()).collect(),
+            max_depth,
+            consistency: None,
+        })
+    };
+    let check = |subject_id, via: &[&str], max_depth| {
+        request(subject_id, via, max_depth).with_bearer_token(user_token)
+    };
+
+    let status = client
+        .check_permission(request(ids[0], &["member"], 5))
+        .await
+        .unwrap_err();
+    assert_eq!(status.code(), tonic::Code::Unauthenticated);
+    let status = client
+        .check_permission(request(ids[0], &["member"], 5).with_bearer_token(other_token)?)
+        .await
+        .unwrap_err();
+    assert_eq!(status.code(), tonic::Code::PermissionDenied);
+
+    let response = client
+        .check_permission(check(ids[0], &["member"], 5)?)
+        .await?
+        .into_inner();
+    assert!(response.allowed);
+    let steps: Vec<(i64, String)> = response
+        .steps
+        .into_iter()
+        .map(|step| (step.edge_id, step.relation))
+        .collect();
+    assert_eq!(
+        steps,
+        vec![
+            (edge_ids[0], "member".to_string()),
+            (edge_ids[1], "member".to_string()),
+            (edge_ids[2], "owns".to_string()),
+        ]
+    );
+
+    // Membership only counts through the listed relations, within max_depth
+    let response = client
+        .check_permission(check(ids[0], &[], 5)?)
+        .await?
+        .into_inner();
+    assert!(!response.allowed);
+    assert!(response.steps.is_empty());
+    let response = client
+        .check_permission(check(ids[0], &["member"], 2)?)
+        .await?
+        .into_inner();
+    assert!(!response.allowed);
+
+    // A direct edge grants the permission on its own
+    let response = client
+        .check_permission(check(ids[4], &[], 1)?)
+        .await?
+        .into_inner();
+    assert!(response.allowed);
+    assert_eq!(response.steps.len(), 1);
+
+    // The granting relation must point at the object itself
+    let response = client
+        .check_permission(check(ids[1], &["follows"], 5)?)
+        .await?
+        .into_inner();
+    assert!(!response.allowed);
+
+    // Deleting the granting edge revokes the permission
+    let request = Request::new(DeleteEdgeRequest {
+        edge_id: edge_ids[2],
+    })
+    .with_bearer_token(user_token)?;
+    client.delete_edge(request).await?;
+    let response = client
+        .check_permission(check(ids[0], &["member"], 5)?)
+        .await?
+        .into_inner();
+    assert!(!response.allowed);
+
+    Ok(())
+}
+
+/// Test that GetEdges only returns edges whose metadata matches the filter
+#[tokio::test]
+async fn test_get_edges_metadata_filter() -> Result<()> {
+    let (address, _pool, _container) = crate::common::spawn_app().await?;
+    let builder = EntTestBuilder::new()
+        .with_basic_schema()
+        .with_user("test_user")
+        .with_attributed_object(0, "test_type", json!({}))
+        .with_attributed_object(0, "test_type", json!({}))
+        .with_attributed_object(0, "test_type", json!({}))
+        .with_attributed_object(0, "test_type", json!({}))
+        .with_edge(0, 0, 1, "follows", json!({ "kind": "close", "weight": 5 }))
+        .with_edge(0, 0, 2, "follows", json!({ "kind": "close", "weight": 1 }))
+        .with_edge(0, 0, 3, "follows", json!({ "kind": "casual", "weight": 5 }));
+
+    let state = builder.build(address.clone()).await?;
+    let user_token = state.get_user_token(0).unwrap();
+    let ids: Vec<i64> = (0..4).map(|i| state.get_object(i).unwrap().id).collect();
+
+    let client = GraphServiceClient::connect(address).await?;
+
+    let get_targets = |metadata_filter| {
+        let request = Request::new(GetEdgesRequest {
+            object_id: ids[0],
+            edge_type: "follows".to_string(),
+            consistency: Some(ConsistencyRequirement {
+                requirement: Some(Requirement::FullConsistency(true)),
+            }),
+            metadata_filter: Some(metadata_filter),
+            ..Default::default()
+        })
+        .with_bearer_token(user_token);
+        let mut client = client.clone();
+        async move {
+            let objects = client.get_edges(request?).await?.into_inner().objects;
+            let mut targets: Vec<i64> = objects.iter().map(|object| object.id).collect();
+            targets.sort();
+            anyhow::Ok(targets)
+        }
+    };
+
+    let targets = get_targets(EdgeMetadataFilter {
+        contains: json_to_protobuf_struct(json!({ "kind": "close" })),
+        ..Default::default()
+    })
+    .await?;
+    assert_eq!(targets, vec![ids[1], ids[2]]);
+
+    let targets = get_targets(EdgeMetadataFilter {
+        key: "weight".to_string(),
+        equals: "5".to_string(),
+        ..Default::default()
+    })
+    .await?;
+    assert_eq!(targets, vec![ids[1], ids[3]]);
+
+    let targets = get_targets(EdgeMetadataFilter {
+        contains: json_to_protobuf_struct(json!({ "kind": "close" })),
+        key: "weight".to_string(),
+        equals: "5".to_string(),
+        ..Default::default()
+    })
+    .await?;
+    assert_eq!(targets, vec![ids[1]]);
+
+    // Values are compared exactly unless asked to ignore case
+    for (ignore_case, expected) in [(false, vec![]), (true, vec![ids[1], ids[2]])] {
+        let targets = get_targets(EdgeMetadataFilter {
+            key: "kind".to_string(),
+            equals: "CLOSE".to_string(),
+            ignore_case,
+            ..Default::default()
+        })
+        .await?;
+        assert_eq!(targets, expected);
+    }
+
+    // An empty filter matches every edge
+    let targets = get_targets(EdgeMetadataFilter::default()).await?;
+    assert_eq!(targets, vec![ids[1], ids[2], ids[3]]);
+
+    // Each target comes with the edge leading to it, metadata included
+    let request = Request::new(GetEdgesRequest {
+        object_id: ids[0],
+        edge_type: "follows".to_string(),
+        consistency: None,
+        metadata_filter: None,
+        ..Default::default()
+    })
+    .with_bearer_token(user_token)?;
+    let response = client.clone().get_edges(request).await?.into_inner();
+    assert_eq!(response.edges.len(), response.objects.len());
+    for (edge, object) in response.edges.iter().zip(&response.objects) {
+        assert_eq!(edge.from_id, ids[0]);
+        assert_eq!(edge.to_id, object.id);
+        assert_eq!(edge.relation, "follows");
+    }
+    assert_eq!(
+        response.edges[2].metadata,
+        json_to_protobuf_struct(json!({ "kind": "casual", "weight": 5 }))
+    );
+
+    Ok(())
+}
+
+/// Test that GetEdges orders edges by creation time, honors the limit and
+/// leaves out targets the caller can't read before cutting off
+#[tokio::test]
+async fn test_get_edges_order_and_limit() -> Result<()> {
+    let (address, _pool, _container) = crate::common::spawn_app().await?;
+    let builder = EntTestBuilder::new()
+        .with_basic_schema()
+        .with_user("test_user")
+        .with_user("other_user")
+        .with_attributed_object(0, "test_type", json!({}))
+        .with_attributed_object(0, "test_type", json!({}))
+        .with_attributed_object(0, "test_type", json!({}))
+        .with_attributed_object(0, "test_type", json!({}))
+        .with_attributed_object(1, "test_type", json!({}))
+        .with_share(0, 1, Permission::Write)
+        .with_edge(1, 0, 4, "follows", json!({}))
+        .with_edge(0, 0, 3, "follows", json!({}))
+        .with_edge(0, 0, 1, "follows", json!({}))
+        .with_edge(0, 0, 2, "follows", json!({}));
+
+    let state = builder.build(address.clone()).await?;
+    let user_token = state.get_user_token(0).unwrap();
+    let ids: Vec<i64> = (0..4).map(|i| state.get_object(i).unwrap().id).collect();
+
+    let client = GraphServiceClient::connect(address).await?;
+
+    let get_targets = |order_by: EdgeOrder, limit| {
+        let request = Request::new(GetEdgesRequest {
+            object_id: ids[0],
+            edge_type: "follows".to_string(),
+            consistency: Some(ConsistencyRequirement {
+                requirement: Some(Requirement::FullConsistency(true)),
+            }),
+            metadata_filter: None,
+            order_by: order_by as i32,
+            limit,
+        })
+        .with_bearer_token(user_token);
+        let mut client = client.clone();
+        async move {
+            let response = client.get_edges(request?).await?.into_inner();
+            let targets: Vec<i64> = response.edges.iter().map(|edge| edge.to_id).collect();
+            anyhow::Ok(targets)
+        }
+    };
+
+    let targets = get_targets(EdgeOrder::CreatedAtAsc, 0).await?;
+    assert_eq!(targets, vec![ids[3], ids[1], ids[2]]);
+
+    // The most recent edges first, cut off at the limit
+    let targets = get_targets(EdgeOrder::CreatedAtDesc, 2).await?;
+    assert_eq!(targets, vec![ids[2], ids[1]]);
+
+    let targets = get_targets(EdgeOrder::CreatedAtAsc, 1).await?;
+    assert_eq!(targets, vec![ids[3]]);
+
+    let status = get_targets(EdgeOrder::Id, -1)
+        .await
+        .unwrap_err()
+        .downcast::<tonic::Status>()?;
+    assert_eq!(status.code(), tonic::Code::InvalidArgument);
+
+    Ok(())
+}
+
+/// Test that all edges of a relation leaving an object are deleted in one call
+#[tokio::test]
+async fn test_delete_edges_by() -> Result<()> {
+    let (address, _pool, _container) = crate::common::spawn_app().await?;
+    let mut builder = EntTestBuilder::new()
+        .with_basic_schema()
+        .with_user("owner")
+        .with_user("stranger");
+    for _ in 0..7 {
+        builder = builder.with_attributed_object(0, "test_type", json!({}));
+    }
+    for to in 1..=5 {
+        builder = builder.with_edge(0, 0, to, "member", json!({}));
+    }
+    builder = builder.with_edge(0, 0, 6, "admin", json!({}));
+
+    let state = builder.build(address.clone()).await?;
+    let owner_token = state.get_user_token(0).unwrap();
+    let stranger_token = state.get_user_token(1).unwrap();
+    let from_id = state.get_object(0).unwrap().id;
+
+    let mut client = GraphServiceClient::connect(address).await?;
+
+    let delete = |token| {
+        Request::new(DeleteEdgesByRequest {
+            from_id,
+            relation: "member".to_string(),
+        })
+        .with_bearer_token(token)
+    };
+
+    // Only the owner of the source object may delete its edges
+    let status = client
+        .delete_edges_by(delete(stranger_token)?)
+        .await
+        .unwrap_err();
+    assert_eq!(status.code(), tonic::Code::PermissionDenied);
+
+    let response = client
+        .delete_edges_by(delete(owner_token)?)
+        .await?
+        .into_inner();
+    assert_eq!(response.deleted_count, 5);
+    assert!(response.revision.is_some());
+
+    let get_edges = |relation: &str| {
+        Request::new(GetEdgesRequest {
+            object_id: from_id,
+            edge_type: relation.to_string(),
+            consistency: Some(ConsistencyRequirement {
+                requirement: Some(Requirement::FullConsistency(true)),
+            }),
+            metadata_filter: None,
+            ..Default::default()
+        })
+        .with_bearer_token(owner_token)
+    };
+
+    let remaining = client.get_edges(get_edges("member")?).await?.into_inner();
+    assert!(remaining.objects.is_empty());
+
+    // Edges of other relations are untouched
+    let admins = client.get_edges(get_edges("admin")?).await?.into_inner();
+    assert_eq!(admins.objects.len(), 1);
+
+    // Deleting again finds nothing left to delete
+    let response = client
+        .delete_edges_by(delete(owner_token)?)
+        .await?
+        .into_inner();
+    assert_eq!(response.deleted_count, 0);
+
+    Ok(())
+}
+
+/// Test that batch edge creation is atomic, reports the failing edge and needs
+/// write access to every source object
+#[tokio::test]
+async fn test_batch_create_edges() -> Result<()> {
+    let (address, _pool, _container) = crate::common::spawn_app().await?;
+    let state = EntTestBuilder::new()
+        .with_basic_schema()
+        .with_user("test_user")
+        .with_user("other_user")
+        .with_attributed_object(0, "test_type", json!({}))
+        .with_attributed_object(0, "test_type", json!({}))
+        .with_attributed_object(0, "test_type", json!({}))
+        .build(address.clone())
+        .await?;
+    let user_token = state.get_user_token(0).unwrap();
+    let other_token = state.get_user_token(1).unwrap();
+    let ids: Vec<i64> = (0..3).map(|i| state.get_object(i).unwrap().id).collect();
+    // Objects take the type name generated for the test schema
+    let object_type = state.get_object(0).unwrap().r#type.clone();
+
+    let mut client = GraphServiceClient::connect(address).await?;
+
+    let edge = |from_id, to_id, to_type: &str, relation: &str| CreateEdgeRequest {
+        from_id,
+        from_type: object_type.clone(),
+        to_id,
+        to_type: to_type.to_string(),
+        relation: relation.to_string(),
+        metadata: None,
+    };
+    let get_edges = |relation: &str| {
+        Request::new(GetEdgesRequest {
+            object_id: ids[0],
+            edge_type: relation.to_string(),
+            consistency: Some(ConsistencyRequirement {
+                requirement: Some(Requirement::FullConsistency(true)),
+            }),
+            metadata_filter: None,
+            ..Default::default()
+        })
+        .with_bearer_token(user_token)
+    };
+
+    let request = Request::new(BatchCreateEdgesRequest {
+        edges: vec![
+            edge(ids[0], ids[1], &object_type, "likes"),
+            edge(ids[0], ids[2], &object_type, "likes"),
+            edge(ids[1], ids[2], &object_type, "likes"),
+        ],
+        ..Default::default()
+    })
+    .with_bearer_token(user_token)?;
+    let response = client.batch_create_edges(request).await?.into_inner();
+    assert_eq!(response.edges.len(), 3);
+    assert!(response.edges.iter().all(|edge| edge.id > 0));
+    assert!(response.revision.is_some());
+
+    let likes = client.get_edges(get_edges("likes")?).await?.into_inner();
+    assert_eq!(likes.objects.len(), 2);
+
+    // A missing endpoint rolls back the whole batch
+    let request = Request::new(BatchCreateEdgesRequest {
+        edges: vec![
+            edge(ids[0], ids[1], &object_type, "blocks"),
+            edge(ids[0], i64::MAX, &object_type, "blocks"),
+        ],
+        ..Default::default()
+    })
+    .with_bearer_token(user_token)?;
+    let status = client.batch_create_edges(request).await.unwrap_err();
+    assert_eq!(status.code(), tonic::Code::InvalidArgument);
+    assert!(
+        status.message().starts_with("edge 1:"),
+        "{}",
+        status.message()
+    );
+
+    let blocks = client.get_edges(get_edges("blocks")?).await?.into_inner();
+    assert!(blocks.objects.is_empty());
+
+    // So does an endpoint whose type doesn't match
+    let request = Request::new(BatchCreateEdgesRequest {
+        edges: vec![edge(ids[0], ids[1], "other_type", "blocks")],
+        ..Default::default()
+    })
+    .with_bearer_token(user_token)?;
+    let status = client.batch_create_edges(request).await.unwrap_err();
+    assert_eq!(status.code(), tonic::Code::InvalidArgument);
+    assert!(
+        status.message().starts_with("edge 0:"),
+        "{}",
+        status.message()
+    );
+
+    // Someone else may not add edges to the user's objects, in a batch or not
+    let request = Request::new(BatchCreateEdgesRequest {
+        edges: vec![edge(ids[0], ids[1], &object_type, "blocks")],
+        ..Default::default()
+    })
+    .with_bearer_token(other_token)?;
+    let status = client.batch_create_edges(request).await.unwrap_err();
+    assert_eq!(status.code(), tonic::Code::PermissionDenied);
+    assert!(
+        status.message().starts_with("edge 0:"),
+        "{}",
+        status.message()
+    );
+
+    let request = Request::new(edge(ids[0], ids[1], &object_type, "blocks"))
+        .with_bearer_token(other_token)?;
+    let status = client.create_edge(request).await.unwrap_err();
+    assert_eq!(status.code(), tonic::Code::PermissionDenied);
+
+    let blocks = client.get_edges(get_edges("blocks")?).await?.into_inner();
+    assert!(blocks.objects.is_empty());
+
+    Ok(())
+}
+
+/// Test that batch object creation validates every object and is atomic
+#[tokio::test]
+async fn test_batch_create_objects() -> Result<()> {
+    let (address, _pool, _container) = crate::common::spawn_app().await?;
+    let schema = json!({
+        "type": "object",
+        "properties": { "name": { "type": "string" } },
+        "required": ["name"]
+    });
+    let state = EntTestBuilder::new()
+        .with_schema_and_type(schema.to_string(), "person")
+        .with_user("test_user")
+        .build(address.clone())
+        .await?;
+    let user_token = state.get_user_token(0).unwrap();
+
+    let mut client = GraphServiceClient::connect(address).await?;
+
+    let person = |metadata| CreateObjectRequest {
+        r#type: "person".to_string(),
+        metadata: json_to_protobuf_struct(metadata),
+    };
+
+    let request = Request::new(BatchCreateObjectsRequest {
+        objects: vec![
+            person(json!({"name": "Ada"})),
+            person(json!({"name": "Grace"})),
+        ],
+        ..Default::default()
+    })
+    .with_bearer_token(user_token)?;
+    let response = client.batch_create_objects(request).await?.into_inner();
+    assert_eq!(response.objects.len(), 2);
+    assert!(response.objects[0].id < response.objects[1].id);
+    assert!(response.revision.is_some());
+
+    // An invalid object rejects the whole batch and is named in the error
+    let request = Request::new(BatchCreateObjectsRequest {
+        objects: vec![person(json!({"name": "Edsger"})), person(json!({}))],
+        ..Default::default()
+    })
+    .with_bearer_token(user_token)?;
+    let status = client.batch_create_objects(request).await.unwrap_err();
+    assert_eq!(status.code(), tonic::Code::InvalidArgument);
+    assert!(
+        status.message().starts_with("object 1:"),
+        "{}",
+        status.message()
+    );
+
+    let request = Request::new(CountObjectsRequest {
+        r#type: "person".to_string(),
+        consistency: Some(ConsistencyRequirement {
+            requirement: Some(Requirement::FullConsistency(true)),
+        }),
+    })
+    .with_bearer_token(user_token)?;
+    assert_eq!(client.count_objects(request).await?.into_inner().count, 2);
+
+    Ok(())
+}
+
+/// Test that batches report which items they created, updated or skipped as
+/// idempotent replays
+#[tokio::test]
+async fn test_batch_counts() -> Result<()> {
+    let (address, _pool, _container) = crate::common::spawn_app().await?;
+    let schema = json!({
+        "type": "object",
+        "properties": {
+            "external_id": { "type": "string" },
+            "name": { "type": "string" }
+        },
+        "x-key": "external_id"
+    });
+    let state = EntTestBuilder::new()
+        .with_schema_and_type(schema.to_string(), "account")
+        .with_user("test_user")
+        .build(address.clone())
+        .await?;
+    let user_token = state.get_user_token(0).unwrap();
+
+    let mut client = GraphServiceClient::connect(address).await?;
+
+    let account = |external_id: &str, name: &str| CreateObjectRequest {
+        r#type: "account".to_string(),
+        metadata: json_to_protobuf_struct(json!({ "external_id": external_id, "name": name })),
+    };
+    let upsert = |objects| {
+        Request::new(BatchCreateObjectsRequest {
+            objects,
+            upsert: true,
+        })
+        .with_bearer_token(user_token)
+    };
+
+    let response = client
+        .batch_create_objects(upsert(vec![account("a", "Ada"), account("b", "Bob")])?)
+        .await?
+        .into_inner();
+    let counts = response.counts.unwrap();
+    assert_eq!((counts.created, counts.updated, counts.skipped), (2, 0, 0));
+    let ids: Vec<i64> = response.objects.iter().map(|object| object.id).collect();
+
+    // Replaying "a" changes nothing, while "b" has new metadata
+    let response = client
+        .batch_create_objects(upsert(vec![
+            account("a", "Ada"),
+            account("b", "Barbara"),
+            account("c", "Cyd"),
+        ])?)
+        .await?
+        .into_inner();
+    let counts = response.counts.unwrap();
+    assert_eq!((counts.created, counts.updated, counts.skipped), (1, 1, 1));
+    assert_eq!(response.objects[0].id, ids[0]);
+    assert_eq!(response.objects[1].id, ids[1]);
+    assert_eq!(
+        response.objects[1].metadata,
+        json_to_protobuf_struct(json!({ "external_id": "b", "name": "Barbara" }))
+    );
+    let c = response.objects[2].id;
+    assert!(!ids.contains(&c));
+
+    let edge = |to_id, metadata| CreateEdgeRequest {
+        from_id: ids[0],
+        from_type: "account".to_string(),
+        to_id,
+        to_type: "account".to_string(),
+        relation: "knows".to_string(),
+        metadata,
+    };
+    let batch = |edges| {
+        Request::new(BatchCreateEdgesRequest {
+            edges,
+            skip_existing: true,
+        })
+        .with_bearer_token(user_token)
+    };
+
+    let response = client
+        .batch_create_edges(batch(vec![edge(ids[1], None), edge(c, None)])?)
+        .await?
+        .into_inner();
+    let counts = response.counts.unwrap();
+    assert_eq!((counts.created, counts.updated, counts.skipped), (2, 0, 0));
+    let knows_b = response.edges[0].id;
+
+    // Only an edge with the same metadata counts as a replay
+    let response = client
+        .batch_create_edges(batch(vec![
+            edge(ids[1], None),
+            edge(ids[1], json_to_protobuf_struct(json!({ "since": 2020 }))),
+        ])?)
+        .await?
+        .into_inner();
+    let counts = response.counts.unwrap();
+    assert_eq!((counts.created, counts.updated, counts.skipped), (1, 0, 1));
+    assert_eq!(response.edges[0].id, knows_b);
+    assert_ne!(response.edges[1].id, knows_b);
+
+    Ok(())
+}
+
+/// Test that relations limited with `x-relations` reject edges beyond the limit
+#[tokio::test]
+async fn test_relation_cardinality() -> Result<()> {
+    let (address, _pool, _container) = crate::common::spawn_app().await?;
+    let state = EntTestBuilder::new()
+        .with_schema(
+            json!({
+                "type": "object",
+                "x-relations": { "parent": { "max": 1 } }
+            })
+            .to_string(),
+        )
+        .with_user("test_user")
+        .with_attributed_object(0, "test_type", json!({}))
+        .with_attributed_object(0, "test_type", json!({}))
+        .with_attributed_object(0, "test_type", json!({}))
+        .with_edge(0, 0, 1, "parent", json!({}))
+        .build(address.clone())
+        .await?;
+    let user_token = state.get_user_token(0).unwrap();
+    let ids: Vec<i64> = (0..3).map(|i| state.get_object(i).unwrap().id).collect();
+    let object_type = state.get_object(0).unwrap().r#type.clone();
+
+    let mut client = GraphServiceClient::connect(address).await?;
+
+    let edge = |to_id, relation: &str| CreateEdgeRequest {
+        from_id: ids[0],
+        from_type: object_type.clone(),
+        to_id,
+        to_type: object_type.clone(),
+        relation: relation.to_string(),
+        metadata: None,
+    };
+
+    // A second parent exceeds the declared limit
+    let request = Request::new(edge(ids[2], "parent")).with_bearer_token(user_token)?;
+    let status = client.create_edge(request).await.unwrap_err();
+    assert_eq!(status.code(), tonic::Code::FailedPrecondition);
+
+    let request = Request::new(GetEdgesRequest {
+        object_id: ids[0],
+        edge_type: "parent".to_string(),
+        consistency: Some(ConsistencyRequirement {
+            requirement: Some(Requirement::FullConsistency(true)),
+        }),
+        metadata_filter: None,
+        ..Default::default()
+    })
+    .with_bearer_token(user_token)?;
+    let parents = client.get_edges(request).await?.into_inner();
+    assert_eq!(parents.objects.len(), 1);
+
+    // Relations without a rule are unlimited
+    for to_id in [ids[1], ids[2]] {
+        let request = Request::new(edge(to_id, "sibling")).with_bearer_token(user_token)?;
+        client.create_edge(request).await?;
+    }
+
+    Ok(())
+}
+
+/// Test that relations declared unique with `x-relations` reject a second live
+/// edge between the same objects
+#[tokio::test]
+async fn test_unique_relation() -> Result<()> {
+    let (address, _pool, _container) = crate::common::spawn_app().await?;
+    let state = EntTestBuilder::new()
+        .with_schema(
+            json!({
+                "type": "object",
+                "x-relations": { "friend": { "unique": true } }
+            })
+            .to_string(),
+        )
+        .with_user("test_user")
+        .with_attributed_object(0, "test_type", json!({}))
+        .with_attributed_object(0, "test_type", json!({}))
+        .with_attributed_object(0, "test_type", json!({}))
+        .build(address.clone())
+        .await?;
+    let user_token = state.get_user_token(0).unwrap();
+    let ids: Vec<i64> = (0..3).map(|i| state.get_object(i).unwrap().id).collect();
+    let object_type = state.get_object(0).unwrap().r#type.clone();
+
+    let mut client = GraphServiceClient::connect(address).await?;
+
+    let edge = |to_id, relation: &str| CreateEdgeRequest {
+        from_id: ids[0],
+        from_type: object_type.clone(),
+        to_id,
+        to_type: object_type.clone(),
+        relation: relation.to_string(),
+        metadata: None,
+    };
+
+    let request = Request::new(edge(ids[1], "friend")).with_bearer_token(user_token)?;
+    let friend = client
+        .create_edge(request)
+        .await?
+        .into_inner()
+        .edge
+        .unwrap();
+
+    // The same triple again is a duplicate, another target is not
+    let request = Request::new(edge(ids[1], "friend")).with_bearer_token(user_token)?;
+    let status = client.create_edge(request).await.unwrap_err();
+    assert_eq!(status.code(), tonic::Code::AlreadyExists);
+
+    let request = Request::new(edge(ids[2], "friend")).with_bearer_token(user_token)?;
+    client.create_edge(request).await?;
+
+    // Duplicates within a batch are caught too
+    let request = Request::new(BatchCreateEdgesRequest {
+        edges: vec![edge(ids[0], "friend"), edge(ids[0], "friend")],
+        ..Default::default()
+    })
+    .with_bearer_token(user_token)?;
+    let status = client.batch_create_edges(request).await.unwrap_err();
+    assert_eq!(status.code(), tonic::Code::AlreadyExists);
+
+    // Only live edges count
+    let request =
+        Request::new(DeleteEdgeRequest { edge_id: friend.id }).with_bearer_token(user_token)?;
+    client.delete_edge(request).await?;
+    let request = Request::new(edge(ids[1], "friend")).with_bearer_token(user_token)?;
+    client.create_edge(request).await?;
+
+    // Relations without the rule keep allowing multi-edges
+    for _ in 0..2 {
+        let request = Request::new(edge(ids[1], "sibling")).with_bearer_token(user_token)?;
+        client.create_edge(request).await?;
+    }
+
+    Ok(())
+}
+
+/// Test that a type declaring `x-edges` only accepts the listed relations and
+/// target types
+#[tokio::test]
+async fn test_relation_allow_list() -> Result<()> {
+    let (address, _pool, _container) = crate::common::spawn_app().await?;
+    let document_type = format!("document_{}", uuid::Uuid::new_v4().simple());
+    let state = EntTestBuilder::new()
+        .with_schema_and_type(
+            json!({
+                "type": "object",
+                "x-edges": { "references": [document_type] }
+            })
+            .to_string(),
+            document_type.clone(),
+        )
+        .with_user("test_user")
+        .with_attributed_object(0, "document", json!({}))
+        .with_attributed_object(0, "document", json!({}))
+        .build(address.clone())
+        .await?;
+    let user_token = state.get_user_token(0).unwrap();
+    let ids: Vec<i64> = (0..2).map(|i| state.get_object(i).unwrap().id).collect();
+
+    let mut client = GraphServiceClient::connect(address).await?;
+
+    let comment_type = format!("comment_{}", uuid::Uuid::new_v4().simple());
+    let request = Request::new(CreateObjectRequest {
+        r#type: comment_type.clone(),
+        metadata: None,
+    })
+    .with_bearer_token(user_token)?;
+    let comment_id = client
+        .create_object(request)
+        .await?
+        .into_inner()
+        .object
+        .unwrap()
+        .id;
+
+    let edge = |to_id, to_type: &str, relation: &str| CreateEdgeRequest {
+        from_id: ids[0],
+        from_type: document_type.clone(),
+        to_id,
+        to_type: to_type.to_string(),
+        relation: relation.to_string(),
+        metadata: None,
+    };
+
+    let request =
+        Request::new(edge(ids[1], &document_type, "references")).with_bearer_token(user_token)?;
+    client.create_edge(request).await?;
+
+    // A relation the type doesn't list
+    let request =
+        Request::new(edge(ids[1], &document_type, "likes")).with_bearer_token(user_token)?;
+    let status = client.create_edge(request).await.unwrap_err();
+    assert_eq!(status.code(), tonic::Code::InvalidArgument);
+
+    // A listed relation to a type it doesn't list
+    let request = Request::new(edge(comment_id, &comment_type, "references"))
+        .with_bearer_token(user_token)?;
+    let status = client.create_edge(request).await.unwrap_err();
+    assert_eq!(status.code(), tonic::Code::InvalidArgument);
+    assert_eq!(
+        status.message(),
+        format!(
+            "type '{}' does not allow 'references' edges to type '{}'",
+            document_type, comment_type
+        )
+    );
+
+    // Types without `x-edges` accept any edge
+    let request = Request::new(CreateEdgeRequest {
+        from_id: comment_id,
+        from_type: comment_type.clone(),
+        to_id: ids[0],
+        to_type: document_type.clone(),
+        relation: "likes".to_string(),
+        metadata: None,
+    })
+    .with_bearer_token(user_token)?;
+    client.create_edge(request).await?;
+
+    Ok(())
+}
+
+/// Test that an edge to an object that doesn't exist, or no longer does, is a
+/// failed precondition naming the missing endpoint
+#[tokio::test]
+async fn test_create_edge_missing_endpoint() -> Result<()> {
+    let (address, _pool, _container) = crate::common::spawn_app().await?;
+    let state = EntTestBuilder::new()
+        .with_basic_schema()
+        .with_user("test_user")
+        .with_attributed_object(0, "test_type", json!({}))
+        .with_attributed_object(0, "test_type", json!({}))
+        .build(address.clone())
+        .await?;
+    let user_token = state.get_user_token(0).unwrap();
+    let from_id = state.get_object(0).unwrap().id;
+    let to_id = state.get_object(1).unwrap().id;
+    let type_name = state.get_object(0).unwrap().r#type.clone();
+
+    let mut client = GraphServiceClient::connect(address).await?;
+
+    let request = Request::new(CreateEdgeRequest {
+        from_id,
+        from_type: type_name.clone(),
+        to_id: i64::MAX,
+        to_type: type_name.clone(),
+        relation: "likes".to_string(),
+        metadata: None,
+    })
+    .with_bearer_token(user_token)?;
+    let status = client.create_edge(request).await.unwrap_err();
+    assert_eq!(status.code(), tonic::Code::FailedPrecondition);
+    assert_eq!(
+        status.message(),
+        format!("to object {} does not exist", i64::MAX)
+    );
+
+    // Deleted objects are gone as far as new edges are concerned
+    let request =
+        Request::new(DeleteObjectRequest { object_id: to_id }).with_bearer_token(user_token)?;
+    client.delete_object(request).await?;
+
+    let request = Request::new(CreateEdgeRequest {
+        from_id,
+        from_type: type_name.clone(),
+        to_id,
+        to_type: type_name.clone(),
+        relation: "likes".to_string(),
+        metadata: None,
+    })
+    .with_bearer_token(user_token)?;
+    let status = client.create_edge(request).await.unwrap_err();
+    assert_eq!(status.code(), tonic::Code::FailedPrecondition);
+    assert_eq!(
+        status.message(),
+        format!("to object {} does not exist", to_id)
+    );
+
+    Ok(())
+}
+
+/// Test that an edge's declared endpoint types must match the objects' types
+#[tokio::test]
+async fn test_create_edge_type_mismatch() -> Result<()> {
+    let (address, _pool, _container) = crate::common::spawn_app().await?;
+    let state = EntTestBuilder::new()
+        .with_basic_schema()
+        .with_user("test_user")
+        .with_attributed_object(0, "test_type", json!({}))
+        .with_attributed_object(0, "test_type", json!({}))
+        .build(address.clone())
+        .await?;
+    let user_token = state.get_user_token(0).unwrap();
+    let from_id = state.get_object(0).unwrap().id;
+    let to_id = state.get_object(1).unwrap().id;
+    let type_name = state.get_object(0).unwrap().r#type.clone();
+
+    let mut client = GraphServiceClient::connect(address).await?;
+
+    let edge = |from_type: &str, to_type: &str| CreateEdgeRequest {
+        from_id,
+        from_type: from_type.to_string(),
+        to_id,
+        to_type: to_type.to_string(),
+        relation: "likes".to_string(),
+        metadata: None,
+    };
+
+    let request = Request::new(edge(&type_name, "comment")).with_bearer_token(user_token)?;
+    let status = client.create_edge(request).await.unwrap_err();
+    assert_eq!(status.code(), tonic::Code::InvalidArgument);
+    assert_eq!(
+        status.message(),
+        format!(
+            "to object {} has type '{}', not 'comment'",
+            to_id, type_name
+        )
+    );
+
+    let request = Request::new(edge("user", &type_name)).with_bearer_token(user_token)?;
+    let status = client.create_edge(request).await.unwrap_err();
+    assert_eq!(status.code(), tonic::Code::InvalidArgument);
+
+    let request = Request::new(edge(&type_name, &type_name)).with_bearer_token(user_token)?;
+    client.create_edge(request).await?;
+
+    Ok(())
+}
+
+/// Test deleting single edges and objects along with their edges
+#[tokio::test]
+async fn test_delete_object_and_edge() -> Result<()> {
+    let (address, _pool, _container) = crate::common::spawn_app().await?;
+    let state = EntTestBuilder::new()
+        .with_basic_schema()
+        .with_user("owner")
+        .with_user("stranger")
+        .with_attributed_object(0, "test_type", json!({}))
+        .with_attributed_object(0, "test_type", json!({}))
+        .with_attributed_object(0, "test_type", json!({}))
+        .with_edge(0, 0, 1, "likes", json!({}))
+        .with_edge(0, 2, 0, "likes", json!({}))
+        .with_edge(0, 0, 2, "follows", json!({}))
+        .build(address.clone())
+        .await?;
+    let owner_token = state.get_user_token(0).unwrap();
+    let stranger_token = state.get_user_token(1).unwrap();
+    let ids: Vec<i64> = (0..3).map(|i| state.get_object(i).unwrap().id).collect();
+    let follows_id = state.get_edge(2).unwrap().id;
+
+    let mut client = GraphServiceClient::connect(address).await?;
+
+    let get_edges = |object_id, relation: &str| {
+        Request::new(GetEdgesRequest {
+            object_id,
+            edge_type: relation.to_string(),
+            consistency: Some(ConsistencyRequirement {
+                requirement: Some(Requirement::FullConsistency(true)),
+            }),
+            metadata_filter: None,
+            ..Default::default()
+        })
+        .with_bearer_token(owner_token)
+    };
+
+    // Only the owner of the source object may delete an edge
+    let delete_edge = |token| {
+        Request::new(DeleteEdgeRequest {
+            edge_id: follows_id,
+        })
+        .with_bearer_token(token)
+    };
+    let status = client
+        .delete_edge(delete_edge(stranger_token)?)
+        .await
+        .unwrap_err();
+    assert_eq!(status.code(), tonic::Code::PermissionDenied);
+
+    let response = client.delete_edge(delete_edge(owner_token)?).await?;
+    assert!(response.into_inner().revision.is_some());
+    let follows = client.get_edges(get_edges(ids[0], "follows")?).await?;
+    assert!(follows.into_inner().objects.is_empty());
+
+    let status = client
+        .delete_edge(delete_edge(owner_token)?)
+        .await
+        .unwrap_err();
+    assert_eq!(status.code(), tonic::Code::NotFound);
+
+    // Deleting an object also deletes the edges from and to it
+    let delete_object =
+        |token| Request::new(DeleteObjectRequest { object_id: ids[0] }).with_bearer_token(token);
+    let status = client
+        .delete_object(delete_object(stranger_token)?)
+        .await
+        .unwrap_err();
+    assert_eq!(status.code(), tonic::Code::PermissionDenied);
+
+    let response = client.delete_object(delete_object(owner_token)?).await?;
+    assert!(response.into_inner().revision.is_some());
+
+    let get_object = Request::new(GetObjectRequest {
+        object_id: ids[0],
+        consistency: Some(ConsistencyRequirement {
+            requirement: Some(Requirement::FullConsistency(true)),
+        }),
+        validate: false,
+    })
+    .with_bearer_token(owner_token)?;
+    let status = client.get_object(get_object).await.unwrap_err();
+    assert_eq!(status.code(), tonic::Code::NotFound);
+
+    let outgoing = client.get_edges(get_edges(ids[0], "likes")?).await?;
+    assert!(outgoing.into_inner().objects.is_empty());
+    let incoming = client.get_edges(get_edges(ids[2], "likes")?).await?;
+    assert!(incoming.into_inner().objects.is_empty());
+
+    let status = client
+        .delete_object(delete_object(owner_token)?)
+        .await
+        .unwrap_err();
+    assert_eq!(status.code(), tonic::Code::NotFound);
+
+    Ok(())
+}
+
+/// Test that reads with the default consistency don't see deleted objects and
+/// edges
+#[tokio::test]
+async fn test_delete_with_default_consistency() -> Result<()> {
+    let (address, _pool, _container) = crate::common::spawn_app().await?;
+    let state = EntTestBuilder::new()
+        .with_basic_schema()
+        .with_user("owner")
+        .with_attributed_object(0, "test_type", json!({}))
+        .with_attributed_object(0, "test_type", json!({}))
+        .with_attributed_object(0, "test_type", json!({}))
+        .with_edge(0, 0, 1, "likes", json!({}))
+        .with_edge(0, 0, 2, "likes", json!({}))
+        .build(address.clone())
+        .await?;
+    let owner_token = state.get_user_token(0).unwrap();
+    let ids: Vec<i64> = (0..3).map(|i| state.get_object(i).unwrap().id).collect();
+    let edge_ids: Vec<i64> = (0..2).map(|i| state.get_edge(i).unwrap().id).collect();
+
+    let mut client = GraphServiceClient::connect(address).await?;
+
+    let request = Request::new(DeleteEdgeRequest {
+        edge_id: edge_ids[0],
+    })
+    .with_bearer_token(owner_token)?;
+    client.delete_edge(request).await?;
+
+    let request = Request::new(GetEdgesRequest {
+        object_id: ids[0],
+        edge_type: "likes".to_string(),
+        ..Default::default()
+    })
+    .with_bearer_token(owner_token)?;
+    let edges = client.get_edges(request).await?.into_inner().edges;
+    assert_eq!(
+        edges.iter().map(|edge| edge.id).collect::<Vec<_>>(),
+        vec![edge_ids[1]]
+    );
+
+    let request = Request::new(GetEdgeRequest {
+        object_id: ids[0],
+        edge_type: "likes".to_string(),
+        consistency: None,
+    })
+    .with_bearer_token(owner_token)?;
+    let edge = client.get_edge(request).await?.into_inner().edge.unwrap();
+    assert_eq!(edge.id, edge_ids[1]);
+
+    let request = Request::new(GetIncomingEdgesRequest {
+        object_id: ids[1],
+        edge_type: "likes".to_string(),
+        consistency: None,
+    })
+    .with_bearer_token(owner_token)?;
+    let incoming = client.get_incoming_edges(request).await?.into_inner().edges;
+    assert!(incoming.is_empty());
+
+    let request = Request::new(GetRelatedObjectsRequest {
+        object_id: ids[0],
+        relation: "likes".to_string(),
+        consistency: None,
+    })
+    .with_bearer_token(owner_token)?;
+    let related = client
+        .get_related_objects(request)
+        .await?
+        .into_inner()
+        .objects;
+    assert_eq!(
+        related.iter().map(|object| object.id).collect::<Vec<_>>(),
+        vec![ids[2]]
+    );
+
+    let request = Request::new(TraverseRequest {
+        object_id: ids[0],
+        edge_type: "likes".to_string(),
+        max_depth: 5,
+        consistency: None,
+    })
+    .with_bearer_token(owner_token)?;
+    let reached = client.traverse(request).await?.into_inner().objects;
+    assert_eq!(
+        reached
+            .into_iter()
+            .map(|node| node.object.unwrap().id)
+            .collect::<Vec<_>>(),
+        vec![ids[2]]
+    );
+
+    let request = Request::new(ShortestPathRequest {
+        from_id: ids[0],
+        to_id: ids[1],
+        max_depth: 5,
+        consistency: None,
+    })
+    .with_bearer_token(owner_token)?;
+    assert!(!client.shortest_path(request).await?.into_inner().found);
+
+    // A deleted object is gone along with its edges
+    let request =
+        Request::new(DeleteObjectRequest { object_id: ids[2] }).with_bearer_token(owner_token)?;
+    client.delete_object(request).await?;
+
+    let request = Request::new(GetObjectRequest {
+        object_id: ids[2],
+        consistency: None,
+        validate: false,
+    })
+    .with_bearer_token(owner_token)?;
+    let status = client.get_object(request).await.unwrap_err();
+    assert_eq!(status.code(), tonic::Code::NotFound);
+
+    let request = Request::new(GetObjectsRequest {
+        object_ids: vec![ids[0], ids[2]],
+        consistency: None,
+    })
+    .with_bearer_token(owner_token)?;
+    let objects = client.get_objects(request).await?.into_inner().objects;
+    assert_eq!(
+        objects.iter().map(|object| object.id).collect::<Vec<_>>(),
+        vec![ids[0]]
+    );
+
+    // Repeating an id would leave the response ambiguous
+    let request = Request::new(GetObjectsRequest {
+        object_ids: vec![ids[0], ids[1], ids[0]],
+        consistency: None,
+    })
+    .with_bearer_token(owner_token)?;
+    let status = client.get_objects(request).await.unwrap_err();
+    assert_eq!(status.code(), tonic::Code::InvalidArgument);
+
+    let request = Request::new(GetEdgeRequest {
+        object_id: ids[0],
+        edge_type: "likes".to_string(),
+        consistency: None,
+    })
+    .with_bearer_token(owner_token)?;
+    let status = client.get_edge(request).await.unwrap_err();
+    assert_eq!(status.code(), tonic::Code::NotFound);
+
+    Ok(())
+}
+
+/// Test reading back the exact edge that was written, by ID
+#[tokio::test]
+async fn test_get_edge_by_id() -> Result<()> {
+    let (address, _pool, _container) = crate::common::spawn_app().await?;
+    let state = EntTestBuilder::new()
+        .with_basic_schema()
+        .with_user("owner")
+        .with_user("stranger")
+        .with_attributed_object(0, "test_type", json!({}))
+        .with_attributed_object(0, "test_type", json!({}))
+        .with_attributed_object(0, "test_type", json!({}))
+        .with_edge(0, 0, 1, "likes", json!({ "weight": 1 }))
+        .with_edge(0, 0, 2, "likes", json!({ "weight": 2 }))
+        .build(address.clone())
+        .await?;
+    let owner_token = state.get_user_token(0).unwrap();
+    let stranger_token = state.get_user_token(1).unwrap();
+    let second = state.get_edge(1).unwrap().clone();
+
+    let mut client = GraphServiceClient::connect(address).await?;
+
+    let get_edge = |edge_id, token| {
+        Request::new(GetEdgeByIdRequest {
+            edge_id,
+            consistency: Some(ConsistencyRequirement {
+                requirement: Some(Requirement::FullConsistency(true)),
+            }),
+        })
+        .with_bearer_token(token)
+    };
+
+    // The second of two edges with the same source and relation
+    let edge = client
+        .get_edge_by_id(get_edge(second.id, owner_token)?)
+        .await?
+        .into_inner()
+        .edge
+        .unwrap();
+    assert_eq!(edge.id, second.id);
+    assert_eq!(edge.to_id, second.to_id);
+    assert_eq!(
+        edge.metadata,
+        json_to_protobuf_struct(json!({ "weight": 2 }))
+    );
+
+    let request = Request::new(UpdateEdgeRequest {
+        edge_id: second.id,
+        metadata: json_to_protobuf_struct(json!({ "weight": 3 })),
+    })
+    .with_bearer_token(owner_token)?;
+    client.update_edge(request).await?;
+
+    let edge = client
+        .get_edge_by_id(get_edge(second.id, owner_token)?)
+        .await?
+        .into_inner()
+        .edge
+        .unwrap();
+    assert_eq!(
+        edge.metadata,
+        json_to_protobuf_struct(json!({ "weight": 3 }))
+    );
+
+    let status = client
+        .get_edge_by_id(get_edge(second.id, stranger_token)?)
+        .await
+        .unwrap_err();
+    assert_eq!(status.code(), tonic::Code::PermissionDenied);
+
+    let request =
+        Request::new(DeleteEdgeRequest { edge_id: second.id }).with_bearer_token(owner_token)?;
+    client.delete_edge(request).await?;
+
+    let status = client
+        .get_edge_by_id(get_edge(second.id, owner_token)?)
+        .await
+        .unwrap_err();
+    assert_eq!(status.code(), tonic::Code::NotFound);
+
+    Ok(())
+}
+
+/// Test that an object's relations are listed with counts of their live edges
+#[tokio::test]
+async fn test_list_relations() -> Result<()> {
+    let (address, _pool, _container) = crate::common::spawn_app().await?;
+    let state = EntTestBuilder::new()
+        .with_basic_schema()
+        .with_user("owner")
+        .with_user("stranger")
+        .with_attributed_object(0, "test_type", json!({}))
+        .with_attributed_object(0, "test_type", json!({}))
+        .with_attributed_object(0, "test_type", json!({}))
+        .with_edge(0, 0, 1, "likes", json!({}))
+        .with_edge(0, 0, 2, "likes", json!({}))
+        .with_edge(0, 0, 1, "follows", json!({}))
+        .with_edge(0, 1, 2, "blocks", json!({}))
+        .build(address.clone())
+        .await?;
+    let owner_token = state.get_user_token(0).unwrap();
+    let stranger_token = state.get_user_token(1).unwrap();
+    let from_id = state.get_object(0).unwrap().id;
+
+    let mut client = GraphServiceClient::connect(address).await?;
+
+    let list_relations = |token| {
+        Request::new(ListRelationsRequest {
+            object_id: from_id,
+            consistency: Some(ConsistencyRequirement {
+                requirement: Some(Requirement::FullConsistency(true)),
+            }),
+        })
+        .with_bearer_token(token)
+    };
+    let counts = |relations: Vec<RelationCount>| {
+        relations
+            .into_iter()
+            .map(|r| (r.relation, r.count))
+            .collect::<Vec<_>>()
+    };
+
+    let relations = client
+        .list_relations(list_relations(owner_token)?)
+        .await?
+        .into_inner()
+        .relations;
+    assert_eq!(
+        counts(relations),
+        vec![("follows".to_string(), 1), ("likes".to_string(), 2)]
+    );
+
+    // Deleted edges no longer count, and a relation without edges disappears
+    for index in [1, 2] {
+        let request = Request::new(DeleteEdgeRequest {
+            edge_id: state.get_edge(index).unwrap().id,
+        })
+        .with_bearer_token(owner_token)?;
+        client.delete_edge(request).await?;
+    }
+    let relations = client
+        .list_relations(list_relations(owner_token)?)
+        .await?
+        .into_inner()
+        .relations;
+    assert_eq!(counts(relations), vec![("likes".to_string(), 1)]);
+
+    let status = client
+        .list_relations(list_relations(stranger_token)?)
+        .await
+        .unwrap_err();
+    assert_eq!(status.code(), tonic::Code::PermissionDenied);
+
+    Ok(())
+}
+
+/// Test that related objects come back with their metadata and skip deleted edges
+/// and objects the caller can't read
+#[tokio::test]
+async fn test_get_related_objects() -> Result<()> {
+    let (address, _pool, _container) = crate::common::spawn_app().await?;
+    let state = EntTestBuilder::new()
+        .with_basic_schema()
+        .with_user("test_user")
+        .with_user("other_user")
+        .with_user("editor")
+        .with_attributed_object(0, "test_type", json!({}))
+        .with_attributed_object(0, "test_type", json!({"name": "first"}))
+        .with_attributed_object(0, "test_type", json!({"name": "second"}))
+        .with_attributed_object(1, "test_type", json!({"name": "foreign"}))
+        // Linked by a user who can read both ends
+        .with_share(0, 2, Permission::Write)
+        .with_share(3, 2, Permission::Read)
+        .with_edge(0, 0, 1, "likes", json!({}))
+        .with_edge(0, 0, 2, "likes", json!({}))
+        .with_edge(0, 0, 2, "follows", json!({}))
+        .with_edge(2, 0, 3, "likes", json!({}))
+        .build(address.clone())
+        .await?;
+    let user_token = state.get_user_token(0).unwrap();
+    let other_token = state.get_user_token(1).unwrap();
+    let ids: Vec<i64> = (0..3).map(|i| state.get_object(i).unwrap().id).collect();
+
+    let mut client = GraphServiceClient::connect(address).await?;
+
+    let get_related = || {
+        Request::new(GetRelatedObjectsRequest {
+            object_id: ids[0],
+            relation: "likes".to_string(),
+            consistency: Some(ConsistencyRequirement {
+                requirement: Some(Requirement::FullConsistency(true)),
+            }),
+        })
+    };
+
+    // Only the owner of the object may follow its relations
+    let status = client.get_related_objects(get_related()).await.unwrap_err();
+    assert_eq!(status.code(), tonic::Code::Unauthenticated);
+    let status = client
+        .get_related_objects(get_related().with_bearer_token(other_token)?)
+        .await
+        .unwrap_err();
+    assert_eq!(status.code(), tonic::Code::PermissionDenied);
+
+    // The other user's object is left out
+    let objects = client
+        .get_related_objects(get_related().with_bearer_token(user_token)?)
+        .await?
+        .into_inner()
+        .objects;
+    assert_eq!(
+        objects.iter().map(|o| o.id).collect::<Vec<_>>(),
+        vec![ids[1], ids[2]]
+    );
+    assert_eq!(
+        objects[0].metadata,
+        json_to_protobuf_struct(json!({"name": "first"}))
+    );
+
+    let request = Request::new(DeleteEdgeRequest {
+        edge_id: state.get_edge(0).unwrap().id,
+    })
+    .with_bearer_token(user_token)?;
+    client.delete_edge(request).await?;
+
+    let objects = client
+        .get_related_objects(get_related().with_bearer_token(user_token)?)
+        .await?
+        .into_inner()
+        .objects;
+    assert_eq!(
+        objects.iter().map(|o| o.id).collect::<Vec<_>>(),
+        vec![ids[2]]
+    );
+
+    Ok(())
+}
+
+/// Test that GetEdge needs read access to the source and hides an edge whose
+/// target the caller can't read
+#[tokio::test]
+async fn test_get_edge_access() -> Result<()> {
+    let (address, _pool, _container) = crate::common::spawn_app().await?;
+    let state = EntTestBuilder::new()
+        .with_basic_schema()
+        .with_user("owner")
+        .with_user("stranger")
+        .with_user("editor")
+        .with_attributed_object(0, "test_type", json!({}))
+        .with_attributed_object(0, "test_type", json!({}))
+        .with_attributed_object(1, "test_type", json!({}))
+        .with_share(0, 2, Permission::Write)
+        .with_share(2, 2, Permission::Read)
+        .with_edge(0, 0, 1, "likes", json!({}))
+        .with_edge(2, 0, 2, "follows", json!({}))
+        .build(address.clone())
+        .await?;
+    let owner_token = state.get_user_token(0).unwrap();
+    let stranger_token = state.get_user_token(1).unwrap();
+    let ids: Vec<i64> = (0..3).map(|i| state.get_object(i).unwrap().id).collect();
+
+    let mut client = GraphServiceClient::connect(address).await?;
+
+    let get_edge = |edge_type: &str| {
+        Request::new(GetEdgeRequest {
+            object_id: ids[0],
+            edge_type: edge_type.to_string(),
+            consistency: None,
+        })
+    };
+
+    let status = client.get_edge(get_edge("likes")).await.unwrap_err();
+    assert_eq!(status.code(), tonic::Code::Unauthenticated);
+    let status = client
+        .get_edge(get_edge("likes").with_bearer_token(stranger_token)?)
+        .await
+        .unwrap_err();
+    assert_eq!(status.code(), tonic::Code::PermissionDenied);
+
+    let response = client
+        .get_edge(get_edge("likes").with_bearer_token(owner_token)?)
+        .await?
+        .into_inner();
+    assert_eq!(response.object.unwrap().id, ids[1]);
+
+    // The editor's edge leads to an object only they and its owner can read
+    let status = client
+        .get_edge(get_edge("follows").with_bearer_token(owner_token)?)
+        .await
+        .unwrap_err();
+    assert_eq!(status.code(), tonic::Code::NotFound);
+
+    Ok(())
+}
+
+/// Test that an export pins a revision and streams the caller's objects then edges
+#[tokio::test]
+async fn test_export_graph() -> Result<()> {
+    let (address, _pool, _container) = crate::common::spawn_app().await?;
+    let state = EntTestBuilder::new()
+        .with_basic_schema()
+        .with_user("test_user")
+        .with_user("other_user")
+        .with_attributed_object(0, "test_type", json!({}))
+        .with_attributed_object(0, "test_type", json!({}))
+        .with_attributed_object(1, "test_type", json!({}))
+        .with_edge(0, 0, 1, "references", json!({"weight": 1}))
+        .build(address.clone())
+        .await?;
+    let user_token = state.get_user_token(0).unwrap();
+
+    let mut client = GraphServiceClient::connect(address).await?;
+    let request = Request::new(ExportGraphRequest {}).with_bearer_token(user_token)?;
+    let mut stream = client.export_graph(request).await?.into_inner();
+
+    let first = stream.message().await?.unwrap();
+    assert!(matches!(first.record, Some(Record::Revision(_))));
+
+    // Writes after the export started are not part of it
+    let request = Request::new(CreateObjectRequest {
+        r#type: "test_type".to_string(),
+        metadata: json_to_protobuf_struct(json!({})),
+    })
+    .with_bearer_token(user_token)?;
+    client.create_object(request).await?;
+
+    let mut records = Vec::new();
+    while let Some(response) = stream.message().await? {
+        records.push(response.record.unwrap());
+    }
+
+    let expected_objects = [
+        state.get_object(0).unwrap().id,
+        state.get_object(1).unwrap().id,
+    ];
+    assert_eq!(records.len(), 3);
+    for (record, expected_id) in records.iter().zip(expected_objects) {
+        let Record::Object(object) = record else {
+            panic!("expected an object, got {:?}", record);
+        };
+        assert_eq!(object.id, expected_id);
+    }
+    let Record::Edge(edge) = &records[2] else {
+        panic!("expected an edge, got {:?}", records[2]);
+    };
+    assert_eq!(edge.from_id, expected_objects[0]);
+    assert_eq!(edge.to_id, expected_objects[1]);
+    assert_eq!(edge.relation, "references");
+
+    Ok(())
+}
@@ -1,3 +1,6 @@
 pub mod access_control_tests;
 pub mod complex_tests;
+pub mod edge_tests;
 pub mod mvcc_tests;
+pub mod not_found_tests;
+pub mod pool_tests;
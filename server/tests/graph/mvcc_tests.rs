@@ -2,10 +2,14 @@ use crate::test_helper::{json_to_protobuf_struct, EntTestBuilder};
 use anyhow::Result;
 use ent_proto::ent::{
     consistency_requirement::Requirement, graph_service_client::GraphServiceClient,
-    ConsistencyRequirement, GetEdgeRequest, GetObjectRequest, UpdateEdgeRequest,
-    UpdateObjectRequest,
+    graph_service_server::GraphService, ConsistencyRequirement, CreateObjectRequest,
+    GetEdgeRequest, GetObjectHistoryRequest, GetObjectRequest, UpdateEdgeRequest,
+    UpdateObjectRequest, UpsertObjectRequest,
 };
 use ent_server::auth::RequestExt;
+use ent_server::db::transaction::Revision;
+use ent_server::server::SNAPSHOT_HEADER;
+use ent_server::GraphServer;
 use serde_json::json;
 use tonic::Request;
 
@@ -23,7 +27,7 @@ async fn test_concurrent_transaction_visibility() -> Result<()> {
 
     let object = state.get_object(0).unwrap();
     let object_id = object.id;
-    let _initial_revision = state.objects[0].revision.clone();
+    let initial_revision = state.objects[0].revision.clone();
 
     let mut client = GraphServiceClient::connect(address).await?;
 
@@ -36,12 +40,20 @@ async fn test_concurrent_transaction_visibility() -> Result<()> {
     let update_req = Request::new(UpdateObjectRequest {
         object_id,
         metadata: Some(metadata),
+        merge_patch: false,
+        expected_revision: None,
     })
     .with_bearer_token(user_token)?;
 
     let update_resp = client.update_object(update_req).await?;
     let updated_revision = update_resp.get_ref().revision.as_ref().unwrap().clone();
 
+    // The update's revision includes the creation and more
+    let initial = Revision::from_zookie(initial_revision)?;
+    let updated = Revision::from_zookie(updated_revision.clone())?;
+    assert!(updated.is_fresher_than(&initial));
+    assert_ne!(updated, initial);
+
     println!(
         "After update - Updated object metadata: {:?}",
         update_resp.get_ref().object.as_ref().unwrap().metadata
@@ -53,6 +65,7 @@ async fn test_concurrent_transaction_visibility() -> Result<()> {
         consistency: Some(ConsistencyRequirement {
             requirement: Some(Requirement::ExactlyAt(updated_revision)),
         }),
+        validate: false,
     })
     .with_bearer_token(user_token)?;
 
@@ -176,3 +189,454 @@ async fn test_edge_snapshot_isolation() -> Result<()> {
 
     Ok(())
 }
+
+/// Test that object history lists every metadata version with a usable revision
+#[tokio::test]
+async fn test_object_history() -> Result<()> {
+    let (address, _pool, _container) = crate::common::spawn_app().await?;
+    let builder = EntTestBuilder::new()
+        .with_basic_schema()
+        .with_user("test_user")
+        .with_attributed_object(0, "test_type", json!({ "version": "1" }));
+
+    let state = builder.build(address.clone()).await?;
+    let user_token = state.get_user_token(0).unwrap();
+    let object_id = state.get_object(0).unwrap().id;
+
+    let mut client = GraphServiceClient::connect(address).await?;
+
+    for version in ["2", "3"] {
+        let update_req = Request::new(UpdateObjectRequest {
+            object_id,
+            metadata: json_to_protobuf_struct(json!({ "version": version })),
+            merge_patch: false,
+            expected_revision: None,
+        })
+        .with_bearer_token(user_token)?;
+        client.update_object(update_req).await?;
+    }
+
+    let history_req =
+        Request::new(GetObjectHistoryRequest { object_id }).with_bearer_token(user_token)?;
+    let versions = client
+        .get_object_history(history_req)
+        .await?
+        .into_inner()
+        .versions;
+
+    let version_of = |metadata: Option<&prost_types::Struct>| {
+        metadata
+            .unwrap()
+            .fields
+            .get("version")
+            .unwrap()
+            .kind
+            .clone()
+    };
+
+    assert_eq!(versions.len(), 3);
+    for (i, version) in versions.iter().enumerate() {
+        assert_eq!(
+            version_of(version.metadata.as_ref()),
+            Some(prost_types::value::Kind::StringValue((i + 1).to_string()))
+        );
+    }
+    // Each version is replaced by the next one; only the last is current
+    assert_eq!(versions[0].deleted_xid, versions[1].created_xid);
+    assert_eq!(versions[1].deleted_xid, versions[2].created_xid);
+    assert_eq!(versions[2].deleted_xid, 0);
+
+    // Reading at a version's revision shows that version
+    let get_req = Request::new(GetObjectRequest {
+        object_id,
+        consistency: Some(ConsistencyRequirement {
+            requirement: Some(Requirement::ExactlyAt(
+                versions[1].revision.clone().unwrap(),
+            )),
+        }),
+        validate: false,
+    })
+    .with_bearer_token(user_token)?;
+    let object = client
+        .get_object(get_req)
+        .await?
+        .into_inner()
+        .object
+        .unwrap();
+    assert_eq!(
+        version_of(object.metadata.as_ref()),
+        Some(prost_types::value::Kind::StringValue("2".to_string()))
+    );
+
+    Ok(())
+}
+
+/// AtLeastAsFresh serves the current head once it has caught up with the
+/// requested revision, while ExactlyAt stays pinned to it
+#[tokio::test]
+async fn test_at_least_as_fresh_reads_head() -> Result<()> {
+    let (address, _pool, _container) = crate::common::spawn_app().await?;
+    let builder = EntTestBuilder::new()
+        .with_basic_schema()
+        .with_user("test_user")
+        .with_attributed_object(0, "test_type", json!({ "version": "1" }))
+        // Moves the transaction horizon past the first object's revision
+        .with_attributed_object(0, "test_type", json!({}));
+
+    let state = builder.build(address.clone()).await?;
+    let user_token = state.get_user_token(0).unwrap();
+    let object_id = state.get_object(0).unwrap().id;
+    let initial_revision = state.objects[0].revision.clone();
+
+    let mut client = GraphServiceClient::connect(address).await?;
+
+    let update_req = Request::new(UpdateObjectRequest {
+        object_id,
+        metadata: json_to_protobuf_struct(json!({ "version": "2" })),
+        merge_patch: false,
+        expected_revision: None,
+    })
+    .with_bearer_token(user_token)?;
+    client.update_object(update_req).await?;
+
+    let version_of = |object: Option<ent_proto::ent::Object>| {
+        object
+            .unwrap()
+            .metadata
+            .unwrap()
+            .fields
+            .get("version")
+            .unwrap()
+            .kind
+            .clone()
+    };
+
+    let exactly_at_req = Request::new(GetObjectRequest {
+        object_id,
+        consistency: Some(ConsistencyRequirement {
+            requirement: Some(Requirement::ExactlyAt(initial_revision.clone())),
+        }),
+        validate: false,
+    })
+    .with_bearer_token(user_token)?;
+    let object = client.get_object(exactly_at_req).await?.into_inner().object;
+    assert_eq!(
+        version_of(object),
+        Some(prost_types::value::Kind::StringValue("1".to_string()))
+    );
+
+    let at_least_as_fresh_req = Request::new(GetObjectRequest {
+        object_id,
+        consistency: Some(ConsistencyRequirement {
+            requirement: Some(Requirement::AtLeastAsFresh(initial_revision)),
+        }),
+        validate: false,
+    })
+    .with_bearer_token(user_token)?;
+    let object = client
+        .get_object(at_least_as_fresh_req)
+        .await?
+        .into_inner()
+        .object;
+    assert_eq!(
+        version_of(object),
+        Some(prost_types::value::Kind::StringValue("2".to_string()))
+    );
+
+    Ok(())
+}
+
+/// Test that reads at a timestamp see the latest transaction at or before it
+#[tokio::test]
+async fn test_read_at_timestamp() -> Result<()> {
+    let (address, pool, _container) = crate::common::spawn_app().await?;
+    let builder = EntTestBuilder::new()
+        .with_basic_schema()
+        .with_user("test_user")
+        .with_attributed_object(0, "test_type", json!({ "version": "1" }));
+
+    let state = builder.build(address.clone()).await?;
+    let user_token = state.get_user_token(0).unwrap();
+    let object = state.get_object(0).unwrap();
+    let object_id = object.id;
+
+    // Use the database clock, which stamps the transactions
+    let before_update: time::OffsetDateTime =
+        sqlx::query_scalar("SELECT now()").fetch_one(&pool).await?;
+
+    let mut client = GraphServiceClient::connect(address).await?;
+
+    // Moves the transaction horizon past the first object's revision
+    let filler_req = Request::new(CreateObjectRequest {
+        r#type: object.r#type.clone(),
+        metadata: json_to_protobuf_struct(json!({})),
+    })
+    .with_bearer_token(user_token)?;
+    client.create_object(filler_req).await?;
+
+    let update_req = Request::new(UpdateObjectRequest {
+        object_id,
+        metadata: json_to_protobuf_struct(json!({ "version": "2" })),
+        merge_patch: false,
+        expected_revision: None,
+    })
+    .with_bearer_token(user_token)?;
+    client.update_object(update_req).await?;
+
+    let get_at = |at: time::OffsetDateTime| {
+        Request::new(GetObjectRequest {
+            object_id,
+            consistency: Some(ConsistencyRequirement {
+                requirement: Some(Requirement::AtTimestamp(prost_types::Timestamp {
+                    seconds: at.unix_timestamp(),
+                    nanos: at.nanosecond() as i32,
+                })),
+            }),
+            validate: false,
+        })
+        .with_bearer_token(user_token)
+    };
+    let version_of = |object: Option<ent_proto::ent::Object>| {
+        object
+            .unwrap()
+            .metadata
+            .unwrap()
+            .fields
+            .get("version")
+            .unwrap()
+            .kind
+            .clone()
+    };
+
+    let object = client.get_object(get_at(before_update)?).await?;
+    assert_eq!(
+        version_of(object.into_inner().object),
+        Some(prost_types::value::Kind::StringValue("1".to_string()))
+    );
+
+    let now: time::OffsetDateTime = sqlx::query_scalar("SELECT now()").fetch_one(&pool).await?;
+    let object = client.get_object(get_at(now)?).await?;
+    assert_eq!(
+        version_of(object.into_inner().object),
+        Some(prost_types::value::Kind::StringValue("2".to_string()))
+    );
+
+    // Nothing had happened yet at the epoch
+    let status = client
+        .get_object(get_at(time::OffsetDateTime::UNIX_EPOCH)?)
+        .await
+        .unwrap_err();
+    assert_eq!(status.code(), tonic::Code::InvalidArgument);
+
+    Ok(())
+}
+
+/// Test that an update conditioned on a stale revision is rejected
+#[tokio::test]
+async fn test_update_with_expected_revision() -> Result<()> {
+    let (address, _pool, _container) = crate::common::spawn_app().await?;
+    let state = EntTestBuilder::new()
+        .with_basic_schema()
+        .with_user("test_user_1")
+        .with_attributed_object(0, "test_type", json!({}))
+        .build(address.clone())
+        .await?;
+    let user_token = state.get_user_token(0).unwrap();
+    let object_id = state.get_object(0).unwrap().id;
+    let created_revision = state.objects[0].revision.clone();
+
+    let mut client = GraphServiceClient::connect(address).await?;
+    let update = |name: &str, expected_revision| {
+        Request::new(UpdateObjectRequest {
+            object_id,
+            metadata: json_to_protobuf_struct(json!({ "name": name })),
+            merge_patch: false,
+            expected_revision: Some(expected_revision),
+        })
+        .with_bearer_token(user_token)
+    };
+
+    let first = client
+        .update_object(update("first", created_revision.clone())?)
+        .await?
+        .into_inner();
+
+    // A second writer that read the object before the first update loses
+    let status = client
+        .update_object(update("second", created_revision)?)
+        .await
+        .unwrap_err();
+    assert_eq!(status.code(), tonic::Code::Aborted);
+    assert!(status.message().contains("revision conflict"));
+
+    // Retrying against the latest revision succeeds
+    let retried = client
+        .update_object(update("second", first.revision.unwrap())?)
+        .await?
+        .into_inner();
+    assert_eq!(
+        retried.object.unwrap().metadata,
+        json_to_protobuf_struct(json!({ "name": "second" }))
+    );
+
+    Ok(())
+}
+
+/// Test that upserts create an object once per key and update it afterwards,
+/// even when racing
+#[tokio::test]
+async fn test_upsert_object() -> Result<()> {
+    let (address, _pool, _container) = crate::common::spawn_app().await?;
+    let type_name = format!("account_{}", uuid::Uuid::new_v4().simple());
+    let state = EntTestBuilder::new()
+        .with_schema_and_type(
+            json!({
+                "type": "object",
+                "properties": {
+                    "external_id": { "type": "string" },
+                    "name": { "type": "string" }
+                },
+                "x-key": "external_id"
+            })
+            .to_string(),
+            type_name.clone(),
+        )
+        .with_user("owner")
+        .with_user("stranger")
+        .build(address.clone())
+        .await?;
+    let owner_token = state.get_user_token(0).unwrap().to_string();
+    let stranger_token = state.get_user_token(1).unwrap();
+
+    let client = GraphServiceClient::connect(address).await?;
+    let upsert = |type_name: &str, metadata, token: &str| {
+        Request::new(UpsertObjectRequest {
+            r#type: type_name.to_string(),
+            metadata: json_to_protobuf_struct(metadata),
+        })
+        .with_bearer_token(token)
+    };
+
+    // Concurrent upserts of a new key create exactly one object
+    let mut racers = Vec::new();
+    for i in 0..5 {
+        let mut client = client.clone();
+        let request = upsert(
+            &type_name,
+            json!({ "external_id": "acme", "name": format!("racer {}", i) }),
+            &owner_token,
+        )?;
+        racers.push(tokio::spawn(async move {
+            client.upsert_object(request).await.map(|r| r.into_inner())
+        }));
+    }
+    let mut ids = Vec::new();
+    let mut created = 0;
+    for racer in racers {
+        let response = racer.await??;
+        created += response.created as usize;
+        ids.push(response.object.unwrap().id);
+    }
+    assert_eq!(created, 1);
+    ids.dedup();
+    assert_eq!(ids.len(), 1);
+
+    let mut client = client.clone();
+    let response = client
+        .upsert_object(upsert(
+            &type_name,
+            json!({ "external_id": "acme", "name": "Acme" }),
+            &owner_token,
+        )?)
+        .await?
+        .into_inner();
+    assert!(!response.created);
+    let object = response.object.unwrap();
+    assert_eq!(object.id, ids[0]);
+    assert_eq!(
+        object.metadata,
+        json_to_protobuf_struct(json!({ "external_id": "acme", "name": "Acme" }))
+    );
+
+    // Someone else's object isn't taken over
+    let status = client
+        .upsert_object(upsert(
+            &type_name,
+            json!({ "external_id": "acme" }),
+            stranger_token,
+        )?)
+        .await
+        .unwrap_err();
+    assert_eq!(status.code(), tonic::Code::PermissionDenied);
+
+    let status = client
+        .upsert_object(upsert(&type_name, json!({ "name": "Acme" }), &owner_token)?)
+        .await
+        .unwrap_err();
+    assert_eq!(status.code(), tonic::Code::InvalidArgument);
+
+    let status = client
+        .upsert_object(upsert(
+            &format!("unkeyed_{}", uuid::Uuid::new_v4().simple()),
+            json!({ "external_id": "acme" }),
+            &owner_token,
+        )?)
+        .await
+        .unwrap_err();
+    assert_eq!(status.code(), tonic::Code::FailedPrecondition);
+
+    Ok(())
+}
+
+/// Test that reads report the snapshot they saw only when asked to
+#[tokio::test]
+async fn test_snapshot_header() -> Result<()> {
+    let (address, pool, _container) = crate::common::spawn_app().await?;
+    let state = EntTestBuilder::new()
+        .with_basic_schema()
+        .with_user("test_user")
+        .with_attributed_object(0, "test_type", json!({}))
+        .build(address)
+        .await?;
+    let user_token = state.get_user_token(0).unwrap();
+    let object_id = state.get_object(0).unwrap().id;
+    let revision = state.objects[0].revision.clone();
+
+    let get_object = |requirement| {
+        Request::new(GetObjectRequest {
+            object_id,
+            consistency: Some(ConsistencyRequirement {
+                requirement: Some(requirement),
+            }),
+            validate: false,
+        })
+        .with_bearer_token(user_token)
+    };
+
+    let server = GraphServer::new(pool.clone()).with_snapshot_header(true);
+
+    // A pinned read reports the revision's snapshot, its own write included
+    let response = server
+        .get_object(get_object(Requirement::ExactlyAt(revision.clone()))?)
+        .await?;
+    let expected = Revision::from_zookie(revision)?.included().to_string();
+    assert_eq!(
+        response.metadata().get(SNAPSHOT_HEADER).unwrap().to_str()?,
+        expected
+    );
+
+    // A read of the latest data reports a well-formed current snapshot
+    let response = server
+        .get_object(get_object(Requirement::FullConsistency(true))?)
+        .await?;
+    let snapshot = response.metadata().get(SNAPSHOT_HEADER).unwrap().to_str()?;
+    assert_eq!(snapshot.split(':').count(), 3);
+
+    let server = GraphServer::new(pool);
+    let response = server
+        .get_object(get_object(Requirement::FullConsistency(true))?)
+        .await?;
+    assert!(response.metadata().get(SNAPSHOT_HEADER).is_none());
+
+    Ok(())
+}
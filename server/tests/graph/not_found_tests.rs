@@ -0,0 +1,115 @@
+use crate::test_helper::EntTestBuilder;
+use anyhow::Result;
+use ent_proto::ent::{
+    consistency_requirement::Requirement, graph_service_client::GraphServiceClient,
+    object_not_found_details::Reason, ConsistencyRequirement, GetObjectRequest,
+    ObjectNotFoundDetails,
+};
+use ent_server::auth::RequestExt;
+use prost::Message;
+use serde_json::json;
+use tonic::{Code, Request, Status};
+
+fn miss_reason(status: &Status) -> Reason {
+    assert_eq!(status.code(), Code::NotFound);
+    let details = ObjectNotFoundDetails::decode(status.details()).unwrap();
+    details.reason()
+}
+
+/// Test that a GetObject miss for an unknown ID reports that it never existed
+#[tokio::test]
+async fn test_get_object_never_existed() -> Result<()> {
+    let (address, _pool, _container) = crate::common::spawn_app().await?;
+    let state = EntTestBuilder::new()
+        .with_basic_schema()
+        .with_user("test_user")
+        .build(address.clone())
+        .await?;
+    let user_token = state.get_user_token(0).unwrap();
+
+    let mut client = GraphServiceClient::connect(address).await?;
+
+    let request = Request::new(GetObjectRequest {
+        object_id: i64::MAX,
+        consistency: None,
+        validate: false,
+    })
+    .with_bearer_token(user_token)?;
+
+    let status = client.get_object(request).await.unwrap_err();
+    assert_eq!(miss_reason(&status), Reason::NeverExisted);
+
+    Ok(())
+}
+
+/// Test that a GetObject miss for a soft-deleted object reports the deletion
+#[tokio::test]
+async fn test_get_object_deleted() -> Result<()> {
+    let (address, pool, _container) = crate::common::spawn_app().await?;
+    let state = EntTestBuilder::new()
+        .with_basic_schema()
+        .with_user("test_user")
+        .with_attributed_object(0, "test_type", json!({}))
+        .build(address.clone())
+        .await?;
+    let user_token = state.get_user_token(0).unwrap();
+    let object_id = state.get_object(0).unwrap().id;
+
+    // There is no delete RPC yet, so soft-delete the row directly
+    sqlx::query("UPDATE objects SET deleted_xid = pg_current_xact_id() WHERE id = $1")
+        .bind(object_id)
+        .execute(&pool)
+        .await?;
+
+    let mut client = GraphServiceClient::connect(address).await?;
+
+    let request = Request::new(GetObjectRequest {
+        object_id,
+        consistency: Some(ConsistencyRequirement {
+            requirement: Some(Requirement::FullConsistency(true)),
+        }),
+        validate: false,
+    })
+    .with_bearer_token(user_token)?;
+
+    let status = client.get_object(request).await.unwrap_err();
+    assert_eq!(miss_reason(&status), Reason::Deleted);
+
+    Ok(())
+}
+
+/// Test that a GetObject miss at a revision older than the object reports it
+/// as not visible
+#[tokio::test]
+async fn test_get_object_not_visible() -> Result<()> {
+    let (address, _pool, _container) = crate::common::spawn_app().await?;
+    // The middle object makes sure the last one is created strictly after the
+    // first object's snapshot
+    let state = EntTestBuilder::new()
+        .with_basic_schema()
+        .with_user("test_user")
+        .with_attributed_object(0, "test_type", json!({}))
+        .with_attributed_object(0, "test_type", json!({}))
+        .with_attributed_object(0, "test_type", json!({}))
+        .build(address.clone())
+        .await?;
+    let user_token = state.get_user_token(0).unwrap();
+    let early_revision = state.objects[0].revision.clone();
+    let object_id = state.get_object(2).unwrap().id;
+
+    let mut client = GraphServiceClient::connect(address).await?;
+
+    let request = Request::new(GetObjectRequest {
+        object_id,
+        consistency: Some(ConsistencyRequirement {
+            requirement: Some(Requirement::ExactlyAt(early_revision)),
+        }),
+        validate: false,
+    })
+    .with_bearer_token(user_token)?;
+
+    let status = client.get_object(request).await.unwrap_err();
+    assert_eq!(miss_reason(&status), Reason::NotVisible);
+
+    Ok(())
+}
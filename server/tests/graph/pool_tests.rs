@@ -0,0 +1,105 @@
+use crate::test_helper::{json_to_protobuf_struct, EntTestBuilder};
+use anyhow::Result;
+use ent_proto::ent::{
+    graph_service_server::GraphService, CreateObjectRequest, GetObjectRequest, UpdateObjectRequest,
+};
+use ent_server::config::DatabaseConfig;
+use ent_server::db::pool_options;
+use ent_server::{auth::RequestExt, GraphServer};
+use serde_json::json;
+use sqlx::postgres::PgPoolOptions;
+use std::time::Duration;
+use tonic::Request;
+
+/// Test that writes still succeed while the read pool is exhausted
+#[tokio::test]
+async fn test_writes_succeed_under_read_load() -> Result<()> {
+    let (address, pool, _container) = crate::common::spawn_app().await?;
+    let state = EntTestBuilder::new()
+        .with_basic_schema()
+        .with_user("test_user")
+        .with_attributed_object(0, "test_type", json!({}))
+        .build(address)
+        .await?;
+    let user_token = state.get_user_token(0).unwrap();
+    let object_id = state.get_object(0).unwrap().id;
+
+    let read_pool = PgPoolOptions::new()
+        .max_connections(1)
+        .acquire_timeout(Duration::from_millis(500))
+        .connect_with((*pool.connect_options()).clone())
+        .await?;
+    let server = GraphServer::new(pool).with_read_pool(read_pool.clone());
+
+    // Saturate the read pool
+    let _held = read_pool.acquire().await?;
+
+    let request = Request::new(GetObjectRequest {
+        object_id,
+        consistency: None,
+        validate: false,
+    })
+    .with_bearer_token(user_token)?;
+    assert!(server.get_object(request).await.is_err());
+
+    let request = Request::new(CreateObjectRequest {
+        r#type: "test_type".to_string(),
+        metadata: json_to_protobuf_struct(json!({ "name": "written under load" })),
+    })
+    .with_bearer_token(user_token)?;
+    let created = server.create_object(request).await?.into_inner();
+    assert!(created.object.unwrap().id > 0);
+
+    Ok(())
+}
+
+/// Test that a write stuck behind a lock is cancelled by the statement timeout
+/// and reported as retriable
+#[tokio::test]
+async fn test_statement_timeout_cancels_lock_wait() -> Result<()> {
+    let (address, pool, _container) = crate::common::spawn_app().await?;
+    let state = EntTestBuilder::new()
+        .with_basic_schema()
+        .with_user("test_user")
+        .with_attributed_object(0, "test_type", json!({}))
+        .build(address)
+        .await?;
+    let user_token = state.get_user_token(0).unwrap();
+    let object_id = state.get_object(0).unwrap().id;
+
+    let config = DatabaseConfig {
+        url: String::new(),
+        max_connections: 2,
+        min_connections: 0,
+        acquire_timeout_seconds: 3,
+        idle_timeout_seconds: None,
+        write_max_connections: None,
+        read_max_connections: None,
+        statement_timeout_seconds: Some(1),
+    };
+    let timeout_pool = pool_options(&config, config.max_connections)
+        .connect_with((*pool.connect_options()).clone())
+        .await?;
+    let server = GraphServer::new(timeout_pool);
+
+    // Hold the object's row lock until the end of the test
+    let mut blocker = pool.begin().await?;
+    sqlx::query("SELECT id FROM objects WHERE id = $1 FOR UPDATE")
+        .bind(object_id)
+        .execute(&mut *blocker)
+        .await?;
+
+    let request = Request::new(UpdateObjectRequest {
+        object_id,
+        metadata: json_to_protobuf_struct(json!({ "name": "blocked" })),
+        merge_patch: true,
+        expected_revision: None,
+    })
+    .with_bearer_token(user_token)?;
+    let status = server.update_object(request).await.unwrap_err();
+    assert_eq!(status.code(), tonic::Code::Unavailable);
+
+    blocker.rollback().await?;
+
+    Ok(())
+}
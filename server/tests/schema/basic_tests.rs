@@ -1,5 +1,15 @@
+use crate::jwt::generate_test_token;
 use crate::test_helper::EntTestBuilder;
 use anyhow::Result;
+use ent_proto::ent::{
+    graph_service_client::GraphServiceClient, import_schemas_request::Mode,
+    schema_service_client::SchemaServiceClient, CreateObjectRequest, CreateSchemaRequest,
+    DeleteObjectRequest, DeleteSchemaRequest, ExportSchemasRequest, GetObjectRequest,
+    GetSchemaRequest, ImportSchemasRequest, ListSchemasRequest,
+};
+use ent_server::auth::RequestExt;
+use serde_json::json;
+use tonic::Request;
 
 #[tokio::test]
 async fn test_create_schema() -> Result<()> {
@@ -16,6 +26,304 @@ async fn test_create_schema() -> Result<()> {
     Ok(())
 }
 
+#[tokio::test]
+async fn test_create_schema_echoes_canonical_schema() -> Result<()> {
+    let (address, _pool, _container) = crate::common::spawn_app().await?;
+
+    let mut client = SchemaServiceClient::connect(address).await?;
+    let response = client
+        .create_schema(CreateSchemaRequest {
+            type_name: "person".to_string(),
+            schema: r#"{ "type": "object",  "required": ["name"] }"#.to_string(),
+            description: "A person".to_string(),
+            ..Default::default()
+        })
+        .await?
+        .into_inner();
+
+    assert!(response.schema_id > 0);
+    assert_eq!(response.schema, r#"{"required":["name"],"type":"object"}"#);
+    assert!(response.created_at.is_some());
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_duplicate_type_name() -> Result<()> {
+    let (address, _pool, _container) = crate::common::spawn_app().await?;
+
+    let mut client = SchemaServiceClient::connect(address).await?;
+    let request = CreateSchemaRequest {
+        type_name: "person".to_string(),
+        schema: json!({ "type": "object" }).to_string(),
+        description: String::new(),
+        ..Default::default()
+    };
+    client.create_schema(request.clone()).await?;
+
+    let status = client.create_schema(request.clone()).await.unwrap_err();
+    assert_eq!(status.code(), tonic::Code::AlreadyExists);
+    assert!(status.message().contains("person"));
+
+    // Type names are compared without regard to case
+    let status = client
+        .create_schema(CreateSchemaRequest {
+            type_name: "Person".to_string(),
+            ..request
+        })
+        .await
+        .unwrap_err();
+    assert_eq!(status.code(), tonic::Code::AlreadyExists);
+    assert!(status.message().contains("'person'"));
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_list_and_get_schemas() -> Result<()> {
+    let (address, _pool, _container) = crate::common::spawn_app().await?;
+
+    let mut client = SchemaServiceClient::connect(address).await?;
+    for (type_name, schema) in [
+        ("person", json!({ "type": "object", "required": ["name"] })),
+        ("company", json!({ "type": "object" })),
+    ] {
+        client
+            .create_schema(CreateSchemaRequest {
+                type_name: type_name.to_string(),
+                schema: schema.to_string(),
+                description: format!("A {}", type_name),
+                ..Default::default()
+            })
+            .await?;
+    }
+
+    // Reading schemas takes a token even without an admin scope configured
+    let status = client
+        .list_schemas(ListSchemasRequest {})
+        .await
+        .unwrap_err();
+    assert_eq!(status.code(), tonic::Code::Unauthenticated);
+    let status = client
+        .get_schema(GetSchemaRequest {
+            type_name: "person".to_string(),
+        })
+        .await
+        .unwrap_err();
+    assert_eq!(status.code(), tonic::Code::Unauthenticated);
+
+    let token = generate_test_token("admin")?;
+    let schemas = client
+        .list_schemas(Request::new(ListSchemasRequest {}).with_bearer_token(&token)?)
+        .await?
+        .into_inner()
+        .schemas;
+    let type_names: Vec<_> = schemas.iter().map(|s| s.type_name.as_str()).collect();
+    assert_eq!(type_names, vec!["company", "person"]);
+    assert_eq!(schemas[1].description, "A person");
+
+    let schema = client
+        .get_schema(
+            Request::new(GetSchemaRequest {
+                type_name: "person".to_string(),
+            })
+            .with_bearer_token(&token)?,
+        )
+        .await?
+        .into_inner()
+        .schema
+        .unwrap();
+    assert_eq!(schema.id, schemas[1].id);
+    assert_eq!(schema.schema, r#"{"required":["name"],"type":"object"}"#);
+    assert!(schema.created_at.is_some());
+
+    let status = client
+        .get_schema(
+            Request::new(GetSchemaRequest {
+                type_name: "unknown".to_string(),
+            })
+            .with_bearer_token(&token)?,
+        )
+        .await
+        .unwrap_err();
+    assert_eq!(status.code(), tonic::Code::NotFound);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_indexed_fields() -> Result<()> {
+    let (address, pool, _container) = crate::common::spawn_app().await?;
+
+    let mut client = SchemaServiceClient::connect(address.clone()).await?;
+    let token = generate_test_token("admin")?;
+    client
+        .create_schema(CreateSchemaRequest {
+            type_name: "customer".to_string(),
+            schema: json!({
+                "type": "object",
+                "properties": { "email": { "type": "string" } },
+                "x-indexed": ["email"]
+            })
+            .to_string(),
+            ..Default::default()
+        })
+        .await?;
+
+    let indexes: Vec<String> = sqlx::query_scalar(
+        "SELECT indexname::text FROM pg_indexes WHERE tablename = 'object_metadata_history'",
+    )
+    .fetch_all(&pool)
+    .await?;
+    assert!(indexes.contains(&"idx_object_metadata_email".to_string()));
+
+    let schema = client
+        .get_schema(
+            Request::new(GetSchemaRequest {
+                type_name: "customer".to_string(),
+            })
+            .with_bearer_token(&token)?,
+        )
+        .await?
+        .into_inner()
+        .schema
+        .unwrap();
+    assert_eq!(schema.indexed_fields, vec!["email".to_string()]);
+
+    // Names that can't be inlined safely into an index are refused
+    let result = client
+        .create_schema(CreateSchemaRequest {
+            type_name: "supplier".to_string(),
+            schema: json!({ "type": "object", "x-indexed": ["e'mail"] }).to_string(),
+            ..Default::default()
+        })
+        .await;
+    assert!(result.is_err());
+
+    // A build that failed earlier leaves an invalid index, which is rebuilt
+    let _ = EntTestBuilder::new()
+        .with_user("test_user")
+        .with_object(0, "customer", json!({ "email": "a@example.com" }))
+        .with_object(0, "customer", json!({ "email": "b@example.com" }))
+        .build(address.clone())
+        .await?;
+    sqlx::query("DROP INDEX IF EXISTS idx_object_metadata_phone")
+        .execute(&pool)
+        .await?;
+    let failed = sqlx::query(
+        "CREATE UNIQUE INDEX CONCURRENTLY idx_object_metadata_phone ON object_metadata_history ((1))",
+    )
+    .execute(&pool)
+    .await;
+    assert!(failed.is_err());
+
+    client
+        .create_schema(CreateSchemaRequest {
+            type_name: "contact".to_string(),
+            schema: json!({ "type": "object", "x-indexed": ["phone"] }).to_string(),
+            ..Default::default()
+        })
+        .await?;
+    let (valid, unique): (bool, bool) = sqlx::query_as(
+        "SELECT i.indisvalid, i.indisunique FROM pg_index i \
+         JOIN pg_class c ON c.oid = i.indexrelid WHERE c.relname = 'idx_object_metadata_phone'",
+    )
+    .fetch_one(&pool)
+    .await?;
+    assert!(valid);
+    assert!(!unique);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_delete_and_deprecate_schema() -> Result<()> {
+    let (address, _pool, _container) = crate::common::spawn_app().await?;
+    let state = EntTestBuilder::new()
+        .with_schema_and_type(json!({ "type": "object" }).to_string(), "person")
+        .with_user("test_user")
+        .with_attributed_object(0, "person", json!({}))
+        .build(address.clone())
+        .await?;
+    let user_token = state.get_user_token(0).unwrap();
+    let object_id = state.get_object(0).unwrap().id;
+
+    let mut client = SchemaServiceClient::connect(address.clone()).await?;
+    let mut graph_client = GraphServiceClient::connect(address).await?;
+
+    let delete = |deprecate| {
+        Request::new(DeleteSchemaRequest {
+            type_name: "person".to_string(),
+            deprecate,
+        })
+        .with_bearer_token(user_token)
+    };
+    let create_person = || {
+        Request::new(CreateObjectRequest {
+            r#type: "person".to_string(),
+            metadata: None,
+        })
+        .with_bearer_token(user_token)
+    };
+
+    // Even without an admin scope configured, deleting takes a token
+    let request = Request::new(DeleteSchemaRequest {
+        type_name: "person".to_string(),
+        deprecate: false,
+    });
+    let status = client.delete_schema(request).await.unwrap_err();
+    assert_eq!(status.code(), tonic::Code::Unauthenticated);
+
+    // A live object blocks deletion
+    let status = client.delete_schema(delete(false)?).await.unwrap_err();
+    assert_eq!(status.code(), tonic::Code::FailedPrecondition);
+    assert!(status.message().starts_with("1 object(s) of type 'person'"));
+
+    // Deprecation keeps existing objects readable but stops new ones
+    let response = client.delete_schema(delete(true)?).await?.into_inner();
+    assert!(response.deprecated);
+    assert!(!response.deleted);
+
+    let status = graph_client
+        .create_object(create_person()?)
+        .await
+        .unwrap_err();
+    assert_eq!(status.code(), tonic::Code::FailedPrecondition);
+
+    let request = Request::new(GetObjectRequest {
+        object_id,
+        consistency: None,
+        validate: false,
+    })
+    .with_bearer_token(user_token)?;
+    graph_client.get_object(request).await?;
+
+    // Once its objects are gone the schema can be removed, lifting the deprecation
+    let request = Request::new(DeleteObjectRequest { object_id }).with_bearer_token(user_token)?;
+    graph_client.delete_object(request).await?;
+
+    let response = client.delete_schema(delete(false)?).await?.into_inner();
+    assert!(response.deleted);
+
+    let status = client
+        .get_schema(
+            Request::new(GetSchemaRequest {
+                type_name: "person".to_string(),
+            })
+            .with_bearer_token(user_token)?,
+        )
+        .await
+        .unwrap_err();
+    assert_eq!(status.code(), tonic::Code::NotFound);
+
+    let status = client.delete_schema(delete(false)?).await.unwrap_err();
+    assert_eq!(status.code(), tonic::Code::NotFound);
+
+    graph_client.create_object(create_person()?).await?;
+
+    Ok(())
+}
+
 #[tokio::test]
 async fn test_invalid_schema() -> Result<()> {
     let (address, _pool, _container) = crate::common::spawn_app().await?;
@@ -27,3 +335,141 @@ async fn test_invalid_schema() -> Result<()> {
 
     Ok(())
 }
+
+#[tokio::test]
+async fn test_export_import_schemas() -> Result<()> {
+    let (source_address, _source_pool, _source_container) = crate::common::spawn_app().await?;
+    let (target_address, _target_pool, _target_container) = crate::common::spawn_app().await?;
+    let token = generate_test_token("admin")?;
+
+    let mut source = SchemaServiceClient::connect(source_address).await?;
+    for (type_name, schema) in [
+        ("person", json!({ "type": "object", "required": ["name"] })),
+        ("company", json!({ "type": "object" })),
+    ] {
+        source
+            .create_schema(CreateSchemaRequest {
+                type_name: type_name.to_string(),
+                schema: schema.to_string(),
+                description: format!("A {}", type_name),
+                ..Default::default()
+            })
+            .await?;
+    }
+
+    let bundle = source
+        .export_schemas(Request::new(ExportSchemasRequest {}).with_bearer_token(&token)?)
+        .await?
+        .into_inner()
+        .bundle;
+
+    // Both need a token even without an admin scope configured
+    let status = source
+        .export_schemas(ExportSchemasRequest {})
+        .await
+        .unwrap_err();
+    assert_eq!(status.code(), tonic::Code::Unauthenticated);
+    let status = source
+        .import_schemas(ImportSchemasRequest {
+            bundle: bundle.clone(),
+            mode: Mode::SkipExisting as i32,
+        })
+        .await
+        .unwrap_err();
+    assert_eq!(status.code(), tonic::Code::Unauthenticated);
+
+    let parsed: serde_json::Value = serde_json::from_str(&bundle)?;
+    assert_eq!(parsed["schemas"][0]["type_name"], "company");
+    assert_eq!(parsed["schemas"][1]["type_name"], "person");
+    assert_eq!(parsed["schemas"][1]["description"], "A person");
+
+    // Importing into a fresh database recreates every type
+    let mut target = SchemaServiceClient::connect(target_address).await?;
+    let response = target
+        .import_schemas(
+            Request::new(ImportSchemasRequest {
+                bundle: bundle.clone(),
+                mode: Mode::SkipExisting as i32,
+            })
+            .with_bearer_token(&token)?,
+        )
+        .await?
+        .into_inner();
+    assert_eq!(response.created, vec!["company", "person"]);
+
+    let exported = target
+        .export_schemas(Request::new(ExportSchemasRequest {}).with_bearer_token(&token)?)
+        .await?
+        .into_inner()
+        .bundle;
+    assert_eq!(exported, bundle);
+
+    // Existing types are skipped or updated depending on the mode
+    for (mode, expect_updated) in [(Mode::SkipExisting, false), (Mode::UpdateExisting, true)] {
+        let response = target
+            .import_schemas(
+                Request::new(ImportSchemasRequest {
+                    bundle: bundle.clone(),
+                    mode: mode as i32,
+                })
+                .with_bearer_token(&token)?,
+            )
+            .await?
+            .into_inner();
+        assert!(response.created.is_empty());
+        assert_eq!(response.updated.len(), if expect_updated { 2 } else { 0 });
+        assert_eq!(response.skipped.len(), if expect_updated { 0 } else { 2 });
+    }
+
+    // A bundle with an invalid schema is rejected without registering anything
+    let status = target
+        .import_schemas(
+            Request::new(ImportSchemasRequest {
+                bundle: json!({
+                    "schemas": [
+                        { "type_name": "valid", "schema": { "type": "object" } },
+                        { "type_name": "invalid", "schema": { "type": 12 } }
+                    ]
+                })
+                .to_string(),
+                mode: Mode::SkipExisting as i32,
+            })
+            .with_bearer_token(&token)?,
+        )
+        .await
+        .unwrap_err();
+    assert_eq!(status.code(), tonic::Code::InvalidArgument);
+
+    // Names differing only in case are the same type, updated under its name
+    let renamed = bundle.replace("\"person\"", "\"Person\"");
+    for (mode, expect_updated) in [(Mode::SkipExisting, false), (Mode::UpdateExisting, true)] {
+        let response = target
+            .import_schemas(
+                Request::new(ImportSchemasRequest {
+                    bundle: renamed.clone(),
+                    mode: mode as i32,
+                })
+                .with_bearer_token(&token)?,
+            )
+            .await?
+            .into_inner();
+        assert!(response.created.is_empty());
+        if expect_updated {
+            assert_eq!(response.updated, vec!["company", "person"]);
+        } else {
+            assert_eq!(response.skipped, vec!["company", "Person"]);
+        }
+    }
+    let status = target
+        .get_schema(
+            Request::new(GetSchemaRequest {
+                type_name: "Person".to_string(),
+            })
+            .with_bearer_token(&token)?,
+        )
+        .await
+        .unwrap_err();
+    assert_eq!(status.code(), tonic::Code::NotFound);
+
+    Ok(())
+}
@@ -1,6 +1,14 @@
-use crate::test_helper::EntTestBuilder;
+use crate::test_helper::{json_to_protobuf_struct, EntTestBuilder};
 use anyhow::Result;
+use ent_proto::ent::{
+    graph_service_client::GraphServiceClient, graph_service_server::GraphService,
+    import_schemas_request::Mode, schema_service_client::SchemaServiceClient, CreateObjectRequest,
+    CreateSchemaRequest, DeleteObjectRequest, GetObjectRequest, ImportSchemasRequest, Permission,
+    RetypeObjectRequest, UpdateObjectRequest,
+};
+use ent_server::{auth::RequestExt, config::SchemaConfig, GraphServer};
 use serde_json::json;
+use tonic::Request;
 
 #[tokio::test]
 async fn test_schema_validation_comprehensive() -> Result<()> {
@@ -130,4 +138,414 @@ async fn test_schema_validation_comprehensive() -> Result<()> {
     Ok(())
 }
 
+#[tokio::test]
+async fn test_computed_fields() -> Result<()> {
+    let (address, _pool, _container) = crate::common::spawn_app().await?;
+
+    let builder = EntTestBuilder::new()
+        .with_schema_and_type(
+            r#"{
+            "type": "object",
+            "required": ["first", "last"],
+            "properties": {
+                "first": { "type": "string" },
+                "last": { "type": "string" },
+                "full_name": {
+                    "type": "string",
+                    "x-computed": "{first} {last}"
+                }
+            }
+        }"#,
+            "person",
+        )
+        .with_user("test_user");
+
+    // The computed field is populated from its components
+    let state = builder
+        .clone()
+        .with_object(0, "person", json!({ "first": "Ada", "last": "Lovelace" }))
+        .build(address.clone())
+        .await?;
+
+    let object = state.get_object(0).unwrap();
+    let full_name = object
+        .metadata
+        .as_ref()
+        .unwrap()
+        .fields
+        .get("full_name")
+        .unwrap();
+    assert_eq!(
+        full_name.kind,
+        Some(prost_types::value::Kind::StringValue(
+            "Ada Lovelace".to_string()
+        ))
+    );
+
+    // Clients cannot set a computed field themselves
+    let result = builder
+        .try_create_object(
+            address.clone(),
+            0,
+            "person",
+            json!({ "first": "Ada", "last": "Lovelace", "full_name": "Countess" }),
+        )
+        .await;
+    assert!(
+        result.is_err(),
+        "Expected error for client-supplied computed field"
+    );
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_get_object_reports_schema_drift() -> Result<()> {
+    let (address, _pool, _container) = crate::common::spawn_app().await?;
+
+    let state = EntTestBuilder::new()
+        .with_schema_and_type(r#"{ "type": "object" }"#, "person")
+        .with_user("test_user")
+        .with_object(0, "person", json!({ "first": "Ada" }))
+        .build(address.clone())
+        .await?;
+    let user_token = state.get_user_token(0).unwrap();
+    let object_id = state.get_object(0).unwrap().id;
+
+    // Tighten the schema after the object was written
+    let mut schema_client = SchemaServiceClient::connect(address.clone()).await?;
+    let request = Request::new(ImportSchemasRequest {
+        bundle: json!({
+            "schemas": [{
+                "type_name": "person",
+                "schema": { "type": "object", "required": ["last"] }
+            }]
+        })
+        .to_string(),
+        mode: Mode::UpdateExisting as i32,
+    })
+    .with_bearer_token(user_token)?;
+    schema_client.import_schemas(request).await?;
+
+    let mut client = GraphServiceClient::connect(address).await?;
+    for validate in [false, true] {
+        let request = Request::new(GetObjectRequest {
+            object_id,
+            consistency: None,
+            validate,
+        })
+        .with_bearer_token(user_token)?;
+        let response = client.get_object(request).await?.into_inner();
+        assert!(response.object.is_some());
+
+        match response.validation {
+            Some(validation) => {
+                assert!(validate);
+                assert!(!validation.valid);
+                assert_eq!(validation.errors.len(), 1);
+                assert!(validation.errors[0].contains("last"));
+            }
+            None => assert!(!validate),
+        }
+    }
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_strict_types() -> Result<()> {
+    let (address, pool, _container) = crate::common::spawn_app().await?;
+
+    let state = EntTestBuilder::new()
+        .with_schema_and_type(r#"{ "type": "object" }"#, "person")
+        .with_user("test_user")
+        .build(address)
+        .await?;
+    let user_token = state.get_user_token(0).unwrap();
+
+    let server = GraphServer::new(pool).with_schema_config(SchemaConfig {
+        strict_types: true,
+        ..Default::default()
+    });
+
+    // A typo in the type name is rejected instead of creating an unvalidated object
+    let request = Request::new(CreateObjectRequest {
+        r#type: "preson".to_string(),
+        metadata: json_to_protobuf_struct(json!({ "name": "Ada" })),
+    })
+    .with_bearer_token(user_token)?;
+    let status = server.create_object(request).await.unwrap_err();
+    assert_eq!(status.code(), tonic::Code::FailedPrecondition);
+
+    let request = Request::new(CreateObjectRequest {
+        r#type: "person".to_string(),
+        metadata: json_to_protobuf_struct(json!({ "name": "Ada" })),
+    })
+    .with_bearer_token(user_token)?;
+    assert!(server.create_object(request).await.is_ok());
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_merge_patch_update() -> Result<()> {
+    let (address, _pool, _container) = crate::common::spawn_app().await?;
+
+    let state = EntTestBuilder::new()
+        .with_schema_and_type(
+            r#"{
+            "type": "object",
+            "required": ["first", "last"],
+            "properties": {
+                "first": { "type": "string" },
+                "last": { "type": "string" },
+                "full_name": {
+                    "type": "string",
+                    "x-computed": "{first} {last}"
+                }
+            }
+        }"#,
+            "person",
+        )
+        .with_user("test_user")
+        .with_object(
+            0,
+            "person",
+            json!({ "first": "Ada", "last": "Lovelace", "title": "Countess" }),
+        )
+        .build(address.clone())
+        .await?;
+    let user_token = state.get_user_token(0).unwrap();
+    let object_id = state.get_object(0).unwrap().id;
+
+    let mut client = GraphServiceClient::connect(address).await?;
+    let patch = |metadata: serde_json::Value| {
+        Request::new(UpdateObjectRequest {
+            object_id,
+            metadata: json_to_protobuf_struct(metadata),
+            merge_patch: true,
+            expected_revision: None,
+        })
+        .with_bearer_token(user_token)
+    };
+
+    // Untouched fields are kept, null removes a field and computed fields follow
+    let object = client
+        .update_object(patch(json!({ "last": "King", "title": null }))?)
+        .await?
+        .into_inner()
+        .object
+        .unwrap();
+    assert_eq!(
+        object.metadata,
+        json_to_protobuf_struct(json!({
+            "first": "Ada",
+            "last": "King",
+            "full_name": "Ada King"
+        }))
+    );
+
+    // The merged document must still match the schema
+    let status = client
+        .update_object(patch(json!({ "first": null }))?)
+        .await
+        .unwrap_err();
+    assert_eq!(status.code(), tonic::Code::InvalidArgument);
+
+    let status = client
+        .update_object(patch(json!({ "full_name": "Countess" }))?)
+        .await
+        .unwrap_err();
+    assert_eq!(status.code(), tonic::Code::InvalidArgument);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_retype_object() -> Result<()> {
+    let (address, _pool, _container) = crate::common::spawn_app().await?;
+
+    let state = EntTestBuilder::new()
+        .with_schema_and_type(r#"{ "type": "object" }"#, "contractor")
+        .with_user("test_user")
+        .with_user("editor")
+        .with_object(0, "contractor", json!({ "name": "Ada" }))
+        .with_share(0, 1, Permission::Write)
+        .build(address.clone())
+        .await?;
+    let user_token = state.get_user_token(0).unwrap();
+    let editor_token = state.get_user_token(1).unwrap();
+    let object_id = state.get_object(0).unwrap().id;
+
+    let mut schema_client = SchemaServiceClient::connect(address.clone()).await?;
+    schema_client
+        .create_schema(CreateSchemaRequest {
+            type_name: "employee".to_string(),
+            schema: json!({ "type": "object", "required": ["name", "employer"] }).to_string(),
+            description: String::new(),
+            ..Default::default()
+        })
+        .await?;
+
+    let mut client = GraphServiceClient::connect(address).await?;
+    let retype = |token| {
+        Request::new(RetypeObjectRequest {
+            object_id,
+            r#type: "employee".to_string(),
+        })
+        .with_bearer_token(token)
+    };
+
+    // The current metadata lacks a field the new type requires
+    let status = client.retype_object(retype(user_token)?).await.unwrap_err();
+    assert_eq!(status.code(), tonic::Code::FailedPrecondition);
+    assert!(status.message().contains("employer"));
+
+    let request = Request::new(UpdateObjectRequest {
+        object_id,
+        metadata: json_to_protobuf_struct(json!({ "employer": "Analytical Engines" })),
+        merge_patch: true,
+        expected_revision: None,
+    })
+    .with_bearer_token(user_token)?;
+    client.update_object(request).await?;
+
+    // A writer other than the owner is recorded as the last writer
+    let object = client
+        .retype_object(retype(editor_token)?)
+        .await?
+        .into_inner()
+        .object
+        .unwrap();
+    assert_eq!(object.r#type, "employee");
+    assert_eq!(object.created_by, "test_user");
+    assert_eq!(object.updated_by, "editor");
+    assert_eq!(
+        object.metadata,
+        json_to_protobuf_struct(json!({ "name": "Ada", "employer": "Analytical Engines" }))
+    );
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_retype_object_checks_edges() -> Result<()> {
+    let (address, _pool, _container) = crate::common::spawn_app().await?;
+
+    let state = EntTestBuilder::new()
+        .with_schema_and_type(
+            r#"{ "type": "object", "x-edges": { "references": ["document"] } }"#,
+            "document",
+        )
+        .with_user("test_user")
+        .with_object(0, "document", json!({}))
+        .with_object(0, "document", json!({}))
+        .with_object(0, "document", json!({}))
+        .with_edge(0, 0, 1, "references", json!({}))
+        .build(address.clone())
+        .await?;
+    let user_token = state.get_user_token(0).unwrap();
+    let referenced_id = state.get_object(1).unwrap().id;
+    let deleted_id = state.get_object(2).unwrap().id;
+
+    let mut schema_client = SchemaServiceClient::connect(address.clone()).await?;
+    schema_client
+        .create_schema(CreateSchemaRequest {
+            type_name: "memo".to_string(),
+            schema: json!({ "type": "object" }).to_string(),
+            description: String::new(),
+            ..Default::default()
+        })
+        .await?;
+
+    let mut client = GraphServiceClient::connect(address).await?;
+    let retype = |object_id| {
+        Request::new(RetypeObjectRequest {
+            object_id,
+            r#type: "memo".to_string(),
+        })
+        .with_bearer_token(user_token)
+    };
+
+    // Documents may only reference documents, so the edge pointing at the
+    // object forbids turning it into a memo
+    let status = client
+        .retype_object(retype(referenced_id)?)
+        .await
+        .unwrap_err();
+    assert_eq!(status.code(), tonic::Code::FailedPrecondition);
+    assert!(status.message().contains("references"));
+
+    let request = Request::new(GetObjectRequest {
+        object_id: referenced_id,
+        ..Default::default()
+    })
+    .with_bearer_token(user_token)?;
+    let object = client
+        .get_object(request)
+        .await?
+        .into_inner()
+        .object
+        .unwrap();
+    assert_eq!(object.r#type, "document");
+
+    // A deleted object is reported as not found rather than as a failure
+    let request = Request::new(DeleteObjectRequest {
+        object_id: deleted_id,
+    })
+    .with_bearer_token(user_token)?;
+    client.delete_object(request).await?;
+
+    let status = client.retype_object(retype(deleted_id)?).await.unwrap_err();
+    assert_eq!(status.code(), tonic::Code::NotFound);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_string_formats() -> Result<()> {
+    let (address, pool, _container) = crate::common::spawn_app().await?;
+
+    let state = EntTestBuilder::new()
+        .with_schema_and_type(
+            r#"{
+                "type": "object",
+                "properties": {
+                    "email": { "type": "string", "format": "email" },
+                    "homepage": { "type": "string", "format": "uri" }
+                }
+            }"#,
+            "contact",
+        )
+        .with_user("test_user")
+        .build(address)
+        .await?;
+    let user_token = state.get_user_token(0).unwrap();
+    let server = GraphServer::new(pool);
+
+    let create = |metadata| {
+        Request::new(CreateObjectRequest {
+            r#type: "contact".to_string(),
+            metadata: json_to_protobuf_struct(metadata),
+        })
+        .with_bearer_token(user_token)
+    };
+
+    let request = create(json!({
+        "email": "ada@example.com",
+        "homepage": "https://example.com/ada"
+    }))?;
+    assert!(server.create_object(request).await.is_ok());
+
+    let request = create(json!({ "email": "not-an-email" }))?;
+    let status = server.create_object(request).await.unwrap_err();
+    assert_eq!(status.code(), tonic::Code::InvalidArgument);
+
+    let request = create(json!({ "homepage": "not a uri" }))?;
+    let status = server.create_object(request).await.unwrap_err();
+    assert_eq!(status.code(), tonic::Code::InvalidArgument);
+
+    Ok(())
+}
+
 // ... rest of the existing tests ...
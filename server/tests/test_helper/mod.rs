@@ -1,17 +1,17 @@
+#![allow(dead_code)]
+
 mod assertions;
 mod fixtures;
 
 use anyhow::Result;
 use ent_proto::ent::{
     graph_service_client::GraphServiceClient, schema_service_client::SchemaServiceClient,
-    CreateEdgeRequest, CreateObjectRequest, CreateSchemaRequest, Edge, Object,
+    CreateEdgeRequest, CreateObjectRequest, CreateSchemaRequest, Edge, Object, Permission,
+    ShareObjectRequest,
 };
 use ent_server::{auth::RequestExt, server::json_value_to_prost_value};
 use prost_types::Struct;
 use serde_json::Value as JsonValue;
-use std::collections::HashMap;
-use std::sync::Arc;
-use tokio::sync::Mutex;
 use tracing::info;
 use uuid::Uuid;
 
@@ -22,7 +22,6 @@ use crate::jwt::generate_test_token;
 // Represents a user context for testing
 #[derive(Debug, Clone)]
 pub struct TestUser {
-    #[allow(dead_code)]
     id: String,
     token: String,
 }
@@ -55,6 +54,14 @@ struct EdgeCreationRequest {
     metadata: JsonValue,
 }
 
+// Grant request with object and user indices
+#[derive(Debug, Clone)]
+struct ShareRequest {
+    object_index: usize,
+    grantee_index: usize,
+    permission: Permission,
+}
+
 // Main builder struct
 #[derive(Default, Clone)]
 pub struct EntTestBuilder {
@@ -62,6 +69,7 @@ pub struct EntTestBuilder {
     type_name: Option<String>,
     users: Vec<TestUser>,
     objects_to_create: Vec<(usize, CreateObjectRequest)>,
+    shares_to_create: Vec<ShareRequest>,
     edges_to_create: Vec<EdgeCreationRequest>,
     created_objects: Vec<CreatedObject>,
     created_edges: Vec<CreatedEdge>,
@@ -117,6 +125,21 @@ impl EntTestBuilder {
         self
     }
 
+    // Shared by the object's owner once objects exist, before any edge
+    pub fn with_share(
+        mut self,
+        object_index: usize,
+        grantee_index: usize,
+        permission: Permission,
+    ) -> Self {
+        self.shares_to_create.push(ShareRequest {
+            object_index,
+            grantee_index,
+            permission,
+        });
+        self
+    }
+
     pub fn with_edge(
         mut self,
         user_index: usize,
@@ -153,6 +176,7 @@ impl EntTestBuilder {
             schema: schema.to_string(),
             type_name: type_name.to_string(),
             description: "Test schema".to_string(),
+            ..Default::default()
         };
 
         schema_client.create_schema(request).await.map(|_| ())
@@ -204,6 +228,7 @@ impl EntTestBuilder {
                 schema: schema.to_string(),
                 type_name: type_name.clone(),
                 description: "Test schema".to_string(),
+                ..Default::default()
             };
             info!(schema = &request.schema);
             let response = schema_client.create_schema(request).await?;
@@ -239,6 +264,21 @@ impl EntTestBuilder {
             }
         }
 
+        // Share objects
+        for share in self.shares_to_create {
+            let created = &self.created_objects[share.object_index];
+            let request = ShareObjectRequest {
+                object_id: created.object.id,
+                grantee_user_id: self.users[share.grantee_index].id.clone(),
+                permission: share.permission as i32,
+            };
+
+            let owner = &self.users[created.user_index];
+            let request = tonic::Request::new(request).with_bearer_token(&owner.token)?;
+
+            graph_client.share_object(request).await?;
+        }
+
         // Create edges
         for edge_request in self.edges_to_create {
             let from_obj = &self.created_objects[edge_request.from_object_index].object;
@@ -395,9 +435,9 @@ impl EntTestBuilder {
         }
 
         // Create edges between consecutive objects
-        for i in 0..user_indices.len() - 1 {
+        for (i, &user_index) in user_indices.iter().enumerate().take(user_indices.len() - 1) {
             self.edges_to_create.push(EdgeCreationRequest {
-                user_index: user_indices[i],
+                user_index,
                 from_object_index: start_index + i,
                 to_object_index: start_index + i + 1,
                 relation: relation.clone(),